@@ -0,0 +1,98 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use life_game::patterns::{GLIDER, GOSPER_GLIDER_GUN};
+use life_game::{Engine, HashLifeUniverse, Universe};
+
+/// 200x200 的面板上半径为 5 的邻居数, 逐格累加和前缀和两种算法
+fn radius_five(c: &mut Criterion) {
+    let u = Universe::builder().width(200).height(200).seed(5).build();
+    let mut group = c.benchmark_group("range_neighbors_r5_200x200");
+    group.bench_function("naive", |b| {
+        b.iter(|| {
+            let mut total = 0u64;
+            for row in 0..u.height() {
+                for col in 0..u.width() {
+                    total += u64::from(u.range_neighbor_count(row, col, black_box(5)));
+                }
+            }
+            total
+        })
+    });
+    group.bench_function("prefix_sum", |b| {
+        b.iter(|| u.range_neighbor_counts(black_box(5)))
+    });
+    group.finish();
+}
+
+fn bosco_tick(c: &mut Criterion) {
+    let mut u = Universe::builder().width(200).height(200).seed(5).build();
+    u.set_rule(life_game::rule::preset("Bosco's Rule").unwrap());
+    c.bench_function("bosco_tick_200x200", |b| b.iter(|| u.tick()));
+}
+
+/// 1024x1024 的 Conway: 打包成位整字演化的 `tick`, 一个字节一个格子同时数 8 个格子的
+/// `Engine::Swar`, 逐格查规则表的 `Engine::Scalar`, 和在面板外面逐格数邻居的老办法
+fn conway_1024(c: &mut Criterion) {
+    let mut u = Universe::builder().width(1024).height(1024).seed(5).build();
+    let mut group = c.benchmark_group("conway_1024x1024");
+    group.sample_size(10);
+    group.bench_function("cell_by_cell", |b| {
+        b.iter(|| {
+            let rule = u.rule();
+            let mut next = Vec::with_capacity(u.cells().len());
+            for row in 0..u.height() {
+                for col in 0..u.width() {
+                    let cell = u.get_cell(row, col).unwrap();
+                    next.push(rule.applies(cell, u.live_neighbor_count(row, col)));
+                }
+            }
+            next
+        })
+    });
+    for &engine in [Engine::Dense, Engine::Swar, Engine::Scalar].iter() {
+        u.set_engine(engine);
+        group.bench_function(format!("{:?}", engine), |b| b.iter(|| u.tick()));
+    }
+    group.finish();
+}
+
+/// 4096x4096 的面板上 200 个互不相碰的滑翔机, 共 1000 个活细胞
+fn sparse_4096(c: &mut Criterion) {
+    let mut u = Universe::with_size(4096, 4096);
+    u.dead_all();
+    for i in 0..200 {
+        u.insert_pattern(GLIDER, i / 20 * 400 + 8, i % 20 * 200 + 8, true)
+            .unwrap();
+    }
+    let mut group = c.benchmark_group("gliders_4096x4096");
+    group.sample_size(10);
+    for &engine in [Engine::Dense, Engine::Sparse].iter() {
+        u.set_engine(engine);
+        group.bench_function(format!("{:?}", engine), |b| b.iter(|| u.tick()));
+    }
+    group.finish();
+}
+
+/// Gosper 滑翔机枪从头推进一百万代, 每次都从空的 HashLife 开始, 不沿用上一次记下的结果
+fn hashlife_gun(c: &mut Criterion) {
+    let mut group = c.benchmark_group("hashlife");
+    group.sample_size(10);
+    group.bench_function("gosper_gun_1000000", |b| {
+        b.iter(|| {
+            let mut u = HashLifeUniverse::new();
+            u.insert_pattern(GOSPER_GLIDER_GUN, 0, 0);
+            u.step_n(black_box(1_000_000)).unwrap();
+            u.population()
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    radius_five,
+    bosco_tick,
+    conway_1024,
+    sparse_4096,
+    hashlife_gun
+);
+criterion_main!(benches);
@@ -0,0 +1,22 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use life_game::Universe;
+
+/// Conway 在 512x512 和 2048x2048 上串行和并行演化一代
+fn conway_parallel(c: &mut Criterion) {
+    let mut group = c.benchmark_group("conway_parallel");
+    group.sample_size(10);
+    for &size in [512u32, 2048].iter() {
+        let mut u = Universe::builder().width(size).height(size).seed(5).build();
+        for &parallel in [false, true].iter() {
+            let name = if parallel { "parallel" } else { "serial" };
+            u.set_parallel(parallel);
+            group.bench_with_input(BenchmarkId::new(name, size), &size, |b, _| {
+                b.iter(|| u.tick())
+            });
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, conway_parallel);
+criterion_main!(benches);
@@ -0,0 +1,80 @@
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use life_game::patterns::GOSPER_GLIDER_GUN;
+use life_game::universe::step;
+use life_game::{Cell, Engine, Rule, Stepper, Topology, Universe};
+
+const SIZES: [u32; 3] = [64, 256, 1024];
+
+/// 按边长生成面板
+type Workload = fn(u32) -> Vec<Cell>;
+
+/// 活细胞比例 0.4 的随机面板
+fn random(size: u32) -> Vec<Cell> {
+    let u = Universe::builder().width(size).height(size).seed(5).build();
+    u.cells().to_vec()
+}
+
+/// 每 64x64 一门 Gosper 滑翔机枪
+fn guns(size: u32) -> Vec<Cell> {
+    let mut u = Universe::from_cells(size, size, vec![Cell::Dead; (size * size) as usize]).unwrap();
+    for row in (0..size).step_by(64) {
+        for col in (0..size).step_by(64) {
+            u.insert_pattern(GOSPER_GLIDER_GUN, row + 2, col + 2, false)
+                .unwrap();
+        }
+    }
+    u.cells().to_vec()
+}
+
+/// 每次都从同一块面板算下一代: 分配新面板的 `step`, 和反复使用缓冲区的 `Stepper`
+fn tick(c: &mut Criterion) {
+    let workloads: [(&str, Workload); 2] = [("random", random), ("guns", guns)];
+    for &(name, build) in workloads.iter() {
+        let mut group = c.benchmark_group(format!("tick_{}", name));
+        group.sample_size(20);
+        for &size in SIZES.iter() {
+            let cells = build(size);
+            group.bench_with_input(BenchmarkId::new("step", size), &size, |b, &size| {
+                b.iter(|| step(black_box(&cells), size, size, Rule::CONWAY))
+            });
+            let mut stepper = Stepper::default();
+            let mut next = vec![Cell::Dead; cells.len()];
+            group.bench_with_input(BenchmarkId::new("stepper", size), &size, |b, &size| {
+                b.iter(|| {
+                    stepper.step_into(
+                        black_box(&cells),
+                        &mut next,
+                        (size, size),
+                        Rule::CONWAY,
+                        Topology::Torus,
+                    )
+                })
+            });
+        }
+        group.finish();
+    }
+}
+
+/// 1024x1024 上逐行扫描和逐块演化: 随机面板演化 2000 代之后只剩静物和振荡器,
+/// 振荡器散在各处, 几乎每块都要重算; 空面板上的一门枪只牵动它和滑翔机附近的块
+fn settled(c: &mut Criterion) {
+    let mut soup = Universe::builder().width(1024).height(1024).seed(5).build();
+    soup.step_n(2000);
+    // 有界的面板上滑翔机撞到边缘就停下来, 不会绕回来打坏枪
+    let mut gun = Universe::from_cells(1024, 1024, vec![Cell::Dead; 1024 * 1024]).unwrap();
+    gun.insert_pattern(GOSPER_GLIDER_GUN, 2, 2, false).unwrap();
+    gun.set_topology(Topology::Bounded);
+    for (name, u) in [("soup", soup), ("gun", gun)].iter() {
+        let mut group = c.benchmark_group(format!("settled_{}_1024", name));
+        group.sample_size(20);
+        for &engine in [Engine::Dense, Engine::Tiled].iter() {
+            let mut u = u.clone();
+            u.set_engine(engine);
+            group.bench_function(format!("{:?}", engine), |b| b.iter(|| u.tick()));
+        }
+        group.finish();
+    }
+}
+
+criterion_group!(benches, tick, settled);
+criterion_main!(benches);
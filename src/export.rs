@@ -0,0 +1,182 @@
+//! 把面板导出成图片, 不依赖窗口界面
+
+use std::fmt::Write as _;
+use std::fs;
+#[cfg(feature = "png")]
+use std::fs::File;
+use std::io;
+#[cfg(feature = "png")]
+use std::io::BufWriter;
+use std::path::Path;
+
+use crate::universe::{Cell, Universe, ACTIVITY_STEP};
+
+#[cfg(feature = "gif")]
+mod record;
+
+#[cfg(feature = "gif")]
+pub use self::record::{RecordError, Recorder};
+
+/// 按 `cell_px` 的缩放把面板画成灰度像素, 活细胞为黑 (0), 死细胞为白 (255), 逐行存放
+pub fn render_gray(universe: &Universe, cell_px: u32) -> Vec<u8> {
+    scale(universe.cells(), universe.width(), cell_px, 0, 255)
+}
+
+/// 每个细胞放大成 `cell_px` x `cell_px` 个字节, 活细胞写 `alive`, 死细胞写 `dead`
+fn scale(cells: &[Cell], width: u32, cell_px: u32, alive: u8, dead: u8) -> Vec<u8> {
+    let cell_px = cell_px as usize;
+    let row_len = width as usize * cell_px;
+    let mut pixels = Vec::with_capacity(row_len * cell_px * cells.len() / width as usize);
+    for row in cells.chunks(width as usize) {
+        let mut line = Vec::with_capacity(row_len);
+        for &cell in row {
+            let value = if cell == Cell::Alive { alive } else { dead };
+            line.extend(std::iter::repeat_n(value, cell_px));
+        }
+        for _ in 0..cell_px {
+            pixels.extend_from_slice(&line);
+        }
+    }
+    pixels
+}
+
+/// 活跃度热度图的颜色 (R, G, B), 从没动过的冷蓝色到变得最频繁的热红色
+pub const HEAT_PALETTE: [[u8; 3]; 8] = [
+    [20, 30, 110],
+    [30, 80, 200],
+    [40, 160, 220],
+    [60, 200, 150],
+    [170, 220, 60],
+    [250, 200, 40],
+    [245, 120, 30],
+    [220, 30, 30],
+];
+
+/// 活跃度到这么多就是最热的一档, 大约是隔一代变一次的格子
+pub const HEAT_FULL: u16 = 16 * ACTIVITY_STEP;
+
+/// 活跃度在 `HEAT_PALETTE` 里的档位. 只有从没变过的格子是 0, 变过一次就至少是 1
+pub fn heat_level(activity: u16) -> usize {
+    let top = (HEAT_PALETTE.len() - 1) as u32;
+    let full = u32::from(HEAT_FULL);
+    ((u32::from(activity.min(HEAT_FULL)) * top).div_ceil(full)) as usize
+}
+
+/// 按 `cell_px` 的缩放把每个格子的活跃度画成 `HEAT_PALETTE` 里的颜色, 每像素 3 个字节 (RGB),
+/// 逐行存放. 和窗口里的热度图一样不分死活
+pub fn render_heat(universe: &Universe, cell_px: u32) -> Vec<u8> {
+    let (width, height) = (universe.width(), universe.height());
+    let cell_px = cell_px as usize;
+    let row_len = width as usize * cell_px * 3;
+    let mut pixels = Vec::with_capacity(row_len * cell_px * height as usize);
+    for row in 0..height {
+        let mut line = Vec::with_capacity(row_len);
+        for col in 0..width {
+            let activity = universe.cell_activity(row, col).unwrap_or(0);
+            let color = HEAT_PALETTE[heat_level(activity)];
+            for _ in 0..cell_px {
+                line.extend_from_slice(&color);
+            }
+        }
+        for _ in 0..cell_px {
+            pixels.extend_from_slice(&line);
+        }
+    }
+    pixels
+}
+
+/// 把面板保存为 PNG, 每个细胞占 `cell_px` x `cell_px` 像素
+#[cfg(feature = "png")]
+pub fn export_png(universe: &Universe, cell_px: u32, path: &Path) -> io::Result<()> {
+    assert!(cell_px > 0, "cell_px must be positive");
+    let file = BufWriter::new(File::create(path)?);
+    let mut encoder = png::Encoder::new(
+        file,
+        universe.width() * cell_px,
+        universe.height() * cell_px,
+    );
+    encoder.set_color(png::ColorType::Grayscale);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(&render_gray(universe, cell_px))?;
+    writer.finish()?;
+    Ok(())
+}
+
+/// 把活跃度的热度图保存为彩色 PNG, 见 `render_heat`
+#[cfg(feature = "png")]
+pub fn export_heat_png(universe: &Universe, cell_px: u32, path: &Path) -> io::Result<()> {
+    assert!(cell_px > 0, "cell_px must be positive");
+    let file = BufWriter::new(File::create(path)?);
+    let mut encoder = png::Encoder::new(
+        file,
+        universe.width() * cell_px,
+        universe.height() * cell_px,
+    );
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(&render_heat(universe, cell_px))?;
+    writer.finish()?;
+    Ok(())
+}
+
+/// 生成 SVG 文本: 白色背景, 每一行连续的活细胞合并成一个黑色 `<rect>`.
+/// `grid` 为 true 时再画上格子之间的细线
+pub fn render_svg(universe: &Universe, cell_px: u32, grid: bool) -> String {
+    let (width, height) = (universe.width(), universe.height());
+    let (px_w, px_h) = (width * cell_px, height * cell_px);
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{0}" height="{1}" viewBox="0 0 {0} {1}">"#,
+        px_w, px_h
+    );
+    let _ = writeln!(
+        out,
+        r#"<rect width="{}" height="{}" fill="white"/>"#,
+        px_w, px_h
+    );
+    for (row, cells) in universe.cells().chunks(width as usize).enumerate() {
+        let mut col = 0;
+        while col < cells.len() {
+            if cells[col] != Cell::Alive {
+                col += 1;
+                continue;
+            }
+            let start = col;
+            while col < cells.len() && cells[col] == Cell::Alive {
+                col += 1;
+            }
+            let _ = writeln!(
+                out,
+                r#"<rect x="{}" y="{}" width="{}" height="{}" fill="black"/>"#,
+                start as u32 * cell_px,
+                row as u32 * cell_px,
+                (col - start) as u32 * cell_px,
+                cell_px
+            );
+        }
+    }
+    if grid {
+        let mut d = String::new();
+        for c in 0..=width {
+            let _ = write!(d, "M{} 0V{}", c * cell_px, px_h);
+        }
+        for r in 0..=height {
+            let _ = write!(d, "M0 {}H{}", r * cell_px, px_w);
+        }
+        let _ = writeln!(
+            out,
+            r#"<path d="{}" stroke="silver" stroke-width="0.5" fill="none"/>"#,
+            d
+        );
+    }
+    out.push_str("</svg>\n");
+    out
+}
+
+/// 把面板保存为 SVG, 每个细胞占 `cell_px` 个单位, 不画网格
+pub fn export_svg(universe: &Universe, cell_px: u32, path: &Path) -> io::Result<()> {
+    fs::write(path, render_svg(universe, cell_px, false))
+}
@@ -0,0 +1,159 @@
+//! 把一段运行录制成 GIF 动画. 每一代记录一帧, 与计时器的快慢无关
+
+use std::borrow::Cow;
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use gif::{Encoder, EncodingError, Frame, Repeat};
+
+use super::scale;
+use crate::universe::{pack_cells, unpack_cells, Universe};
+
+/// 调色板: 0 为白色的死细胞, 1 为黑色的活细胞
+const PALETTE: [u8; 6] = [255, 255, 255, 0, 0, 0];
+
+#[derive(Debug)]
+pub enum RecordError {
+    Encoding(EncodingError),
+    /// 没有录到任何帧
+    NoFrames,
+    /// 放大后的图像超出 GIF 的 65535 像素上限
+    TooLarge {
+        width: u32,
+        height: u32,
+    },
+}
+
+impl fmt::Display for RecordError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RecordError::Encoding(e) => write!(f, "{}", e),
+            RecordError::NoFrames => write!(f, "nothing was recorded"),
+            RecordError::TooLarge { width, height } => {
+                write!(f, "{}x{} pixels is too large for a GIF", width, height)
+            }
+        }
+    }
+}
+
+impl Error for RecordError {}
+
+impl From<EncodingError> for RecordError {
+    fn from(e: EncodingError) -> RecordError {
+        RecordError::Encoding(e)
+    }
+}
+
+impl From<std::io::Error> for RecordError {
+    fn from(e: std::io::Error) -> RecordError {
+        RecordError::Encoding(e.into())
+    }
+}
+
+/// GIF 录制器. `start` 之后每次 `capture` 遇到新的一代就存一帧按位压缩的面板,
+/// 达到 `max_frames` 后不再增加
+pub struct Recorder {
+    cell_px: u32,
+    /// 每帧的停留时间, 单位 1/100 秒
+    frame_delay: u16,
+    max_frames: usize,
+    recording: bool,
+    width: u32,
+    height: u32,
+    last_generation: i64,
+    frames: Vec<Vec<u8>>,
+}
+
+impl Recorder {
+    pub fn new(cell_px: u32, frame_delay: u16, max_frames: usize) -> Recorder {
+        assert!(cell_px > 0, "cell_px must be positive");
+        Recorder {
+            cell_px,
+            frame_delay,
+            max_frames,
+            recording: false,
+            width: 0,
+            height: 0,
+            last_generation: 0,
+            frames: Vec::new(),
+        }
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.recording
+    }
+
+    pub fn frame_count(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// 丢弃之前的帧, 以当前面板作为第一帧开始录制
+    pub fn start(&mut self, universe: &Universe) {
+        self.recording = true;
+        self.width = universe.width();
+        self.height = universe.height();
+        self.frames.clear();
+        self.push(universe);
+    }
+
+    /// 面板进入新的一代时记录一帧. 同一代重复调用, 或者面板尺寸变了都会被忽略
+    pub fn capture(&mut self, universe: &Universe) -> bool {
+        if !self.recording
+            || universe.count() == self.last_generation
+            || universe.width() != self.width
+            || universe.height() != self.height
+            || self.frames.len() >= self.max_frames
+        {
+            return false;
+        }
+        self.push(universe);
+        true
+    }
+
+    fn push(&mut self, universe: &Universe) {
+        self.last_generation = universe.count();
+        self.frames.push(pack_cells(universe.cells()));
+    }
+
+    /// 停止录制并把录到的帧编码成 GIF, 之后清空
+    pub fn finish<W: Write>(&mut self, out: W) -> Result<usize, RecordError> {
+        self.recording = false;
+        let frames = std::mem::take(&mut self.frames);
+        if frames.is_empty() {
+            return Err(RecordError::NoFrames);
+        }
+        let (width, height) = (self.width * self.cell_px, self.height * self.cell_px);
+        if width > u32::from(u16::MAX) || height > u32::from(u16::MAX) {
+            return Err(RecordError::TooLarge { width, height });
+        }
+
+        let mut encoder = Encoder::new(out, width as u16, height as u16, &PALETTE)?;
+        encoder.set_repeat(Repeat::Infinite)?;
+        let len = (self.width * self.height) as usize;
+        for packed in &frames {
+            let cells = unpack_cells(packed, len);
+            let frame = Frame {
+                width: width as u16,
+                height: height as u16,
+                delay: self.frame_delay,
+                buffer: Cow::Owned(scale(&cells, self.width, self.cell_px, 1, 0)),
+                ..Frame::default()
+            };
+            encoder.write_frame(&frame)?;
+        }
+        Ok(frames.len())
+    }
+
+    /// 同 `finish`, 写到文件
+    pub fn finish_to_file(&mut self, path: &Path) -> Result<usize, RecordError> {
+        if self.frames.is_empty() {
+            self.recording = false;
+            return Err(RecordError::NoFrames);
+        }
+        let file = BufWriter::new(File::create(path)?);
+        self.finish(file)
+    }
+}
@@ -0,0 +1,87 @@
+//! Life 1.06 格式: `#Life 1.06` 头, 之后每行一个活细胞的 `x y` 坐标
+
+use std::convert::TryFrom;
+use std::error::Error;
+use std::fmt;
+
+use super::Pattern;
+
+pub const HEADER: &str = "#Life 1.06";
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Life106Error {
+    /// 第一行不是 `#Life 1.06`
+    MissingHeader,
+    /// 无法解析的坐标行, 行号从 1 开始
+    BadLine { line: usize },
+    /// 坐标的范围超出 u32
+    TooLarge,
+}
+
+impl fmt::Display for Life106Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Life106Error::MissingHeader => write!(f, "missing `{}` header", HEADER),
+            Life106Error::BadLine { line } => write!(f, "malformed coordinates on line {}", line),
+            Life106Error::TooLarge => write!(f, "pattern is too large"),
+        }
+    }
+}
+
+impl Error for Life106Error {}
+
+/// 文本是否以 Life 1.06 的头开始
+pub fn is_life106(text: &str) -> bool {
+    text.trim_start().starts_with(HEADER)
+}
+
+/// 解析 Life 1.06 文本, 坐标平移到以 0 为起点
+pub fn parse(text: &str) -> Result<Pattern, Life106Error> {
+    let mut lines = text.lines().enumerate();
+    match lines.next() {
+        Some((_, line)) if line.trim() == HEADER => {}
+        _ => return Err(Life106Error::MissingHeader),
+    }
+    let mut points = Vec::new();
+    for (i, line) in lines {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let mut parts = line.split_whitespace().map(|p| p.parse::<i64>());
+        match (parts.next(), parts.next(), parts.next()) {
+            (Some(Ok(x)), Some(Ok(y)), None) => points.push((y, x)),
+            _ => return Err(Life106Error::BadLine { line: i + 1 }),
+        }
+    }
+
+    let mut pattern = Pattern::default();
+    let min_row = points.iter().map(|p| p.0).min().unwrap_or(0);
+    let min_col = points.iter().map(|p| p.1).min().unwrap_or(0);
+    // 相对外接矩形的偏移, 以及偏移加一后的宽高都要放得进 u32
+    let offset = |v: i64, min: i64| -> Option<(u32, u32)> {
+        let d = u32::try_from(v.checked_sub(min)?).ok()?;
+        Some((d, d.checked_add(1)?))
+    };
+    for &(r, c) in &points {
+        let (row, bottom) = offset(r, min_row).ok_or(Life106Error::TooLarge)?;
+        let (col, right) = offset(c, min_col).ok_or(Life106Error::TooLarge)?;
+        pattern.height = pattern.height.max(bottom);
+        pattern.width = pattern.width.max(right);
+        pattern.cells.push((row, col));
+    }
+    pattern.cells.sort_unstable();
+    pattern.cells.dedup();
+    Ok(pattern)
+}
+
+/// 把图案写成 Life 1.06 文本, 坐标相对于活细胞的外接矩形
+pub fn serialize(pattern: &Pattern) -> String {
+    let min_row = pattern.cells.iter().map(|p| p.0).min().unwrap_or(0);
+    let min_col = pattern.cells.iter().map(|p| p.1).min().unwrap_or(0);
+    let mut out = format!("{}\n", HEADER);
+    for &(r, c) in &pattern.cells {
+        out.push_str(&format!("{} {}\n", c - min_col, r - min_row));
+    }
+    out
+}
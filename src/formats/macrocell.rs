@@ -0,0 +1,427 @@
+//! Golly 的 Macrocell (.mc) 格式: 把宇宙存成四叉树, 相同的子树只写一次.
+//!
+//! 第一行是 `[M2]` 头, `#` 开头的是注释或元数据. 之后每行定义一个节点, 编号从 1 开始:
+//! 8x8 的叶子用 `.`/`*` 表示一行里的细胞、`$` 结束一行; 内部节点写成
+//! `level nw ne sw se`, 子节点引用之前的编号, 0 表示全空.
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+
+use super::Pattern;
+use crate::universe::{Cell, Universe};
+
+/// 叶子节点的层数, 边长 2^3 = 8
+const LEAF_LEVEL: u32 = 3;
+/// 坐标用 u64 表示, 更高的层数无法展开
+const MAX_LEVEL: u32 = 62;
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum MacrocellError {
+    /// 第一行不是 `[M2]` 头
+    MissingHeader,
+    /// 叶子超出 8x8 或含有无法识别的字符, 行号从 1 开始
+    BadLeaf { line: usize },
+    /// 内部节点格式错误, 或引用了未定义、层数不对的子节点
+    BadNode { line: usize },
+    /// 活细胞的范围或个数超出 u32 的面板, 或者活细胞数超出 u64
+    TooLarge,
+}
+
+impl fmt::Display for MacrocellError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MacrocellError::MissingHeader => write!(f, "missing `[M2]` header"),
+            MacrocellError::BadLeaf { line } => write!(f, "malformed leaf on line {}", line),
+            MacrocellError::BadNode { line } => write!(f, "malformed node on line {}", line),
+            MacrocellError::TooLarge => write!(f, "pattern is too large"),
+        }
+    }
+}
+
+impl Error for MacrocellError {}
+
+/// `read_universe` 时有活细胞落在面板外面被丢弃
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Cropped {
+    pub dropped: u64,
+}
+
+impl fmt::Display for Cropped {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} live cells outside the board were dropped",
+            self.dropped
+        )
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Hash)]
+enum Node {
+    /// 每个字节是一行, 最高位是第 0 列
+    Leaf([u8; 8]),
+    Inner {
+        level: u32,
+        children: [usize; 4],
+    },
+}
+
+impl Node {
+    fn level(&self) -> u32 {
+        match self {
+            Node::Leaf(_) => LEAF_LEVEL,
+            Node::Inner { level, .. } => *level,
+        }
+    }
+}
+
+/// 子树里活细胞的外接矩形 (min_row, min_col, max_row, max_col), 相对子树的左上角, 含两端
+type Bounds = (u64, u64, u64, u64);
+
+/// 解析出的一行: 节点、它的活细胞数和 `Bounds`
+type Parsed = (Node, u64, Option<Bounds>);
+
+/// 解析出的四叉树, `nodes[i - 1]` 是编号为 i 的节点
+struct Tree {
+    nodes: Vec<Node>,
+    population: Vec<u64>,
+    /// 每个节点的 `Bounds`, 全空的节点为 None. 不用展开整棵树就知道图案有多大
+    bounds: Vec<Option<Bounds>>,
+    pattern: Pattern,
+}
+
+impl Tree {
+    /// 最后定义的节点是根, 文件里没有节点时为 None
+    fn root(&self) -> Option<usize> {
+        if self.nodes.is_empty() {
+            None
+        } else {
+            Some(self.nodes.len())
+        }
+    }
+
+    /// 展开编号 `index` 的子树, 左上角位于 (`row`, `col`). 完全落在 `limit` 之外的子树
+    /// 直接跳过, 只统计其中的活细胞数
+    fn collect(
+        &self,
+        index: usize,
+        row: u64,
+        col: u64,
+        limit: (u64, u64),
+        out: &mut Vec<(u64, u64)>,
+        dropped: &mut u64,
+    ) {
+        if index == 0 {
+            return;
+        }
+        if row >= limit.0 || col >= limit.1 {
+            *dropped += self.population[index - 1];
+            return;
+        }
+        match &self.nodes[index - 1] {
+            Node::Leaf(rows) => {
+                for (r, bits) in rows.iter().enumerate() {
+                    for c in 0..8 {
+                        if bits & (0x80 >> c) == 0 {
+                            continue;
+                        }
+                        let cell = (row + r as u64, col + c as u64);
+                        if cell.0 < limit.0 && cell.1 < limit.1 {
+                            out.push(cell);
+                        } else {
+                            *dropped += 1;
+                        }
+                    }
+                }
+            }
+            Node::Inner { level, children } => {
+                let half = 1u64 << (level - 1);
+                let [nw, ne, sw, se] = *children;
+                self.collect(nw, row, col, limit, out, dropped);
+                self.collect(ne, row, col + half, limit, out, dropped);
+                self.collect(sw, row + half, col, limit, out, dropped);
+                self.collect(se, row + half, col + half, limit, out, dropped);
+            }
+        }
+    }
+}
+
+fn parse_tree(text: &str) -> Result<Tree, MacrocellError> {
+    let mut lines = text.lines().enumerate();
+    match lines.next() {
+        Some((_, line)) if line.trim_start().starts_with("[M2]") => {}
+        _ => return Err(MacrocellError::MissingHeader),
+    }
+    let mut tree = Tree {
+        nodes: Vec::new(),
+        population: Vec::new(),
+        bounds: Vec::new(),
+        pattern: Pattern::default(),
+    };
+    for (i, line) in lines {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        if let Some(meta) = line.strip_prefix('#') {
+            let value = meta.get(1..).unwrap_or("").trim().to_string();
+            match meta.chars().next() {
+                Some('R') => tree.pattern.rule = Some(value),
+                Some('N') => tree.pattern.name = Some(value),
+                Some('C') | Some('D') => tree.pattern.comments.push(value),
+                _ => {}
+            }
+            continue;
+        }
+        let (node, population, bounds) = if line.starts_with(|c: char| c.is_ascii_digit()) {
+            parse_inner(line, &tree).ok_or(MacrocellError::BadNode { line: i + 1 })??
+        } else {
+            parse_leaf(line).ok_or(MacrocellError::BadLeaf { line: i + 1 })?
+        };
+        tree.nodes.push(node);
+        tree.population.push(population);
+        tree.bounds.push(bounds);
+    }
+    Ok(tree)
+}
+
+fn parse_leaf(line: &str) -> Option<Parsed> {
+    let mut rows = [0u8; 8];
+    let (mut row, mut col) = (0, 0);
+    for ch in line.chars() {
+        match ch {
+            '.' | '*' if row < 8 && col < 8 => {
+                if ch == '*' {
+                    rows[row] |= 0x80 >> col;
+                }
+                col += 1;
+            }
+            '$' => {
+                row += 1;
+                col = 0;
+            }
+            _ => return None,
+        }
+    }
+    let population = rows.iter().map(|r| u64::from(r.count_ones())).sum();
+    let mut bounds: Option<Bounds> = None;
+    for (r, &bits) in rows.iter().enumerate() {
+        if bits == 0 {
+            continue;
+        }
+        let (first, last) = (
+            bits.leading_zeros() as u64,
+            7 - bits.trailing_zeros() as u64,
+        );
+        let r = r as u64;
+        bounds = Some(match bounds {
+            Some((r0, c0, _, c1)) => (r0, c0.min(first), r, c1.max(last)),
+            None => (r, first, r, last),
+        });
+    }
+    Some((Node::Leaf(rows), population, bounds))
+}
+
+/// 格式不对时返回 None, 活细胞数加起来超出 u64 时返回 `TooLarge`
+fn parse_inner(line: &str, tree: &Tree) -> Option<Result<Parsed, MacrocellError>> {
+    let fields: Vec<usize> = line
+        .split_whitespace()
+        .map(|f| f.parse().ok())
+        .collect::<Option<_>>()?;
+    if fields.len() != 5 {
+        return None;
+    }
+    let level = fields[0] as u32;
+    if level <= LEAF_LEVEL || level > MAX_LEVEL {
+        return None;
+    }
+    let half = 1u64 << (level - 1);
+    let offsets = [(0, 0), (0, half), (half, 0), (half, half)];
+    let mut children = [0; 4];
+    let mut population = 0u64;
+    let mut bounds: Option<Bounds> = None;
+    for ((slot, &child), &(dr, dc)) in children.iter_mut().zip(&fields[1..]).zip(&offsets) {
+        if child != 0 {
+            if tree.nodes.get(child - 1)?.level() != level - 1 {
+                return None;
+            }
+            population = match population.checked_add(tree.population[child - 1]) {
+                Some(sum) => sum,
+                None => return Some(Err(MacrocellError::TooLarge)),
+            };
+            if let Some((r0, c0, r1, c1)) = tree.bounds[child - 1] {
+                let (r0, c0, r1, c1) = (r0 + dr, c0 + dc, r1 + dr, c1 + dc);
+                bounds = Some(match bounds {
+                    Some((a0, b0, a1, b1)) => (a0.min(r0), b0.min(c0), a1.max(r1), b1.max(c1)),
+                    None => (r0, c0, r1, c1),
+                });
+            }
+        }
+        *slot = child;
+    }
+    Some(Ok((Node::Inner { level, children }, population, bounds)))
+}
+
+/// 解析 Macrocell 文本, 活细胞坐标平移到以外接矩形的左上角为起点.
+/// 外接矩形的宽高或活细胞数超出 u32 时不展开, 直接返回 `TooLarge`
+pub fn parse(text: &str) -> Result<Pattern, MacrocellError> {
+    let tree = parse_tree(text)?;
+    let (root, (min_row, min_col, max_row, max_col)) = match tree
+        .root()
+        .and_then(|root| Some((root, tree.bounds[root - 1]?)))
+    {
+        Some(found) => found,
+        None => return Ok(tree.pattern),
+    };
+    let limit = u64::from(u32::MAX);
+    let (height, width) = (max_row - min_row + 1, max_col - min_col + 1);
+    if height > limit || width > limit || tree.population[root - 1] > limit {
+        return Err(MacrocellError::TooLarge);
+    }
+    let mut points = Vec::new();
+    tree.collect(root, 0, 0, (max_row + 1, max_col + 1), &mut points, &mut 0);
+    let mut pattern = tree.pattern;
+    pattern.height = height as u32;
+    pattern.width = width as u32;
+    pattern.cells = points
+        .into_iter()
+        .map(|(r, c)| ((r - min_row) as u32, (c - min_col) as u32))
+        .collect();
+    pattern.cells.sort_unstable();
+    Ok(pattern)
+}
+
+/// 把 Macrocell 文本读进 `width` x `height` 的面板, 四叉树的左上角对齐面板的左上角.
+/// 超出面板的活细胞被丢弃, 此时返回 `Cropped` 作为警告而不是报错
+pub fn read_universe(
+    text: &str,
+    width: u32,
+    height: u32,
+) -> Result<(Universe, Option<Cropped>), MacrocellError> {
+    let tree = parse_tree(text)?;
+    let mut points = Vec::new();
+    let mut dropped = 0;
+    if let Some(root) = tree.root() {
+        let limit = (u64::from(height), u64::from(width));
+        tree.collect(root, 0, 0, limit, &mut points, &mut dropped);
+    }
+    let mut cells = vec![Cell::Dead; width as usize * height as usize];
+    for (r, c) in points {
+        cells[r as usize * width as usize + c as usize] = Cell::Alive;
+    }
+    let universe =
+        Universe::from_cells(width, height, cells).expect("cell count matches the board size");
+    let cropped = if dropped > 0 {
+        Some(Cropped { dropped })
+    } else {
+        None
+    };
+    Ok((universe, cropped))
+}
+
+/// 写 Macrocell 时去重节点用
+struct Builder {
+    nodes: Vec<Node>,
+    index: HashMap<Node, usize>,
+}
+
+impl Builder {
+    /// 为左上角在原点、层数为 `level` 的正方形建节点, `cells` 是其中活细胞的相对坐标.
+    /// 返回节点编号, 全空返回 0
+    fn build(&mut self, level: u32, cells: Vec<(u64, u64)>) -> usize {
+        if cells.is_empty() {
+            return 0;
+        }
+        let node = if level == LEAF_LEVEL {
+            let mut rows = [0u8; 8];
+            for (r, c) in cells {
+                rows[r as usize] |= 0x80 >> c;
+            }
+            Node::Leaf(rows)
+        } else {
+            let half = 1u64 << (level - 1);
+            let mut quads: [Vec<(u64, u64)>; 4] = Default::default();
+            for (r, c) in cells {
+                let q = (r >= half) as usize * 2 + (c >= half) as usize;
+                quads[q].push((r % half, c % half));
+            }
+            let mut children = [0; 4];
+            for (slot, quad) in children.iter_mut().zip(quads.iter_mut()) {
+                *slot = self.build(level - 1, std::mem::take(quad));
+            }
+            Node::Inner { level, children }
+        };
+        if let Some(&i) = self.index.get(&node) {
+            return i;
+        }
+        self.nodes.push(node.clone());
+        self.index.insert(node, self.nodes.len());
+        self.nodes.len()
+    }
+}
+
+fn write_tree(pattern: &Pattern, side: u64, cells: Vec<(u64, u64)>) -> String {
+    let mut level = LEAF_LEVEL;
+    while (1u64 << level) < side {
+        level += 1;
+    }
+    let mut builder = Builder {
+        nodes: Vec::new(),
+        index: HashMap::new(),
+    };
+    builder.build(level, cells);
+
+    let mut out = String::from("[M2] (game_life)\n");
+    if let Some(name) = &pattern.name {
+        out.push_str(&format!("#N {}\n", name));
+    }
+    for comment in &pattern.comments {
+        out.push_str(&format!("#C {}\n", comment));
+    }
+    out.push_str(&format!(
+        "#R {}\n",
+        pattern.rule.as_deref().unwrap_or("B3/S23")
+    ));
+    for node in &builder.nodes {
+        match node {
+            Node::Leaf(rows) => {
+                let used = rows.iter().rposition(|&r| r != 0).map_or(0, |r| r + 1);
+                for bits in &rows[..used] {
+                    let width = 8 - bits.trailing_zeros() as usize;
+                    for c in 0..width {
+                        out.push(if bits & (0x80 >> c) != 0 { '*' } else { '.' });
+                    }
+                    out.push('$');
+                }
+            }
+            Node::Inner { level, children } => {
+                let [nw, ne, sw, se] = children;
+                out.push_str(&format!("{} {} {} {} {}", level, nw, ne, sw, se));
+            }
+        }
+        out.push('\n');
+    }
+    out
+}
+
+/// 把图案写成 Macrocell 文本, 图案的左上角对齐四叉树的左上角
+pub fn serialize(pattern: &Pattern) -> String {
+    let side = u64::from(pattern.width.max(pattern.height));
+    let cells = pattern
+        .cells
+        .iter()
+        .map(|&(r, c)| (u64::from(r), u64::from(c)))
+        .collect();
+    write_tree(pattern, side, cells)
+}
+
+/// 把整个面板写成 Macrocell 文本, 与 `read_universe` 对应
+pub fn write_universe(universe: &Universe) -> String {
+    let side = u64::from(universe.width().max(universe.height()));
+    let cells = universe
+        .live_cells()
+        .map(|(r, c)| (u64::from(r), u64::from(c)))
+        .collect();
+    write_tree(&Pattern::default(), side, cells)
+}
@@ -0,0 +1,203 @@
+//! 图案文件格式的读写, 各种格式都解析成同一个 `Pattern`
+
+pub mod life106;
+pub mod macrocell;
+pub mod plaintext;
+pub mod rle;
+
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::universe::{Universe, UniverseError};
+
+use self::life106::Life106Error;
+use self::macrocell::{Cropped, MacrocellError};
+use self::plaintext::PlaintextError;
+use self::rle::RleError;
+
+#[derive(Debug)]
+pub enum FormatError {
+    Io(io::Error),
+    Rle(RleError),
+    Plaintext(PlaintextError),
+    Life106(Life106Error),
+    Macrocell(MacrocellError),
+    /// 无法从扩展名判断文件格式
+    UnknownFormat(String),
+}
+
+impl fmt::Display for FormatError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FormatError::Io(e) => write!(f, "{}", e),
+            FormatError::Rle(e) => write!(f, "RLE: {}", e),
+            FormatError::Plaintext(e) => write!(f, "plaintext: {}", e),
+            FormatError::Life106(e) => write!(f, "Life 1.06: {}", e),
+            FormatError::Macrocell(e) => write!(f, "macrocell: {}", e),
+            FormatError::UnknownFormat(name) => write!(f, "unknown pattern format: {}", name),
+        }
+    }
+}
+
+impl Error for FormatError {}
+
+impl From<io::Error> for FormatError {
+    fn from(e: io::Error) -> FormatError {
+        FormatError::Io(e)
+    }
+}
+
+impl From<RleError> for FormatError {
+    fn from(e: RleError) -> FormatError {
+        FormatError::Rle(e)
+    }
+}
+
+impl From<PlaintextError> for FormatError {
+    fn from(e: PlaintextError) -> FormatError {
+        FormatError::Plaintext(e)
+    }
+}
+
+impl From<Life106Error> for FormatError {
+    fn from(e: Life106Error) -> FormatError {
+        FormatError::Life106(e)
+    }
+}
+
+impl From<MacrocellError> for FormatError {
+    fn from(e: MacrocellError) -> FormatError {
+        FormatError::Macrocell(e)
+    }
+}
+
+/// 按扩展名选择解析器. .lif 和 .life 可能是不同版本的 Life 格式, 根据文件头判断
+pub fn parse_by_extension(path: &Path, text: &str) -> Result<Pattern, FormatError> {
+    let ext = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_ascii_lowercase());
+    match ext.as_deref() {
+        Some("rle") => Ok(rle::parse_rle(text)?),
+        Some("cells") => Ok(plaintext::parse(text)?),
+        Some("mc") => Ok(macrocell::parse(text)?),
+        Some("lif") | Some("life") if life106::is_life106(text) => Ok(life106::parse(text)?),
+        _ => Err(FormatError::UnknownFormat(path.display().to_string())),
+    }
+}
+
+/// 解析没有文件名的文本, 例如剪贴板内容: 先按 RLE, 第一行不是 RLE 头时再按 plaintext
+pub fn parse_text(text: &str) -> Result<Pattern, FormatError> {
+    match rle::parse_rle(text) {
+        Err(RleError::MissingHeader) | Err(RleError::BadHeader(_)) => Ok(plaintext::parse(text)?),
+        result => Ok(result?),
+    }
+}
+
+/// 读取图案文件, 格式由扩展名决定
+pub fn load_file(path: &Path) -> Result<Pattern, FormatError> {
+    let text = fs::read_to_string(path)?;
+    parse_by_extension(path, &text)
+}
+
+/// 读取要放到 `width` x `height` 面板上的图案文件. Macrocell 文件放不下时不整个展开,
+/// 只展开面板范围内的部分, 返回的图案和面板一样大, 同时返回被裁掉的活细胞数
+pub fn load_board_file(
+    path: &Path,
+    width: u32,
+    height: u32,
+) -> Result<(Pattern, Option<Cropped>), FormatError> {
+    let text = fs::read_to_string(path)?;
+    let ext = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_ascii_lowercase());
+    if ext.as_deref() != Some("mc") {
+        return Ok((parse_by_extension(path, &text)?, None));
+    }
+    match macrocell::parse(&text) {
+        Ok(p) if p.width <= width && p.height <= height => return Ok((p, None)),
+        Ok(_) | Err(MacrocellError::TooLarge) => {}
+        Err(e) => return Err(e.into()),
+    }
+    let (universe, cropped) = macrocell::read_universe(&text, width, height)?;
+    let pattern = Pattern {
+        width,
+        height,
+        cells: universe.live_cells().collect(),
+        ..Pattern::default()
+    };
+    Ok((pattern, cropped))
+}
+
+/// 与具体格式无关的图案: 尺寸加上活细胞相对左上角的 (row, col) 偏移
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct Pattern {
+    pub width: u32,
+    pub height: u32,
+    pub cells: Vec<(u32, u32)>,
+    /// 文件中声明的规则, 例如 "B3/S23"
+    pub rule: Option<String>,
+    pub name: Option<String>,
+    pub comments: Vec<String>,
+}
+
+impl Pattern {
+    /// 取出宇宙中的所有活细胞, 裁剪到允许跨越接缝的最小外接矩形
+    pub fn from_universe(universe: &Universe) -> Pattern {
+        let region = match universe.wrapped_bounding_box() {
+            Some(region) => region,
+            None => return Pattern::default(),
+        };
+        let (width, height) = (universe.width(), universe.height());
+        let mut cells: Vec<(u32, u32)> = universe
+            .live_cells()
+            .map(|(r, c)| {
+                (
+                    (r + height - region.row) % height,
+                    (c + width - region.col) % width,
+                )
+            })
+            .collect();
+        cells.sort_unstable();
+        Pattern {
+            width: region.width,
+            height: region.height,
+            cells,
+            ..Pattern::default()
+        }
+    }
+
+    /// 把图案放到宇宙中以 (`row`, `col`) 为左上角的位置, 参见 `Universe::insert_pattern`
+    pub fn stamp(
+        &self,
+        universe: &mut Universe,
+        row: u32,
+        col: u32,
+        wrap: bool,
+    ) -> Result<(), UniverseError> {
+        universe.insert_pattern(&self.cells, row, col, wrap)
+    }
+
+    /// 把图案放到宇宙中, 左上角在 `origin`, 没有给出时居中. 超出边界的部分绕到另一侧;
+    /// 图案比宇宙大时返回错误. 返回实际使用的左上角
+    pub fn place(
+        &self,
+        universe: &mut Universe,
+        origin: Option<(u32, u32)>,
+    ) -> Result<(u32, u32), UniverseError> {
+        let (width, height) = (universe.width(), universe.height());
+        if self.width > width || self.height > height {
+            return Err(UniverseError::PatternTooLarge {
+                pattern: (self.width, self.height),
+                board: (width, height),
+            });
+        }
+        let (row, col) = origin.unwrap_or(((height - self.height) / 2, (width - self.width) / 2));
+        universe.insert_pattern(&self.cells, row % height, col % width, true)?;
+        Ok((row % height, col % width))
+    }
+}
@@ -0,0 +1,75 @@
+//! 纯文本 .cells 格式, `.` 为死细胞, `O` 为活细胞, `!` 开头的行是注释
+
+use std::error::Error;
+use std::fmt;
+
+use super::Pattern;
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum PlaintextError {
+    /// 出现了不认识的字符, 行号从 1 开始
+    UnexpectedChar { line: usize, ch: char },
+}
+
+impl fmt::Display for PlaintextError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PlaintextError::UnexpectedChar { line, ch } => {
+                write!(f, "unexpected character {:?} on line {}", ch, line)
+            }
+        }
+    }
+}
+
+impl Error for PlaintextError {}
+
+/// 解析 .cells 文本, 长短不一的行用死细胞补齐
+pub fn parse(text: &str) -> Result<Pattern, PlaintextError> {
+    let mut pattern = Pattern::default();
+    let mut rows = 0;
+    let mut width = 0;
+    for (i, line) in text.lines().enumerate() {
+        if let Some(comment) = line.strip_prefix('!') {
+            match comment.strip_prefix("Name:") {
+                Some(name) => pattern.name = Some(name.trim().to_string()),
+                None => pattern.comments.push(comment.trim().to_string()),
+            }
+            continue;
+        }
+        let line = line.trim_end();
+        for (col, ch) in line.chars().enumerate() {
+            match ch {
+                '.' => {}
+                'O' | '*' => pattern.cells.push((rows, col as u32)),
+                _ => return Err(PlaintextError::UnexpectedChar { line: i + 1, ch }),
+            }
+        }
+        width = width.max(line.chars().count() as u32);
+        rows += 1;
+        if !line.is_empty() {
+            pattern.height = rows;
+        }
+    }
+    pattern.width = width;
+    Ok(pattern)
+}
+
+/// 把图案写成 .cells 文本, 每行补齐到图案宽度
+pub fn serialize(pattern: &Pattern) -> String {
+    let mut out = String::new();
+    if let Some(name) = &pattern.name {
+        out.push_str(&format!("!Name: {}\n", name));
+    }
+    for comment in &pattern.comments {
+        out.push_str(&format!("!{}\n", comment));
+    }
+    let mut grid = vec![vec!['.'; pattern.width as usize]; pattern.height as usize];
+    for &(r, c) in &pattern.cells {
+        grid[r as usize][c as usize] = 'O';
+    }
+    for row in grid {
+        out.extend(row);
+        out.push('\n');
+    }
+    out
+}
@@ -0,0 +1,224 @@
+//! RLE 格式, 参见 <https://conwaylife.com/wiki/Run_Length_Encoded>
+
+use std::error::Error;
+use std::fmt;
+
+use super::Pattern;
+use crate::rule::Rule;
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum RleError {
+    /// 没有 `x = .., y = ..` 头
+    MissingHeader,
+    /// 头的格式不对
+    BadHeader(String),
+    /// 头里声明的规则不是合法的 B/S 规则
+    UnsupportedRule(String),
+    /// 图案数据里出现了不认识的字符, 行号从 1 开始
+    UnexpectedChar { line: usize, ch: char },
+    /// 重复次数太大
+    BadCount { line: usize },
+    /// 细胞超出了头里声明的范围
+    OutOfExtent { row: u32, col: u32 },
+    /// 缺少结尾的 `!`
+    MissingTerminator,
+}
+
+impl fmt::Display for RleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RleError::MissingHeader => write!(f, "missing `x = .., y = ..` header line"),
+            RleError::BadHeader(line) => write!(f, "malformed header line: {}", line),
+            RleError::UnsupportedRule(rule) => write!(f, "unsupported rule: {}", rule),
+            RleError::UnexpectedChar { line, ch } => {
+                write!(f, "unexpected character {:?} on line {}", ch, line)
+            }
+            RleError::BadCount { line } => write!(f, "run count too large on line {}", line),
+            RleError::OutOfExtent { row, col } => write!(
+                f,
+                "cell ({}, {}) lies outside the extent declared in the header",
+                row, col
+            ),
+            RleError::MissingTerminator => write!(f, "pattern is not terminated with `!`"),
+        }
+    }
+}
+
+impl Error for RleError {}
+
+fn parse_header(line: &str, pattern: &mut Pattern) -> Result<(), RleError> {
+    let bad = || RleError::BadHeader(line.to_string());
+    let (mut width, mut height) = (None, None);
+    let mut rest = line;
+    while !rest.trim().is_empty() {
+        let (part, tail) = rest.split_once(',').unwrap_or((rest, ""));
+        let mut kv = part.splitn(2, '=');
+        let key = kv.next().unwrap_or("").trim();
+        let value = kv.next().ok_or_else(bad)?.trim();
+        match key {
+            "x" => width = Some(value.parse::<u32>().map_err(|_| bad())?),
+            "y" => height = Some(value.parse::<u32>().map_err(|_| bad())?),
+            // Larger than Life 的规则本身带逗号, 所以 rule 占到行尾
+            "rule" => {
+                let (_, value) = rest.split_once('=').ok_or_else(bad)?;
+                pattern.rule = Some(value.trim().to_string());
+                break;
+            }
+            _ => return Err(bad()),
+        }
+        rest = tail;
+    }
+    pattern.width = width.ok_or_else(bad)?;
+    pattern.height = height.ok_or_else(bad)?;
+    Ok(())
+}
+
+pub fn parse_rle(text: &str) -> Result<Pattern, RleError> {
+    let mut pattern = Pattern::default();
+    let mut lines = text.lines().enumerate();
+
+    // 头之前的 # 注释
+    let mut header = None;
+    for (_, line) in &mut lines {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        if let Some(rest) = line.strip_prefix('#') {
+            let mut chars = rest.chars();
+            let kind = chars.next();
+            let body = chars.as_str().trim().to_string();
+            match kind {
+                Some('N') => pattern.name = Some(body),
+                Some('r') => pattern.rule = Some(body),
+                _ => pattern.comments.push(body),
+            }
+            continue;
+        }
+        header = Some(line);
+        break;
+    }
+    parse_header(header.ok_or(RleError::MissingHeader)?, &mut pattern)?;
+    if let Some(rule) = &pattern.rule {
+        if rule.parse::<Rule>().is_err() {
+            return Err(RleError::UnsupportedRule(rule.clone()));
+        }
+    }
+
+    let (mut row, mut col) = (0u32, 0u32);
+    for (i, line) in lines {
+        let line_no = i + 1;
+        if line.trim_start().starts_with('#') {
+            continue;
+        }
+        let mut count: Option<u32> = None;
+        for ch in line.chars() {
+            if let Some(d) = ch.to_digit(10) {
+                let n = count
+                    .unwrap_or(0)
+                    .checked_mul(10)
+                    .and_then(|n| n.checked_add(d))
+                    .ok_or(RleError::BadCount { line: line_no })?;
+                count = Some(n);
+                continue;
+            }
+            let n = count.take().unwrap_or(1);
+            match ch {
+                'b' | '.' => {
+                    col = col
+                        .checked_add(n)
+                        .ok_or(RleError::BadCount { line: line_no })?;
+                    if col > pattern.width {
+                        return Err(RleError::OutOfExtent { row, col: col - 1 });
+                    }
+                }
+                'o' | 'A' => {
+                    for _ in 0..n {
+                        if row >= pattern.height || col >= pattern.width {
+                            return Err(RleError::OutOfExtent { row, col });
+                        }
+                        pattern.cells.push((row, col));
+                        col += 1;
+                    }
+                }
+                '$' => {
+                    row = row
+                        .checked_add(n)
+                        .ok_or(RleError::BadCount { line: line_no })?;
+                    col = 0;
+                }
+                '!' => return Ok(pattern),
+                c if c.is_whitespace() => {}
+                c => {
+                    return Err(RleError::UnexpectedChar {
+                        line: line_no,
+                        ch: c,
+                    })
+                }
+            }
+        }
+    }
+    Err(RleError::MissingTerminator)
+}
+
+/// 把图案写成 RLE 文本, 每行不超过 70 个字符
+pub fn write_rle(pattern: &Pattern) -> String {
+    let mut out = String::new();
+    if let Some(name) = &pattern.name {
+        out.push_str(&format!("#N {}\n", name));
+    }
+    for comment in &pattern.comments {
+        out.push_str(&format!("#C {}\n", comment));
+    }
+    out.push_str(&format!(
+        "x = {}, y = {}, rule = {}\n",
+        pattern.width,
+        pattern.height,
+        pattern.rule.as_deref().unwrap_or("B3/S23")
+    ));
+
+    let mut cells = pattern.cells.clone();
+    cells.sort_unstable();
+    cells.dedup();
+
+    // 先生成 (次数, 符号) 序列, 再按行宽折行
+    fn push(runs: &mut Vec<(u32, char)>, n: u32, ch: char) {
+        if n == 0 {
+            return;
+        }
+        match runs.last_mut() {
+            Some((count, last)) if *last == ch => *count += n,
+            _ => runs.push((n, ch)),
+        }
+    }
+    let mut runs: Vec<(u32, char)> = Vec::new();
+    let (mut row, mut col) = (0, 0);
+    for &(r, c) in &cells {
+        if r > row {
+            push(&mut runs, r - row, '$');
+            row = r;
+            col = 0;
+        }
+        push(&mut runs, c - col, 'b');
+        push(&mut runs, 1, 'o');
+        col = c + 1;
+    }
+    runs.push((1, '!'));
+
+    let mut line_len = 0;
+    for (n, ch) in runs {
+        let token = if n == 1 {
+            ch.to_string()
+        } else {
+            format!("{}{}", n, ch)
+        };
+        if line_len + token.len() > 70 {
+            out.push('\n');
+            line_len = 0;
+        }
+        line_len += token.len();
+        out.push_str(&token);
+    }
+    out.push('\n');
+    out
+}
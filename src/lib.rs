@@ -0,0 +1,22 @@
+pub mod export;
+pub mod formats;
+pub mod patterns;
+pub mod rule;
+pub mod settings;
+pub mod sim;
+pub mod stats;
+pub mod universe;
+pub mod view;
+pub mod watch;
+
+pub use rule::{Neighborhood, Rule, RuleError};
+#[cfg(feature = "image")]
+pub use universe::ImageOptions;
+#[cfg(feature = "json")]
+pub use universe::JsonError;
+pub use universe::{
+    Anchor, Cell, CombineOp, Counters, Engine, FrameSnapshot, HashLifeError, HashLifeUniverse,
+    ParseUniverseError, Region, RunState, SparseError, SparseUniverse, Stability,
+    StabilityDetector, StableResult, StateError, Stepper, Topology, Universe, UniverseBuilder,
+    UniverseError,
+};
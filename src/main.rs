@@ -1,17 +1,28 @@
+#[cfg(windows)]
 #[macro_use]
 extern crate lazy_static;
 #[cfg(windows)]
 extern crate winapi;
 
-use std::ffi::OsStr;
-use std::fmt;
+#[cfg(windows)]
+use std::collections::HashMap;
+#[cfg(windows)]
+use std::ffi::{OsStr, OsString};
+#[cfg(windows)]
 use std::io::Error;
+#[cfg(windows)]
 use std::iter::once;
-use std::os::windows::ffi::OsStrExt;
-use std::sync::RwLock;
+#[cfg(windows)]
+use std::os::windows::ffi::{OsStrExt, OsStringExt};
+#[cfg(windows)]
+use std::path::{Path, PathBuf};
+#[cfg(windows)]
+use std::sync::atomic::{AtomicBool, AtomicI32, AtomicI64, AtomicU32, AtomicUsize, Ordering};
+#[cfg(windows)]
+use std::sync::{Arc, RwLock};
+#[cfg(windows)]
+use std::time::{Duration, Instant};
 
-use rand;
-use rand::Rng;
 #[cfg(windows)]
 use winapi::_core::ptr::null_mut;
 // #[cfg(windows)]
@@ -23,412 +34,4029 @@ use winapi::shared::minwindef::*;
 #[cfg(windows)]
 use winapi::shared::windef::*;
 #[cfg(windows)]
+use winapi::um::commdlg::*;
+#[cfg(windows)]
 use winapi::um::libloaderapi::*;
 #[cfg(windows)]
+use winapi::um::shellapi::*;
+#[cfg(windows)]
+use winapi::um::synchapi::Sleep;
+#[cfg(windows)]
+use winapi::um::winbase::*;
+#[cfg(windows)]
+use winapi::um::timeapi::{timeBeginPeriod, timeEndPeriod};
+#[cfg(windows)]
 use winapi::um::wingdi::*;
 #[cfg(windows)]
 use winapi::um::winuser::*;
 
-const _WIDTH: i32 = 800;
-const _HEIGHT: i32 = 800;
-const CELL_SIZE: i32 = 64;
-const COL_LEN: i32 = _WIDTH / CELL_SIZE as i32;
-const ROW_LEN: i32 = _HEIGHT / CELL_SIZE as i32;
+#[cfg(all(windows, feature = "png"))]
+use life_game::export::{export_heat_png, export_png};
+#[cfg(windows)]
+use life_game::export::{export_svg, heat_level, HEAT_PALETTE};
+#[cfg(all(windows, feature = "gif"))]
+use life_game::export::Recorder;
+#[cfg(windows)]
+use life_game::formats::macrocell::Cropped;
+#[cfg(windows)]
+use life_game::formats::plaintext;
+#[cfg(windows)]
+use life_game::formats::rle::write_rle;
+#[cfg(windows)]
+use life_game::formats::{load_board_file, parse_text, FormatError, Pattern};
+#[cfg(windows)]
+use life_game::patterns::{wireworld_cells, WIREWORLD_CLOCKS_OR};
+#[cfg(windows)]
+use life_game::rule::{
+    self, RuleHistory, FIRE_BURNING, FIRE_TREE, PRESETS, WIRE_CONDUCTOR, WIRE_HEAD, WIRE_TAIL,
+};
+#[cfg(windows)]
+use life_game::settings::{Settings, BLANK_COLOR};
+#[cfg(windows)]
+use life_game::sim::{
+    self, Command, FrameSkip, Jump, SimHandle, Simulation, TickRate, SPEED_LADDER,
+};
+#[cfg(windows)]
+use life_game::stats::{PopulationHistory, StatsLog};
+#[cfg(windows)]
+use life_game::universe::TRAIL_LENGTH;
+#[cfg(windows)]
+use life_game::view::{Camera, Layout};
+#[cfg(windows)]
+use life_game::watch::{self, WatchHandle};
+#[cfg(all(windows, feature = "image"))]
+use life_game::ImageOptions;
+#[cfg(windows)]
+use life_game::{
+    Cell, Counters, Engine, FrameSnapshot, Neighborhood, Region, Rule, RunState, SparseUniverse,
+    Stability, StabilityDetector, StateError, Topology, Universe, UniverseError,
+};
+
+/// 窗口里显示多少行多少列, 面板更大时用方向键移动
+#[cfg(windows)]
+const GRID_SIZE: u32 = 64;
+/// 格子的边长和网格线的宽度, 像素; 和 `GRID_SIZE` 互不相关, 可以分别调整
+#[cfg(windows)]
+const CELL_PX: i32 = 12;
+#[cfg(windows)]
+const GRIDLINE_PX: i32 = 1;
+/// 面板在内存位图里一开始的布局, 位图和窗口的工具栏下面一一对应. 窗口按它的大小创建,
+/// 滚轮缩放之后画格子和鼠标换算都按 `CAMERA` 给的布局
+#[cfg(windows)]
+const LAYOUT: Layout = Layout {
+    grid_cols: GRID_SIZE,
+    grid_rows: GRID_SIZE,
+    cell_px: CELL_PX,
+    gridline_px: GRIDLINE_PX,
+    origin: (0, 0),
+};
+/// 方格和六边形两种网格里大的那个
+#[cfg(windows)]
+const BOARD_WIDTH: i32 = if LAYOUT.size().0 > LAYOUT.hex_size().0 {
+    LAYOUT.size().0
+} else {
+    LAYOUT.hex_size().0
+};
+#[cfg(windows)]
+const BOARD_HEIGHT: i32 = if LAYOUT.size().1 > LAYOUT.hex_size().1 {
+    LAYOUT.size().1
+} else {
+    LAYOUT.hex_size().1
+};
+
+/// 客户区的大小, 从上到下是工具栏、面板和状态栏. 窗口的边框和菜单栏创建时另算
+#[cfg(windows)]
+const CLIENT_WIDTH: i32 = BOARD_WIDTH;
+#[cfg(windows)]
+const CLIENT_HEIGHT: i32 = TOOLBAR_HEIGHT + STATUS_TOP + STATUS_HEIGHT;
+
+/// 客户区最上面一行放工具栏按钮, 面板画在它下面
+#[cfg(windows)]
+const TOOLBAR_HEIGHT: i32 = 28;
+/// 工具栏按钮的大小, 按钮之间和四周留 2 像素
+#[cfg(windows)]
+const BUTTON_WIDTH: i32 = 64;
+#[cfg(windows)]
+const BUTTON_HEIGHT: i32 = TOOLBAR_HEIGHT - 4;
+
+/// 状态栏的顶边, 在两种网格最下面一行的下面
+#[cfg(windows)]
+const STATUS_TOP: i32 = BOARD_HEIGHT;
+/// 状态栏的一行字
+#[cfg(windows)]
+const STATUS_LINE: i32 = 18;
+#[cfg(windows)]
+const STATUS_HEIGHT: i32 = 2 * STATUS_LINE;
+/// 状态栏和面板一样宽
+#[cfg(windows)]
+const STATUS_WIDTH: i32 = BOARD_WIDTH;
+/// 状态栏每一行除了最后一格以外每格的宽度, 最后一格一直到右边
+#[cfg(windows)]
+const STATUS_PANES: [&[i32]; 2] = [&[130, 130, 280, 180], &[280, 110, 110]];
+
+#[cfg(windows)]
+const TITLE: &str = "生命游戏";
+
+/// Ctrl+Shift+S / Ctrl+Shift+O 使用的存档文件
+#[cfg(windows)]
+const STATE_FILE: &str = "game_life.sav";
+/// 监视的文件保存后发给窗口的消息
+#[cfg(windows)]
+const WM_APP_RELOAD: UINT = WM_APP + 1;
+/// 演化线程算完一代后请窗口重画, 不带参数, 重画时自己去读面板
+#[cfg(windows)]
+const WM_APP_REDRAW: UINT = WM_APP + 2;
+/// 演化线程发现自动扩展到了上限, 由窗口线程弹出提示
+#[cfg(windows)]
+const WM_APP_GROW_CAPPED: UINT = WM_APP + 3;
+/// 跳过代数时报告进度, wParam 是已经演化的代数, lParam 为 1 时表示已经结束
+#[cfg(windows)]
+const WM_APP_TURBO: UINT = WM_APP + 4;
+/// 跳过代数时每次 `tick` 最多演化这么久, 之后先回去处理命令, Esc 才能马上生效
+#[cfg(windows)]
+const TURBO_SLICE: Duration = Duration::from_millis(20);
+/// 跳过代数时每隔这么久在窗口标题里报告一次进度
+#[cfg(windows)]
+const TURBO_REPORT: Duration = Duration::from_millis(250);
+/// Shift+S 一直演化到稳定时每隔这么久画一帧
+#[cfg(windows)]
+const SETTLE_FRAME: Duration = Duration::from_millis(200);
+/// Shift+S 最多演化的代数, 可以用 --stable-cap <代数> 修改
+#[cfg(windows)]
+const STABLE_CAP: u64 = 100_000;
+/// Shift+S 结束时报告结果, wParam 是演化的代数, lParam 为 1 表示稳定了, 0 表示到了上限, 2 表示取消
+#[cfg(windows)]
+const WM_APP_SETTLED: UINT = WM_APP + 5;
+/// 演化线程改了运行状态 (比如自动停下), 窗口线程更新工具栏的运行按钮
+#[cfg(windows)]
+const WM_APP_RUN_STATE: UINT = WM_APP + 6;
+/// 跳过的那几代里只重画人口曲线, 面板还是上一次画的样子
+#[cfg(windows)]
+const WM_APP_GRAPH: UINT = WM_APP + 7;
+/// 每秒更新一次窗口标题里的统计的定时器
+#[cfg(windows)]
+const TITLE_TIMER: UINT_PTR = 1;
+#[cfg(windows)]
+const TITLE_REFRESH: UINT = 1000;
+/// `show_note` 的提示在窗口标题里显示这么久, 之后换回统计
+#[cfg(windows)]
+const NOTE_DURATION: Duration = Duration::from_secs(5);
+/// 演化线程默认的演化间隔, 可以用 --interval <毫秒> 修改
+#[cfg(windows)]
+const TICK_INTERVAL: Duration = Duration::from_millis(10);
+#[cfg(windows)]
+const WATCH_INTERVAL: Duration = Duration::from_millis(250);
+/// 编辑器保存时可能连续写几次, 停止变化这么久之后才重新加载
+#[cfg(windows)]
+const WATCH_DEBOUNCE: Duration = Duration::from_millis(300);
+/// 内存中最多保留多少代的统计, F3 导出到 STATS_FILE
+#[cfg(windows)]
+const STATS_CAPACITY: usize = 100_000;
+#[cfg(windows)]
+const STATS_FILE: &str = "stats.csv";
+/// F5 重新生成面板时是否清空统计
+#[cfg(windows)]
+const CLEAR_STATS_ON_RESET: bool = true;
+/// 打开图片时亮度低于此值的像素为活细胞
+#[cfg(all(windows, feature = "image"))]
+const IMAGE_THRESHOLD: u8 = 128;
+/// 退出时自动保存的文件名, 放在 %APPDATA%\game_life 下
+#[cfg(windows)]
+const AUTOSAVE_FILE: &str = "autosave.bin";
+/// 配色、网格线这些开关的设置文件, 和 `AUTOSAVE_FILE` 放在一起
+#[cfg(windows)]
+const SETTINGS_FILE: &str = "settings.ini";
+/// Ctrl+J 导出的 JSON 文件, 放在 exe 同一目录
+#[cfg(all(windows, feature = "json"))]
+const JSON_FILE: &str = "board.json";
+/// F12 导出 PNG 和 Ctrl+F11 导出 SVG 时每个细胞的像素数
+#[cfg(windows)]
+const EXPORT_CELL_PX: u32 = 8;
+/// F9 录制 GIF 的缩放、每帧停留时间 (1/100 秒) 和最多帧数
+#[cfg(all(windows, feature = "gif"))]
+const GIF_CELL_PX: u32 = 4;
+#[cfg(all(windows, feature = "gif"))]
+const GIF_FRAME_DELAY: u16 = 10;
+#[cfg(all(windows, feature = "gif"))]
+const GIF_MAX_FRAMES: usize = 2000;
+/// 方向键每次移动窗口的格数
+#[cfg(windows)]
+const PAN_STEP: i64 = 8;
+/// Shift + '+' / '-' 每次调整的突变率, 以及突变率的上限
+#[cfg(windows)]
+const MUTATION_STEP: f64 = 0.001;
+#[cfg(windows)]
+const MAX_MUTATION_RATE: f64 = 0.05;
+/// 森林火灾模型里 '[' / ']' 每次调整的长树概率, 加 Shift 时调整雷击概率
+#[cfg(windows)]
+const GROWTH_STEP: f64 = 0.01;
+#[cfg(windows)]
+const LIGHTNING_STEP: f64 = 0.0001;
+/// 缓存的画刷最多有多少种颜色, 循环元胞自动机最多 255 种状态, 换几次规则也够用
+#[cfg(windows)]
+const MAX_BRUSHES: usize = 1024;
+/// F8 标出重新计算过的块时反色用的颜色 RGB(0, 64, 128), 白色的死细胞变成浅橙色
+#[cfg(windows)]
+const TILE_TINT: COLORREF = 0x0080_4000;
+/// F6 打开自动扩展时面板宽高的上限
+#[cfg(windows)]
+const MAX_GROW_SIZE: u32 = 512;
+
+/// 无边界模式下的世界, 以及窗口左上角在世界里的坐标.
+/// 窗口里的面板就是世界的一部分, 每一代前后和世界同步一次
+#[cfg(windows)]
+struct Infinite {
+    world: SparseUniverse,
+    top: i64,
+    left: i64,
+}
+
+#[cfg(windows)]
+lazy_static! {
+ static  ref   UNIVERSE:RwLock<Universe> = RwLock::new(Universe::new());
+ // 左键按下时翻转的格子, 拖动离开这个格子之前不再重复绘制
+ static ref TOGGLED: RwLock<Option<(i32, i32)>> = RwLock::new(None);
+ // 鼠标下面是窗口里的哪一格 (col, row), 显示在状态栏里
+ static ref MOUSE_CELL: RwLock<Option<(i32, i32)>> = RwLock::new(None);
+ static ref STATS: RwLock<StatsLog> = RwLock::new(StatsLog::new(STATS_CAPACITY));
+ // 窗口标题里的提示和显示的时间, 见 `show_note`
+ static ref TITLE_NOTE: RwLock<Option<(String, Instant)>> = RwLock::new(None);
+ // 最近打开的图案或图片的文件名, 显示在窗口标题里
+ static ref OPENED_NAME: RwLock<Option<String>> = RwLock::new(None);
+ // 面板的计数器, 换掉整个面板时也不变; 更新窗口标题时不用拿面板的锁
+ static ref COUNTERS: Arc<Counters> = UNIVERSE.read().unwrap().counters();
+ // 最近打开的图案文件, Ctrl+W 监视它
+ static ref LAST_FILE: RwLock<Option<PathBuf>> = RwLock::new(None);
+ static ref WATCH: RwLock<Option<WatchHandle>> = RwLock::new(None);
+ // 命令行 --watch 指定的文件, 窗口创建后打开
+ static ref STARTUP_WATCH: RwLock<Option<PathBuf>> = RwLock::new(None);
+ // 上次在文件对话框中选择的目录, 只在本次运行中有效
+ static ref LAST_DIR: RwLock<Option<PathBuf>> = RwLock::new(None);
+ // Immigration / QuadLife 下左键画的颜色, Ctrl+P 切换
+ static ref PAINT_COLOR: RwLock<u8> = RwLock::new(1);
+ // Ctrl+I 打开的无边界模式
+ static ref INFINITE: RwLock<Option<Infinite>> = RwLock::new(None);
+ // 窗口看着面板的哪一块、格子多大. 方向键移动, 滚轮缩放; 每次画之前按面板的大小收回到面板里
+ static ref CAMERA: RwLock<Camera> =
+     RwLock::new(Camera::new(CELL_PX, GRIDLINE_PX, (BOARD_WIDTH, BOARD_HEIGHT)));
+ // Ctrl+R 探索过的随机规则, Ctrl+左右方向键前后翻看
+ static ref EXPLORED: RwLock<RuleHistory> = RwLock::new(RuleHistory::new());
+ // 运行时每演化几代画一次, ',' / '.' 调整, 重新生成面板时不变
+ static ref FRAME_SKIP: RwLock<FrameSkip> = RwLock::new(FrameSkip::default());
+ // 演化线程当前的演化间隔, PageUp / PageDown 调整
+ static ref INTERVAL: RwLock<Duration> = RwLock::new(TICK_INTERVAL);
+ // Ctrl+G 之后输入的要跳过的代数, None 表示不在输入
+ static ref TURBO_INPUT: RwLock<Option<String>> = RwLock::new(None);
+ // WM_CREATE 时启动的演化线程, WM_DESTROY 时结束. 演化线程和窗口线程都要先拿 UNIVERSE
+ // 再拿其他的锁, 反过来会互相等住
+ static ref SIM: RwLock<Option<SimHandle>> = RwLock::new(None);
+ // Ctrl+Shift+K 选好的颜色, 排在 `THEMES` 后面参加 K 键的切换; 记在设置文件里
+ static ref CUSTOM_THEME: RwLock<Option<Theme>> = RwLock::new(None);
+ // 颜色对话框下面一排自定义颜色, 下次打开对话框时还在, 也记在设置文件里
+ static ref CUSTOM_COLORS: RwLock<[COLORREF; 16]> = RwLock::new([BLANK_COLOR; 16]);
+}
+
+/// 已经发出还没处理的 WM_APP_REDRAW. 窗口忙不过来时不再重复发, 消息队列不会越积越多
+#[cfg(windows)]
+static REDRAW_PENDING: AtomicBool = AtomicBool::new(false);
+/// 滚轮还没凑够一格的转动量, 触摸板一次只转一点点
+#[cfg(windows)]
+static WHEEL_REST: AtomicI32 = AtomicI32::new(0);
+/// 演化线程最近测到的每秒代数
+#[cfg(windows)]
+static TICK_RATE: AtomicU32 = AtomicU32::new(0);
+/// Ctrl+K 打开后: 窗口最小化时继续演化, 只是不画
+#[cfg(windows)]
+static BACKGROUND_SIM: AtomicBool = AtomicBool::new(false);
+/// 面板不再变化或者全部死亡时自动暂停, A 键开关
+#[cfg(windows)]
+static AUTO_STOP: AtomicBool = AtomicBool::new(true);
+/// 方格面板画不画网格线, G 键开关. 关掉时格子占满网格线的位置. 记在设置文件里
+#[cfg(windows)]
+static SHOW_GRID: AtomicBool = AtomicBool::new(true);
+/// 活细胞按活了几代着色, C 键开关. 默认关, 活细胞画成黑色
+#[cfg(windows)]
+static SHOW_AGES: AtomicBool = AtomicBool::new(false);
+/// M 键开关: 按最近的活跃度给所有格子着色, 活细胞只画边框
+#[cfg(windows)]
+static SHOW_HEAT: AtomicBool = AtomicBool::new(false);
+/// X 键开关: 标出上一代活过来和死掉的格子
+#[cfg(windows)]
+static SHOW_DIFF: AtomicBool = AtomicBool::new(false);
+/// P 键开关: 面板右上角画最近几代的人口曲线
+#[cfg(windows)]
+static SHOW_GRAPH: AtomicBool = AtomicBool::new(false);
+/// 已经发出还没处理的 WM_APP_GRAPH, 和 `REDRAW_PENDING` 一样不重复发
+#[cfg(windows)]
+static GRAPH_PENDING: AtomicBool = AtomicBool::new(false);
+/// D 键开关: 暂停时在每个格子上写出活邻居的个数
+#[cfg(windows)]
+static SHOW_NEIGHBORS: AtomicBool = AtomicBool::new(false);
+/// 当前配色在 `THEMES` 里的位置, 等于 `THEMES.len()` 时是 `CUSTOM_THEME`. K 键切换,
+/// 记在设置文件里
+#[cfg(windows)]
+static THEME: AtomicUsize = AtomicUsize::new(0);
+/// 自动暂停时面板稳定下来的那一代, -1 表示还在变化; 继续演化时清掉
+#[cfg(windows)]
+static STABLE_AT: AtomicI64 = AtomicI64::new(-1);
+/// 已经发出还没画出来的单步. 按住 N 自动重复时画完一步才走下一步, 不会越积越多
+#[cfg(windows)]
+static STEP_PENDING: AtomicBool = AtomicBool::new(false);
+/// Ctrl+G 输入的目标代数, 正在前往时窗口标题里显示; -1 表示没有
+#[cfg(windows)]
+static GOTO_TARGET: AtomicI64 = AtomicI64::new(-1);
+/// 正按着 T 跳过代数, 松开时取消
+#[cfg(windows)]
+static TURBO_HELD: AtomicBool = AtomicBool::new(false);
+/// F1 的帮助正盖在面板上, 按任何键关掉
+#[cfg(windows)]
+static HELP_SHOWN: AtomicBool = AtomicBool::new(false);
+
+#[cfg(all(windows, feature = "gif"))]
+lazy_static! {
+    static ref RECORDER: RwLock<Recorder> =
+        RwLock::new(Recorder::new(GIF_CELL_PX, GIF_FRAME_DELAY, GIF_MAX_FRAMES));
+}
+
+/// 画格子的实心画刷, 每种颜色第一次用到时创建, 之后一直留着反复使用.
+/// 各种配色都从这里取画刷, 超过 `MAX_BRUSHES` 种颜色时全部删掉重新开始
+#[cfg(windows)]
+struct Brushes {
+    cache: HashMap<COLORREF, HBRUSH>,
+    /// 一共创建过多少个画刷
+    created: usize,
+    /// 六边形边框的画笔, 颜色变了才重建
+    pen: Option<(COLORREF, HPEN)>,
+}
+
+#[cfg(windows)]
+impl Brushes {
+    fn new() -> Brushes {
+        Brushes {
+            cache: HashMap::new(),
+            created: 0,
+            pen: None,
+        }
+    }
+
+    fn get(&mut self, color: COLORREF) -> HBRUSH {
+        if let Some(&brush) = self.cache.get(&color) {
+            return brush;
+        }
+        if self.cache.len() >= MAX_BRUSHES {
+            self.clear();
+        }
+        let brush = unsafe { CreateSolidBrush(color) };
+        self.cache.insert(color, brush);
+        self.created += 1;
+        brush
+    }
+
+    fn created(&self) -> usize {
+        self.created
+    }
+
+    fn pen(&mut self, color: COLORREF) -> HPEN {
+        match self.pen {
+            Some((current, pen)) if current == color => pen,
+            _ => {
+                self.delete_pen();
+                let pen = unsafe { CreatePen(PS_SOLID as i32, 1, color) };
+                self.pen = Some((color, pen));
+                pen
+            }
+        }
+    }
+
+    fn delete_pen(&mut self) {
+        if let Some((_, pen)) = self.pen.take() {
+            unsafe {
+                DeleteObject(pen as HGDIOBJ);
+            }
+        }
+    }
+
+    /// 删掉所有的画刷和画笔, 换配色时调用, 之后用到的颜色重新创建
+    fn clear(&mut self) {
+        for (_, brush) in self.cache.drain() {
+            unsafe {
+                DeleteObject(brush as HGDIOBJ);
+            }
+        }
+        self.delete_pen();
+    }
+}
+
+#[cfg(windows)]
+impl Drop for Brushes {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
+
+/// 窗口自己的绘图状态: 内存 DC 和选进去的位图. 每一帧先画在位图上,
+/// 再一次 BitBlt 到窗口, 不会闪烁. WM_CREATE 时放进 GWLP_USERDATA, WM_DESTROY 时释放
+#[cfg(windows)]
+struct RenderContext {
+    dc: HDC,
+    bitmap: HBITMAP,
+    /// 选进位图之前内存 DC 上原来的位图, 删除之前要选回去
+    old_bitmap: HGDIOBJ,
+    width: i32,
+    height: i32,
+    /// 位图上画的是不是六边形网格, 画没画网格线, 用的哪种配色, 按哪个位置和缩放画的
+    hex: bool,
+    grid: bool,
+    theme: usize,
+    camera: Option<Camera>,
+    brushes: Brushes,
+    /// F8 打开时在窗口上反色标出最近一次逐块演化重新计算过的块
+    show_tiles: bool,
+    /// 位图上盖着帮助, 网格线也被压暗了, 下一帧要连网格一起重画
+    help_drawn: bool,
+    /// 位图的格子上写着邻居数或者标着上一代的变化, 下一帧要整个重画盖掉
+    neighbors_drawn: bool,
+    diff_drawn: bool,
+}
+
+#[cfg(windows)]
+impl RenderContext {
+    fn new() -> RenderContext {
+        RenderContext {
+            dc: null_mut(),
+            bitmap: null_mut(),
+            old_bitmap: null_mut(),
+            width: 0,
+            height: 0,
+            hex: false,
+            grid: true,
+            theme: 0,
+            camera: None,
+            brushes: Brushes::new(),
+            show_tiles: false,
+            help_drawn: false,
+            neighbors_drawn: false,
+            diff_drawn: false,
+        }
+    }
+
+    /// 按客户区的大小准备内存位图, 大小变了才重建. 客户区是空的 (最小化) 时返回 None,
+    /// 这一帧不用画; 否则返回格子是否要整个重画, 即位图是新建的、换了网格的形状、
+    /// 开关了网格线、换了配色、移动或缩放过窗口, 或者盖过帮助
+    unsafe fn prepare(&mut self, hwnd: HWND, hex: bool) -> Option<bool> {
+        let (width, height) = board_size(hwnd);
+        if width <= 0 || height <= 0 {
+            return None;
+        }
+        let grid = SHOW_GRID.load(Ordering::Relaxed);
+        let theme = THEME.load(Ordering::Relaxed);
+        let camera = Some(*CAMERA.read().unwrap());
+        if !self.dc.is_null() && (width, height) == (self.width, self.height) {
+            let same = hex == self.hex && grid == self.grid && theme == self.theme;
+            if same && camera == self.camera && !self.help_drawn {
+                return Some(false);
+            }
+        } else {
+            self.release();
+            let hdc = GetDC(hwnd);
+            self.dc = CreateCompatibleDC(hdc);
+            self.bitmap = CreateCompatibleBitmap(hdc, width, height);
+            ReleaseDC(hwnd, hdc);
+            self.old_bitmap = SelectObject(self.dc, self.bitmap as HGDIOBJ);
+            self.width = width;
+            self.height = height;
+        }
+        // 清成背景色再画网格, 盖掉另一种形状的网格线
+        let client = RECT {
+            left: 0,
+            top: 0,
+            right: width,
+            bottom: height,
+        };
+        FillRect(self.dc, &client, self.brushes.get(theme_colors().background));
+        draw_grid(self.dc, hex, &mut self.brushes);
+        self.hex = hex;
+        self.grid = grid;
+        self.theme = theme;
+        self.camera = camera;
+        Some(true)
+    }
+
+    /// 把画好的位图复制到窗口上
+    unsafe fn present(&self, hdc: HDC) {
+        BitBlt(hdc, 0, 0, self.width, self.height, self.dc, 0, 0, SRCCOPY);
+    }
+
+    /// 直接在窗口上按 `TILE_TINT` 反色, 不画进位图, 下一帧复制位图时自然盖掉
+    unsafe fn tint(&mut self, hdc: HDC, rects: &[RECT]) {
+        if rects.is_empty() {
+            return;
+        }
+        let old = SelectObject(hdc, self.brushes.get(TILE_TINT) as HGDIOBJ);
+        for rec in rects {
+            PatBlt(
+                hdc,
+                rec.left,
+                rec.top,
+                rec.right - rec.left,
+                rec.bottom - rec.top,
+                PATINVERT,
+            );
+        }
+        SelectObject(hdc, old);
+    }
+
+    unsafe fn release(&mut self) {
+        if self.dc.is_null() {
+            return;
+        }
+        SelectObject(self.dc, self.old_bitmap);
+        DeleteObject(self.bitmap as HGDIOBJ);
+        DeleteDC(self.dc);
+        self.dc = null_mut();
+        self.bitmap = null_mut();
+        self.old_bitmap = null_mut();
+    }
+}
+
+#[cfg(windows)]
+impl Drop for RenderContext {
+    fn drop(&mut self) {
+        unsafe { self.release() }
+    }
+}
+
+/// 窗口的画刷. 鼠标消息只会在窗口创建之后、销毁之前收到
+#[cfg(windows)]
+unsafe fn window_brushes<'a>(hwnd: HWND) -> &'a mut Brushes {
+    &mut render_context(hwnd)
+        .expect("render context lives as long as the window")
+        .brushes
+}
+
+/// 窗口的 `RenderContext`, 窗口还没创建好或已经销毁时为 None
+#[cfg(windows)]
+unsafe fn render_context<'a>(hwnd: HWND) -> Option<&'a mut RenderContext> {
+    (GetWindowLongPtrW(hwnd, GWLP_USERDATA) as *mut RenderContext).as_mut()
+}
+
+/// 把面板画到内存位图上再复制到 `hdc`. 位图刚建好或面板要整个重画时画全部格子,
+/// 否则只画变过的格子. 锁里只拍快照、复制几个小列表, 叠加层、状态栏和 GDI 调用都在
+/// 放开锁之后, 不会挡住演化线程
+#[cfg(windows)]
+unsafe fn render(hwnd: HWND, hdc: HDC) {
+    let ctx = match render_context(hwnd) {
+        Some(ctx) => ctx,
+        None => return,
+    };
+    let (frame, board, regions, changes, graph) = {
+        // `mark_drawn` 要改面板, 只能拿写锁
+        let mut u = UNIVERSE.write().unwrap();
+        let regions: Vec<Region> = if ctx.show_tiles {
+            u.dirty_tiles().collect()
+        } else {
+            Vec::new()
+        };
+        let taken = (
+            u.snapshot(),
+            BoardStatus::of(&u),
+            regions,
+            last_changes(&u),
+            population_graph(&u),
+        );
+        u.mark_drawn();
+        taken
+    };
+    let origin = board.origin;
+    let hex = frame.rule().neighborhood() == Neighborhood::Hexagonal;
+    let tiles = dirty_tile_rects(&regions, origin, hex);
+    let neighbors = neighbor_counts(&frame, origin, board.paused);
+    let diff = diff_cells(&frame, origin, &changes);
+    let status = status_text(&board);
+    // 帮助画在位图里, 显示期间每一帧整个重画, 关掉后的下一帧在 `prepare` 里连网格一起重画
+    let help = HELP_SHOWN.load(Ordering::Relaxed);
+    // 按年龄着色时活着的细胞每代都变色, 却不在变化的格子里, 只能整个重画
+    let ages = SHOW_AGES.load(Ordering::Relaxed);
+    // 热度图也一样, 每一代所有格子都在冷却
+    let heat = SHOW_HEAT.load(Ordering::Relaxed);
+    // 邻居数和上一代的变化画在格子上, 只重画变过的格子时别的格子上的就旧了
+    let overlay = !neighbors.is_empty() || !diff.is_empty();
+    let overlaid = overlay || ctx.neighbors_drawn || ctx.diff_drawn;
+    let full = match ctx.prepare(hwnd, hex) {
+        Some(full) => full || frame.needs_redraw() || help || ages || heat || overlaid,
+        None => {
+            // 这一帧没画, 快照里的变化已经清掉了, 下次整个重画
+            UNIVERSE.write().unwrap().request_redraw();
+            return;
+        }
+    };
+    let created = ctx.brushes.created();
+    SetViewportOrgEx(hdc, 0, TOOLBAR_HEIGHT, null_mut());
+    draw_frame(&frame, origin, ctx.dc, &mut ctx.brushes, full);
+    draw_diff(ctx.dc, &mut ctx.brushes, hex, &diff);
+    ctx.diff_drawn = !diff.is_empty();
+    draw_neighbor_counts(ctx.dc, &neighbors);
+    ctx.neighbors_drawn = !neighbors.is_empty();
+    draw_status(ctx.dc, &status);
+    if help {
+        draw_help(ctx.dc, ctx.width);
+    }
+    ctx.help_drawn = help;
+    // 每种状态一种颜色, 再加上年龄、拖尾和热度的几档、上一代变化的两种颜色,
+    // 一帧里新建的画刷不会更多, 和格子数、帧数无关
+    let colors = usize::from(frame.rule().states())
+        + if ages { AGE_STEPS as usize + 1 } else { 0 }
+        + TRAIL_SHADES as usize
+        + if heat { HEAT_PALETTE.len() + 1 } else { 0 }
+        + 2;
+    debug_assert!(ctx.brushes.created() - created <= colors);
+    ctx.present(hdc);
+    ctx.tint(hdc, &tiles);
+    if let Some(history) = graph {
+        draw_graph(hdc, ctx.width, &history);
+    }
+}
+
+/// 打开 P 键的人口曲线时, 最近几代活细胞数的副本
+#[cfg(windows)]
+fn population_graph(u: &Universe) -> Option<PopulationHistory> {
+    if SHOW_GRAPH.load(Ordering::Relaxed) {
+        Some(u.population_history().clone())
+    } else {
+        None
+    }
+}
+
+/// 跳过不画的那几代里只更新人口曲线: 先从位图把曲线那块盖回上一帧的面板, 再画新的曲线
+#[cfg(windows)]
+unsafe fn render_graph(hwnd: HWND) {
+    let ctx = match render_context(hwnd) {
+        Some(ctx) if !ctx.dc.is_null() => ctx,
+        _ => return,
+    };
+    let history = match population_graph(&UNIVERSE.read().unwrap()) {
+        Some(history) => history,
+        None => return,
+    };
+    let hdc = board_dc(hwnd);
+    let rec = graph_rect(ctx.width);
+    let (w, h) = (rec.right - rec.left, rec.bottom - rec.top);
+    BitBlt(hdc, rec.left, rec.top, w, h, ctx.dc, rec.left, rec.top, SRCCOPY);
+    draw_graph(hdc, ctx.width, &history);
+    ReleaseDC(hwnd, hdc);
+}
+
+/// 人口曲线的大小、离面板右上角的距离、左边写最多和最少人数的宽度, 以及折线的颜色 RGB(80, 220, 120)
+#[cfg(windows)]
+const GRAPH_WIDTH: i32 = 260;
+#[cfg(windows)]
+const GRAPH_HEIGHT: i32 = 64;
+#[cfg(windows)]
+const GRAPH_MARGIN: i32 = 8;
+#[cfg(windows)]
+const GRAPH_LABEL_WIDTH: i32 = 56;
+#[cfg(windows)]
+const GRAPH_COLOR: COLORREF = 0x0078_DC50;
+
+/// 面板 `width` 像素宽时人口曲线占的矩形, 在面板的右上角
+#[cfg(windows)]
+fn graph_rect(width: i32) -> RECT {
+    let right = width - GRAPH_MARGIN;
+    RECT {
+        left: right - GRAPH_WIDTH,
+        top: GRAPH_MARGIN,
+        right,
+        bottom: GRAPH_MARGIN + GRAPH_HEIGHT,
+    }
+}
+
+/// 半透明的黑底上画折线, 按窗口里这几代的最少到最多缩放, 两个数写在左边.
+/// 直接画在窗口上, 不进位图, 下一帧复制位图时盖掉
+#[cfg(windows)]
+unsafe fn draw_graph(hdc: HDC, width: i32, history: &PopulationHistory) {
+    let rec = graph_rect(width);
+    shade(hdc, &rec, 160);
+    let (min, max) = match history.range() {
+        Some(range) => range,
+        None => return,
+    };
+    let pad = 6;
+    let (left, top) = (rec.left + GRAPH_LABEL_WIDTH, rec.top + pad);
+    let plot_height = GRAPH_HEIGHT - 2 * pad;
+    let points = history.points(rec.right - pad - left, plot_height);
+
+    let old_font = SelectObject(hdc, GetStockObject(DEFAULT_GUI_FONT as i32));
+    let old_mode = SetBkMode(hdc, TRANSPARENT as i32);
+    let old_color = SetTextColor(hdc, RGB(255, 255, 255));
+    let text = |y: i32, n: u32| {
+        let z = n.to_string().encode_utf16().collect::<Vec<u16>>();
+        TextOutW(hdc, rec.left + pad, y, z.as_ptr(), z.len() as i32);
+    };
+    text(rec.top + 2, max);
+    text(rec.bottom - HELP_LINE, min);
+    SetTextColor(hdc, old_color);
+    SetBkMode(hdc, old_mode);
+    SelectObject(hdc, old_font);
+
+    let pen = CreatePen(PS_SOLID as i32, 1, GRAPH_COLOR);
+    let old_pen = SelectObject(hdc, pen as HGDIOBJ);
+    for (i, &(x, y)) in points.iter().enumerate() {
+        if i == 0 {
+            MoveToEx(hdc, left + x, top + y, null_mut());
+        } else {
+            LineTo(hdc, left + x, top + y);
+        }
+    }
+    // 只有一代时 LineTo 画不出东西, 补一个点
+    if let [(x, y)] = points.as_slice() {
+        SetPixel(hdc, left + x, top + y, GRAPH_COLOR);
+    }
+    SelectObject(hdc, old_pen);
+    DeleteObject(pen as HGDIOBJ);
+}
+
+/// 最近一次演化中活过来和死掉的格子的 (row, col)
+#[cfg(windows)]
+type LastChanges = (Vec<(u32, u32)>, Vec<(u32, u32)>);
+
+/// 打开 X 键的对比时, `LastChanges` 的副本, 其他时候为空
+#[cfg(windows)]
+fn last_changes(u: &Universe) -> LastChanges {
+    if SHOW_DIFF.load(Ordering::Relaxed) {
+        (u.last_born().to_vec(), u.last_died().to_vec())
+    } else {
+        (Vec::new(), Vec::new())
+    }
+}
+
+/// 窗口里上一代变过并且之后没再被编辑改回去的格子: (col, row, 是否活过来).
+/// `changes` 来自 `last_changes`, 和 `frame` 在同一次锁里取出
+#[cfg(windows)]
+fn diff_cells(
+    frame: &FrameSnapshot,
+    (top, left): (u32, u32),
+    (born, died): &LastChanges,
+) -> Vec<(i32, i32, bool)> {
+    let layout = layout(frame.rule().neighborhood() == Neighborhood::Hexagonal);
+    let visible = |&(row, col): &(u32, u32), state: u8| {
+        let (r, c) = (row.wrapping_sub(top), col.wrapping_sub(left));
+        let inside = r < layout.grid_rows && c < layout.grid_cols;
+        if inside && frame.state(row, col) == Some(state) {
+            Some((c as i32, r as i32))
+        } else {
+            None
+        }
+    };
+    let born = born.iter().filter_map(|at| visible(at, 1));
+    let died = died.iter().filter_map(|at| visible(at, 0));
+    born.map(|(c, r)| (c, r, true))
+        .chain(died.map(|(c, r)| (c, r, false)))
+        .collect()
+}
+
+/// 活过来的格子画成偏绿的活细胞颜色, 死掉的画成偏红的死细胞颜色
+#[cfg(windows)]
+fn draw_diff(hdc: HDC, brushes: &mut Brushes, hex: bool, cells: &[(i32, i32, bool)]) {
+    let theme = theme_colors();
+    let born = blend(theme.alive, RGB(0, 200, 60), 1, 2);
+    let died = blend(theme.dead, RGB(230, 40, 40), 1, 2);
+    for &(c, r, alive) in cells {
+        fill_cell(if alive { born } else { died }, hex, hdc, brushes, c, r);
+    }
+}
+
+/// 格子小于这么多像素时写不下数字, 不显示邻居数
+#[cfg(windows)]
+const NEIGHBOR_MIN_PX: i32 = 12;
+
+/// 打开 D 键的邻居数并且暂停着时, 窗口里每个有活邻居的格子:
+/// (中心 x, 中心 y, 活邻居数, 是否活着). 按快照的邻域和拓扑数, 其他时候为空
+#[cfg(windows)]
+fn neighbor_counts(
+    frame: &FrameSnapshot,
+    (top, left): (u32, u32),
+    paused: bool,
+) -> Vec<(i32, i32, u8, bool)> {
+    let hex = frame.rule().neighborhood() == Neighborhood::Hexagonal;
+    let layout = layout(hex);
+    let shown = SHOW_NEIGHBORS.load(Ordering::Relaxed) && paused;
+    if !shown || layout.cell_px < NEIGHBOR_MIN_PX {
+        return Vec::new();
+    }
+    let mut counts = Vec::new();
+    for r in 0..layout.grid_rows {
+        for c in 0..layout.grid_cols {
+            let (row, col) = (r + top, c + left);
+            if row >= frame.height() || col >= frame.width() {
+                continue;
+            }
+            let count = frame.live_neighbor_count(row, col);
+            if count == 0 {
+                continue;
+            }
+            let (x, y) = if hex {
+                let corners = layout.hex_corners(r, c);
+                let sum = corners.iter().fold((0, 0), |(x, y), &(cx, cy)| (x + cx, y + cy));
+                (sum.0 / 6, sum.1 / 6)
+            } else {
+                let (left, top, right, bottom) = layout.cell_rect(r, c);
+                ((left + right) / 2, (top + bottom) / 2)
+            };
+            let alive = frame.state(row, col) == Some(1);
+            counts.push((x, y, count, alive));
+        }
+    }
+    counts
+}
+
+/// 透明背景写在格子中间, 活细胞上用死细胞的颜色写, 死细胞上反过来
+#[cfg(windows)]
+unsafe fn draw_neighbor_counts(hdc: HDC, counts: &[(i32, i32, u8, bool)]) {
+    if counts.is_empty() {
+        return;
+    }
+    let theme = theme_colors();
+    let old_font = SelectObject(hdc, GetStockObject(DEFAULT_GUI_FONT as i32));
+    let old_mode = SetBkMode(hdc, TRANSPARENT as i32);
+    let old_align = SetTextAlign(hdc, TA_CENTER | TA_TOP);
+    let old_color = GetTextColor(hdc);
+    let mut size: SIZE = std::mem::zeroed();
+    GetTextExtentPoint32W(hdc, to_wide("0").as_ptr(), 1, &mut size);
+    for &(x, y, count, alive) in counts {
+        SetTextColor(hdc, if alive { theme.dead } else { theme.alive });
+        let digit = to_wide(&count.to_string());
+        TextOutW(hdc, x, y - size.cy / 2, digit.as_ptr(), digit.len() as i32 - 1);
+    }
+    SetTextColor(hdc, old_color);
+    SetTextAlign(hdc, old_align);
+    SetBkMode(hdc, old_mode);
+    SelectObject(hdc, old_font);
+}
+
+/// 窗口里能看到的重新计算过的块, 六边形网格按外接的矩形算
+#[cfg(windows)]
+fn dirty_tile_rects(regions: &[Region], (top, left): (u32, u32), hex: bool) -> Vec<RECT> {
+    let layout = layout(hex);
+    let (bottom, right) = (top + layout.grid_rows, left + layout.grid_cols);
+    regions
+        .iter()
+        .filter_map(|tile| {
+            let (r0, r1) = (tile.row.max(top), (tile.row + tile.height).min(bottom));
+            let (c0, c1) = (tile.col.max(left), (tile.col + tile.width).min(right));
+            if r0 >= r1 || c0 >= c1 {
+                return None;
+            }
+            Some(RECT {
+                left: layout.line_x(c0 - left),
+                top: layout.line_y(r0 - top),
+                right: layout.line_x(c1 - left),
+                bottom: layout.line_y(r1 - top),
+            })
+        })
+        .collect()
+}
+
+#[cfg(windows)]
+fn client_size(hwnd: HWND) -> (i32, i32) {
+    let mut client = RECT {
+        left: 0,
+        top: 0,
+        right: 0,
+        bottom: 0,
+    };
+    unsafe {
+        GetClientRect(hwnd, &mut client);
+    }
+    (client.right - client.left, client.bottom - client.top)
+}
+
+/// 客户区去掉上面的工具栏, 面板和状态栏占的部分
+#[cfg(windows)]
+fn board_size(hwnd: HWND) -> (i32, i32) {
+    let (width, height) = client_size(hwnd);
+    (width, height - TOOLBAR_HEIGHT)
+}
+
+/// 窗口的 DC, 原点移到工具栏下面, 之后按面板坐标画. 用完照样 ReleaseDC
+#[cfg(windows)]
+unsafe fn board_dc(hwnd: HWND) -> HDC {
+    let hdc = GetDC(hwnd);
+    SetViewportOrgEx(hdc, 0, TOOLBAR_HEIGHT, null_mut());
+    hdc
+}
+
+/// 每一格先用配色的 `panel` 清掉再写字, 字写不下时裁掉; 之前更长的字不会留下来
+#[cfg(windows)]
+fn draw_status(hdc: HDC, lines: &[Vec<String>]) {
+    let theme = theme_colors();
+    unsafe {
+        let old = SetBkColor(hdc, theme.panel);
+        let old_color = SetTextColor(hdc, theme.accent);
+        for (row, (line, widths)) in lines.iter().zip(STATUS_PANES.iter()).enumerate() {
+            let top = STATUS_TOP + row as i32 * STATUS_LINE;
+            let mut left = 0;
+            for (i, text) in line.iter().enumerate() {
+                let right = widths.get(i).map_or(STATUS_WIDTH, |width| left + width);
+                // 格子之间留 1 像素的背景色当分隔线
+                let rec = RECT {
+                    left,
+                    top,
+                    right: right - 1,
+                    bottom: top + STATUS_LINE - 1,
+                };
+                let z = text.encode_utf16().collect::<Vec<u16>>();
+                ExtTextOutW(
+                    hdc,
+                    left + 4,
+                    top + 1,
+                    ETO_OPAQUE | ETO_CLIPPED,
+                    &rec,
+                    z.as_ptr(),
+                    z.len() as UINT,
+                    std::ptr::null(),
+                );
+                left = right;
+            }
+        }
+        SetTextColor(hdc, old_color);
+        SetBkColor(hdc, old);
+    }
+}
+
+/// 帮助的一行字
+#[cfg(windows)]
+const HELP_LINE: i32 = 18;
+/// 帮助里按键那一列的宽度, 说明那一列再往右这么多是下一栏
+#[cfg(windows)]
+const HELP_KEYS_WIDTH: i32 = 130;
+#[cfg(windows)]
+const HELP_COLUMN_WIDTH: i32 = 380;
+
+/// 把面板压暗, 在上面一行一行写出 `help_lines`, 一栏写不下时接着写下一栏
+#[cfg(windows)]
+unsafe fn draw_help(hdc: HDC, width: i32) {
+    let (pad, height) = (16, STATUS_TOP);
+    let board = RECT {
+        left: 0,
+        top: 0,
+        right: width,
+        bottom: height,
+    };
+    shade(hdc, &board, 190);
+
+    let old_font = SelectObject(hdc, GetStockObject(DEFAULT_GUI_FONT as i32));
+    let old_mode = SetBkMode(hdc, TRANSPARENT as i32);
+    let old_color = SetTextColor(hdc, RGB(255, 255, 255));
+    let per_column = ((height - 2 * pad) / HELP_LINE).max(1) as usize;
+    let text = |x: i32, y: i32, s: &str| {
+        let z = s.encode_utf16().collect::<Vec<u16>>();
+        TextOutW(hdc, x, y, z.as_ptr(), z.len() as i32);
+    };
+    for (i, (keys, label)) in help_lines().iter().enumerate() {
+        let x = pad + (i / per_column) as i32 * HELP_COLUMN_WIDTH;
+        let y = pad + (i % per_column) as i32 * HELP_LINE;
+        text(x, y, keys);
+        text(x + HELP_KEYS_WIDTH, y, label);
+    }
+    SetTextColor(hdc, old_color);
+    SetBkMode(hdc, old_mode);
+    SelectObject(hdc, old_font);
+}
+
+/// 把 1x1 的黑色位图拉伸到 `rec`, 按常量透明度 `alpha` 混合上去, 压暗下面的画面
+#[cfg(windows)]
+unsafe fn shade(hdc: HDC, rec: &RECT, alpha: u8) {
+    let black = CreateCompatibleDC(hdc);
+    let bitmap = CreateCompatibleBitmap(hdc, 1, 1);
+    let old_bitmap = SelectObject(black, bitmap as HGDIOBJ);
+    SetPixel(black, 0, 0, RGB(0, 0, 0));
+    let blend = BLENDFUNCTION {
+        BlendOp: AC_SRC_OVER,
+        BlendFlags: 0,
+        SourceConstantAlpha: alpha,
+        AlphaFormat: 0,
+    };
+    let (w, h) = (rec.right - rec.left, rec.bottom - rec.top);
+    AlphaBlend(hdc, rec.left, rec.top, w, h, black, 0, 0, 1, 1, blend);
+    SelectObject(black, old_bitmap);
+    DeleteObject(bitmap as HGDIOBJ);
+    DeleteDC(black);
+}
+
+/// 窗口左上角那一格在面板里的位置. 先按面板现在的大小把 `CAMERA` 收回到面板范围内,
+/// 之后 `layout` 给的布局和这里对得上
+#[cfg(windows)]
+fn view_origin(u: &Universe) -> (u32, u32) {
+    let mut camera = CAMERA.write().unwrap();
+    camera.fit((u.width(), u.height()), (BOARD_WIDTH, BOARD_HEIGHT));
+    let (top, left) = camera.top_left(is_hex(u));
+    (top as u32, left as u32)
+}
+
+/// 窗口里第 `r` 行第 `c` 列的格子在面板上的 (row, col)
+#[cfg(windows)]
+fn board_cell(u: &Universe, c: i32, r: i32) -> (u32, u32) {
+    let (top, left) = view_origin(u);
+    (r as u32 + top, c as u32 + left)
+}
+
+#[cfg(windows)]
+fn draw_change(u: &mut Universe, cell: Cell, hdc: HDC, brushes: &mut Brushes, c: i32, r: i32) {
+    // let index = u.get_index(r as u32, c as u32);
+    // println!("index: {}", index);
+    let (row, col) = board_cell(u, c, r);
+    if let Err(e) = u.set_cell(cell, col, row) {
+        return note_edit_error(e);
+    }
+    draw_rec(&cell, is_hex(u), hdc, brushes, c, r);
+    // println!("cell: {:?}", u.cells()[index]);
+}
+
+#[cfg(windows)]
+fn draw_toggle(u: &mut Universe, hdc: HDC, brushes: &mut Brushes, c: i32, r: i32) {
+    let (row, col) = board_cell(u, c, r);
+    match u.toggle_cell(row, col) {
+        Ok(cell) => draw_rec(&cell, is_hex(u), hdc, brushes, c, r),
+        Err(e) => note_edit_error(e),
+    }
+}
+
+/// 鼠标改格子失败的原因放进窗口标题的提示, 标题定时刷新时显示.
+/// 拖动时经过的每一格都可能失败, 不弹消息框
+#[cfg(windows)]
+fn note_edit_error(e: UniverseError) {
+    *TITLE_NOTE.write().unwrap() = Some((e.to_string(), Instant::now()));
+}
+
+/// 鼠标左键在 Wireworld 下画的状态: 按住 Ctrl 时放电子头, 否则画导线
+#[cfg(windows)]
+fn wire_brush() -> u8 {
+    if key_down(VK_CONTROL) {
+        WIRE_HEAD
+    } else {
+        WIRE_CONDUCTOR
+    }
+}
+
+#[cfg(windows)]
+fn draw_wire(u: &mut Universe, hdc: HDC, brushes: &mut Brushes, c: i32, r: i32) {
+    draw_state(u, wire_brush(), hdc, brushes, c, r);
+}
+
+/// 多状态规则下左键画的格子: Wireworld 画导线, 带颜色的规则画当前颜色.
+/// 其余规则返回 false, 仍按活细胞处理
+#[cfg(windows)]
+fn draw_paint(u: &mut Universe, hdc: HDC, brushes: &mut Brushes, c: i32, r: i32) -> bool {
+    if u.rule() == Rule::Sandpile {
+        // 落一粒沙, 坍塌可能波及很多格子, 整个面板重画
+        let (row, col) = board_cell(u, c, r);
+        match u.add_grain(row, col) {
+            Ok(0) => fill_cell(
+                state_color(u.state(row, col).unwrap_or(0), u.rule()),
+                is_hex(u),
+                hdc,
+                brushes,
+                c,
+                r,
+            ),
+            Ok(_) => draw_board(u, hdc, brushes, true),
+            Err(e) => note_edit_error(e),
+        }
+    } else if u.rule() == Rule::Wireworld {
+        draw_wire(u, hdc, brushes, c, r);
+    } else if u.rule().colors() > 1 {
+        let color = (*PAINT_COLOR.read().unwrap()).min(u.rule().colors());
+        draw_state(u, color, hdc, brushes, c, r);
+    } else {
+        return false;
+    }
+    true
+}
+
+#[cfg(windows)]
+fn draw_state(u: &mut Universe, state: u8, hdc: HDC, brushes: &mut Brushes, c: i32, r: i32) {
+    let (row, col) = board_cell(u, c, r);
+    match u.set_state(row, col, state) {
+        Ok(()) => fill_cell(state_color(state, u.rule()), is_hex(u), hdc, brushes, c, r),
+        Err(e) => note_edit_error(e),
+    }
+}
+
+/// 清空面板, 换成 Wireworld 并把示例电路放在左上角附近. 面板太小放不下时返回第一个放不下的格子的错误
+#[cfg(windows)]
+fn load_circuit() -> Result<(), UniverseError> {
+    let mut u = UNIVERSE.write().unwrap();
+    u.dead_all();
+    u.set_rule(Rule::Wireworld);
+    for (row, col, state) in wireworld_cells(WIREWORLD_CLOCKS_OR) {
+        u.set_state(row + 2, col + 2, state)?;
+    }
+    Ok(())
+}
+
+#[cfg(windows)]
+fn draw_rec(cell: &Cell, hex: bool, hdc: HDC, brushes: &mut Brushes, c: i32, r: i32) {
+    let theme = theme_colors();
+    let color = match cell {
+        Cell::Alive => theme.alive,
+        Cell::Dead => theme.dead,
+    };
+    fill_cell(color, hex, hdc, brushes, c, r);
+}
+
+/// 一套配色. 背景是面板外和六边形网格下面的底色, 也是状态栏各格之间的分隔线;
+/// 网格线也是六边形的边框. 活细胞和死细胞是黑白那几类规则的颜色,
+/// 状态栏的字用强调色写在 `panel` 上
+#[cfg(windows)]
+#[derive(Copy, Clone)]
+struct Theme {
+    name: &'static str,
+    background: COLORREF,
+    gridline: COLORREF,
+    alive: COLORREF,
+    dead: COLORREF,
+    accent: COLORREF,
+    panel: COLORREF,
+}
+
+/// 同 `RGB`, 可以用在常量里
+#[cfg(windows)]
+const fn rgb(r: u8, g: u8, b: u8) -> COLORREF {
+    r as COLORREF | (g as COLORREF) << 8 | (b as COLORREF) << 16
+}
+
+/// 内置的配色, K 键依次切换
+#[cfg(windows)]
+const THEMES: [Theme; 3] = [
+    Theme {
+        name: "经典",
+        background: rgb(255, 255, 255),
+        gridline: rgb(0, 0, 0),
+        alive: rgb(0, 0, 0),
+        dead: rgb(255, 255, 255),
+        accent: rgb(0, 0, 0),
+        panel: rgb(240, 240, 240),
+    },
+    Theme {
+        name: "暗色",
+        background: rgb(24, 24, 28),
+        gridline: rgb(58, 58, 66),
+        alive: rgb(225, 225, 215),
+        dead: rgb(32, 32, 38),
+        accent: rgb(255, 180, 60),
+        panel: rgb(44, 44, 52),
+    },
+    Theme {
+        name: "Solarized",
+        background: rgb(253, 246, 227),
+        gridline: rgb(147, 161, 161),
+        alive: rgb(7, 54, 66),
+        dead: rgb(253, 246, 227),
+        accent: rgb(38, 139, 210),
+        panel: rgb(238, 232, 213),
+    },
+];
+
+#[cfg(windows)]
+fn theme_colors() -> Theme {
+    let index = THEME.load(Ordering::Relaxed);
+    match THEMES.get(index) {
+        Some(&theme) => theme,
+        None => CUSTOM_THEME.read().unwrap().unwrap_or(THEMES[0]),
+    }
+}
+
+/// 依次问活细胞、死细胞和网格线的颜色, 其余的颜色沿用现在的配色. 中途取消时返回 None,
+/// 已经选好的也不用
+#[cfg(windows)]
+fn ask_custom_theme(hwnd: HWND) -> Option<Theme> {
+    let current = theme_colors();
+    // 对话框打开期间不拿锁, 关掉之后再存回去
+    let mut custom = *CUSTOM_COLORS.read().unwrap();
+    let mut ask = |prompt: &str, initial: COLORREF| {
+        show_note(hwnd, prompt);
+        unsafe {
+            let mut cc: CHOOSECOLORW = std::mem::zeroed();
+            cc.lStructSize = std::mem::size_of::<CHOOSECOLORW>() as DWORD;
+            cc.hwndOwner = hwnd;
+            cc.rgbResult = initial;
+            cc.lpCustColors = custom.as_mut_ptr();
+            cc.Flags = CC_RGBINIT | CC_FULLOPEN;
+            if ChooseColorW(&mut cc) == 0 {
+                None
+            } else {
+                Some(cc.rgbResult)
+            }
+        }
+    };
+    let colors = ask("选择活细胞的颜色", current.alive).and_then(|alive| {
+        let dead = ask("选择死细胞和背景的颜色", current.dead)?;
+        let gridline = ask("选择网格线的颜色", current.gridline)?;
+        Some((alive, dead, gridline))
+    });
+    *CUSTOM_COLORS.write().unwrap() = custom;
+    let (alive, dead, gridline) = colors?;
+    Some(custom_theme(current, [alive, dead, gridline]))
+}
+
+/// 在 `base` 上换掉活细胞、死细胞 (连同背景) 和网格线的颜色
+#[cfg(windows)]
+fn custom_theme(base: Theme, [alive, dead, gridline]: [COLORREF; 3]) -> Theme {
+    Theme {
+        name: "自定义",
+        background: dead,
+        gridline,
+        alive,
+        dead,
+        ..base
+    }
+}
+
+/// 年龄的颜色分几档, 每 `AGE_STEP` 代一档, 活到 `AGE_STEP * AGE_STEPS` 代以后都是最深的蓝色
+#[cfg(windows)]
+const AGE_STEP: u16 = 5;
+#[cfg(windows)]
+const AGE_STEPS: u16 = 10;
+
+/// 刚出生的细胞是亮绿色, 活得越久越接近深蓝色. 按档取色, 画刷只有几种
+#[cfg(windows)]
+fn age_color(age: u16) -> COLORREF {
+    let step = u32::from((age / AGE_STEP).min(AGE_STEPS));
+    blend(RGB(0, 230, 60), RGB(20, 30, 140), step, u32::from(AGE_STEPS))
+}
+
+/// 拖尾的颜色分几档, 刚死的最接近活细胞的颜色, 快褪完时接近死细胞的颜色
+#[cfg(windows)]
+const TRAIL_SHADES: u8 = 8;
+
+#[cfg(windows)]
+fn trail_color(decay: u8) -> COLORREF {
+    let shade = u32::from(decay - 1) * u32::from(TRAIL_SHADES) / u32::from(TRAIL_LENGTH);
+    let level = 245 - shade * 125 / u32::from(TRAIL_SHADES - 1);
+    let theme = theme_colors();
+    blend(theme.alive, theme.dead, level, 255)
+}
+
+/// 热度图按 `HEAT_PALETTE` 的几档取色, 画刷只有这么几种
+#[cfg(windows)]
+fn heat_color(activity: u16) -> COLORREF {
+    let [r, g, b] = HEAT_PALETTE[heat_level(activity)];
+    RGB(r, g, b)
+}
+
+/// 热度图上活细胞的边框颜色, 冷色和暖色上都看得清
+#[cfg(windows)]
+const HEAT_OUTLINE: COLORREF = 0x00FF_FFFF;
+
+/// 只有黑白的那几类规则才有年龄、拖尾和热度图的颜色
+#[cfg(windows)]
+fn is_plain(rule: Rule) -> bool {
+    matches!(rule, Rule::Life { .. } | Rule::Generations { .. } | Rule::LargerThanLife { .. })
+}
+
+/// 格子的颜色. 只有黑白的那几类规则才换成热度、年龄的颜色和拖尾的灰色
+#[cfg(windows)]
+fn cell_color(frame: &FrameSnapshot, row: u32, col: u32, state: u8) -> COLORREF {
+    let rule = frame.rule();
+    let plain = is_plain(rule);
+    if plain && SHOW_HEAT.load(Ordering::Relaxed) {
+        if let Some(activity) = frame.cell_activity(row, col) {
+            return heat_color(activity);
+        }
+    }
+    if plain && state == 1 && SHOW_AGES.load(Ordering::Relaxed) {
+        if let Some(age) = frame.cell_age(row, col) {
+            return age_color(age);
+        }
+    }
+    if plain && state == 0 {
+        if let Some(decay) = frame.cell_decay(row, col) {
+            return trail_color(decay);
+        }
+    }
+    state_color(state, rule)
+}
+
+/// 两状态规则的细胞按配色画, Generations 规则下衰亡中的细胞越接近死亡越接近死细胞的颜色.
+/// Wireworld 的四种状态各用一种颜色, Immigration 和 QuadLife 的活细胞按颜色画,
+/// 森林火灾模型的空地、树和火分别是黑、绿、红, 沙堆的沙粒越多颜色越深,
+/// 循环元胞自动机的状态沿色相环均匀取色
+#[cfg(windows)]
+fn state_color(state: u8, rule: Rule) -> COLORREF {
+    if rule == Rule::Sandpile {
+        return match state {
+            0 => theme_colors().dead,
+            1 => RGB(250, 220, 120),
+            2 => RGB(230, 140, 30),
+            _ => RGB(140, 60, 0),
+        };
+    }
+    if rule == Rule::ForestFire {
+        return match state {
+            FIRE_TREE => RGB(0, 150, 40),
+            FIRE_BURNING => RGB(230, 30, 0),
+            _ => RGB(0, 0, 0),
+        };
+    }
+    if let Rule::Cyclic { states, .. } = rule {
+        return hue_color(u32::from(state), u32::from(states));
+    }
+    if rule == Rule::Wireworld {
+        return match state {
+            WIRE_HEAD => RGB(0, 90, 255),
+            WIRE_TAIL => RGB(220, 40, 40),
+            WIRE_CONDUCTOR => RGB(230, 170, 0),
+            _ => theme_colors().dead,
+        };
+    }
+    if rule.colors() > 1 {
+        return match state {
+            0 => theme_colors().dead,
+            1 => RGB(220, 40, 40),
+            2 => RGB(0, 90, 255),
+            3 => RGB(0, 160, 60),
+            _ => RGB(230, 170, 0),
+        };
+    }
+    let (states, theme) = (rule.states(), theme_colors());
+    match state {
+        0 => theme.dead,
+        1 => theme.alive,
+        s => blend(theme.alive, theme.dead, u32::from(s - 1), u32::from(states.max(s + 1) - 1)),
+    }
+}
+
+/// 从 `from` 往 `to` 走 `num` / `den` 的颜色
+#[cfg(windows)]
+fn blend(from: COLORREF, to: COLORREF, num: u32, den: u32) -> COLORREF {
+    let mix = |a: u8, b: u8| ((u32::from(a) * (den - num) + u32::from(b) * num) / den) as u8;
+    RGB(
+        mix(GetRValue(from), GetRValue(to)),
+        mix(GetGValue(from), GetGValue(to)),
+        mix(GetBValue(from), GetBValue(to)),
+    )
+}
+
+/// 色相环上第 `i` / `n` 处的饱和色
+#[cfg(windows)]
+fn hue_color(i: u32, n: u32) -> COLORREF {
+    let h = 6 * 255 * (i % n.max(1)) / n.max(1);
+    let x = (h % 255) as u8;
+    let (r, g, b) = match h / 255 {
+        0 => (255, x, 0),
+        1 => (255 - x, 255, 0),
+        2 => (0, 255, x),
+        3 => (0, 255 - x, 255),
+        4 => (x, 0, 255),
+        _ => (255, 0, 255 - x),
+    };
+    RGB(r, g, b)
+}
+
+#[cfg(windows)]
+fn fill_cell(color: COLORREF, hex: bool, hdc: HDC, brushes: &mut Brushes, c: i32, r: i32) {
+    if hex {
+        return fill_hex(color, hdc, brushes, c, r);
+    }
+    let (left, top, right, bottom) = layout(false).cell_rect(r as u32, c as u32);
+    let rec = RECT {
+        left,
+        top,
+        right,
+        bottom,
+    };
+    // FillRect 直接用传进去的画刷, 不用选进 DC
+    unsafe {
+        FillRect(hdc, &rec, brushes.get(color));
+    }
+}
+
+/// 六边形用网格线颜色的画笔描边, 边框就是网格线
+#[cfg(windows)]
+fn fill_hex(color: COLORREF, hdc: HDC, brushes: &mut Brushes, c: i32, r: i32) {
+    let points: Vec<POINT> = layout(true)
+        .hex_corners(r as u32, c as u32)
+        .iter()
+        .map(|&(x, y)| POINT { x, y })
+        .collect();
+    // Polygon 用 DC 上选中的画刷填充
+    unsafe {
+        let org_brs = SelectObject(hdc, brushes.get(color) as HGDIOBJ);
+        let org_pen = SelectObject(hdc, brushes.pen(theme_colors().gridline) as HGDIOBJ);
+        Polygon(hdc, points.as_ptr(), points.len() as i32);
+        SelectObject(hdc, org_pen);
+        SelectObject(hdc, org_brs);
+    }
+}
+
+#[cfg(windows)]
+fn is_hex(u: &Universe) -> bool {
+    u.neighborhood() == Neighborhood::Hexagonal
+}
+
+/// 鼠标消息里的坐标落在哪个格子上, 返回 (col, row), 见 `client_cell`
+#[cfg(windows)]
+fn screen_cell(hex: bool, l_param: LPARAM) -> Option<(i32, i32)> {
+    let x = i32::from(LOWORD(l_param as u32));
+    let y = i32::from(HIWORD(l_param as u32));
+    client_cell(hex, x, y).map(|(row, col)| (col as i32, row as i32))
+}
+
+/// 客户区坐标落在哪个格子上, 返回 (row, col). 先减掉工具栏换成位图里的坐标;
+/// 方格的网格线上不算任何格子, 六边形网格的奇数行错开了半格
+#[cfg(windows)]
+fn client_cell(hex: bool, x: i32, y: i32) -> Option<(u32, u32)> {
+    let y = y - TOOLBAR_HEIGHT;
+    if hex {
+        layout(true).hex_cell_at(x, y)
+    } else {
+        layout(false).cell_at(x, y)
+    }
+}
+
+/// `CAMERA` 现在的布局, 再按 `SHOW_GRID` 去掉网格线; 画格子和鼠标换算用同一个.
+/// 六边形网格从偶数行画起, 见 `Camera::top_left`
+#[cfg(windows)]
+fn layout(hex: bool) -> Layout {
+    let layout = CAMERA.read().unwrap().layout(hex);
+    if SHOW_GRID.load(Ordering::Relaxed) {
+        layout
+    } else {
+        layout.without_gridlines()
+    }
+}
+
+/// 六边形网格不画直线, 只清成背景色, 格子的边框在 `fill_hex` 里画.
+/// 关掉网格线时方格面板什么也不画
+#[cfg(windows)]
+fn draw_grid(hdc: HDC, hex: bool, brushes: &mut Brushes) {
+    let theme = theme_colors();
+    let layout = layout(hex);
+    let (x0, y0) = layout.origin;
+    if hex {
+        let (width, height) = layout.hex_size();
+        let rec = RECT {
+            left: x0,
+            top: y0,
+            right: x0 + width,
+            bottom: y0 + height,
+        };
+        unsafe {
+            FillRect(hdc, &rec, brushes.get(theme.background));
+        }
+        return;
+    }
+    if layout.gridline_px == 0 {
+        return;
+    }
+    let (width, height) = layout.size();
+    let brush = brushes.get(theme.gridline);
+    let line = |left: i32, top: i32, right: i32, bottom: i32| {
+        let rec = RECT {
+            left,
+            top,
+            right,
+            bottom,
+        };
+        unsafe {
+            FillRect(hdc, &rec, brush);
+        }
+    };
+    for i in 0..=layout.grid_rows {
+        let y = layout.line_y(i);
+        line(x0, y, x0 + width, y + layout.gridline_px);
+    }
+    for i in 0..=layout.grid_cols {
+        let x = layout.line_x(i);
+        line(x, y0, x + layout.gridline_px, y0 + height);
+    }
+}
+
+/// 细胞和状态栏. `full` 为 false 时只画 `changed_cells` 里落在窗口中的格子
+#[cfg(windows)]
+fn draw_board(u: &Universe, hdc: HDC, brushes: &mut Brushes, full: bool) {
+    draw_frame(&u.snapshot(), view_origin(u), hdc, brushes, full);
+    draw_status(hdc, &status_text(&BoardStatus::of(u)));
+}
+
+/// 照着快照画窗口里的格子, 窗口左上角是面板上的 (`top`, `left`)
+#[cfg(windows)]
+fn draw_frame(
+    frame: &FrameSnapshot,
+    (top, left): (u32, u32),
+    hdc: HDC,
+    brushes: &mut Brushes,
+    full: bool,
+) {
+    let rule = frame.rule();
+    let hex = rule.neighborhood() == Neighborhood::Hexagonal;
+    let layout = layout(hex);
+    if full {
+        for c in 0..layout.grid_cols {
+            for r in 0..layout.grid_rows {
+                if let Some(state) = frame.state(r + top, c + left) {
+                    let color = cell_color(frame, r + top, c + left, state);
+                    fill_cell(color, hex, hdc, brushes, c as i32, r as i32);
+                }
+            }
+        }
+        if is_plain(rule) && SHOW_HEAT.load(Ordering::Relaxed) {
+            outline_live(frame, (top, left), hex, hdc, brushes);
+        }
+    } else {
+        for &(row, col) in frame.changed_cells() {
+            let (r, c) = (row.wrapping_sub(top), col.wrapping_sub(left));
+            if r >= layout.grid_rows || c >= layout.grid_cols {
+                continue;
+            }
+            if let Some(state) = frame.state(row, col) {
+                fill_cell(cell_color(frame, row, col, state), hex, hdc, brushes, c as i32, r as i32);
+            }
+        }
+    }
+}
+
+/// 热度图上的格子都填成了热度的颜色, 再给窗口里的活细胞描上 `HEAT_OUTLINE` 的边框.
+/// 所有格子填完之后一起描, 六边形的画笔只换一次
+#[cfg(windows)]
+fn outline_live(
+    frame: &FrameSnapshot,
+    (top, left): (u32, u32),
+    hex: bool,
+    hdc: HDC,
+    brushes: &mut Brushes,
+) {
+    let layout = layout(hex);
+    unsafe {
+        let org_brs = SelectObject(hdc, GetStockObject(NULL_BRUSH as i32));
+        let org_pen = SelectObject(hdc, brushes.pen(HEAT_OUTLINE) as HGDIOBJ);
+        for c in 0..layout.grid_cols {
+            for r in 0..layout.grid_rows {
+                if frame.state(r + top, c + left) != Some(1) {
+                    continue;
+                }
+                if hex {
+                    let points: Vec<POINT> = layout
+                        .hex_corners(r, c)
+                        .iter()
+                        .map(|&(x, y)| POINT { x, y })
+                        .collect();
+                    Polygon(hdc, points.as_ptr(), points.len() as i32);
+                } else {
+                    let (x0, y0, x1, y1) = layout.cell_rect(r, c);
+                    let rec = RECT {
+                        left: x0,
+                        top: y0,
+                        right: x1,
+                        bottom: y1,
+                    };
+                    FrameRect(hdc, &rec, brushes.get(HEAT_OUTLINE));
+                }
+            }
+        }
+        SelectObject(hdc, org_pen);
+        SelectObject(hdc, org_brs);
+    }
+}
+
+/// 内置规则的名字, 其他规则的规则串
+#[cfg(windows)]
+fn rule_name(rule: Rule) -> String {
+    rule.name().map_or_else(|| rule.to_string(), String::from)
+}
+
+/// 状态栏要的面板信息和窗口左上角的位置. 在锁里复制出来, 放开锁之后再拼成字
+#[cfg(windows)]
+struct BoardStatus {
+    generation: i64,
+    population: u32,
+    rule: Rule,
+    growth_rate: f64,
+    lightning_rate: f64,
+    mutation_rate: f64,
+    topology: Topology,
+    auto_grow: bool,
+    size: (u32, u32),
+    paused: bool,
+    origin: (u32, u32),
+}
+
+#[cfg(windows)]
+impl BoardStatus {
+    fn of(u: &Universe) -> BoardStatus {
+        BoardStatus {
+            generation: u.count(),
+            population: u.population(),
+            rule: u.rule(),
+            growth_rate: u.growth_rate(),
+            lightning_rate: u.lightning_rate(),
+            mutation_rate: u.mutation_rate(),
+            topology: u.topology(),
+            auto_grow: u.auto_grow().is_some(),
+            size: (u.width(), u.height()),
+            paused: u.is_paused(),
+            origin: view_origin(u),
+        }
+    }
+}
+
+/// 状态栏两行里每一格的字: 周期、细胞数、规则、速度和鼠标下的格子, 边界、突变率、绘制间隔和稳定的代数
+#[cfg(windows)]
+fn status_text(board: &BoardStatus) -> Vec<Vec<String>> {
+    let mut rule = rule_name(board.rule);
+    if board.rule == Rule::ForestFire {
+        rule.push_str(&format!(
+            " p={:.0}% f={:.2}%",
+            board.growth_rate * 100.0,
+            board.lightning_rate * 100.0
+        ));
+    }
+    let mut border = match INFINITE.read().unwrap().as_ref() {
+        Some(inf) => format!("无边界 ({}, {})", inf.top, inf.left),
+        None => board.topology.to_string(),
+    };
+    if board.auto_grow {
+        border.push_str(&format!(" 自动扩展 {}x{}", board.size.0, board.size.1));
+    }
+    let interval = *INTERVAL.read().unwrap();
+    let stable = match STABLE_AT.load(Ordering::Relaxed) {
+        -1 => String::new(),
+        at if board.population == 0 => format!("稳定于第 {} 代 (全部死亡)", at),
+        at => format!("稳定于第 {} 代", at),
+    };
+    let mouse = match *MOUSE_CELL.read().unwrap() {
+        Some((c, r)) => {
+            let (top, left) = board.origin;
+            format!("格子: ({}, {})", r as u32 + top, c as u32 + left)
+        }
+        None => "格子: -".to_string(),
+    };
+    let speed = if interval == Duration::from_secs(0) {
+        format!("不限 {} 代/秒", TICK_RATE.load(Ordering::Relaxed))
+    } else {
+        format!(
+            "{}ms {} 代/秒",
+            interval.as_millis(),
+            TICK_RATE.load(Ordering::Relaxed)
+        )
+    };
+    vec![
+        vec![
+            format!("周期: {}", board.generation),
+            format!("细胞: {}", board.population),
+            format!("规则: {}", rule),
+            format!("速度: {}", speed),
+            mouse,
+        ],
+        vec![
+            format!("边界: {}", border),
+            format!("突变: {:.1}%", board.mutation_rate * 100.0),
+            format!("绘制: 1/{}", FRAME_SKIP.read().unwrap().every()),
+            stable,
+        ],
+    ]
+}
+
+/// 演化一代. 无边界模式下先把窗口里的面板 (可能刚被鼠标改过) 写回世界,
+/// 世界演化后再把窗口对应的区域读出来; 换成了世界不支持的规则时退出无边界模式.
+/// 自动扩展时窗口跟着原来的格子移动, 到了上限就关掉自动扩展并返回 true
+#[cfg(windows)]
+fn step_world() -> bool {
+    let mut u = UNIVERSE.write().unwrap();
+    let mut infinite = INFINITE.write().unwrap();
+    if let Some(inf) = infinite.as_mut() {
+        if inf.world.set_rule(u.rule()).is_ok() {
+            inf.world.store_viewport(&u, inf.top, inf.left);
+            inf.world.tick();
+            u.load_viewport(&inf.world, inf.top, inf.left);
+            u.record_population();
+            STATS.write().unwrap().record(&u);
+            return false;
+        }
+        *infinite = None;
+    }
+    // 活细胞很少时自动换成稀疏演化. 读档、打开文件都会换掉整个面板, 所以每一代都设一次
+    u.set_engine(Engine::Auto);
+    STATS.write().unwrap().tick(&mut u);
+    let (rows, cols) = u.grown();
+    if rows + cols > 0 {
+        let mut camera = CAMERA.write().unwrap();
+        camera.fit((u.width(), u.height()), (BOARD_WIDTH, BOARD_HEIGHT));
+        camera.pan(i64::from(rows), i64::from(cols));
+    }
+    if u.is_grow_capped() {
+        u.set_auto_grow(None);
+        return true;
+    }
+    false
+}
+
+/// 打开或关闭无边界模式, 打开时窗口里的面板放在世界的原点
+#[cfg(windows)]
+fn toggle_infinite(hwnd: HWND) {
+    let result = {
+        let mut u = UNIVERSE.write().unwrap();
+        let mut infinite = INFINITE.write().unwrap();
+        u.request_redraw();
+        if infinite.take().is_some() {
+            Ok(())
+        } else {
+            SparseUniverse::from_dense(&u, 0, 0).map(|world| {
+                *infinite = Some(Infinite {
+                    world,
+                    top: 0,
+                    left: 0,
+                });
+            })
+        }
+    };
+    if let Err(e) = result {
+        print_message(hwnd, &format!("无法切换到无边界模式: {}", e));
+    }
+}
+
+/// 移动窗口: 无边界模式下在世界里移动, 否则在比窗口大的面板里移动
+#[cfg(windows)]
+fn pan(d_row: i64, d_col: i64) {
+    let mut u = UNIVERSE.write().unwrap();
+    let mut infinite = INFINITE.write().unwrap();
+    match infinite.as_mut() {
+        Some(inf) => {
+            inf.world.store_viewport(&u, inf.top, inf.left);
+            inf.top += d_row;
+            inf.left += d_col;
+            u.load_viewport(&inf.world, inf.top, inf.left);
+        }
+        None => {
+            view_origin(&u);
+            CAMERA.write().unwrap().pan(d_row, d_col);
+        }
+    }
+    u.request_redraw();
+}
+
+/// 换成第 `index` 个内置规则, 面板保持不变
+#[cfg(windows)]
+fn select_preset(index: usize) {
+    let rule = rule::preset(PRESETS[index].0).expect("preset names are unique");
+    let mut u = UNIVERSE.write().unwrap();
+    u.set_rule(rule);
+    u.request_redraw();
+}
+
+/// 在内存 DC 上按窗口的画法重画一遍面板, 再用 GetDIBits 取出像素写成 24 位 BMP
+#[cfg(windows)]
+fn export_bmp(hwnd: HWND, u: &Universe, path: &Path) -> Result<(), Error> {
+    let (width, height) = board_size(hwnd);
+    let board = RECT {
+        left: 0,
+        top: 0,
+        right: width,
+        bottom: height,
+    };
+    let (width, height, pixels) = unsafe {
+        let hdc = GetDC(hwnd);
+        let mem_dc = CreateCompatibleDC(hdc);
+        let bitmap = CreateCompatibleBitmap(hdc, width, height);
+        ReleaseDC(hwnd, hdc);
+        let old = SelectObject(mem_dc, bitmap as HGDIOBJ);
+        let brushes = window_brushes(hwnd);
+        FillRect(mem_dc, &board, brushes.get(theme_colors().background));
+        draw_grid(mem_dc, is_hex(u), brushes);
+        draw_board(u, mem_dc, brushes, true);
+        SelectObject(mem_dc, old);
+
+        let mut info: BITMAPINFO = std::mem::zeroed();
+        info.bmiHeader.biSize = std::mem::size_of::<BITMAPINFOHEADER>() as u32;
+        info.bmiHeader.biWidth = width;
+        // 正数表示自底向上, 与 BMP 文件的行序一致
+        info.bmiHeader.biHeight = height;
+        info.bmiHeader.biPlanes = 1;
+        info.bmiHeader.biBitCount = 24;
+        info.bmiHeader.biCompression = BI_RGB;
+        let stride = (width as usize * 3).div_ceil(4) * 4;
+        let mut pixels = vec![0u8; stride * height as usize];
+        let lines = GetDIBits(
+            mem_dc,
+            bitmap,
+            0,
+            height as u32,
+            pixels.as_mut_ptr() as *mut _,
+            &mut info,
+            DIB_RGB_COLORS,
+        );
+        DeleteObject(bitmap as HGDIOBJ);
+        DeleteDC(mem_dc);
+        if lines == 0 {
+            return Err(Error::last_os_error());
+        }
+        (width, height, pixels)
+    };
+
+    // BITMAPFILEHEADER (14 字节) + BITMAPINFOHEADER (40 字节) + 像素
+    let offset = 14 + 40u32;
+    let mut bmp = Vec::with_capacity(offset as usize + pixels.len());
+    bmp.extend_from_slice(b"BM");
+    bmp.extend_from_slice(&(offset + pixels.len() as u32).to_le_bytes());
+    bmp.extend_from_slice(&[0; 4]);
+    bmp.extend_from_slice(&offset.to_le_bytes());
+    bmp.extend_from_slice(&40u32.to_le_bytes());
+    bmp.extend_from_slice(&width.to_le_bytes());
+    bmp.extend_from_slice(&height.to_le_bytes());
+    bmp.extend_from_slice(&1u16.to_le_bytes());
+    bmp.extend_from_slice(&24u16.to_le_bytes());
+    bmp.extend_from_slice(&BI_RGB.to_le_bytes());
+    bmp.extend_from_slice(&(pixels.len() as u32).to_le_bytes());
+    // 水平和垂直分辨率 (约 96 DPI), 调色板颜色数, 重要颜色数
+    bmp.extend_from_slice(&3780i32.to_le_bytes());
+    bmp.extend_from_slice(&3780i32.to_le_bytes());
+    bmp.extend_from_slice(&[0; 8]);
+    bmp.extend_from_slice(&pixels);
+    std::fs::write(path, bmp)
+}
+
+#[cfg(windows)]
+fn key_down(vk_code: i32) -> bool {
+    unsafe {
+        if GetAsyncKeyState(vk_code) < 0 {
+            true
+        } else {
+            false
+        }
+    }
+}
+
+#[cfg(windows)]
+unsafe extern "system" fn window_proc(
+    hwnd: HWND,
+    u_msg: UINT,
+    w_param: WPARAM,
+    l_param: LPARAM,
+) -> LRESULT {
+    match u_msg {
+        WM_CLOSE => {
+            if let Err(e) = autosave() {
+                print_message(hwnd, &format!("自动保存失败: {}", e));
+            }
+            DestroyWindow(hwnd);
+        }
+        WM_DESTROY => {
+            // 先等演化线程退出, 它不会再碰面板和窗口
+            let sim = SIM.write().unwrap().take();
+            drop(sim);
+            timeEndPeriod(1);
+            KillTimer(hwnd, TITLE_TIMER);
+            let ctx = GetWindowLongPtrW(hwnd, GWLP_USERDATA) as *mut RenderContext;
+            if !ctx.is_null() {
+                SetWindowLongPtrW(hwnd, GWLP_USERDATA, 0);
+                drop(Box::from_raw(ctx));
+            }
+            PostQuitMessage(u_msg as i32);
+        }
+        WM_CREATE => {
+            let ctx = Box::into_raw(Box::new(RenderContext::new()));
+            SetWindowLongPtrW(hwnd, GWLP_USERDATA, ctx as LONG_PTR);
+            // 让演化线程的睡眠精确到 1 毫秒左右, WM_DESTROY 时恢复
+            timeBeginPeriod(1);
+            let interval = tick_interval();
+            *INTERVAL.write().unwrap() = interval;
+            let sim = sim::spawn(interval, WindowSim {
+                hwnd: hwnd as usize,
+                turbo: None,
+                rate: TickRate::new(Instant::now()),
+                hidden: false,
+                stability: StabilityDetector::new(2),
+                settle: None,
+            });
+            *SIM.write().unwrap() = Some(sim);
+            // 之后演化线程一直拿着面板的锁也能取到计数器
+            lazy_static::initialize(&COUNTERS);
+            SetTimer(hwnd, TITLE_TIMER, TITLE_REFRESH, None);
+            refresh_title(hwnd);
+            create_toolbar(hwnd);
+            DragAcceptFiles(hwnd, TRUE);
+            let watch_arg = STARTUP_WATCH.write().unwrap().take();
+            if let Some(path) = watch_arg {
+                open_file(hwnd, &path);
+                start_watch(hwnd, &path);
+            }
+            // SetTimer(hwnd, 1, 10, Some(draw_run));
+        }
+        WM_PAINT => {
+            let mut ps: PAINTSTRUCT = PAINTSTRUCT {
+                hdc: null_mut(),
+                fErase: 0,
+                rcPaint: RECT {
+                    left: 0,
+                    right: 0,
+                    bottom: 0,
+                    top: 0,
+                },
+                fRestore: 0,
+                fIncUpdate: 0,
+                rgbReserved: [0; 32],
+            };
+            let hdc = BeginPaint(hwnd, &mut ps);
+            // 内存位图里是完整的画面, 露出来的部分直接复制过去
+            render(hwnd, hdc);
+            EndPaint(hwnd, &ps);
+        }
+        // 背景在内存位图里画, 这里不擦, 否则复制之前会先闪一下白色
+        WM_ERASEBKGND => return 1,
+        WM_KEYDOWN => {
+            // 帮助显示着的时候按什么键都只是把它关掉
+            if HELP_SHOWN.swap(false, Ordering::Relaxed) {
+                UNIVERSE.write().unwrap().request_redraw();
+            } else {
+                let typing = TURBO_INPUT.read().unwrap().is_some();
+                // 按住 T 时按键自动重复的消息不再重新开始跳过
+                let repeat = l_param & (1 << 30) != 0;
+                match key_action(w_param, typing) {
+                    Some(Action::HoldTurbo) if repeat => {}
+                    Some(action) => handle_action(hwnd, action),
+                    None => {}
+                }
+            }
+        }
+        WM_DROPFILES => {
+            let hdrop = w_param as HDROP;
+            let count = DragQueryFileW(hdrop, 0xFFFF_FFFF, null_mut(), 0);
+            let path = dropped_file(hdrop, 0);
+            DragFinish(hdrop);
+            open_file(hwnd, &path);
+            if count > 1 {
+                print_message(
+                    hwnd,
+                    &format!("一次只能打开一个文件, 只载入了 {}", path.display()),
+                );
+            }
+        }
+        WM_APP_RELOAD => reload_watched(hwnd),
+        WM_SIZE => match w_param {
+            SIZE_MINIMIZED => send_command(Command::Hide),
+            SIZE_RESTORED | SIZE_MAXIMIZED => {
+                send_command(Command::Show);
+                // 不等演化线程, 恢复时马上把整个客户区重画一遍
+                InvalidateRect(hwnd, null_mut(), FALSE);
+            }
+            _ => {}
+        },
+        // 高位不为 0 表示窗口激活状态变化时处于最小化, 恢复由 WM_SIZE 处理
+        WM_ACTIVATE if HIWORD(w_param as u32) != 0 => send_command(Command::Hide),
+        WM_APP_GROW_CAPPED => print_message(
+            hwnd,
+            &format!("面板已经达到 {0}x{0}, 停止自动扩展", MAX_GROW_SIZE),
+        ),
+        // 松开 T 时停下按住 T 开始的跳过
+        WM_KEYUP if w_param == 'T' as WPARAM && TURBO_HELD.swap(false, Ordering::Relaxed) => {
+            send_command(Command::Cancel)
+        }
+        WM_CHAR => turbo_input_char(hwnd, w_param),
+        WM_TIMER if w_param == TITLE_TIMER => refresh_title(hwnd),
+        // HIWORD 为 0 表示来自菜单, 或者是工具栏按钮的 BN_CLICKED
+        WM_COMMAND if HIWORD(w_param as u32) == 0 => {
+            if let Some(action) = menu_action(LOWORD(w_param as u32)) {
+                handle_action(hwnd, action);
+            }
+            // 点按钮时焦点跑到了按钮上, 还回来, 快捷键才能继续用
+            if l_param != 0 {
+                SetFocus(hwnd);
+            }
+        }
+        // 运行按钮在下面的 `sync_run_button` 里更新
+        WM_APP_RUN_STATE => (),
+        WM_INITMENUPOPUP => update_menu_checks(GetMenu(hwnd)),
+        WM_APP_SETTLED => {
+            let (at, empty) = {
+                let u = UNIVERSE.read().unwrap();
+                (u.count(), u.population() == 0)
+            };
+            let message = match l_param {
+                1 if empty => format!("演化了 {} 代, 第 {} 代全部死亡", w_param, at),
+                1 => format!(
+                    "演化了 {} 代, 稳定于第 {} 代",
+                    w_param,
+                    STABLE_AT.load(Ordering::Relaxed)
+                ),
+                0 => format!("演化了 {} 代仍在变化, 停在第 {} 代", w_param, at),
+                _ => format!("已取消, 演化了 {} 代, 停在第 {} 代", w_param, at),
+            };
+            show_note(hwnd, &message);
+            if l_param != 2 {
+                print_message(hwnd, &message);
+            }
+        }
+        WM_APP_TURBO => {
+            let target = GOTO_TARGET.load(Ordering::Relaxed);
+            let note = match (target, l_param) {
+                (-1, 1) => format!("跳过了 {} 代", w_param),
+                (-1, _) => format!("正在跳过: 已演化 {} 代 (Esc 取消)", w_param),
+                (_, 1) => {
+                    GOTO_TARGET.store(-1, Ordering::Relaxed);
+                    let at = COUNTERS.generation();
+                    if at == target {
+                        format!("到达第 {} 代", target)
+                    } else {
+                        format!("已取消, 停在第 {} 代 (目标第 {} 代)", at, target)
+                    }
+                }
+                (_, _) => format!("正在前往第 {} 代: 已演化 {} 代 (Esc 取消)", target, w_param),
+            };
+            show_note(hwnd, &note);
+        }
+        WM_MOUSEMOVE => {
+            // 换了格子时重画一次, 暂停时状态栏里的坐标也跟着变
+            let hovered = screen_cell(is_hex(&UNIVERSE.read().unwrap()), l_param);
+            let moved = {
+                let mut mouse = MOUSE_CELL.write().unwrap();
+                std::mem::replace(&mut *mouse, hovered) != hovered
+            };
+            if moved && !REDRAW_PENDING.swap(true, Ordering::Relaxed) {
+                PostMessageW(hwnd, WM_APP_REDRAW, 0, 0);
+            }
+            // println!("WM_MOUSEMOVE");
+            // let key_state = GetAsyncKeyState(VK_LBUTTON);
+            // println!("key_state: {}", key_state);
+            if key_down(VK_LBUTTON) {
+                if UNIVERSE.write().unwrap().begin_edit() {
+                    let hdc = board_dc(hwnd);
+                    let hex = is_hex(&UNIVERSE.read().unwrap());
+                    if let Some((col, row)) = screen_cell(hex, l_param) {
+                        let mut toggled = TOGGLED.write().unwrap();
+                        if *toggled != Some((col, row)) {
+                            *toggled = None;
+                            let mut u = UNIVERSE.write().unwrap();
+                            let brushes = window_brushes(hwnd);
+                            if !draw_paint(&mut u, hdc, brushes, col, row) {
+                                draw_change(&mut u, Cell::Alive, hdc, brushes, col, row);
+                            }
+                        }
+                    }
+                    // println!("c: {}, r: {}", col, row);
+                    ReleaseDC(hwnd, hdc); //归还系统绘图设备
+                }
+            }
+            if key_down(VK_RBUTTON) {
+                if UNIVERSE.write().unwrap().begin_edit() {
+                    let hdc = board_dc(hwnd);
+                    let hex = is_hex(&UNIVERSE.read().unwrap());
+                    if let Some((col, row)) = screen_cell(hex, l_param) {
+                        let mut u = UNIVERSE.write().unwrap();
+                        draw_change(&mut u, Cell::Dead, hdc, window_brushes(hwnd), col, row);
+                    }
+                    // println!("c: {}, r: {}", col, row);
+                    ReleaseDC(hwnd, hdc); //归还系统绘图设备
+                }
+            }
+        }
+        WM_LBUTTONUP => {
+            *TOGGLED.write().unwrap() = None;
+            UNIVERSE.write().unwrap().end_edit();
+        }
+        WM_RBUTTONUP => UNIVERSE.write().unwrap().end_edit(),
+        WM_RBUTTONDOWN => {
+            if UNIVERSE.write().unwrap().begin_edit() {
+                let hdc = board_dc(hwnd);
+                let mut u = UNIVERSE.write().unwrap();
+                if let Some((col, row)) = screen_cell(is_hex(&u), l_param) {
+                    draw_change(&mut u, Cell::Dead, hdc, window_brushes(hwnd), col, row);
+                }
+                // SendMessageW(hwnd, WM_DRAWITEM, 0, 0);
+                ReleaseDC(hwnd, hdc); //归还系统绘图设备
+            }
+        }
+        WM_LBUTTONDOWN => {
+            if UNIVERSE.write().unwrap().begin_edit() {
+                let hdc = board_dc(hwnd);
+                let mut u = UNIVERSE.write().unwrap();
+                if let Some((col, row)) = screen_cell(is_hex(&u), l_param) {
+                    let brushes = window_brushes(hwnd);
+                    if !draw_paint(&mut u, hdc, brushes, col, row) {
+                        draw_toggle(&mut u, hdc, brushes, col, row);
+                    }
+                    *TOGGLED.write().unwrap() = Some((col, row));
+                }
+                // SendMessageW(hwnd, WM_DRAWITEM, 0, 0);
+                ReleaseDC(hwnd, hdc); //归还系统绘图设备
+            }
+        }
+        // 滚轮缩放, 鼠标下面的那一格不动; 每转一格整个重画一次
+        WM_MOUSEWHEEL => {
+            let delta = i32::from(GET_WHEEL_DELTA_WPARAM(w_param))
+                + WHEEL_REST.load(Ordering::Relaxed);
+            let notches = delta / WHEEL_DELTA as i32;
+            WHEEL_REST.store(delta % WHEEL_DELTA as i32, Ordering::Relaxed);
+            // lParam 是屏幕坐标
+            let mut point = POINT {
+                x: i32::from(LOWORD(l_param as u32) as i16),
+                y: i32::from(HIWORD(l_param as u32) as i16),
+            };
+            if notches != 0 && ScreenToClient(hwnd, &mut point) != 0 {
+                let mut u = UNIVERSE.write().unwrap();
+                view_origin(&u);
+                let mut camera = CAMERA.write().unwrap();
+                let cell_px = camera.zoomed(notches);
+                if cell_px != camera.cell_px() {
+                    camera.zoom_at(point.x, point.y - TOOLBAR_HEIGHT, cell_px);
+                    u.request_redraw();
+                }
+            }
+        }
+        WM_APP_GRAPH => {
+            GRAPH_PENDING.store(false, Ordering::Relaxed);
+            render_graph(hwnd);
+        }
+        WM_APP_REDRAW => {
+            REDRAW_PENDING.store(false, Ordering::Relaxed);
+            let hdc = GetDC(hwnd);
+            // 只重画上一帧之后变过的格子, 面板被整体换掉时才整个重画
+            render(hwnd, hdc);
+            STEP_PENDING.store(false, Ordering::Relaxed);
+            // SetWindowTextW(hwnd, z.as_ptr());
+            ReleaseDC(hwnd, hdc); //归还系统绘图设备
+        }
+        _ => (),
+    };
+    if u_msg != WM_APP_REDRAW {
+        present_paused(hwnd);
+    }
+    sync_run_button(hwnd);
+    return DefWindowProcW(hwnd, u_msg, w_param, l_param);
+}
+
+/// 暂停时演化线程在睡觉, 窗口线程处理完消息后如果改过面板就自己请求重画.
+/// 弹出的对话框里嵌套处理消息时外面可能还拿着锁, 拿不到就等下一条消息
+#[cfg(windows)]
+fn present_paused(hwnd: HWND) {
+    let u = match UNIVERSE.try_read() {
+        Ok(u) => u,
+        Err(_) => return,
+    };
+    if u.is_paused() && u.needs_frame() && !REDRAW_PENDING.swap(true, Ordering::Relaxed) {
+        unsafe { PostMessageW(hwnd, WM_APP_REDRAW, 0, 0) };
+    }
+}
+
+/// 窗口的演化线程: 运行时每个间隔演化一代, 画面要更新时请窗口重画.
+/// 暂停时线程睡到下一条命令, 这期间窗口自己改了面板就自己请求重画, 见 `present_paused`
+#[cfg(windows)]
+struct WindowSim {
+    /// HWND 不能跨线程传递, 存成整数; PostMessageW 可以从任意线程调用
+    hwnd: usize,
+    /// 正在跳过的代数, 见 `Command::Advance`
+    turbo: Option<Turbo>,
+    rate: TickRate,
+    /// 窗口最小化期间不画; 没打开后台演化时也不演化
+    hidden: bool,
+    /// 认出静物和周期 2 的振荡器, 见 `AUTO_STOP`
+    stability: StabilityDetector,
+    /// 正在一直演化到稳定, 见 `Command::RunUntilStable`
+    settle: Option<Settle>,
+}
+
+#[cfg(windows)]
+struct Settle {
+    left: u64,
+    done: u64,
+    drawn: Instant,
+}
+
+#[cfg(windows)]
+struct Turbo {
+    left: u64,
+    done: u64,
+    reported: Instant,
+    /// `Command::AdvanceTo`: 一定要演化完, 演化完后暂停
+    exact: bool,
+}
+
+#[cfg(windows)]
+impl WindowSim {
+    fn post(&self, message: UINT) {
+        self.post_with(message, 0, 0);
+    }
+
+    fn post_with(&self, message: UINT, w_param: WPARAM, l_param: LPARAM) {
+        unsafe {
+            PostMessageW(self.hwnd as HWND, message, w_param, l_param);
+        }
+    }
+
+    /// 演化一代并记下实际的速度. `detect` 为 true 并且面板停下来时暂停, 返回 true
+    fn step(&mut self, detect: bool) -> bool {
+        if step_world() {
+            self.post(WM_APP_GROW_CAPPED);
+        }
+        self.rate.tick(Instant::now());
+        TICK_RATE.store(self.rate.per_second().round() as u32, Ordering::Relaxed);
+        if !detect {
+            return false;
+        }
+        let mut u = UNIVERSE.write().unwrap();
+        let at = match self.stability.observe(&u) {
+            Stability::Changing => return false,
+            Stability::Empty => u.count(),
+            Stability::Repeating { period } => u.count() - i64::from(period),
+        };
+        STABLE_AT.store(at, Ordering::Relaxed);
+        u.set_run_state(RunState::Paused);
+        self.post(WM_APP_RUN_STATE);
+        // 状态栏跟着整个面板一起重画
+        u.request_redraw();
+        true
+    }
+
+    /// 面板要重新开始演化时忘掉之前见过的面板
+    fn restart(&mut self) {
+        self.stability.clear();
+        STABLE_AT.store(-1, Ordering::Relaxed);
+    }
+
+    /// 跳过期间不画, 每一小段演化若干代, 到时间了报告进度
+    fn advance(&mut self) {
+        let start = Instant::now();
+        let mut turbo = match self.turbo.take() {
+            Some(turbo) => turbo,
+            None => return,
+        };
+        let mut stopped = false;
+        while !stopped && turbo.left > 0 && start.elapsed() < TURBO_SLICE {
+            stopped = self.step(!turbo.exact && AUTO_STOP.load(Ordering::Relaxed));
+            turbo.left -= 1;
+            turbo.done += 1;
+        }
+        if stopped || turbo.left == 0 {
+            self.finish_turbo(turbo);
+            return;
+        }
+        if turbo.reported.elapsed() >= TURBO_REPORT {
+            turbo.reported = Instant::now();
+            self.post_with(WM_APP_TURBO, turbo.done as WPARAM, 0);
+        }
+        self.turbo = Some(turbo);
+    }
+
+    /// 不管有没有打开自动暂停都检查稳定, 每隔 `SETTLE_FRAME` 画一帧
+    fn settle(&mut self) {
+        let start = Instant::now();
+        let mut settle = match self.settle.take() {
+            Some(settle) => settle,
+            None => return,
+        };
+        // 中途被空格暂停了就算取消
+        if UNIVERSE.read().unwrap().is_paused() {
+            self.finish_settle(settle, 2);
+            return;
+        }
+        let mut stable = false;
+        while !stable && settle.left > 0 && start.elapsed() < TURBO_SLICE {
+            stable = self.step(true);
+            settle.left -= 1;
+            settle.done += 1;
+        }
+        if stable || settle.left == 0 {
+            self.finish_settle(settle, if stable { 1 } else { 0 });
+            return;
+        }
+        if settle.drawn.elapsed() >= SETTLE_FRAME {
+            settle.drawn = Instant::now();
+            self.present(&UNIVERSE.read().unwrap());
+        }
+        self.settle = Some(settle);
+    }
+
+    /// 暂停并画出最后一代. 速度和绘制的设置一直没动过, 之后照原来的设置演化
+    fn finish_settle(&mut self, settle: Settle, outcome: LPARAM) {
+        let mut u = UNIVERSE.write().unwrap();
+        u.set_run_state(RunState::Paused);
+        u.request_redraw();
+        self.present(&u);
+        self.post_with(WM_APP_SETTLED, settle.done as WPARAM, outcome);
+    }
+
+    /// 把整个面板重画一次. 暂停时线程之后就睡了, 要在这里请求重画
+    fn finish_turbo(&mut self, turbo: Turbo) {
+        let mut u = UNIVERSE.write().unwrap();
+        if turbo.exact {
+            u.set_run_state(RunState::Paused);
+        }
+        u.request_redraw();
+        self.present(&u);
+        self.post_with(WM_APP_TURBO, turbo.done as WPARAM, 1);
+    }
+
+    /// 面板有没画过的变化时请窗口重画, 已经发出还没处理的就不再发. 最小化时不画
+    fn present(&self, u: &Universe) {
+        if !self.hidden && u.needs_frame() && !REDRAW_PENDING.swap(true, Ordering::Relaxed) {
+            self.post(WM_APP_REDRAW);
+        }
+    }
+}
+
+#[cfg(windows)]
+impl Simulation for WindowSim {
+    fn apply(&mut self, command: Command) {
+        let mut u = UNIVERSE.write().unwrap();
+        let was_paused = u.is_paused();
+        match command {
+            Command::Pause => u.set_run_state(RunState::Paused),
+            Command::Resume => {
+                u.set_run_state(RunState::Running);
+                self.restart();
+            }
+            Command::Toggle => {
+                u.toggle_run_state();
+                if !u.is_paused() {
+                    self.restart();
+                }
+            }
+            Command::Reset => {
+                u.reset();
+                self.restart();
+                if CLEAR_STATS_ON_RESET {
+                    STATS.write().unwrap().clear();
+                }
+            }
+            // 跳过期间 `tick` 只演化不请求重画
+            Command::Advance(left) => {
+                self.restart();
+                self.turbo = Some(Turbo {
+                    left,
+                    done: 0,
+                    reported: Instant::now(),
+                    exact: false,
+                });
+            }
+            // 命令发出之后面板可能又演化了几代, 按现在的代数算还差多少
+            Command::AdvanceTo(target) => {
+                let turbo = Turbo {
+                    left: Jump::To(target).steps_from(u.count()).unwrap_or(0),
+                    done: 0,
+                    reported: Instant::now(),
+                    exact: true,
+                };
+                if turbo.left == 0 {
+                    drop(u);
+                    self.finish_turbo(turbo);
+                    return;
+                }
+                self.restart();
+                self.turbo = Some(turbo);
+            }
+            Command::RunUntilStable(cap) => {
+                self.restart();
+                self.settle = Some(Settle {
+                    left: cap,
+                    done: 0,
+                    drawn: Instant::now(),
+                });
+                // 运行中窗口不会自己请求重画, 帧数由 `settle` 控制
+                u.set_run_state(RunState::Running);
+            }
+            Command::Cancel => {
+                drop(u);
+                if let Some(turbo) = self.turbo.take() {
+                    self.finish_turbo(turbo);
+                }
+                if let Some(settle) = self.settle.take() {
+                    self.finish_settle(settle, 2);
+                }
+                return;
+            }
+            Command::Step if u.is_paused() => {
+                drop(u);
+                self.step(AUTO_STOP.load(Ordering::Relaxed));
+                u = UNIVERSE.write().unwrap();
+                // 没有格子变化时也要画, 状态栏里的代数跟着走
+                if !u.needs_frame() {
+                    u.request_redraw();
+                }
+            }
+            Command::Step => {}
+            Command::Hide => self.hidden = true,
+            Command::Show => {
+                self.hidden = false;
+                u.request_redraw();
+            }
+            Command::SetInterval(_) => {}
+        }
+        if u.is_paused() != was_paused {
+            self.post(WM_APP_RUN_STATE);
+        }
+        if u.is_paused() || self.hidden {
+            // 接下来线程要睡到下一条命令, 暂停前的最后一代和命令造成的变化现在就画
+            self.present(&u);
+            self.rate = TickRate::new(Instant::now());
+            TICK_RATE.store(0, Ordering::Relaxed);
+        }
+    }
+
+    fn busy(&self) -> bool {
+        self.turbo.is_some() || self.settle.is_some()
+    }
+
+    fn idle(&self) -> bool {
+        let suspended = self.hidden && !BACKGROUND_SIM.load(Ordering::Relaxed);
+        self.turbo.is_none()
+            && self.settle.is_none()
+            && (suspended || UNIVERSE.read().unwrap().is_paused())
+    }
+
+    fn tick(&mut self) {
+        if self.turbo.is_some() {
+            self.advance();
+            return;
+        }
+        if self.settle.is_some() {
+            self.settle();
+            return;
+        }
+        let running = !UNIVERSE.read().unwrap().is_paused();
+        let mut stopped = false;
+        if running {
+            stopped = self.step(AUTO_STOP.load(Ordering::Relaxed));
+            #[cfg(feature = "gif")]
+            {
+                // 和 F9 一样先拿面板再拿录像的锁
+                let u = UNIVERSE.read().unwrap();
+                RECORDER.write().unwrap().capture(&u);
+            }
+        }
+        let u = UNIVERSE.read().unwrap();
+        // 刚被别的地方暂停时这一代总是要画, 画面上才是暂停时真实的面板
+        if !running || stopped || FRAME_SKIP.write().unwrap().tick() {
+            self.present(&u);
+        } else if !self.hidden
+            && SHOW_GRAPH.load(Ordering::Relaxed)
+            && !GRAPH_PENDING.swap(true, Ordering::Relaxed)
+        {
+            // 跳过的这一代不画面板, 人口曲线照样往前走
+            self.post(WM_APP_GRAPH);
+        }
+    }
+}
+
+/// 把命令交给演化线程, 线程还没启动或已经结束时什么都不做
+#[cfg(windows)]
+fn send_command(command: Command) {
+    if let Some(sim) = SIM.read().unwrap().as_ref() {
+        sim.send(command);
+    }
+}
+
+/// 菜单和按键共用的操作, 都交给 `handle_action` 处理. 发给演化线程的是 `sim::Command`
+#[cfg(windows)]
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+enum Action {
+    Open,
+    SaveAs,
+    #[cfg(feature = "png")]
+    ExportPng,
+    Exit,
+    Clear,
+    Randomize,
+    Copy,
+    Paste,
+    ToggleRun,
+    Step,
+    Faster,
+    Slower,
+    /// `SPEED_LADDER` 里的第几档
+    Speed(usize),
+    /// `PRESETS` 里的第几个规则
+    Preset(usize),
+    ToggleAutoStop,
+    ToggleBackground,
+    /// 在棋盘上盖一层按键说明, 再按任何键关掉
+    Help,
+    About,
+    ExportStats,
+    /// 换成 `PRESETS` 里的下一个规则
+    NextPreset,
+    /// 在这个规则和 Conway 之间来回切换
+    ToggleBrain,
+    ToggleCyclic,
+    ToggleSandpile,
+    ToggleForest,
+    GrowthUp,
+    GrowthDown,
+    LightningUp,
+    LightningDown,
+    MutationUp,
+    MutationDown,
+    LoadCircuit,
+    NextTopology,
+    /// 按住时一直跳过, 松开时在 WM_KEYUP 里停下
+    HoldTurbo,
+    GoTo,
+    Cancel,
+    /// 少画几帧
+    DrawFewer,
+    DrawMore,
+    ToggleTiles,
+    ToggleGrid,
+    ToggleAges,
+    ToggleTrails,
+    NextTheme,
+    PickColors,
+    ToggleNeighbors,
+    ToggleDiff,
+    ToggleGraph,
+    ToggleHeat,
+    #[cfg(feature = "png")]
+    ExportHeatPng,
+    ToggleGrow,
+    ToggleInfinite,
+    ExploreRule,
+    /// 探索过的规则里的上一条 / 下一条
+    RuleBack,
+    RuleForward,
+    /// 无边界模式下移动窗口: 行、列方向各移动几格
+    Pan(i64, i64),
+    NextPaintColor,
+    RunUntilStable,
+    SaveState,
+    LoadState,
+    ToggleWatch,
+    #[cfg(feature = "json")]
+    ExportJson,
+    ExportBmp,
+    ExportSvg,
+    #[cfg(feature = "gif")]
+    ToggleGif,
+}
+
+/// 菜单项的 id. 速度和规则子菜单从 `SPEED` 和 `PRESET` 开始按档位和下标排
+#[cfg(windows)]
+mod menu_id {
+    pub const OPEN: u16 = 100;
+    pub const SAVE_AS: u16 = 101;
+    #[cfg(feature = "png")]
+    pub const EXPORT_PNG: u16 = 102;
+    pub const EXIT: u16 = 103;
+    pub const CLEAR: u16 = 200;
+    pub const RANDOMIZE: u16 = 201;
+    pub const COPY: u16 = 202;
+    pub const PASTE: u16 = 203;
+    pub const RUN: u16 = 300;
+    pub const STEP: u16 = 301;
+    pub const FASTER: u16 = 302;
+    pub const SLOWER: u16 = 303;
+    pub const AUTO_STOP: u16 = 304;
+    pub const BACKGROUND: u16 = 305;
+    pub const SPEED: u16 = 400;
+    pub const PRESET: u16 = 500;
+    pub const GRID: u16 = 600;
+    pub const THEME: u16 = 601;
+    pub const COLORS: u16 = 602;
+    pub const SHORTCUTS: u16 = 900;
+    pub const ABOUT: u16 = 901;
+}
+
+/// 按键要求按下的修饰键, 多按或少按了都不算
+#[cfg(windows)]
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+enum Mods {
+    Plain,
+    Ctrl,
+    Shift,
+    CtrlShift,
+}
+
+/// 一个按键绑定. 按键处理和 F1 的帮助都只看 `BINDINGS`, 帮助不会和实际的按键对不上
+#[cfg(windows)]
+#[derive(Copy, Clone, Debug)]
+struct Binding {
+    key: i32,
+    mods: Mods,
+    /// 输入代数时也能用; 否则这个键可能是输入的一部分, 或者会打断输入
+    while_typing: bool,
+    /// 帮助里显示的按键名
+    name: &'static str,
+    label: &'static str,
+    action: Action,
+}
+
+#[cfg(windows)]
+impl Binding {
+    const fn except_typing(self) -> Binding {
+        Binding {
+            while_typing: false,
+            ..self
+        }
+    }
+}
+
+#[cfg(windows)]
+const fn bind(
+    key: i32,
+    mods: Mods,
+    name: &'static str,
+    label: &'static str,
+    action: Action,
+) -> Binding {
+    Binding {
+        key,
+        mods,
+        while_typing: true,
+        name,
+        label,
+        action,
+    }
+}
+
+/// 所有的按键, 按帮助里列出的顺序. 连着几条说明一样的在帮助里合成一行
+#[cfg(windows)]
+const BINDINGS: &[Binding] = &[
+    bind(VK_SPACE, Mods::Plain, "空格", "暂停/继续", Action::ToggleRun).except_typing(),
+    bind(VK_F2, Mods::Plain, "F2", "暂停/继续", Action::ToggleRun),
+    bind('N' as i32, Mods::Plain, "N", "暂停时演化一代", Action::Step).except_typing(),
+    bind(VK_OEM_PLUS, Mods::Plain, "+", "加快", Action::Faster).except_typing(),
+    bind(VK_ADD, Mods::Plain, "+", "加快", Action::Faster).except_typing(),
+    bind(VK_PRIOR, Mods::Plain, "PageUp", "加快", Action::Faster),
+    bind(VK_OEM_MINUS, Mods::Plain, "-", "减慢", Action::Slower).except_typing(),
+    bind(VK_SUBTRACT, Mods::Plain, "-", "减慢", Action::Slower).except_typing(),
+    bind(VK_NEXT, Mods::Plain, "PageDown", "减慢", Action::Slower),
+    bind(VK_OEM_PERIOD, Mods::Plain, ".", "少画几帧", Action::DrawFewer),
+    bind(VK_OEM_COMMA, Mods::Plain, ",", "画得更频繁", Action::DrawMore),
+    bind(VK_F5, Mods::Plain, "F5", "重新随机生成", Action::Randomize),
+    bind(VK_F4, Mods::Plain, "F4", "清屏", Action::Clear),
+    bind('A' as i32, Mods::Plain, "A", "稳定时自动暂停", Action::ToggleAutoStop).except_typing(),
+    bind('S' as i32, Mods::Shift, "Shift+S", "一直演化到稳定", Action::RunUntilStable).except_typing(),
+    bind('T' as i32, Mods::Plain, "按住 T", "不画画面尽快演化", Action::HoldTurbo).except_typing(),
+    bind('G' as i32, Mods::Ctrl, "Ctrl+G", "前往第几代", Action::GoTo),
+    bind(VK_ESCAPE, Mods::Plain, "Esc", "取消跳过", Action::Cancel).except_typing(),
+    bind('1' as i32, Mods::Ctrl, "Ctrl+1", "内置规则", Action::Preset(0)).except_typing(),
+    bind('2' as i32, Mods::Ctrl, "Ctrl+2", "内置规则", Action::Preset(1)).except_typing(),
+    bind('3' as i32, Mods::Ctrl, "Ctrl+3", "内置规则", Action::Preset(2)).except_typing(),
+    bind('4' as i32, Mods::Ctrl, "Ctrl+4", "内置规则", Action::Preset(3)).except_typing(),
+    bind('5' as i32, Mods::Ctrl, "Ctrl+5", "内置规则", Action::Preset(4)).except_typing(),
+    bind('6' as i32, Mods::Ctrl, "Ctrl+6", "内置规则", Action::Preset(5)).except_typing(),
+    bind('7' as i32, Mods::Ctrl, "Ctrl+7", "内置规则", Action::Preset(6)).except_typing(),
+    bind('8' as i32, Mods::Ctrl, "Ctrl+8", "内置规则", Action::Preset(7)).except_typing(),
+    bind('9' as i32, Mods::Ctrl, "Ctrl+9", "内置规则", Action::Preset(8)).except_typing(),
+    bind(VK_TAB, Mods::Plain, "Tab", "下一个内置规则", Action::NextPreset),
+    bind('B' as i32, Mods::Ctrl, "Ctrl+B", "Brian's Brain / Conway", Action::ToggleBrain),
+    bind('Y' as i32, Mods::Ctrl, "Ctrl+Y", "循环元胞自动机 / Conway", Action::ToggleCyclic),
+    bind('D' as i32, Mods::Ctrl, "Ctrl+D", "沙堆 / Conway", Action::ToggleSandpile),
+    bind('F' as i32, Mods::Ctrl, "Ctrl+F", "森林火灾 / Conway", Action::ToggleForest),
+    bind(VK_OEM_6, Mods::Plain, "]", "调高长树的概率", Action::GrowthUp),
+    bind(VK_OEM_4, Mods::Plain, "[", "调低长树的概率", Action::GrowthDown),
+    bind(VK_OEM_6, Mods::Shift, "Shift+]", "调高雷击的概率", Action::LightningUp),
+    bind(VK_OEM_4, Mods::Shift, "Shift+[", "调低雷击的概率", Action::LightningDown),
+    // 输入代数时 Shift+'=' 是开头的 '+'
+    bind(VK_OEM_PLUS, Mods::Shift, "Shift++", "调高突变率", Action::MutationUp).except_typing(),
+    bind(VK_ADD, Mods::Shift, "Shift++", "调高突变率", Action::MutationUp).except_typing(),
+    bind(VK_OEM_MINUS, Mods::Shift, "Shift+-", "调低突变率", Action::MutationDown).except_typing(),
+    bind(VK_SUBTRACT, Mods::Shift, "Shift+-", "调低突变率", Action::MutationDown).except_typing(),
+    bind('L' as i32, Mods::Ctrl, "Ctrl+L", "载入 Wireworld 示例电路", Action::LoadCircuit),
+    bind('P' as i32, Mods::Ctrl, "Ctrl+P", "换一种画的颜色", Action::NextPaintColor),
+    bind('T' as i32, Mods::Ctrl, "Ctrl+T", "切换边界", Action::NextTopology),
+    bind(VK_F6, Mods::Plain, "F6", "自动扩展面板", Action::ToggleGrow),
+    bind('I' as i32, Mods::Ctrl, "Ctrl+I", "无边界模式", Action::ToggleInfinite),
+    bind(VK_UP, Mods::Plain, "方向键", "移动窗口", Action::Pan(-PAN_STEP, 0)),
+    bind(VK_DOWN, Mods::Plain, "方向键", "移动窗口", Action::Pan(PAN_STEP, 0)),
+    bind(VK_LEFT, Mods::Plain, "方向键", "移动窗口", Action::Pan(0, -PAN_STEP)),
+    bind(VK_RIGHT, Mods::Plain, "方向键", "移动窗口", Action::Pan(0, PAN_STEP)),
+    bind('R' as i32, Mods::Ctrl, "Ctrl+R", "随机探索一条规则", Action::ExploreRule),
+    bind(VK_LEFT, Mods::Ctrl, "Ctrl+←", "上一条探索过的规则", Action::RuleBack),
+    bind(VK_RIGHT, Mods::Ctrl, "Ctrl+→", "下一条探索过的规则", Action::RuleForward),
+    bind('O' as i32, Mods::Ctrl, "Ctrl+O", "打开", Action::Open).except_typing(),
+    bind('E' as i32, Mods::CtrlShift, "Ctrl+Shift+E", "另存为", Action::SaveAs).except_typing(),
+    bind('W' as i32, Mods::Ctrl, "Ctrl+W", "监视打开的文件", Action::ToggleWatch),
+    bind('S' as i32, Mods::CtrlShift, "Ctrl+Shift+S", "保存状态", Action::SaveState),
+    bind('O' as i32, Mods::CtrlShift, "Ctrl+Shift+O", "读取保存的状态", Action::LoadState),
+    bind('C' as i32, Mods::Ctrl, "Ctrl+C", "复制 RLE", Action::Copy).except_typing(),
+    bind('V' as i32, Mods::Ctrl, "Ctrl+V", "粘贴 RLE", Action::Paste).except_typing(),
+    #[cfg(feature = "png")]
+    bind(VK_F12, Mods::Plain, "F12", "导出 PNG", Action::ExportPng),
+    bind(VK_F12, Mods::Ctrl, "Ctrl+F12", "导出 BMP", Action::ExportBmp),
+    #[cfg(feature = "png")]
+    bind(VK_F12, Mods::Shift, "Shift+F12", "导出热度图 PNG", Action::ExportHeatPng),
+    bind(VK_F11, Mods::Ctrl, "Ctrl+F11", "导出 SVG", Action::ExportSvg),
+    #[cfg(feature = "json")]
+    bind('J' as i32, Mods::Ctrl, "Ctrl+J", "导出 JSON", Action::ExportJson),
+    #[cfg(feature = "gif")]
+    bind(VK_F9, Mods::Plain, "F9", "开始/结束录制 GIF", Action::ToggleGif),
+    bind(VK_F3, Mods::Plain, "F3", "导出统计", Action::ExportStats),
+    bind(VK_F8, Mods::Plain, "F8", "显示重新计算的块", Action::ToggleTiles),
+    bind('G' as i32, Mods::Plain, "G", "显示/隐藏网格线", Action::ToggleGrid).except_typing(),
+    bind('C' as i32, Mods::Plain, "C", "按年龄着色", Action::ToggleAges).except_typing(),
+    bind('F' as i32, Mods::Plain, "F", "显示刚死的细胞留下的拖尾", Action::ToggleTrails).except_typing(),
+    bind('D' as i32, Mods::Plain, "D", "暂停时显示邻居数", Action::ToggleNeighbors).except_typing(),
+    bind('X' as i32, Mods::Plain, "X", "标出上一代的变化", Action::ToggleDiff).except_typing(),
+    bind('P' as i32, Mods::Plain, "P", "显示人口曲线", Action::ToggleGraph).except_typing(),
+    bind('M' as i32, Mods::Plain, "M", "按活跃度显示热度图", Action::ToggleHeat).except_typing(),
+    bind('K' as i32, Mods::Plain, "K", "换一套配色", Action::NextTheme).except_typing(),
+    bind('K' as i32, Mods::CtrlShift, "Ctrl+Shift+K", "自己选颜色", Action::PickColors),
+    bind('K' as i32, Mods::Ctrl, "Ctrl+K", "最小化时继续演化", Action::ToggleBackground),
+    bind(VK_F1, Mods::Plain, "F1", "显示这份帮助", Action::Help),
+    bind('H' as i32, Mods::Plain, "H", "显示这份帮助", Action::Help).except_typing(),
+];
+
+/// 按下的键对应的操作
+#[cfg(windows)]
+fn key_action(w_param: WPARAM, typing: bool) -> Option<Action> {
+    let mods = match (key_down(VK_CONTROL), key_down(VK_SHIFT)) {
+        (false, false) => Mods::Plain,
+        (true, false) => Mods::Ctrl,
+        (false, true) => Mods::Shift,
+        (true, true) => Mods::CtrlShift,
+    };
+    let binding = BINDINGS.iter().find(|binding| {
+        binding.key == w_param as i32 && binding.mods == mods && (binding.while_typing || !typing)
+    })?;
+    match binding.action {
+        Action::Preset(index) if index >= PRESETS.len() => None,
+        action => Some(action),
+    }
+}
+
+/// 帮助里的每一行: (按键, 说明). 连着几条说明一样的合成一行, 三个键以上只写头尾
+#[cfg(windows)]
+fn help_lines() -> Vec<(String, &'static str)> {
+    let mut groups: Vec<(Vec<&str>, &str)> = Vec::new();
+    for binding in BINDINGS {
+        match groups.last_mut() {
+            Some((names, label)) if *label == binding.label => {
+                if !names.contains(&binding.name) {
+                    names.push(binding.name);
+                }
+            }
+            _ => groups.push((vec![binding.name], binding.label)),
+        }
+    }
+    groups
+        .into_iter()
+        .map(|(names, label)| {
+            let keys = match names.len() {
+                1 | 2 => names.join(" / "),
+                n => format!("{} ~ {}", names[0], names[n - 1]),
+            };
+            (keys, label)
+        })
+        .collect()
+}
+
+/// 菜单项 id 对应的操作
+#[cfg(windows)]
+fn menu_action(id: u16) -> Option<Action> {
+    let speeds = menu_id::SPEED..menu_id::SPEED + SPEED_LADDER.len() as u16;
+    let presets = menu_id::PRESET..menu_id::PRESET + PRESETS.len() as u16;
+    let action = match id {
+        menu_id::OPEN => Action::Open,
+        menu_id::SAVE_AS => Action::SaveAs,
+        #[cfg(feature = "png")]
+        menu_id::EXPORT_PNG => Action::ExportPng,
+        menu_id::EXIT => Action::Exit,
+        menu_id::CLEAR => Action::Clear,
+        menu_id::RANDOMIZE => Action::Randomize,
+        menu_id::COPY => Action::Copy,
+        menu_id::PASTE => Action::Paste,
+        menu_id::RUN => Action::ToggleRun,
+        menu_id::STEP => Action::Step,
+        menu_id::FASTER => Action::Faster,
+        menu_id::SLOWER => Action::Slower,
+        menu_id::AUTO_STOP => Action::ToggleAutoStop,
+        menu_id::BACKGROUND => Action::ToggleBackground,
+        menu_id::GRID => Action::ToggleGrid,
+        menu_id::THEME => Action::NextTheme,
+        menu_id::COLORS => Action::PickColors,
+        menu_id::SHORTCUTS => Action::Help,
+        menu_id::ABOUT => Action::About,
+        id if speeds.contains(&id) => Action::Speed(usize::from(id - menu_id::SPEED)),
+        id if presets.contains(&id) => Action::Preset(usize::from(id - menu_id::PRESET)),
+        _ => return None,
+    };
+    Some(action)
+}
+
+/// 换上第 `index` 套配色. 旧配色的画刷和画笔都删掉, `prepare` 发现换了配色会连背景和网格一起重画
+#[cfg(windows)]
+fn set_theme(hwnd: HWND, index: usize) {
+    THEME.store(index, Ordering::Relaxed);
+    if let Some(ctx) = unsafe { render_context(hwnd) } {
+        ctx.brushes.clear();
+    }
+    UNIVERSE.write().unwrap().request_redraw();
+    match save_settings() {
+        Ok(()) => show_note(hwnd, &format!("配色: {}", theme_colors().name)),
+        Err(e) => show_note(hwnd, &format!("保存设置失败: {}", e)),
+    }
+}
+
+/// 执行一个操作, 不管它来自按键还是菜单
+#[cfg(windows)]
+fn handle_action(hwnd: HWND, action: Action) {
+    match action {
+        Action::Open => {
+            // 对话框打开期间消息循环照常运行, 选好文件之后才去锁 UNIVERSE
+            if let Some(path) = ask_open_path(hwnd) {
+                open_file(hwnd, &path);
+            }
+        }
+        Action::SaveAs => save_as(hwnd),
+        #[cfg(feature = "png")]
+        Action::ExportPng => {
+            let (path, result) = {
+                let u = UNIVERSE.read().unwrap();
+                let path = format!("life_{}.png", u.count());
+                let result = export_png(&u, EXPORT_CELL_PX, Path::new(&path));
+                (path, result)
+            };
+            if let Err(e) = result {
+                print_message(hwnd, &format!("导出 {} 失败: {}", path, e));
+            }
+        }
+        #[cfg(feature = "png")]
+        Action::ExportHeatPng => {
+            let (path, result) = {
+                let u = UNIVERSE.read().unwrap();
+                let path = format!("life_{}_heat.png", u.count());
+                let result = export_heat_png(&u, EXPORT_CELL_PX, Path::new(&path));
+                (path, result)
+            };
+            if let Err(e) = result {
+                print_message(hwnd, &format!("导出 {} 失败: {}", path, e));
+            }
+        }
+        // 和点关闭按钮一样先自动保存
+        Action::Exit => unsafe {
+            PostMessageW(hwnd, WM_CLOSE, 0, 0);
+        },
+        Action::Clear => UNIVERSE.write().unwrap().dead_all(),
+        Action::Randomize => send_command(Command::Reset),
+        Action::Copy => {
+            // 空面板时只有 RLE 头和结束符 `!`
+            let rle = write_rle(&Pattern::from_universe(&UNIVERSE.read().unwrap()));
+            if let Err(e) = set_clipboard_text(hwnd, &rle) {
+                print_message(hwnd, &format!("复制到剪贴板失败: {}", e));
+            }
+        }
+        // 弹出消息框时消息循环仍在运行, 计时器会再去锁 UNIVERSE, 所以先释放锁再提示
+        Action::Paste => paste_clipboard(hwnd),
+        Action::ToggleRun => send_command(Command::Toggle),
+        Action::Step => {
+            let paused = UNIVERSE.read().unwrap().is_paused();
+            if paused && !STEP_PENDING.swap(true, Ordering::Relaxed) {
+                send_command(Command::Step);
+            }
+        }
+        Action::Faster | Action::Slower | Action::Speed(_) => {
+            let mut interval = INTERVAL.write().unwrap();
+            *interval = match action {
+                Action::Faster => sim::faster(*interval),
+                Action::Slower => sim::slower(*interval),
+                Action::Speed(index) => SPEED_LADDER[index],
+                _ => unreachable!(),
+            };
+            send_command(Command::SetInterval(*interval));
+            drop(interval);
+            UNIVERSE.write().unwrap().request_redraw();
+        }
+        Action::Preset(index) => select_preset(index),
+        Action::ToggleAutoStop => {
+            let auto_stop = !AUTO_STOP.fetch_xor(true, Ordering::Relaxed);
+            let message = if auto_stop {
+                "稳定或全部死亡时自动暂停"
+            } else {
+                "稳定后继续演化"
+            };
+            show_note(hwnd, message);
+        }
+        Action::ToggleBackground => {
+            let background = !BACKGROUND_SIM.fetch_xor(true, Ordering::Relaxed);
+            let message = if background {
+                "最小化时继续演化 (不画)"
+            } else {
+                "最小化时暂停演化"
+            };
+            show_note(hwnd, message);
+        }
+        Action::Help => {
+            HELP_SHOWN.fetch_xor(true, Ordering::Relaxed);
+            UNIVERSE.write().unwrap().request_redraw();
+        }
+        Action::About => print_message(
+            hwnd,
+            &format!("生命游戏 {}\n使用 winapi 实现", env!("CARGO_PKG_VERSION")),
+        ),
+        Action::ExportStats => {
+            let result = STATS.read().unwrap().export_csv(Path::new(STATS_FILE));
+            if let Err(e) = result {
+                print_message(hwnd, &format!("导出 {} 失败: {}", STATS_FILE, e));
+            }
+        }
+        Action::NextPreset => {
+            let current = rule::preset_index(&UNIVERSE.read().unwrap().rule());
+            select_preset(current.map_or(0, |i| (i + 1) % PRESETS.len()));
+        }
+        Action::ToggleBrain | Action::ToggleForest => {
+            // 两种规则适合的初始面板和 Conway 不同, 所以重新随机生成
+            let target = if action == Action::ToggleBrain {
+                Rule::BRIANS_BRAIN
+            } else {
+                // 森林火灾模型从按密度随机种下的树开始, 还没有着火的格子
+                Rule::ForestFire
+            };
+            let mut u = UNIVERSE.write().unwrap();
+            let rule = if u.rule() == target { Rule::CONWAY } else { target };
+            u.set_rule(rule);
+            u.reset();
+            if CLEAR_STATS_ON_RESET {
+                STATS.write().unwrap().clear();
+            }
+        }
+        Action::ToggleCyclic => {
+            // 进入循环元胞自动机时按状态均匀随机生成面板, 再按一次回到 Conway
+            let mut u = UNIVERSE.write().unwrap();
+            if let Rule::Cyclic { .. } = u.rule() {
+                u.set_rule(Rule::CONWAY);
+                u.reset();
+            } else {
+                u.set_rule(Rule::CYCLIC);
+                u.randomize_states();
+            }
+            if CLEAR_STATS_ON_RESET {
+                STATS.write().unwrap().clear();
+            }
+        }
+        Action::ToggleSandpile => {
+            // 沙堆从空面板开始并暂停, 方便先用鼠标堆沙
+            let mut u = UNIVERSE.write().unwrap();
+            if u.rule() == Rule::Sandpile {
+                u.set_rule(Rule::CONWAY);
+                u.reset();
+            } else {
+                u.set_rule(Rule::Sandpile);
+                u.dead_all();
+            }
+            if CLEAR_STATS_ON_RESET {
+                STATS.write().unwrap().clear();
+            }
+        }
+        Action::GrowthUp | Action::GrowthDown | Action::LightningUp | Action::LightningDown => {
+            let mut u = UNIVERSE.write().unwrap();
+            let sign = match action {
+                Action::GrowthUp | Action::LightningUp => 1.0,
+                _ => -1.0,
+            };
+            if let Action::LightningUp | Action::LightningDown = action {
+                let rate = ((u.lightning_rate() / LIGHTNING_STEP).round() + sign) * LIGHTNING_STEP;
+                u.set_lightning_rate(rate);
+            } else {
+                let rate = ((u.growth_rate() / GROWTH_STEP).round() + sign) * GROWTH_STEP;
+                u.set_growth_rate(rate);
+            }
+            u.request_redraw();
+        }
+        Action::MutationUp | Action::MutationDown => {
+            let mut u = UNIVERSE.write().unwrap();
+            let step = if action == Action::MutationUp {
+                MUTATION_STEP
+            } else {
+                -MUTATION_STEP
+            };
+            // 四舍五入到步长, 避免反复加减后出现 0.30000000000000004% 这样的值
+            let rate = ((u.mutation_rate() + step) / MUTATION_STEP).round() * MUTATION_STEP;
+            u.set_mutation_rate(rate.min(MAX_MUTATION_RATE));
+            u.request_redraw();
+        }
+        Action::LoadCircuit => {
+            if let Err(e) = load_circuit() {
+                print_message(hwnd, &format!("示例电路放不下: {}", e));
+            }
+        }
+        Action::NextTopology => {
+            let mut u = UNIVERSE.write().unwrap();
+            let topology = u.topology().next();
+            u.set_topology(topology);
+            u.request_redraw();
+        }
+        Action::HoldTurbo => {
+            TURBO_HELD.store(true, Ordering::Relaxed);
+            GOTO_TARGET.store(-1, Ordering::Relaxed);
+            send_command(Command::Advance(u64::MAX));
+        }
+        Action::GoTo => set_turbo_input(hwnd, Some(String::new())),
+        Action::Cancel => send_command(Command::Cancel),
+        Action::DrawFewer | Action::DrawMore => {
+            let mut skip = FRAME_SKIP.write().unwrap();
+            if action == Action::DrawFewer {
+                skip.increase();
+            } else {
+                skip.decrease();
+            }
+            drop(skip);
+            // 暂停时也重画一次, 状态栏里显示新的设置
+            UNIVERSE.write().unwrap().request_redraw();
+        }
+        Action::ToggleTiles => {
+            if let Some(ctx) = unsafe { render_context(hwnd) } {
+                ctx.show_tiles = !ctx.show_tiles;
+            }
+            UNIVERSE.write().unwrap().request_redraw();
+        }
+        // 暂停时也马上整个重画, `prepare` 发现开关变了会连网格一起重画
+        Action::ToggleGrid => {
+            SHOW_GRID.fetch_xor(true, Ordering::Relaxed);
+            UNIVERSE.write().unwrap().request_redraw();
+            if let Err(e) = save_settings() {
+                show_note(hwnd, &format!("保存设置失败: {}", e));
+            }
+        }
+        Action::ToggleDiff => {
+            SHOW_DIFF.fetch_xor(true, Ordering::Relaxed);
+            UNIVERSE.write().unwrap().request_redraw();
+        }
+        Action::ToggleGraph => {
+            SHOW_GRAPH.fetch_xor(true, Ordering::Relaxed);
+            UNIVERSE.write().unwrap().request_redraw();
+        }
+        Action::ToggleHeat => {
+            SHOW_HEAT.fetch_xor(true, Ordering::Relaxed);
+            UNIVERSE.write().unwrap().request_redraw();
+        }
+        Action::ToggleNeighbors => {
+            SHOW_NEIGHBORS.fetch_xor(true, Ordering::Relaxed);
+            UNIVERSE.write().unwrap().request_redraw();
+        }
+        Action::ToggleAges => {
+            SHOW_AGES.fetch_xor(true, Ordering::Relaxed);
+            UNIVERSE.write().unwrap().request_redraw();
+        }
+        Action::NextTheme => {
+            let count = THEMES.len() + CUSTOM_THEME.read().unwrap().is_some() as usize;
+            set_theme(hwnd, (THEME.load(Ordering::Relaxed) + 1) % count);
+        }
+        // 三种颜色都选好了才换上, 中途取消时配色不变
+        Action::PickColors => match ask_custom_theme(hwnd) {
+            Some(theme) => {
+                *CUSTOM_THEME.write().unwrap() = Some(theme);
+                set_theme(hwnd, THEMES.len());
+            }
+            // 对话框里的自定义颜色就算取消了也留着
+            None => match save_settings() {
+                Ok(()) => show_note(hwnd, "没有改颜色"),
+                Err(e) => show_note(hwnd, &format!("保存设置失败: {}", e)),
+            },
+        },
+        Action::ToggleTrails => {
+            let mut u = UNIVERSE.write().unwrap();
+            let on = !u.trails();
+            u.set_trails(on);
+        }
+        Action::ToggleGrow => {
+            let mut u = UNIVERSE.write().unwrap();
+            let max_size = match u.auto_grow() {
+                Some(_) => None,
+                None => Some(MAX_GROW_SIZE),
+            };
+            u.set_auto_grow(max_size);
+            u.request_redraw();
+        }
+        Action::ToggleInfinite => toggle_infinite(hwnd),
+        Action::ExploreRule => {
+            let rule = UNIVERSE.write().unwrap().explore_rule();
+            EXPLORED.write().unwrap().push(rule);
+            show_rule(hwnd, rule);
+        }
+        Action::RuleBack | Action::RuleForward => {
+            let mut explored = EXPLORED.write().unwrap();
+            let rule = if action == Action::RuleBack {
+                explored.back()
+            } else {
+                explored.forward()
+            };
+            drop(explored);
+            if let Some(rule) = rule {
+                let mut u = UNIVERSE.write().unwrap();
+                u.set_rule(rule);
+                u.reset();
+                drop(u);
+                show_rule(hwnd, rule);
+            }
+        }
+        Action::Pan(d_row, d_col) => pan(d_row, d_col),
+        Action::NextPaintColor => {
+            let colors = UNIVERSE.read().unwrap().rule().colors();
+            let mut color = PAINT_COLOR.write().unwrap();
+            *color = *color % colors.max(1) + 1;
+        }
+        Action::RunUntilStable => {
+            let cap = stable_cap();
+            show_note(hwnd, &format!("一直演化到稳定, 最多 {} 代 (Esc 取消)", cap));
+            send_command(Command::RunUntilStable(cap));
+        }
+        Action::SaveState => {
+            let result = UNIVERSE.read().unwrap().save_state(Path::new(STATE_FILE));
+            if let Err(e) = result {
+                print_message(hwnd, &format!("保存失败: {}", e));
+            }
+        }
+        Action::LoadState => match Universe::load_state(Path::new(STATE_FILE)) {
+            Ok(loaded) => {
+                let mut u = UNIVERSE.write().unwrap();
+                u.replace_with(loaded);
+                u.set_run_state(RunState::Paused);
+            }
+            Err(e) => print_message(hwnd, &format!("读取 {} 失败: {}", STATE_FILE, e)),
+        },
+        Action::ToggleWatch => {
+            let watching = WATCH.write().unwrap().take();
+            if watching.is_none() {
+                let last = LAST_FILE.read().unwrap().clone();
+                match last {
+                    Some(path) => start_watch(hwnd, &path),
+                    None => print_message(hwnd, "请先打开一个图案文件"),
+                }
+            }
+        }
+        #[cfg(feature = "json")]
+        Action::ExportJson => {
+            let json = UNIVERSE.read().unwrap().to_json();
+            let path = beside_exe(JSON_FILE);
+            if let Err(e) = std::fs::write(&path, json) {
+                print_message(hwnd, &format!("导出 {} 失败: {}", path.display(), e));
+            }
+        }
+        Action::ExportBmp | Action::ExportSvg => {
+            let (path, result) = {
+                let u = UNIVERSE.read().unwrap();
+                if action == Action::ExportBmp {
+                    let path = format!("life_{}.bmp", u.count());
+                    let result = export_bmp(hwnd, &u, Path::new(&path));
+                    (path, result)
+                } else {
+                    let path = format!("life_{}.svg", u.count());
+                    let result = export_svg(&u, EXPORT_CELL_PX, Path::new(&path));
+                    (path, result)
+                }
+            };
+            if let Err(e) = result {
+                print_message(hwnd, &format!("导出 {} 失败: {}", path, e));
+            }
+        }
+        #[cfg(feature = "gif")]
+        Action::ToggleGif => {
+            let finished = {
+                let u = UNIVERSE.read().unwrap();
+                let mut recorder = RECORDER.write().unwrap();
+                if recorder.is_recording() {
+                    let path = format!("life_{}.gif", u.count());
+                    let result = recorder.finish_to_file(Path::new(&path));
+                    Some((path, result))
+                } else {
+                    recorder.start(&u);
+                    None
+                }
+            };
+            if let Some((path, Err(e))) = finished {
+                print_message(hwnd, &format!("保存 {} 失败: {}", path, e));
+            }
+        }
+    }
+}
+
+/// 菜单栏, 在创建窗口时交给 CreateWindowExW
+#[cfg(windows)]
+unsafe fn create_menu() -> HMENU {
+    let item = |menu: HMENU, id: u16, text: &str| {
+        AppendMenuW(menu, MF_STRING, id as UINT_PTR, to_wide(text).as_ptr());
+    };
+    let popup = |bar: HMENU, menu: HMENU, text: &str| {
+        AppendMenuW(bar, MF_POPUP, menu as UINT_PTR, to_wide(text).as_ptr());
+    };
+    let separator = |menu: HMENU| {
+        AppendMenuW(menu, MF_SEPARATOR, 0, std::ptr::null());
+    };
+
+    let file = CreatePopupMenu();
+    item(file, menu_id::OPEN, "打开(&O)...\tCtrl+O");
+    item(file, menu_id::SAVE_AS, "另存为(&S)...\tCtrl+Shift+E");
+    #[cfg(feature = "png")]
+    item(file, menu_id::EXPORT_PNG, "导出 PNG(&P)\tF12");
+    separator(file);
+    item(file, menu_id::EXIT, "退出(&X)");
+
+    let edit = CreatePopupMenu();
+    item(edit, menu_id::CLEAR, "清屏(&L)\tF4");
+    item(edit, menu_id::RANDOMIZE, "随机生成(&R)\tF5");
+    separator(edit);
+    item(edit, menu_id::COPY, "复制 RLE(&C)\tCtrl+C");
+    item(edit, menu_id::PASTE, "粘贴(&V)\tCtrl+V");
+
+    let speed = CreatePopupMenu();
+    for (i, rung) in SPEED_LADDER.iter().enumerate() {
+        let text = if *rung == Duration::from_secs(0) {
+            "不限速".to_string()
+        } else {
+            format!("{} 毫秒", rung.as_millis())
+        };
+        item(speed, menu_id::SPEED + i as u16, &text);
+    }
+    let rules = CreatePopupMenu();
+    for (i, (name, _)) in PRESETS.iter().enumerate() {
+        let text = if i < 9 {
+            format!("{}\tCtrl+{}", name, i + 1)
+        } else {
+            name.to_string()
+        };
+        item(rules, menu_id::PRESET + i as u16, &text);
+    }
+    let simulation = CreatePopupMenu();
+    item(simulation, menu_id::RUN, "运行(&R)\t空格");
+    item(simulation, menu_id::STEP, "单步(&N)\tN");
+    separator(simulation);
+    item(simulation, menu_id::FASTER, "加快(&F)\t+");
+    item(simulation, menu_id::SLOWER, "减慢(&S)\t-");
+    popup(simulation, speed, "速度(&P)");
+    popup(simulation, rules, "规则(&U)");
+    separator(simulation);
+    item(simulation, menu_id::AUTO_STOP, "稳定时自动暂停(&A)\tA");
+    item(simulation, menu_id::BACKGROUND, "最小化时继续演化(&B)\tCtrl+K");
+
+    let view = CreatePopupMenu();
+    item(view, menu_id::GRID, "网格线(&G)\tG");
+    separator(view);
+    item(view, menu_id::THEME, "换一套配色(&T)\tK");
+    item(view, menu_id::COLORS, "自定义颜色(&C)...\tCtrl+Shift+K");
+
+    let help = CreatePopupMenu();
+    item(help, menu_id::SHORTCUTS, "快捷键(&K)\tF1");
+    item(help, menu_id::ABOUT, "关于(&A)");
+
+    let bar = CreateMenu();
+    popup(bar, file, "文件(&F)");
+    popup(bar, edit, "编辑(&E)");
+    popup(bar, simulation, "模拟(&S)");
+    popup(bar, view, "视图(&V)");
+    popup(bar, help, "帮助(&H)");
+    bar
+}
+
+/// 打开菜单前按现在的状态打勾, 状态不管是按键、菜单还是自动暂停改的都对得上
+#[cfg(windows)]
+unsafe fn update_menu_checks(menu: HMENU) {
+    let check = |id: u16, on: bool| {
+        let state = if on { MF_CHECKED } else { MF_UNCHECKED };
+        CheckMenuItem(menu, UINT::from(id), MF_BYCOMMAND | state);
+    };
+    let (running, preset) = {
+        let u = UNIVERSE.read().unwrap();
+        (!u.is_paused(), rule::preset_index(&u.rule()))
+    };
+    check(menu_id::RUN, running);
+    check(menu_id::AUTO_STOP, AUTO_STOP.load(Ordering::Relaxed));
+    check(menu_id::BACKGROUND, BACKGROUND_SIM.load(Ordering::Relaxed));
+    check(menu_id::GRID, SHOW_GRID.load(Ordering::Relaxed));
+    let interval = *INTERVAL.read().unwrap();
+    for (i, rung) in SPEED_LADDER.iter().enumerate() {
+        check(menu_id::SPEED + i as u16, *rung == interval);
+    }
+    for i in 0..PRESETS.len() {
+        check(menu_id::PRESET + i as u16, preset == Some(i));
+    }
+}
+
+/// 工具栏的按钮, 控件 id 用菜单项的 id, 点击和菜单一样走 `handle_action`
+#[cfg(windows)]
+const TOOLBAR: [(u16, &str); 6] = [
+    (menu_id::RUN, "运行"),
+    (menu_id::STEP, "单步"),
+    (menu_id::RANDOMIZE, "重置"),
+    (menu_id::CLEAR, "清空"),
+    (menu_id::OPEN, "打开"),
+    (menu_id::SAVE_AS, "保存"),
+];
+
+/// 运行按钮上现在写的是不是 "暂停", 见 `sync_run_button`
+#[cfg(windows)]
+static RUN_SHOWN: AtomicBool = AtomicBool::new(false);
+
+#[cfg(windows)]
+unsafe fn create_toolbar(hwnd: HWND) {
+    let instance = GetModuleHandleW(null_mut());
+    let font = GetStockObject(DEFAULT_GUI_FONT as i32);
+    // 字符串要活到 CreateWindowExW 返回之后
+    let class = to_wide("BUTTON");
+    for (i, &(id, label)) in TOOLBAR.iter().enumerate() {
+        let text = to_wide(label);
+        let button = CreateWindowExW(
+            0,
+            class.as_ptr(),
+            text.as_ptr(),
+            WS_CHILD | WS_VISIBLE | BS_PUSHBUTTON as DWORD,
+            2 + i as i32 * (BUTTON_WIDTH + 2),
+            2,
+            BUTTON_WIDTH,
+            BUTTON_HEIGHT,
+            hwnd,
+            id as usize as HMENU,
+            instance,
+            null_mut(),
+        );
+        SendMessageW(button, WM_SETFONT, font as WPARAM, FALSE as LPARAM);
+    }
+    RUN_SHOWN.store(false, Ordering::Relaxed);
+    sync_run_button(hwnd);
+}
+
+/// 运行按钮跟着面板的运行状态改字. 不管是按键、菜单还是自动停下改的状态,
+/// 窗口之后总会收到一条消息, 每条消息处理完都来对一下; 拿不到锁就等下一条
+#[cfg(windows)]
+fn sync_run_button(hwnd: HWND) {
+    let running = match UNIVERSE.try_read() {
+        Ok(u) => !u.is_paused(),
+        Err(_) => return,
+    };
+    if RUN_SHOWN.swap(running, Ordering::Relaxed) == running {
+        return;
+    }
+    unsafe {
+        let button = GetDlgItem(hwnd, i32::from(menu_id::RUN));
+        if !button.is_null() {
+            let text = to_wide(if running { "暂停" } else { "运行" });
+            SetWindowTextW(button, text.as_ptr());
+        }
+    }
+}
 
-const WIDTH: i32 = COL_LEN * CELL_SIZE + COL_LEN * 7;
-const HEIGHT: i32 = ROW_LEN * CELL_SIZE + ROW_LEN * 9;
+/// 开始或结束输入要跳过的代数, 输入时窗口标题显示输入的内容
+#[cfg(windows)]
+fn set_turbo_input(hwnd: HWND, input: Option<String>) {
+    let title = match input.as_ref() {
+        Some(text) => format!(
+            "{} - 前往第几代 (+N 再演化 N 代): {}_ (Enter 开始, Esc 取消)",
+            TITLE, text
+        ),
+        None => {
+            *TURBO_INPUT.write().unwrap() = None;
+            return refresh_title(hwnd);
+        }
+    };
+    unsafe { SetWindowTextW(hwnd, to_wide(&title).as_ptr()) };
+    *TURBO_INPUT.write().unwrap() = input;
+}
 
-lazy_static! {
- static  ref   UNIVERSE:RwLock<Universe> = RwLock::new(Universe::new());
+/// 输入代数时收到的字符: 数字和开头的 '+' 追加在末尾, 退格删掉一位, Enter 开始, Esc 放弃
+#[cfg(windows)]
+fn turbo_input_char(hwnd: HWND, ch: WPARAM) {
+    let mut input = match TURBO_INPUT.read().unwrap().clone() {
+        Some(input) => input,
+        None => return,
+    };
+    match std::char::from_u32(ch as u32) {
+        Some(digit @ '0'..='9') if input.len() < 12 => input.push(digit),
+        Some('+') if input.is_empty() => input.push('+'),
+        Some('\u{8}') => {
+            input.pop();
+        }
+        Some('\r') => {
+            set_turbo_input(hwnd, None);
+            start_jump(hwnd, &input);
+            return;
+        }
+        Some('\u{1b}') => {
+            set_turbo_input(hwnd, None);
+            return;
+        }
+        _ => {}
+    }
+    set_turbo_input(hwnd, Some(input));
 }
 
-#[derive(Copy, PartialEq, Clone, Debug)]
-enum Cell {
-    Alive = 1,
-    Dead = 0,
+/// 按输入的代数开始跳转, 输入不对或者目标已经过去时在标题里说明
+#[cfg(windows)]
+fn start_jump(hwnd: HWND, input: &str) {
+    let current = UNIVERSE.read().unwrap().count();
+    let message = match input.parse::<Jump>() {
+        Err(e) => format!("输入的代数不对: {}", e),
+        Ok(Jump::By(0)) => return,
+        Ok(Jump::By(n)) => {
+            GOTO_TARGET.store(-1, Ordering::Relaxed);
+            send_command(Command::Advance(n));
+            return;
+        }
+        Ok(Jump::To(target)) => match Jump::To(target).steps_from(current) {
+            None => format!("已经过了第 {} 代, 现在是第 {} 代, 不能倒回去", target, current),
+            Some(0) => format!("已经在第 {} 代", target),
+            Some(_) => {
+                GOTO_TARGET.store(target, Ordering::Relaxed);
+                send_command(Command::AdvanceTo(target));
+                return;
+            }
+        },
+    };
+    show_note(hwnd, &message);
 }
 
-pub struct Universe {
-    width: u32,
-    height: u32,
-    cells: Vec<Cell>,
-    count: i64,
-    calc_state: bool,
-    draw_state: bool,
+/// 命令行 --stable-cap <代数> 指定的 Shift+S 最多演化的代数
+#[cfg(windows)]
+fn stable_cap() -> u64 {
+    let mut args = std::env::args().skip_while(|arg| arg != "--stable-cap").skip(1);
+    args.next()
+        .and_then(|n| n.parse().ok())
+        .filter(|&n| n > 0)
+        .unwrap_or(STABLE_CAP)
 }
 
-impl Universe {
-    pub fn new() -> Universe {
-        let width = CELL_SIZE as u32;
-        let height = CELL_SIZE as u32;
+/// 命令行 --interval <毫秒> 指定的演化间隔, 0 表示不停地演化
+#[cfg(windows)]
+fn tick_interval() -> Duration {
+    let mut args = std::env::args().skip_while(|arg| arg != "--interval").skip(1);
+    args.next()
+        .and_then(|ms| ms.parse().ok())
+        .map_or(TICK_INTERVAL, Duration::from_millis)
+}
 
-        let cells = Universe::gen_map(width, height);
-        Universe {
-            width,
-            height,
-            cells,
-            count: 0,
-            calc_state: true,
-            draw_state: true,
+/// 剪贴板可能正被其他程序占用, 打开失败时稍等再试
+#[cfg(windows)]
+fn open_clipboard(hwnd: HWND) -> Result<(), Error> {
+    const ATTEMPTS: u32 = 3;
+    for attempt in 1..=ATTEMPTS {
+        if unsafe { OpenClipboard(hwnd) } != 0 {
+            return Ok(());
+        }
+        if attempt < ATTEMPTS {
+            unsafe { Sleep(20) };
         }
     }
-    fn gen_map(width: u32, height: u32) -> Vec<Cell> {
-        let mut rag = rand::thread_rng();
-        (0..width * height)
-            .map(|_| {
-                let r: i32 = rag.gen_range(0, 10);
-                if r > 5 {
-                    Cell::Alive
-                } else {
-                    Cell::Dead
-                }
-            })
-            .collect()
-    }
-    fn get_index(&self, row: u32, column: u32) -> usize {
-        (row * self.width + column) as usize
-    }
-
-    fn set_cell(&mut self, cell: Cell, c: u32, r: u32) {
-        let index = self.get_index(r, c);
-        self.cells[index] = cell;
-        // self.cells.insert(index, Cell::Alive);
-    }
+    Err(Error::last_os_error())
+}
 
-    fn live_neighbor_count(&self, row: u32, column: u32) -> u8 {
-        let mut count = 0;
-        for delta_row in [self.height - 1, 0, 1].iter().cloned() {
-            for delta_col in [self.width - 1, 0, 1].iter().cloned() {
-                if delta_row == 0 && delta_col == 0 {
-                    continue;
-                }
-                let neighbor_row = (row + delta_row) % self.height;
-                let neighbor_col = (column + delta_col) % self.width;
-                let idx = self.get_index(neighbor_row, neighbor_col);
-                count += self.cells[idx] as u8
+/// 以 CF_UNICODETEXT 格式把文本放到剪贴板
+#[cfg(windows)]
+fn set_clipboard_text(hwnd: HWND, text: &str) -> Result<(), Error> {
+    let wide = to_wide(text);
+    open_clipboard(hwnd)?;
+    let result = unsafe {
+        EmptyClipboard();
+        let size = wide.len() * std::mem::size_of::<u16>();
+        let mem = GlobalAlloc(GMEM_MOVEABLE, size);
+        if mem.is_null() {
+            Err(Error::last_os_error())
+        } else {
+            let dst = GlobalLock(mem) as *mut u16;
+            std::ptr::copy_nonoverlapping(wide.as_ptr(), dst, wide.len());
+            GlobalUnlock(mem);
+            // 成功后内存归剪贴板所有, 失败时要自己释放
+            if SetClipboardData(CF_UNICODETEXT, mem).is_null() {
+                let e = Error::last_os_error();
+                GlobalFree(mem);
+                Err(e)
+            } else {
+                Ok(())
             }
         }
+    };
+    unsafe { CloseClipboard() };
+    result
+}
 
-        count
+/// 读取剪贴板里的 CF_UNICODETEXT 文本, 剪贴板里没有文本时返回 None
+#[cfg(windows)]
+fn get_clipboard_text(hwnd: HWND) -> Result<Option<String>, Error> {
+    if unsafe { IsClipboardFormatAvailable(CF_UNICODETEXT) } == 0 {
+        return Ok(None);
     }
+    open_clipboard(hwnd)?;
+    let result = unsafe {
+        let mem = GetClipboardData(CF_UNICODETEXT);
+        let src = if mem.is_null() {
+            null_mut()
+        } else {
+            GlobalLock(mem) as *const u16
+        };
+        if src.is_null() {
+            Err(Error::last_os_error())
+        } else {
+            let mut len = 0;
+            while *src.add(len) != 0 {
+                len += 1;
+            }
+            let text = String::from_utf16_lossy(std::slice::from_raw_parts(src, len));
+            GlobalUnlock(mem);
+            Ok(Some(text))
+        }
+    };
+    unsafe { CloseClipboard() };
+    result
 }
 
-impl Universe {
-    pub fn tick(&mut self) {
-        let mut next = self.cells.clone();
-        for row in 0..self.height {
-            for col in 0..self.width {
-                let idx = self.get_index(row, col);
-                let cell = self.cells[idx];
-                let live_neighbors = self.live_neighbor_count(row, col);
-                let next_cell = match (cell, live_neighbors) {
-                    (Cell::Alive, x) if x < 2 => Cell::Dead,
-                    (Cell::Alive, 2) | (Cell::Alive, 3) => Cell::Alive,
-                    (Cell::Alive, x) if x > 3 => Cell::Dead,
-                    (Cell::Dead, 3) => Cell::Alive,
-                    (otherwise, _) => otherwise,
-                };
-                next[idx] = next_cell;
-            }
+/// 鼠标当前所在的格子 (row, col), 不在面板上时返回 None
+#[cfg(windows)]
+fn cursor_cell(hwnd: HWND) -> Option<(u32, u32)> {
+    let mut point = POINT { x: 0, y: 0 };
+    unsafe {
+        if GetCursorPos(&mut point) == 0 || ScreenToClient(hwnd, &mut point) == 0 {
+            return None;
         }
-        self.cells = next;
-        self.count += 1;
     }
+    client_cell(is_hex(&UNIVERSE.read().unwrap()), point.x, point.y)
 }
 
-impl fmt::Display for Universe {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        for line in self.cells.as_slice().chunks(self.width as usize) {
-            for &cell in line {
-                let symbol = if cell == Cell::Dead { '◻' } else { '◼' };
-                write!(f, "{}", symbol)?;
-            }
-            write!(f, "\n")?;
-        }
-        Ok(())
+/// Ctrl+V: 把剪贴板里的 RLE 或 plaintext 图案以鼠标所在格子为左上角贴到面板上,
+/// 鼠标不在面板上时居中. 运行中的模拟会先暂停
+#[cfg(windows)]
+fn paste_clipboard(hwnd: HWND) {
+    let text = match get_clipboard_text(hwnd) {
+        Ok(Some(text)) => text,
+        Ok(None) => return,
+        Err(e) => return print_message(hwnd, &format!("读取剪贴板失败: {}", e)),
+    };
+    let pattern = match parse_text(&text) {
+        Ok(pattern) => pattern,
+        Err(e) => return print_message(hwnd, &format!("剪贴板里不是图案: {}", e)),
+    };
+    let origin = cursor_cell(hwnd);
+    let result = {
+        let mut u = UNIVERSE.write().unwrap();
+        let origin = origin.map(|(r, c)| board_cell(&u, c as i32, r as i32));
+        u.set_run_state(RunState::Paused);
+        pattern.place(&mut u, origin)
+    };
+    if let Err(e) = result {
+        print_message(hwnd, &format!("无法粘贴: {}", e));
     }
 }
 
+/// 用系统的打开文件对话框选择图案文件, 取消时返回 None
+#[cfg(windows)]
+fn ask_open_path(hwnd: HWND) -> Option<PathBuf> {
+    let mut filter = String::from(
+        "图案文件 (*.rle;*.cells;*.lif;*.life;*.mc)\0*.rle;*.cells;*.lif;*.life;*.mc\0",
+    );
+    #[cfg(feature = "image")]
+    filter.push_str("图片 (*.png;*.bmp;*.jpg;*.jpeg)\0*.png;*.bmp;*.jpg;*.jpeg\0");
+    filter.push_str("所有文件\0*.*\0");
+    // to_wide 再补上最后一个 0
+    let filter = to_wide(&filter);
+    let initial_dir = LAST_DIR
+        .read()
+        .unwrap()
+        .as_ref()
+        .map(|dir| to_wide(&dir.to_string_lossy()));
+    let mut file = vec![0u16; MAX_PATH * 4];
+    let path = unsafe {
+        let mut ofn: OPENFILENAMEW = std::mem::zeroed();
+        ofn.lStructSize = std::mem::size_of::<OPENFILENAMEW>() as DWORD;
+        ofn.hwndOwner = hwnd;
+        ofn.lpstrFilter = filter.as_ptr();
+        ofn.lpstrFile = file.as_mut_ptr();
+        ofn.nMaxFile = file.len() as DWORD;
+        ofn.lpstrInitialDir = initial_dir
+            .as_ref()
+            .map_or(std::ptr::null(), |dir| dir.as_ptr());
+        ofn.Flags = OFN_FILEMUSTEXIST | OFN_PATHMUSTEXIST | OFN_NOCHANGEDIR;
+        if GetOpenFileNameW(&mut ofn) == 0 {
+            return None;
+        }
+        let len = file.iter().position(|&c| c == 0).unwrap_or(file.len());
+        PathBuf::from(OsString::from_wide(&file[..len]))
+    };
+    *LAST_DIR.write().unwrap() = path.parent().map(Path::to_path_buf);
+    Some(path)
+}
+
+/// 另存为对话框中可选的格式, 顺序与过滤器一致
+#[cfg(windows)]
+#[derive(Clone, Copy)]
+enum SaveFormat {
+    Rle,
+    Cells,
+    #[cfg(feature = "png")]
+    Png,
+}
 
+#[cfg(windows)]
+impl SaveFormat {
+    #[cfg(not(feature = "png"))]
+    const ALL: &'static [SaveFormat] = &[SaveFormat::Rle, SaveFormat::Cells];
+    #[cfg(feature = "png")]
+    const ALL: &'static [SaveFormat] = &[SaveFormat::Rle, SaveFormat::Cells, SaveFormat::Png];
 
-impl Universe {
-    fn dead_all(&mut self) {
-        self.count = 0;
-        for i in 0..self.width * self.height {
-            self.cells[i as usize] = Cell::Dead;
+    fn label(self) -> &'static str {
+        match self {
+            SaveFormat::Rle => "RLE (*.rle)",
+            SaveFormat::Cells => "Plaintext (*.cells)",
+            #[cfg(feature = "png")]
+            SaveFormat::Png => "PNG 图片 (*.png)",
         }
-        self.stop_calc();
-        self.start_draw();
     }
 
-    fn reset(&mut self) {
-        self.count = 0;
-        self.start_draw();
-        self.cells = Universe::gen_map(self.width, self.height);
+    fn extension(self) -> &'static str {
+        match self {
+            SaveFormat::Rle => "rle",
+            SaveFormat::Cells => "cells",
+            #[cfg(feature = "png")]
+            SaveFormat::Png => "png",
+        }
     }
 
-
-    fn is_calc_stop(&self) -> bool {
-        !self.calc_state
+    fn write(self, u: &Universe, path: &Path) -> Result<(), Error> {
+        match self {
+            SaveFormat::Rle => std::fs::write(path, write_rle(&Pattern::from_universe(u))),
+            SaveFormat::Cells => {
+                std::fs::write(path, plaintext::serialize(&Pattern::from_universe(u)))
+            }
+            #[cfg(feature = "png")]
+            SaveFormat::Png => export_png(u, EXPORT_CELL_PX, path),
+        }
     }
+}
 
-
-    fn is_draw_stop(&self) -> bool {
-        !self.draw_state
+/// 用系统的另存为对话框选择文件名和格式, 取消时返回 None.
+/// 扩展名与所选格式不符时补上正确的扩展名
+#[cfg(windows)]
+fn ask_save_path(hwnd: HWND, default_name: &str) -> Option<(PathBuf, SaveFormat)> {
+    let mut filter = String::new();
+    for format in SaveFormat::ALL {
+        filter.push_str(&format!("{}\0*.{}\0", format.label(), format.extension()));
     }
-
-    fn stop_draw(&mut self) {
-        self.draw_state = false
+    let filter = to_wide(&filter);
+    let initial_dir = LAST_DIR
+        .read()
+        .unwrap()
+        .as_ref()
+        .map(|dir| to_wide(&dir.to_string_lossy()));
+    let mut file = vec![0u16; MAX_PATH * 4];
+    for (dst, src) in file.iter_mut().zip(default_name.encode_utf16()) {
+        *dst = src;
     }
+    let (path, index) = unsafe {
+        let mut ofn: OPENFILENAMEW = std::mem::zeroed();
+        ofn.lStructSize = std::mem::size_of::<OPENFILENAMEW>() as DWORD;
+        ofn.hwndOwner = hwnd;
+        ofn.lpstrFilter = filter.as_ptr();
+        ofn.nFilterIndex = 1;
+        ofn.lpstrFile = file.as_mut_ptr();
+        ofn.nMaxFile = file.len() as DWORD;
+        ofn.lpstrInitialDir = initial_dir
+            .as_ref()
+            .map_or(std::ptr::null(), |dir| dir.as_ptr());
+        ofn.Flags = OFN_OVERWRITEPROMPT | OFN_PATHMUSTEXIST | OFN_NOCHANGEDIR;
+        if GetSaveFileNameW(&mut ofn) == 0 {
+            return None;
+        }
+        let len = file.iter().position(|&c| c == 0).unwrap_or(file.len());
+        (
+            PathBuf::from(OsString::from_wide(&file[..len])),
+            ofn.nFilterIndex,
+        )
+    };
+    // nFilterIndex 从 1 开始
+    let format = SaveFormat::ALL[(index.max(1) as usize - 1).min(SaveFormat::ALL.len() - 1)];
+    let matches = path.extension().is_some_and(|ext| {
+        ext.to_string_lossy()
+            .eq_ignore_ascii_case(format.extension())
+    });
+    let path = if matches {
+        path
+    } else {
+        let mut name = path.into_os_string();
+        name.push(".");
+        name.push(format.extension());
+        PathBuf::from(name)
+    };
+    *LAST_DIR.write().unwrap() = path.parent().map(Path::to_path_buf);
+    Some((path, format))
+}
 
-    fn start_draw(&mut self) {
-        self.draw_state = true
+/// Ctrl+Shift+E: 选择文件名和格式后导出面板. 空面板照样保存, 但给出提示
+#[cfg(windows)]
+fn save_as(hwnd: HWND) {
+    let default_name = format!("life_gen{}", UNIVERSE.read().unwrap().count());
+    let (path, format) = match ask_save_path(hwnd, &default_name) {
+        Some(choice) => choice,
+        None => return,
+    };
+    let (empty, result) = {
+        let u = UNIVERSE.read().unwrap();
+        (u.population() == 0, format.write(&u, &path))
+    };
+    if let Err(e) = result {
+        print_message(hwnd, &format!("保存 {} 失败: {}", path.display(), e));
+    } else if empty {
+        print_message(
+            hwnd,
+            &format!("面板上没有活细胞, 已保存空图案到 {}", path.display()),
+        );
     }
+}
 
-    fn stop_calc(&mut self) {
-        self.calc_state = false
-    }
+/// 拖放到窗口上的第 `index` 个文件
+#[cfg(windows)]
+unsafe fn dropped_file(hdrop: HDROP, index: UINT) -> PathBuf {
+    let len = DragQueryFileW(hdrop, index, null_mut(), 0) as usize;
+    let mut buf = vec![0u16; len + 1];
+    DragQueryFileW(hdrop, index, buf.as_mut_ptr(), buf.len() as UINT);
+    PathBuf::from(OsString::from_wide(&buf[..len]))
+}
 
-    fn change_calc_state(&mut self) {
-        self.calc_state = !self.calc_state;
-    }
-    fn change_draw_state(&mut self) {
-        self.draw_state = !self.draw_state;
-    }
-    fn change_state(&mut self) {
-        self.change_calc_state();
-        self.change_draw_state();
+/// 打开拖放或在对话框中选择的文件: 图片按阈值转换成面板, 其余按图案格式读取
+#[cfg(windows)]
+fn open_file(hwnd: HWND, path: &Path) {
+    #[cfg(feature = "image")]
+    {
+        let ext = path
+            .extension()
+            .map(|e| e.to_string_lossy().to_ascii_lowercase());
+        if let Some("png") | Some("bmp") | Some("jpg") | Some("jpeg") = ext.as_deref() {
+            return open_image(hwnd, path);
+        }
     }
+    open_pattern(hwnd, path);
+}
 
-    fn draw_title(&self, hdc: HDC, title: String) {
-        let z = title.encode_utf16().collect::<Vec<u16>>();
-        unsafe {
-            TextOutW(hdc, CELL_SIZE * (COL_LEN + 0) - 2 * COL_LEN, 0, z.as_ptr(), z.len() as i32);
+/// 图片缩放到面板大小后替换整个面板并暂停
+#[cfg(all(windows, feature = "image"))]
+fn open_image(hwnd: HWND, path: &Path) {
+    let options = ImageOptions {
+        width: LAYOUT.grid_cols,
+        height: LAYOUT.grid_rows,
+        threshold: IMAGE_THRESHOLD,
+        scale_up: true,
+    };
+    match Universe::from_image_with(path, &options) {
+        Ok(mut loaded) => {
+            loaded.set_run_state(RunState::Paused);
+            UNIVERSE.write().unwrap().replace_with(loaded);
+            show_file_name(hwnd, path);
         }
+        Err(e) => print_message(hwnd, &format!("无法打开 {}: {}", path.display(), e)),
     }
+}
 
-    fn draw_change(&mut self, cell: Cell, hdc: HDC, c: i32, r: i32) {
-        if c >= self.width as i32 || r >= self.height as i32 {
-            return;
-        }
-        // let index = self.get_index(r as u32, c as u32);
-        // println!("index: {}", index);
-        self.set_cell(cell, c as u32, r as u32);
-        self.draw_rec(&cell, hdc, c, r);
-        // println!("cell: {:?}", self.cells[index]);
+/// 窗口标题显示探索到的规则串, 状态栏在下一次重画时也会更新
+#[cfg(windows)]
+fn show_rule(hwnd: HWND, rule: Rule) {
+    show_note(hwnd, &rule.to_string());
+    if CLEAR_STATS_ON_RESET {
+        STATS.write().unwrap().clear();
     }
+}
 
-    fn draw_rec(&self, cell: &Cell, hdc: HDC, c: i32, r: i32) {
-        unsafe {
-            let hbr = match cell {
-                Cell::Alive => {
-                    CreateSolidBrush(RGB(0, 0, 0))
-                }
-                Cell::Dead => {
-                    CreateSolidBrush(RGB(255, 255, 255))
-                }
-            };
-            let rec = RECT {
-                left: c * (COL_LEN + 1) + 1,
-                top: r * (ROW_LEN + 1) + 1,
-                right: c * (COL_LEN + 1) + COL_LEN,
-                bottom: r * (ROW_LEN + 1) + ROW_LEN,
-            };
-            // 画刷选择到当前DC中
-            let org_brs = SelectObject(hdc, hbr as HGDIOBJ) as HBRUSH;
-            // Rectangle(hdc, c * (COL_LEN + 1) + 1, r * (ROW_LEN + 1) + 1, c * (COL_LEN + 1) + COL_LEN, r * (ROW_LEN + 1) + ROW_LEN);
+/// 窗口标题显示打开的文件名, 直到打开下一个文件
+#[cfg(windows)]
+fn show_file_name(hwnd: HWND, path: &Path) {
+    let name = path
+        .file_name()
+        .unwrap_or(path.as_os_str())
+        .to_string_lossy();
+    *OPENED_NAME.write().unwrap() = Some(name.into_owned());
+    refresh_title(hwnd);
+}
 
-            FillRect(
-                hdc,
-                &rec,
-                hbr,
-            );
+/// 在窗口标题里显示一条提示, `NOTE_DURATION` 之后换回统计
+#[cfg(windows)]
+fn show_note(hwnd: HWND, note: &str) {
+    *TITLE_NOTE.write().unwrap() = Some((note.to_string(), Instant::now()));
+    refresh_title(hwnd);
+}
 
-            // 选回原先的画刷
-            SelectObject(hdc, org_brs as HGDIOBJ);
-            DeleteObject(hbr as HGDIOBJ);
-        }
+/// 窗口标题: 有新的提示时显示提示, 否则显示文件名、代数、细胞数、规则和速度, 暂停时前面加 ⏸.
+/// 代数和细胞数来自 `COUNTERS`; 规则和运行状态要读面板, 面板正被锁住时等下一次更新.
+/// 只在窗口线程里调用, 演化线程不直接改标题
+#[cfg(windows)]
+fn refresh_title(hwnd: HWND) {
+    if TURBO_INPUT.read().unwrap().is_some() {
+        return;
     }
+    let note = TITLE_NOTE
+        .read()
+        .unwrap()
+        .as_ref()
+        .filter(|(_, shown)| shown.elapsed() < NOTE_DURATION)
+        .map(|(note, _)| note.clone());
+    let title = match note {
+        Some(note) => format!("{} - {}", TITLE, note),
+        None => {
+            let (rule, paused) = match UNIVERSE.try_read() {
+                Ok(u) => (rule_name(u.rule()), u.is_paused()),
+                Err(_) => return,
+            };
+            let mut title = String::new();
+            if paused {
+                title.push_str("⏸ ");
+            }
+            title.push_str(TITLE);
+            if let Some(name) = OPENED_NAME.read().unwrap().as_ref() {
+                title.push_str(&format!(" — {}", name));
+            }
+            title.push_str(&format!(
+                " — 周期 {} · 细胞 {} · {} · {} 代/秒",
+                COUNTERS.generation(),
+                COUNTERS.population(),
+                rule,
+                TICK_RATE.load(Ordering::Relaxed)
+            ));
+            title
+        }
+    };
+    unsafe { SetWindowTextW(hwnd, to_wide(&title).as_ptr()) };
 }
 
+/// 按扩展名读取图案文件, 清空面板后居中放置并暂停, 窗口标题显示文件名.
+/// 比面板大的 Macrocell 文件只保留面板范围内的部分, 并提示裁掉了多少活细胞.
+/// 读取或放置失败时面板保持不变
 #[cfg(windows)]
-fn key_down(vk_code: i32) -> bool {
-    unsafe {
-        if GetAsyncKeyState(vk_code) < 0 {
-            true
-        } else {
-            false
+fn open_pattern(hwnd: HWND, path: &Path) {
+    let (pattern, cropped) = match load_for_board(path) {
+        Ok(loaded) => loaded,
+        Err(e) => return print_message(hwnd, &format!("无法打开 {}: {}", path.display(), e)),
+    };
+    match replace_with_pattern(&pattern, false) {
+        Ok(()) => {
+            show_file_name(hwnd, path);
+            *LAST_FILE.write().unwrap() = Some(path.to_path_buf());
+            if let Some(cropped) = cropped {
+                print_message(hwnd, &format!("{}: {}", path.display(), cropped));
+            }
         }
+        Err(e) => print_message(hwnd, &format!("无法打开 {}: {}", path.display(), e)),
     }
 }
 
+/// 按当前面板的大小读取图案文件
+#[cfg(windows)]
+fn load_for_board(path: &Path) -> Result<(Pattern, Option<Cropped>), FormatError> {
+    let (width, height) = {
+        let u = UNIVERSE.read().unwrap();
+        (u.width(), u.height())
+    };
+    load_board_file(path, width, height)
+}
 
+/// 清空面板后把图案居中放置, 代数归零. `keep_running` 为 false 时暂停, 否则保持原来的
+/// 运行/暂停状态. 放不下时面板保持不变
 #[cfg(windows)]
-unsafe extern "system" fn window_proc(hwnd: HWND, u_msg: UINT, w_param: WPARAM, l_param: LPARAM) -> LRESULT {
-    match u_msg {
-        WM_CLOSE => {
-            DestroyWindow(hwnd);
-        }
-        WM_DESTROY => {
-            PostQuitMessage(u_msg as i32);
-        }
-        WM_CREATE => {
-            SetTimer(hwnd, 0, 10, Some(tick_run));
-            // SetTimer(hwnd, 1, 10, Some(draw_run));
-        }
-        WM_PAINT => {
-            let mut ps: PAINTSTRUCT = PAINTSTRUCT {
-                hdc: null_mut(),
-                fErase: 0,
-                rcPaint: RECT {
-                    left: 0,
-                    right: 0,
-                    bottom: 0,
-                    top: 0,
-                },
-                fRestore: 0,
-                fIncUpdate: 0,
-                rgbReserved: [0; 32],
-            };
-            let hdc = BeginPaint(hwnd, &mut ps);
-            for i in 0..=CELL_SIZE {
-                MoveToEx(hdc, 0, i * (ROW_LEN + 1), null_mut());
-                LineTo(hdc, (ROW_LEN + 1) * CELL_SIZE, i * (ROW_LEN + 1));
+fn replace_with_pattern(pattern: &Pattern, keep_running: bool) -> Result<(), UniverseError> {
+    let mut u = UNIVERSE.write().unwrap();
+    let running = !u.is_paused();
+    let mut board = u.clone();
+    board.dead_all();
+    pattern.place(&mut board, None)?;
+    if keep_running && running {
+        board.set_run_state(RunState::Running);
+    }
+    u.replace_with(board);
+    Ok(())
+}
 
-                MoveToEx(hdc, i * (COL_LEN + 1), 0, null_mut());
-                LineTo(hdc, i * (COL_LEN + 1), (COL_LEN + 1) * CELL_SIZE);
-            }
-            EndPaint(hwnd, &ps);
-        }
-        WM_KEYDOWN => {
-            if key_down(VK_F5) {
-                let mut u = UNIVERSE.write().unwrap();
-                u.reset();
-            }
-            if key_down(VK_F4) {
-                let mut u = UNIVERSE.write().unwrap();
-                u.dead_all();
-            }
+/// 开始监视图案文件, 文件保存后向窗口发送 WM_APP_RELOAD
+#[cfg(windows)]
+fn start_watch(hwnd: HWND, path: &Path) {
+    // HWND 不能跨线程传递, 转成整数; PostMessageW 可以从任意线程调用
+    let target = hwnd as usize;
+    let handle = watch::spawn(path, WATCH_INTERVAL, WATCH_DEBOUNCE, move || unsafe {
+        PostMessageW(target as HWND, WM_APP_RELOAD, 0, 0);
+    });
+    *WATCH.write().unwrap() = Some(handle);
+}
 
-            if key_down(VK_F2) {
-                let mut u = UNIVERSE.write().unwrap();
-                u.change_state();
-            }
-        }
-        WM_KEYUP => {}
-        WM_MOUSEMOVE => {
-            // println!("WM_MOUSEMOVE");
-            // let key_state = GetAsyncKeyState(VK_LBUTTON);
-            // println!("key_state: {}", key_state);
-            if key_down(VK_LBUTTON) {
-                if UNIVERSE.read().unwrap().is_calc_stop() {
-                    let hdc = GetDC(hwnd);
-                    let x_pos = LOWORD(l_param as u32);
-                    let y_pos = HIWORD(l_param as u32);
-                    let col = x_pos / (COL_LEN + 1) as u16;
-                    let row = y_pos / (ROW_LEN + 1) as u16;
-                    let mut u = UNIVERSE.write().unwrap();
-                    u.draw_change(Cell::Alive, hdc, col as i32, row as i32);
-                    // println!("c: {}, r: {}", col, row);
-                    ReleaseDC(hwnd, hdc);   //归还系统绘图设备
-                }
-            }
-            if key_down(VK_RBUTTON) {
-                if UNIVERSE.read().unwrap().is_calc_stop() {
-                    let hdc = GetDC(hwnd);
-                    let x_pos = LOWORD(l_param as u32);
-                    let y_pos = HIWORD(l_param as u32);
-                    let col = x_pos / (COL_LEN + 1) as u16;
-                    let row = y_pos / (ROW_LEN + 1) as u16;
-                    let mut u = UNIVERSE.write().unwrap();
-                    u.draw_change(Cell::Dead, hdc, col as i32, row as i32);
-                    // println!("c: {}, r: {}", col, row);
-                    ReleaseDC(hwnd, hdc);   //归还系统绘图设备
-                }
-            }
-        }
-        WM_LBUTTONUP => {
-            let mut u = UNIVERSE.write().unwrap();
-            u.start_draw();
-        }
-        WM_RBUTTONUP => {
-            let mut u = UNIVERSE.write().unwrap();
-            u.start_draw();
-        }
-        WM_RBUTTONDOWN => {
-            if UNIVERSE.read().unwrap().is_calc_stop() {
-                let hdc = GetDC(hwnd);
-                let x_pos = LOWORD(l_param as u32);
-                let y_pos = HIWORD(l_param as u32);
-                let col = x_pos / (COL_LEN + 1) as u16;
-                let row = y_pos / (ROW_LEN + 1) as u16;
-                let mut u = UNIVERSE.write().unwrap();
-                u.stop_draw();
-                u.draw_change(Cell::Dead, hdc, col as i32, row as i32);
-                // SendMessageW(hwnd, WM_DRAWITEM, 0, 0);
-                ReleaseDC(hwnd, hdc);   //归还系统绘图设备
-            }
-        }
-        WM_LBUTTONDOWN => {
-            if UNIVERSE.read().unwrap().is_calc_stop() {
-                let hdc = GetDC(hwnd);
-                let x_pos = LOWORD(l_param as u32);
-                let y_pos = HIWORD(l_param as u32);
-                let col = x_pos / (COL_LEN + 1) as u16;
-                let row = y_pos / (ROW_LEN + 1) as u16;
-                let mut u = UNIVERSE.write().unwrap();
-                u.stop_draw();
-                u.draw_change(Cell::Alive, hdc, col as i32, row as i32);
-                // SendMessageW(hwnd, WM_DRAWITEM, 0, 0);
-                ReleaseDC(hwnd, hdc);   //归还系统绘图设备
-            }
-        }
-        WM_DRAWITEM => {
-            let hdc = GetDC(hwnd);
-            let u = UNIVERSE.read().unwrap();
-            // println!("{}", u);
-            for c in 0..CELL_SIZE {
-                for r in 0..CELL_SIZE {
-                    u.draw_rec(&u.cells[u.get_index(r as u32, c as u32)], hdc, c, r);
-                }
-            }
-            u.draw_title(hdc, format!("周期: {}", u.count));
-            // SetWindowTextW(hwnd, z.as_ptr());
-            // BitBlt(hdc, 0, 0, WIDTH, HEIGHT, mem_dc, 0, 0, SRCCOPY);//复制到系统设备上显示
-            // DeleteDC(mem_dc);        //释放辅助绘图设备
-            ReleaseDC(hwnd, hdc);   //归还系统绘图设备
-        }
-        _ => ()
+/// 被监视的文件变了: 重新解析, 代数归零并保持运行/暂停状态. 解析失败时保留原来的面板
+#[cfg(windows)]
+fn reload_watched(hwnd: HWND) {
+    let path = match WATCH.read().unwrap().as_ref() {
+        Some(handle) => handle.path().to_path_buf(),
+        None => return,
     };
-    return DefWindowProcW(hwnd, u_msg, w_param, l_param);
+    let result = load_for_board(&path)
+        .map_err(|e| e.to_string())
+        .and_then(|(pattern, cropped)| {
+            replace_with_pattern(&pattern, true).map_err(|e| e.to_string())?;
+            Ok(cropped)
+        });
+    match result {
+        Ok(Some(cropped)) => show_note(hwnd, &cropped.to_string()),
+        Ok(None) => {}
+        Err(e) => print_message(hwnd, &format!("重新加载 {} 失败: {}", path.display(), e)),
+    }
 }
 
+/// %APPDATA%\game_life 下的文件, 没有 APPDATA 时为 None
 #[cfg(windows)]
-unsafe extern "system" fn tick_run(
-    hwnd: HWND,
-    _a: UINT,
-    _b: UINT_PTR,
-    _d: DWORD,
-) {
-    let mut stop_draw = false;
-    if !UNIVERSE.read().unwrap().is_calc_stop() {
-        UNIVERSE.write().unwrap().tick();
-    } else {
-        stop_draw = true;
+fn app_data_path(name: &str) -> Option<PathBuf> {
+    std::env::var_os("APPDATA").map(|dir| PathBuf::from(dir).join("game_life").join(name))
+}
+
+/// %APPDATA%\game_life\autosave.bin
+#[cfg(windows)]
+fn autosave_path() -> Option<PathBuf> {
+    app_data_path(AUTOSAVE_FILE)
+}
+
+/// 把现在的配色、网格线开关和自己选的颜色写进设置文件
+#[cfg(windows)]
+fn save_settings() -> std::io::Result<()> {
+    let path = match app_data_path(SETTINGS_FILE) {
+        Some(path) => path,
+        None => return Ok(()),
+    };
+    Settings {
+        theme: THEME.load(Ordering::Relaxed),
+        show_grid: SHOW_GRID.load(Ordering::Relaxed),
+        custom_theme: CUSTOM_THEME
+            .read()
+            .unwrap()
+            .map(|theme| [theme.alive, theme.dead, theme.gridline]),
+        custom_colors: *CUSTOM_COLORS.read().unwrap(),
     }
-    if !UNIVERSE.read().unwrap().is_draw_stop() {
-        SendMessageW(hwnd, WM_DRAWITEM, 0, 0);
+    .save(&path)
+}
+
+/// 启动时读回设置文件, 在窗口第一次画之前. 读不了或者配色编号不认识时用默认值.
+/// 自己选的配色只记了三种颜色, 其余的按第一套内置配色
+#[cfg(windows)]
+fn restore_settings() {
+    let settings = app_data_path(SETTINGS_FILE)
+        .and_then(|path| Settings::load(&path).ok())
+        .unwrap_or_default();
+    let custom = settings
+        .custom_theme
+        .map(|colors| custom_theme(THEMES[0], colors));
+    *CUSTOM_THEME.write().unwrap() = custom;
+    *CUSTOM_COLORS.write().unwrap() = settings.custom_colors;
+    let custom = custom.is_some();
+    let count = THEMES.len() + custom as usize;
+    let theme = if settings.theme < count {
+        settings.theme
+    } else {
+        0
+    };
+    THEME.store(theme, Ordering::Relaxed);
+    SHOW_GRID.store(settings.show_grid, Ordering::Relaxed);
+}
+
+/// 关闭窗口时保存完整状态, 下次启动时恢复
+#[cfg(windows)]
+fn autosave() -> Result<(), StateError> {
+    let path = match autosave_path() {
+        Some(path) => path,
+        None => return Ok(()),
+    };
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
     }
-    if stop_draw {
-        if !UNIVERSE.read().unwrap().is_draw_stop() {
-            UNIVERSE.write().unwrap().stop_draw();
+    UNIVERSE.read().unwrap().save_state(&path)
+}
+
+/// 读取自动保存的面板并暂停. 文件损坏时改名为 autosave.bin.corrupt 放到一边, 照常随机生成
+#[cfg(windows)]
+fn restore_autosave() {
+    let path = match autosave_path() {
+        Some(path) if path.exists() => path,
+        _ => return,
+    };
+    match Universe::load_state(&path) {
+        Ok(mut restored) => {
+            restored.set_run_state(RunState::Paused);
+            UNIVERSE.write().unwrap().replace_with(restored);
+        }
+        Err(_) => {
+            let _ = std::fs::rename(&path, path.with_extension("bin.corrupt"));
         }
     }
 }
 
+/// 以 0 结尾的 UTF-16 字符串
+#[cfg(windows)]
+fn to_wide(str: &str) -> Vec<u16> {
+    OsStr::new(str).encode_wide().chain(once(0)).collect()
+}
+
+/// exe 所在目录下的文件, 取不到 exe 路径时退回当前目录
+#[cfg(all(windows, feature = "json"))]
+fn beside_exe(name: &str) -> PathBuf {
+    std::env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(|dir| dir.join(name)))
+        .unwrap_or_else(|| PathBuf::from(name))
+}
+
+/// 弹出消息框提示用户
+#[cfg(windows)]
+fn print_message(hwnd: HWND, message: &str) {
+    let text = to_wide(message);
+    let caption = to_wide("生命游戏");
+    unsafe {
+        MessageBoxW(
+            hwnd,
+            text.as_ptr(),
+            caption.as_ptr(),
+            MB_OK | MB_ICONINFORMATION,
+        );
+    }
+}
 
+#[cfg(windows)]
 fn to_wstring(str: &str) -> *const u16 {
-    let v: Vec<u16> = OsStr::new(str).to_os_string().encode_wide().chain(once(0)).collect();
+    let v: Vec<u16> = OsStr::new(str)
+        .to_os_string()
+        .encode_wide()
+        .chain(once(0))
+        .collect();
     return v.as_ptr();
 }
 
@@ -451,17 +4079,26 @@ fn create_windows(title: &str) -> Result<(), Error> {
             hIconSm: LoadIconW(null_mut(), IDI_APPLICATION),
         };
         RegisterClassExW(&wnd_class);
+        let style = WS_EX_LAYERED | WS_OVERLAPPED | WS_CAPTION | WS_SYSMENU | WS_MINIMIZEBOX | WS_CLIPCHILDREN;
+        // 按客户区的大小加上边框、标题栏和菜单栏
+        let mut frame = RECT {
+            left: 0,
+            top: 0,
+            right: CLIENT_WIDTH,
+            bottom: CLIENT_HEIGHT,
+        };
+        AdjustWindowRectEx(&mut frame, style, TRUE, WS_EX_APPWINDOW);
         let hwnd = CreateWindowExW(
             WS_EX_APPWINDOW,
             wnd_class.lpszClassName,
             to_wstring(title),
-            WS_EX_LAYERED | WS_OVERLAPPED | WS_CAPTION | WS_SYSMENU | WS_MINIMIZEBOX,
+            style,
             CW_USEDEFAULT,
             CW_USEDEFAULT,
-            WIDTH,
-            HEIGHT,
-            null_mut(),
+            frame.right - frame.left,
+            frame.bottom - frame.top,
             null_mut(),
+            create_menu(),
             h_instance,
             null_mut(),
         );
@@ -472,10 +4109,7 @@ fn create_windows(title: &str) -> Result<(), Error> {
             wParam: 0,
             lParam: 0,
             time: 0,
-            pt: POINT {
-                x: 0,
-                y: 0,
-            },
+            pt: POINT { x: 0, y: 0 },
         };
         while GetMessageW(&mut msg, null_mut(), 0, 0) != 0 {
             TranslateMessage(&msg);
@@ -486,6 +4120,7 @@ fn create_windows(title: &str) -> Result<(), Error> {
     Ok(())
 }
 
+#[cfg(windows)]
 fn hide_console_window() {
     unsafe {
         let window = winapi::um::wincon::GetConsoleWindow();
@@ -495,7 +4130,22 @@ fn hide_console_window() {
     }
 }
 
+#[cfg(windows)]
 fn main() {
+    // 启动时按住 Shift 或者带 --no-restore 参数时不恢复上次的面板
+    let skip_restore = key_down(VK_SHIFT) || std::env::args().any(|arg| arg == "--no-restore");
+    if !skip_restore {
+        restore_autosave();
+    }
+    restore_settings();
+    // --watch <文件>: 打开图案文件, 文件保存后自动重新加载
+    let mut args = std::env::args().skip_while(|arg| arg != "--watch").skip(1);
+    *STARTUP_WATCH.write().unwrap() = args.next().map(PathBuf::from);
     hide_console_window();
-    create_windows("生命游戏").unwrap();
+    create_windows(TITLE).unwrap();
+}
+
+#[cfg(not(windows))]
+fn main() {
+    eprintln!("生命游戏的窗口界面目前只支持 Windows");
 }
@@ -0,0 +1,82 @@
+//! 常用图案, 每个图案是一组活细胞相对于左上角的 (row, col) 偏移
+
+use crate::rule::{WIRE_CONDUCTOR, WIRE_HEAD, WIRE_TAIL};
+
+/// 向右下方移动的滑翔机
+pub const GLIDER: &[(u32, u32)] = &[(0, 1), (1, 2), (2, 0), (2, 1), (2, 2)];
+
+/// Gosper 滑翔机枪, 36x9, 每 30 代向右下方发射一个滑翔机
+pub const GOSPER_GLIDER_GUN: &[(u32, u32)] = &[
+    (0, 24),
+    (1, 22),
+    (1, 24),
+    (2, 12),
+    (2, 13),
+    (2, 20),
+    (2, 21),
+    (2, 34),
+    (2, 35),
+    (3, 11),
+    (3, 15),
+    (3, 20),
+    (3, 21),
+    (3, 34),
+    (3, 35),
+    (4, 0),
+    (4, 1),
+    (4, 10),
+    (4, 16),
+    (4, 20),
+    (4, 21),
+    (5, 0),
+    (5, 1),
+    (5, 10),
+    (5, 14),
+    (5, 16),
+    (5, 17),
+    (5, 22),
+    (5, 24),
+    (6, 10),
+    (6, 16),
+    (6, 24),
+    (7, 11),
+    (7, 15),
+    (8, 12),
+    (8, 13),
+];
+
+/// 周期为 2 的横向闪烁器
+pub const BLINKER: &[(u32, u32)] = &[(0, 0), (0, 1), (0, 2)];
+
+/// 2x2 的方块静物
+pub const BLOCK: &[(u32, u32)] = &[(0, 0), (0, 1), (1, 0), (1, 1)];
+
+/// Wireworld 示例电路: 周期为 8 和 10 的两个时钟各经过一个二极管接到或门上,
+/// 输出线从第 4 行向右引出. `#` 是导线, `H` 是电子头, `t` 是电子尾
+pub const WIREWORLD_CLOCKS_OR: &str = "\
+.tH#..........##.................\n\
+#...###########.###..............\n\
+.###..........##...#.............\n\
+....................#............\n\
+...................##############\n\
+....................#............\n\
+.tH##.........##...#.............\n\
+#....##########.###..............\n\
+.####.........##.................\n";
+
+/// 把上面这种文本图案转成 (row, col, 状态) 的列表, 只列出非空白的格子
+pub fn wireworld_cells(text: &str) -> Vec<(u32, u32, u8)> {
+    let mut cells = Vec::new();
+    for (row, line) in text.lines().enumerate() {
+        for (col, ch) in line.chars().enumerate() {
+            let state = match ch {
+                'H' => WIRE_HEAD,
+                't' => WIRE_TAIL,
+                '#' => WIRE_CONDUCTOR,
+                _ => continue,
+            };
+            cells.push((row as u32, col as u32, state));
+        }
+    }
+    cells
+}
@@ -0,0 +1,836 @@
+//! outer-totalistic 规则, 用 "B3/S23" 这样的字符串表示:
+//! B 后面是死细胞复活需要的邻居数, S 后面是活细胞存活需要的邻居数.
+//! Generations 规则多一段状态数, 例如 "B2/S345/C4" 或 "345/2/4".
+//! 末尾带 `V` 时只数上下左右 4 个邻居, 带 `H` 时是六边形网格上的 6 个邻居.
+//! 另外还支持不属于这一类的 Wireworld、森林火灾模型 ("ForestFire")、沙堆模型 ("Sandpile")、循环元胞自动机 (例如 "Cyclic/C14" 或 "Cyclic/C14V")
+//! 和 Larger than Life, 后者用 Golly 的写法, 例如 "R5,C0,M1,S34..58,B34..45,NM"
+
+use std::collections::VecDeque;
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+use rand::Rng;
+
+use crate::universe::Cell;
+
+/// 数活邻居时看哪些格子
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug, Default)]
+pub enum Neighborhood {
+    /// 周围 8 格
+    #[default]
+    Moore,
+    /// 上下左右 4 格, 规则串末尾带 `V`, 例如 B2/S013V
+    VonNeumann,
+    /// 六边形网格上相邻的 6 格, 奇数行向右错开半格. 规则串末尾带 `H`, 例如 B2/S34H
+    Hexagonal,
+}
+
+impl Neighborhood {
+    /// 最多有几个邻居
+    pub fn size(&self) -> u8 {
+        match self {
+            Neighborhood::Moore => 8,
+            Neighborhood::VonNeumann => 4,
+            Neighborhood::Hexagonal => 6,
+        }
+    }
+}
+
+/// 复活和存活条件都用 9 位掩码表示, 第 n 位表示有 n 个活邻居时复活 / 存活
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub enum Rule {
+    /// 只有生死两种状态的 B/S 规则
+    Life {
+        birth: u16,
+        survive: u16,
+        neighborhood: Neighborhood,
+    },
+    /// Generations 规则: 不满足存活条件的活细胞不会立刻死亡, 而是依次经过
+    /// 2..states-1 这些衰亡状态. 衰亡中的细胞不算活邻居, 也不能复活
+    Generations {
+        birth: u16,
+        survive: u16,
+        states: u8,
+        neighborhood: Neighborhood,
+    },
+    /// Wireworld: 空白 (0)、电子头 (1)、电子尾 (2) 和导线 (3).
+    /// 电子头算作活细胞, 周围有一个或两个电子头的导线变成电子头
+    Wireworld,
+    /// Immigration: 两种颜色的 B3/S23, 新生的细胞取父细胞里占多数的颜色
+    Immigration,
+    /// QuadLife: 四种颜色的 B3/S23, 三个父细胞颜色都不同时取第四种颜色
+    QuadLife,
+    /// 森林火灾模型: 空地 (0)、树 (1) 和着火 (2). 着火的格子下一代变成空地,
+    /// 邻居着火的树跟着着火; 空地长树和树被雷击着火是随机的, 概率由 `Universe` 设置
+    ForestFire,
+    /// Abelian 沙堆模型: 状态是格子里的沙粒数 0 到 3. 每一代在面板中心落一粒沙,
+    /// 沙粒数达到 4 的格子坍塌, 给上下左右各分一粒, 直到没有格子再坍塌
+    Sandpile,
+    /// 循环元胞自动机: 状态 0..states-1 首尾相接, 只要有一个邻居处于下一个状态,
+    /// 格子就前进到那个状态. 会长出螺旋和色块
+    Cyclic {
+        states: u8,
+        neighborhood: Neighborhood,
+    },
+    /// Larger than Life: 邻域是半径为 `radius` 的正方形, 条件是邻居数的区间.
+    /// `include_center` 为 true 时细胞自己也算在邻居数里
+    LargerThanLife {
+        radius: u8,
+        birth: Range,
+        survive: Range,
+        include_center: bool,
+    },
+}
+
+/// 两状态规则的查找表, `table[cell as usize][neighbors]` 是下一代的状态
+pub type RuleTable = [[Cell; 9]; 2];
+
+/// 用函数生成查找表
+pub fn table_from_fn<F>(f: F) -> RuleTable
+where
+    F: Fn(Cell, u8) -> Cell,
+{
+    let mut table = [[Cell::Dead; 9]; 2];
+    for (cell, row) in [Cell::Dead, Cell::Alive].iter().zip(table.iter_mut()) {
+        for (n, next) in row.iter_mut().enumerate() {
+            *next = f(*cell, n as u8);
+        }
+    }
+    table
+}
+
+/// 闭区间 `min..=max`
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct Range {
+    pub min: u16,
+    pub max: u16,
+}
+
+impl Range {
+    pub fn new(min: u16, max: u16) -> Range {
+        Range { min, max }
+    }
+
+    #[inline]
+    pub fn contains(&self, n: u32) -> bool {
+        u32::from(self.min) <= n && n <= u32::from(self.max)
+    }
+}
+
+impl fmt::Display for Range {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}..{}", self.min, self.max)
+    }
+}
+
+/// Wireworld 的电子头, 即活细胞
+pub const WIRE_HEAD: u8 = 1;
+/// Wireworld 的电子尾
+pub const WIRE_TAIL: u8 = 2;
+/// Wireworld 的导线
+pub const WIRE_CONDUCTOR: u8 = 3;
+
+/// 森林火灾模型里的树, 即活细胞
+pub const FIRE_TREE: u8 = 1;
+/// 森林火灾模型里着火的格子
+pub const FIRE_BURNING: u8 = 2;
+
+/// 沙堆模型里格子达到这么多沙粒就坍塌
+pub const TOPPLE_GRAINS: u8 = 4;
+
+/// 循环元胞自动机默认的状态数
+pub const CYCLIC_STATES: u8 = 14;
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum RuleError {
+    /// 不是 `B../S..`、`S../B..` 或 `../..` 的形式
+    Malformed(String),
+    /// 邻居数只能是 0 到 8
+    NeighborOutOfRange(char),
+    /// 同一个数字出现了两次
+    Duplicate(u8),
+    /// Generations 的状态数不是 2 到 255 之间的整数
+    BadStateCount(String),
+}
+
+impl fmt::Display for RuleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RuleError::Malformed(rule) => write!(
+                f,
+                "malformed rule {:?}, expected something like B3/S23 or 23/3",
+                rule
+            ),
+            RuleError::NeighborOutOfRange(ch) => {
+                write!(f, "neighbor count {:?} is not between 0 and 8", ch)
+            }
+            RuleError::Duplicate(n) => write!(f, "neighbor count {} appears twice", n),
+            RuleError::BadStateCount(states) => {
+                write!(f, "state count {:?} is not between 2 and 255", states)
+            }
+        }
+    }
+}
+
+impl Error for RuleError {}
+
+/// 内置的规则, (名字, 规则串)
+pub const PRESETS: &[(&str, &str)] = &[
+    ("Conway", "B3/S23"),
+    ("HighLife", "B36/S23"),
+    ("Seeds", "B2/S"),
+    ("Day & Night", "B3678/S34678"),
+    ("Life without Death", "B3/S012345678"),
+    ("Maze", "B3/S12345"),
+    ("Bosco's Rule", "R5,C0,M1,S34..58,B34..45,NM"),
+    ("Immigration", "Immigration"),
+    ("QuadLife", "QuadLife"),
+    ("Hexagonal Life", "B2/S34H"),
+];
+
+/// 按名字查找内置规则, 不区分大小写
+pub fn preset(name: &str) -> Option<Rule> {
+    PRESETS
+        .iter()
+        .find(|(preset, _)| preset.eq_ignore_ascii_case(name.trim()))
+        .map(|(_, rule)| rule.parse().expect("built-in presets are valid"))
+}
+
+/// `rule` 在 `PRESETS` 里的下标
+pub fn preset_index(rule: &Rule) -> Option<usize> {
+    PRESETS
+        .iter()
+        .position(|(_, text)| text.parse::<Rule>().as_ref() == Ok(rule))
+}
+
+impl Rule {
+    /// B3/S23
+    pub const CONWAY: Rule = Rule::Life {
+        birth: 1 << 3,
+        survive: 1 << 2 | 1 << 3,
+        neighborhood: Neighborhood::Moore,
+    };
+
+    /// Brian's Brain, 即 B2/S/C3: 放电 (活) 的细胞下一代进入不应期 (状态 2),
+    /// 再下一代恢复就绪 (死), 就绪的细胞在恰好两个邻居放电时放电
+    pub const BRIANS_BRAIN: Rule = Rule::Generations {
+        birth: 1 << 2,
+        survive: 0,
+        states: 3,
+        neighborhood: Neighborhood::Moore,
+    };
+
+    /// 14 个状态、Moore 邻域的循环元胞自动机
+    pub const CYCLIC: Rule = Rule::Cyclic {
+        states: CYCLIC_STATES,
+        neighborhood: Neighborhood::Moore,
+    };
+
+    /// 用邻居数的列表构造规则, 超过 8 的数字被忽略
+    pub fn new(birth: &[u8], survive: &[u8]) -> Rule {
+        Rule::Life {
+            birth: mask(birth),
+            survive: mask(survive),
+            neighborhood: Neighborhood::Moore,
+        }
+    }
+
+    /// 带 `states` 个状态的 Generations 规则, `states` 不超过 2 时就是普通的 B/S 规则
+    pub fn generations(birth: &[u8], survive: &[u8], states: u8) -> Rule {
+        let rule = Rule::Generations {
+            birth: mask(birth),
+            survive: mask(survive),
+            states,
+            neighborhood: Neighborhood::Moore,
+        };
+        rule.normalized()
+    }
+
+    /// 只有两个状态的 Generations 规则换成等价的 `Life`
+    fn normalized(self) -> Rule {
+        match self {
+            Rule::Generations {
+                birth,
+                survive,
+                states,
+                neighborhood,
+            } if states <= 2 => Rule::Life {
+                birth,
+                survive,
+                neighborhood,
+            },
+            rule => rule,
+        }
+    }
+
+    pub fn neighborhood(&self) -> Neighborhood {
+        match *self {
+            Rule::Life { neighborhood, .. }
+            | Rule::Generations { neighborhood, .. }
+            | Rule::Cyclic { neighborhood, .. } => neighborhood,
+            _ => Neighborhood::Moore,
+        }
+    }
+
+    /// 换一种邻域, 其余条件不变. Wireworld 和 Larger than Life 总是用正方形邻域
+    pub fn with_neighborhood(self, neighborhood: Neighborhood) -> Rule {
+        match self {
+            Rule::Life { birth, survive, .. } => Rule::Life {
+                birth,
+                survive,
+                neighborhood,
+            },
+            Rule::Generations {
+                birth,
+                survive,
+                states,
+                ..
+            } => Rule::Generations {
+                birth,
+                survive,
+                states,
+                neighborhood,
+            },
+            Rule::Cyclic { states, .. } => Rule::Cyclic {
+                states,
+                neighborhood,
+            },
+            rule => rule,
+        }
+    }
+
+    /// 查找表对应的 B/S 规则, 邻域为 Moore
+    pub fn from_table(table: &RuleTable) -> Rule {
+        let mask = |row: &[Cell; 9]| {
+            row.iter()
+                .enumerate()
+                .filter(|(_, &cell)| cell == Cell::Alive)
+                .fold(0u16, |m, (n, _)| m | 1 << n)
+        };
+        Rule::Life {
+            birth: mask(&table[Cell::Dead as usize]),
+            survive: mask(&table[Cell::Alive as usize]),
+            neighborhood: Neighborhood::Moore,
+        }
+    }
+
+    /// 按 `applies` 生成的查找表, 两状态的演化都查这张表
+    pub fn table(&self) -> RuleTable {
+        table_from_fn(|cell, n| self.applies(cell, n))
+    }
+
+    /// 两状态的规则是否含 B0
+    pub fn has_b0(&self) -> bool {
+        match *self {
+            Rule::Life { birth, .. } => birth & 1 != 0,
+            _ => false,
+        }
+    }
+
+    /// B0 规则的相位交替: 存的细胞处于 `inverted` 相位时, 这一代实际用来演化
+    /// 存的细胞的规则, 以及演化之后的相位. 含 S8 (V 邻域为 S4) 时背景亮起后不再熄灭,
+    /// 第一代之后一直取反; 否则背景每代亮灭一次, 相位每代交替. 换算出来的规则都不含 B0.
+    /// 其他规则原样返回, 相位为 false
+    pub fn phase_step(&self, inverted: bool) -> (Rule, bool) {
+        let (birth, survive, neighborhood) = match *self {
+            Rule::Life {
+                birth,
+                survive,
+                neighborhood,
+            } if birth & 1 != 0 => (birth, survive, neighborhood),
+            rule => return (rule, false),
+        };
+        let max = neighborhood.size();
+        let next_inverted = survive & (1 << max) != 0 || !inverted;
+        let (mut phase_birth, mut phase_survive) = (0u16, 0u16);
+        for stored in 0..=max {
+            let actual = if inverted { max - stored } else { stored };
+            for &alive in [false, true].iter() {
+                // 存的细胞换回实际状态, 按原规则演化, 再换成下一代的相位
+                let actual_alive = alive != inverted;
+                let mask = if actual_alive { survive } else { birth };
+                let next_alive = (mask & (1 << actual) != 0) != next_inverted;
+                if next_alive {
+                    if alive {
+                        phase_survive |= 1 << stored;
+                    } else {
+                        phase_birth |= 1 << stored;
+                    }
+                }
+            }
+        }
+        let rule = Rule::Life {
+            birth: phase_birth,
+            survive: phase_survive,
+            neighborhood,
+        };
+        (rule, next_inverted)
+    }
+
+    /// 条件里出现了、但当前邻域永远数不到的邻居数, 例如 V 邻域下的 5 到 8.
+    /// 解析时不把它们当成错误, 由调用者决定是否提示
+    pub fn unreachable_counts(&self) -> impl Iterator<Item = u8> {
+        let max = self.neighborhood().size();
+        let (birth, survive) = self.masks();
+        (max + 1..=8).filter(move |n| (birth | survive) & (1 << n) != 0)
+    }
+
+    fn masks(&self) -> (u16, u16) {
+        match *self {
+            Rule::Life { birth, survive, .. } | Rule::Generations { birth, survive, .. } => {
+                (birth, survive)
+            }
+            Rule::Immigration | Rule::QuadLife => Rule::CONWAY.masks(),
+            Rule::Wireworld
+            | Rule::ForestFire
+            | Rule::Sandpile
+            | Rule::Cyclic { .. }
+            | Rule::LargerThanLife { .. } => (0, 0),
+        }
+    }
+
+    /// 细胞的状态数, 包括活和死. B/S 规则为 2
+    pub fn states(&self) -> u8 {
+        match *self {
+            Rule::Life { .. } | Rule::LargerThanLife { .. } => 2,
+            Rule::Immigration | Rule::QuadLife => self.colors() + 1,
+            Rule::Generations { states, .. } | Rule::Cyclic { states, .. } => states.max(2),
+            Rule::Wireworld => 4,
+            Rule::ForestFire => 3,
+            Rule::Sandpile => TOPPLE_GRAINS,
+        }
+    }
+
+    /// 活细胞有几种颜色, 不带颜色的规则为 1
+    pub fn colors(&self) -> u8 {
+        match self {
+            Rule::Immigration => 2,
+            Rule::QuadLife => 4,
+            _ => 1,
+        }
+    }
+
+    /// 有 `neighbors` 个活邻居的细胞在下一代是否为活细胞. Generations 规则下
+    /// 活细胞变成 Dead 表示开始衰亡
+    #[inline]
+    pub fn applies(&self, cell: Cell, neighbors: u8) -> Cell {
+        if self.next_state(cell as u8, neighbors) == 1 {
+            Cell::Alive
+        } else {
+            Cell::Dead
+        }
+    }
+
+    /// 状态为 `state` 的格子在下一代的状态, 状态的含义见 `Universe::state`.
+    /// 超出规则状态数的值当作死细胞. Larger than Life 的邻居数可能超过 255,
+    /// `Universe` 演化时不经过这里. 循环元胞自动机的 `neighbors` 是处于下一个状态的邻居数,
+    /// 森林火灾模型的 `neighbors` 是着火的邻居数, 这里只有确定的那部分, 不会随机长树和着火
+    #[inline]
+    pub fn next_state(&self, state: u8, neighbors: u8) -> u8 {
+        let (birth, survive) = match *self {
+            Rule::Wireworld => {
+                return match state {
+                    WIRE_HEAD => WIRE_TAIL,
+                    WIRE_TAIL => WIRE_CONDUCTOR,
+                    WIRE_CONDUCTOR if neighbors == 1 || neighbors == 2 => WIRE_HEAD,
+                    WIRE_CONDUCTOR => WIRE_CONDUCTOR,
+                    _ => 0,
+                }
+            }
+            Rule::LargerThanLife {
+                birth,
+                survive,
+                include_center,
+                ..
+            } => {
+                let alive = state == 1;
+                let count = u32::from(neighbors) + (include_center && alive) as u32;
+                let range = if alive { survive } else { birth };
+                return range.contains(count) as u8;
+            }
+            Rule::ForestFire => {
+                return match state {
+                    FIRE_TREE if neighbors > 0 => FIRE_BURNING,
+                    FIRE_TREE => FIRE_TREE,
+                    _ => 0,
+                }
+            }
+            // 坍塌要看整个面板, 单个格子的状态不会自己变化
+            Rule::Sandpile => return if state < TOPPLE_GRAINS { state } else { 0 },
+            Rule::Cyclic { states, .. } => {
+                let next = (state + 1) % states.max(2);
+                return if state >= states {
+                    0
+                } else if neighbors > 0 {
+                    next
+                } else {
+                    state
+                };
+            }
+            // 不知道父细胞的颜色, 新生的细胞取第一种颜色, 存活的保持原色.
+            // `Universe` 演化时走带颜色的那条路, 不经过这里
+            Rule::Immigration | Rule::QuadLife => {
+                let (birth, survive) = self.masks();
+                return match state {
+                    0 => (birth & (1 << neighbors) != 0) as u8,
+                    s if s <= self.colors() && survive & (1 << neighbors) != 0 => s,
+                    _ => 0,
+                };
+            }
+            rule => rule.masks(),
+        };
+        let hit = |mask: u16| mask & (1 << neighbors) != 0;
+        match state {
+            0 => hit(birth) as u8,
+            1 if hit(survive) => 1,
+            // 不满足存活条件的活细胞进入第一个衰亡状态, 两状态的规则直接死亡
+            1 if self.states() > 2 => 2,
+            s if s > 1 && s < self.states() - 1 => s + 1,
+            _ => 0,
+        }
+    }
+
+    /// 内置规则的名字, 不是内置规则时为 None
+    pub fn name(&self) -> Option<&'static str> {
+        match *self {
+            Rule::BRIANS_BRAIN => return Some("Brian's Brain"),
+            Rule::Wireworld => return Some("Wireworld"),
+            Rule::ForestFire => return Some("Forest Fire"),
+            Rule::Sandpile => return Some("Sandpile"),
+            _ => {}
+        }
+        preset_index(self).map(|i| PRESETS[i].0)
+    }
+
+    pub fn births(&self) -> impl Iterator<Item = u8> {
+        let (birth, _) = self.masks();
+        (0..=8).filter(move |n| birth & (1 << n) != 0)
+    }
+
+    pub fn survivals(&self) -> impl Iterator<Item = u8> {
+        let (_, survive) = self.masks();
+        (0..=8).filter(move |n| survive & (1 << n) != 0)
+    }
+}
+
+/// 随机生成一条 Moore 邻域的 B/S 规则, 偏向不会马上爆满或死光的规则:
+/// 不含 B0 和 B1 (几乎总是铺满整个面板), B2 出现得比较少, 至少有一个复活条件,
+/// 大多数情况下含 S2 或 S3. 同样的随机数生成器状态总是得到同样的规则
+pub fn random_rule<R: Rng + ?Sized>(rng: &mut R) -> Rule {
+    let mut birth = 0u16;
+    while birth == 0 {
+        for n in 2..=8 {
+            let p = if n == 2 { 0.15 } else { 0.3 };
+            if rng.gen_bool(p) {
+                birth |= 1 << n;
+            }
+        }
+    }
+    let mut survive = 0u16;
+    for n in 0..=8 {
+        if rng.gen_bool(0.35) {
+            survive |= 1 << n;
+        }
+    }
+    if rng.gen_bool(0.75) {
+        survive |= 1 << rng.gen_range(2, 4);
+    }
+    Rule::Life {
+        birth,
+        survive,
+        neighborhood: Neighborhood::Moore,
+    }
+}
+
+/// `RuleHistory` 最多记住的规则数
+pub const HISTORY_LEN: usize = 10;
+
+/// 最近探索过的规则, 可以前后翻看. 新规则总是加在最后, 超过 `HISTORY_LEN` 条时丢掉最早的
+#[derive(Clone, Debug, Default)]
+pub struct RuleHistory {
+    rules: VecDeque<Rule>,
+    cursor: usize,
+}
+
+impl RuleHistory {
+    pub fn new() -> RuleHistory {
+        RuleHistory::default()
+    }
+
+    /// 记下一条新规则并翻到它
+    pub fn push(&mut self, rule: Rule) {
+        if self.rules.len() == HISTORY_LEN {
+            self.rules.pop_front();
+        }
+        self.rules.push_back(rule);
+        self.cursor = self.rules.len() - 1;
+    }
+
+    /// 当前翻到的规则, 还没有记录时为 None
+    pub fn current(&self) -> Option<Rule> {
+        self.rules.get(self.cursor).copied()
+    }
+
+    /// 翻到上一条, 已经是最早的一条时不动
+    pub fn back(&mut self) -> Option<Rule> {
+        self.cursor = self.cursor.saturating_sub(1);
+        self.current()
+    }
+
+    /// 翻到下一条, 已经是最新的一条时不动
+    pub fn forward(&mut self) -> Option<Rule> {
+        if self.cursor + 1 < self.rules.len() {
+            self.cursor += 1;
+        }
+        self.current()
+    }
+
+    pub fn len(&self) -> usize {
+        self.rules.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// 从最早到最新的规则
+    pub fn iter(&self) -> impl Iterator<Item = &Rule> {
+        self.rules.iter()
+    }
+}
+
+fn mask(counts: &[u8]) -> u16 {
+    counts
+        .iter()
+        .filter(|&&n| n <= 8)
+        .fold(0u16, |m, &n| m | 1 << n)
+}
+
+impl Default for Rule {
+    fn default() -> Rule {
+        Rule::CONWAY
+    }
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Rule::Wireworld => return write!(f, "WireWorld"),
+            Rule::ForestFire => return write!(f, "ForestFire"),
+            Rule::Sandpile => return write!(f, "Sandpile"),
+            Rule::Immigration => return write!(f, "Immigration"),
+            Rule::QuadLife => return write!(f, "QuadLife"),
+            Rule::Cyclic {
+                states,
+                neighborhood,
+            } => {
+                write!(f, "Cyclic/C{}", states)?;
+                return match neighborhood {
+                    Neighborhood::Moore => Ok(()),
+                    Neighborhood::VonNeumann => write!(f, "V"),
+                    Neighborhood::Hexagonal => write!(f, "H"),
+                };
+            }
+            Rule::LargerThanLife {
+                radius,
+                birth,
+                survive,
+                include_center,
+            } => {
+                return write!(
+                    f,
+                    "R{},C0,M{},S{},B{},NM",
+                    radius, include_center as u8, survive, birth
+                )
+            }
+            _ => {}
+        }
+        write!(f, "B")?;
+        for n in self.births() {
+            write!(f, "{}", n)?;
+        }
+        write!(f, "/S")?;
+        for n in self.survivals() {
+            write!(f, "{}", n)?;
+        }
+        if let Rule::Generations { .. } = self {
+            write!(f, "/C{}", self.states())?;
+        }
+        match self.neighborhood() {
+            Neighborhood::Moore => {}
+            Neighborhood::VonNeumann => write!(f, "V")?,
+            Neighborhood::Hexagonal => write!(f, "H")?,
+        }
+        Ok(())
+    }
+}
+
+fn parse_counts(digits: &str) -> Result<u16, RuleError> {
+    let mut mask = 0u16;
+    for ch in digits.chars() {
+        let n = match ch.to_digit(10) {
+            Some(n) if n <= 8 => n as u8,
+            Some(_) => return Err(RuleError::NeighborOutOfRange(ch)),
+            None => return Err(RuleError::Malformed(digits.to_string())),
+        };
+        if mask & (1 << n) != 0 {
+            return Err(RuleError::Duplicate(n));
+        }
+        mask |= 1 << n;
+    }
+    Ok(mask)
+}
+
+impl FromStr for Rule {
+    type Err = RuleError;
+
+    fn from_str(s: &str) -> Result<Rule, RuleError> {
+        let text = s.trim().to_ascii_uppercase();
+        let malformed = || RuleError::Malformed(s.trim().to_string());
+        match text.as_str() {
+            "WIREWORLD" => return Ok(Rule::Wireworld),
+            "FORESTFIRE" => return Ok(Rule::ForestFire),
+            "SANDPILE" => return Ok(Rule::Sandpile),
+            "IMMIGRATION" => return Ok(Rule::Immigration),
+            "QUADLIFE" => return Ok(Rule::QuadLife),
+            _ => {}
+        }
+        if let Some(rest) = text.strip_prefix("CYCLIC") {
+            return parse_cyclic(rest).ok_or_else(malformed)?;
+        }
+        if text.starts_with('R') && text.contains(',') {
+            return parse_larger_than_life(&text).ok_or_else(malformed)?;
+        }
+        // 末尾的 V 表示 von Neumann 邻域, H 表示六边形.
+        // 邻居数仍然接受 0 到 8, 见 `unreachable_counts`
+        let (text, neighborhood) = if let Some(rest) = text.strip_suffix('V') {
+            (rest, Neighborhood::VonNeumann)
+        } else if let Some(rest) = text.strip_suffix('H') {
+            (rest, Neighborhood::Hexagonal)
+        } else {
+            (text.as_str(), Neighborhood::Moore)
+        };
+        let mut parts = text.split('/');
+        let (first, second, third) = match (parts.next(), parts.next(), parts.next(), parts.next())
+        {
+            (Some(a), Some(b), c, None) => (a, b, c),
+            _ => return Err(malformed()),
+        };
+        let (birth, survive) = match (first.chars().next(), second.chars().next()) {
+            (Some('B'), Some('S')) => (&first[1..], &second[1..]),
+            (Some('S'), Some('B')) => (&second[1..], &first[1..]),
+            // 旧写法 "存活/复活", 例如 23/3
+            _ if !first.starts_with(char::is_alphabetic)
+                && !second.starts_with(char::is_alphabetic) =>
+            {
+                (second, first)
+            }
+            _ => return Err(malformed()),
+        };
+        let digits = |part: &str| {
+            parse_counts(part).map_err(|e| match e {
+                RuleError::Malformed(_) => malformed(),
+                e => e,
+            })
+        };
+        let (birth, survive) = (digits(birth)?, digits(survive)?);
+        let states = match third {
+            None => {
+                return Ok(Rule::Life {
+                    birth,
+                    survive,
+                    neighborhood,
+                })
+            }
+            // Generations 的第三段是状态数, 例如 345/2/4 或 B2/S345/C4
+            Some(part) => {
+                let count = part.strip_prefix('C').unwrap_or(part);
+                match count.parse::<u8>() {
+                    Ok(n) if n >= 2 => n,
+                    _ => return Err(RuleError::BadStateCount(count.to_string())),
+                }
+            }
+        };
+        let rule = Rule::Generations {
+            birth,
+            survive,
+            states,
+            neighborhood,
+        };
+        Ok(rule.normalized())
+    }
+}
+
+/// "Cyclic" 后面的部分, 例如 "/C14V". 省略时为 14 个状态、Moore 邻域, 带 H 时是六边形.
+/// 格式不对时返回 None, 状态数不是 2 到 255 时返回 `BadStateCount`
+fn parse_cyclic(rest: &str) -> Option<Result<Rule, RuleError>> {
+    let (rest, neighborhood) = if let Some(rest) = rest.strip_suffix('V') {
+        (rest, Neighborhood::VonNeumann)
+    } else if let Some(rest) = rest.strip_suffix('H') {
+        (rest, Neighborhood::Hexagonal)
+    } else {
+        (rest, Neighborhood::Moore)
+    };
+    let states = match rest {
+        "" => CYCLIC_STATES,
+        _ => {
+            let count = rest.strip_prefix('/')?;
+            let count = count.strip_prefix('C').unwrap_or(count);
+            match count.parse::<u8>() {
+                Ok(n) if n >= 2 => n,
+                _ => return Some(Err(RuleError::BadStateCount(count.to_string()))),
+            }
+        }
+    };
+    Some(Ok(Rule::Cyclic {
+        states,
+        neighborhood,
+    }))
+}
+
+fn parse_range(text: &str) -> Option<Range> {
+    let (min, max) = text.split_once("..")?;
+    let range = Range::new(min.parse().ok()?, max.parse().ok()?);
+    if range.min > range.max {
+        return None;
+    }
+    Some(range)
+}
+
+/// Golly 的写法, 例如 R5,C0,M1,S34..58,B34..45,NM. 格式不对时返回 None,
+/// 状态数不是 0 到 2 时返回 `BadStateCount`
+fn parse_larger_than_life(text: &str) -> Option<Result<Rule, RuleError>> {
+    let (mut radius, mut birth, mut survive) = (None, None, None);
+    let mut include_center = false;
+    for part in text.split(',') {
+        let part = part.trim();
+        let value = part.get(1..)?;
+        match part.chars().next()? {
+            'R' => radius = Some(value.parse::<u8>().ok().filter(|&r| r > 0)?),
+            'C' => {
+                if !matches!(value, "0" | "1" | "2") {
+                    return Some(Err(RuleError::BadStateCount(value.to_string())));
+                }
+            }
+            'M' => {
+                include_center = match value {
+                    "0" => false,
+                    "1" => true,
+                    _ => return None,
+                }
+            }
+            'S' => survive = Some(parse_range(value)?),
+            'B' => birth = Some(parse_range(value)?),
+            // 只支持正方形邻域
+            'N' if value == "M" => {}
+            _ => return None,
+        }
+    }
+    Some(Ok(Rule::LargerThanLife {
+        radius: radius?,
+        birth: birth?,
+        survive: survive?,
+        include_center,
+    }))
+}
@@ -0,0 +1,133 @@
+//! 窗口的设置文件, 每行一个 `键=值`. 不认识的键和格式不对的行跳过, 缺的键用默认值,
+//! 这样旧版本写的文件和手改坏的文件都还能读
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// 颜色对话框里 16 个自定义颜色格子的默认值, 白色
+pub const BLANK_COLOR: u32 = 0x00FF_FFFF;
+
+/// 颜色都和 Win32 的 `COLORREF` 一样是 0x00BBGGRR, 文件里写成 `#RRGGBB`
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Settings {
+    /// 当前配色的编号, 见窗口里的 `THEMES`
+    pub theme: usize,
+    pub show_grid: bool,
+    /// 自己选的 (活细胞, 死细胞, 网格线) 颜色, 没选过时为 None
+    pub custom_theme: Option<[u32; 3]>,
+    /// 颜色对话框里的自定义颜色
+    pub custom_colors: [u32; 16],
+}
+
+impl Default for Settings {
+    fn default() -> Settings {
+        Settings {
+            theme: 0,
+            show_grid: true,
+            custom_theme: None,
+            custom_colors: [BLANK_COLOR; 16],
+        }
+    }
+}
+
+fn format_color(color: u32) -> String {
+    let (r, g, b) = (color & 0xFF, (color >> 8) & 0xFF, (color >> 16) & 0xFF);
+    format!("#{:02X}{:02X}{:02X}", r, g, b)
+}
+
+fn parse_color(text: &str) -> Option<u32> {
+    let hex = text.trim().strip_prefix('#')?;
+    if hex.len() != 6 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    let rgb = u32::from_str_radix(hex, 16).ok()?;
+    let (r, g, b) = (rgb >> 16, (rgb >> 8) & 0xFF, rgb & 0xFF);
+    Some(r | g << 8 | b << 16)
+}
+
+/// 逗号分开的 `N` 个颜色, 个数不对或有一个不认识时为 None
+fn parse_colors<const N: usize>(text: &str) -> Option<[u32; N]> {
+    let mut colors = [0; N];
+    let mut parts = text.split(',');
+    for color in colors.iter_mut() {
+        *color = parse_color(parts.next()?)?;
+    }
+    match parts.next() {
+        Some(_) => None,
+        None => Some(colors),
+    }
+}
+
+fn format_colors(colors: &[u32]) -> String {
+    colors
+        .iter()
+        .map(|&c| format_color(c))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+impl Settings {
+    pub fn parse(text: &str) -> Settings {
+        let mut settings = Settings::default();
+        for line in text.lines() {
+            let (key, value) = match line.split_once('=') {
+                Some((key, value)) => (key.trim(), value.trim()),
+                None => continue,
+            };
+            match key {
+                "theme" => {
+                    if let Ok(theme) = value.parse() {
+                        settings.theme = theme;
+                    }
+                }
+                "show_grid" => {
+                    if let Ok(show) = value.parse() {
+                        settings.show_grid = show;
+                    }
+                }
+                "custom_theme" => {
+                    if let Some(colors) = parse_colors(value) {
+                        settings.custom_theme = Some(colors);
+                    }
+                }
+                "custom_colors" => {
+                    if let Some(colors) = parse_colors(value) {
+                        settings.custom_colors = colors;
+                    }
+                }
+                _ => {}
+            }
+        }
+        settings
+    }
+
+    pub fn serialize(&self) -> String {
+        let mut out = format!("theme={}\nshow_grid={}\n", self.theme, self.show_grid);
+        if let Some(colors) = &self.custom_theme {
+            out.push_str(&format!("custom_theme={}\n", format_colors(colors)));
+        }
+        out.push_str(&format!(
+            "custom_colors={}\n",
+            format_colors(&self.custom_colors)
+        ));
+        out
+    }
+
+    /// 读取设置文件, 文件不存在时返回默认设置
+    pub fn load(path: &Path) -> io::Result<Settings> {
+        match fs::read_to_string(path) {
+            Ok(text) => Ok(Settings::parse(&text)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Settings::default()),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// 写入设置文件, 目录不存在时先建好
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, self.serialize())
+    }
+}
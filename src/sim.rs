@@ -0,0 +1,335 @@
+//! 在单独的线程里按固定的间隔演化, 窗口线程只负责画面和输入.
+//! 窗口通过 `SimHandle::send` 发命令, 线程在两次演化之间按顺序处理.
+//!
+//! 线程先睡到离下一次演化还差 `SPIN` 的时候, 剩下的一小段让出时间片空转等待,
+//! 所以间隔可以短到 1 毫秒. 睡眠能多准取决于系统的定时器精度: Windows 默认大约
+//! 15.6 毫秒, 调用 `timeBeginPeriod(1)` 之后是 1 毫秒左右; 睡过头超过 `SPIN` 时
+//! 这一代会晚一点, 但不会连续补上落下的代数
+
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::mpsc::{self, RecvTimeoutError, Sender, TryRecvError};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// 发给演化线程的命令
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Command {
+    Pause,
+    Resume,
+    /// 暂停时继续, 运行时暂停
+    Toggle,
+    /// 重新随机生成面板
+    Reset,
+    /// 暂停时演化一代并重画, 运行时什么都不做
+    Step,
+    /// 换成新的演化间隔, 从现在开始计时. 间隔为 0 时不停地演化
+    SetInterval(Duration),
+    /// 不画画面, 尽快演化这么多代; `u64::MAX` 表示一直演化到 `Cancel`
+    Advance(u64),
+    /// 不画画面演化到第这么多代, 到了之后暂停, 中途不会因为面板稳定而停下.
+    /// 已经过了这一代时什么都不做
+    AdvanceTo(i64),
+    /// 尽快演化到面板稳定或者演化了这么多代为止, 期间隔一会儿画一帧, 结束后暂停
+    RunUntilStable(u64),
+    /// 停下还没演化完的 `Advance`、`AdvanceTo` 或 `RunUntilStable`
+    Cancel,
+    /// 窗口最小化了, 画面看不见
+    Hide,
+    /// 窗口从最小化恢复
+    Show,
+}
+
+/// 演化线程驱动的对象
+pub trait Simulation: Send + 'static {
+    /// 处理除了 `Command::SetInterval` 以外的命令
+    fn apply(&mut self, command: Command);
+    /// 每个间隔调用一次, 暂停时也照常调用, 由实现自己决定暂停时做什么
+    fn tick(&mut self);
+    /// 为 true 时不等间隔, 处理完已经到了的命令就接着调用 `tick`.
+    /// 每次 `tick` 只做一小段, 命令才不会等太久
+    fn busy(&self) -> bool {
+        false
+    }
+    /// 为 true 时不再按间隔调用 `tick`, 线程一直睡到下一条命令到达, 暂停时不占 CPU
+    fn idle(&self) -> bool {
+        false
+    }
+}
+
+/// 离下一次演化不到这么久时不再睡眠, 空转等待
+const SPIN: Duration = Duration::from_millis(2);
+
+/// 翻页键依次切换的演化间隔, 从慢到快, 最后的 0 表示不限速
+pub const SPEED_LADDER: [Duration; 11] = [
+    Duration::from_millis(1000),
+    Duration::from_millis(500),
+    Duration::from_millis(250),
+    Duration::from_millis(100),
+    Duration::from_millis(50),
+    Duration::from_millis(25),
+    Duration::from_millis(10),
+    Duration::from_millis(5),
+    Duration::from_millis(2),
+    Duration::from_millis(1),
+    Duration::from_millis(0),
+];
+
+/// `SPEED_LADDER` 里比 `interval` 快一档的间隔, 已经最快时不变
+pub fn faster(interval: Duration) -> Duration {
+    SPEED_LADDER
+        .iter()
+        .copied()
+        .find(|&rung| rung < interval)
+        .unwrap_or(interval)
+}
+
+/// `SPEED_LADDER` 里比 `interval` 慢一档的间隔, 已经最慢时不变
+pub fn slower(interval: Duration) -> Duration {
+    SPEED_LADDER
+        .iter()
+        .rev()
+        .copied()
+        .find(|&rung| rung > interval)
+        .unwrap_or(interval)
+}
+
+/// 按最近一段时间演化的代数估计每秒演化多少代
+#[derive(Copy, Clone, Debug)]
+pub struct TickRate {
+    started: Instant,
+    ticks: u32,
+    per_second: f64,
+}
+
+impl TickRate {
+    /// 每隔这么久重新估计一次
+    const WINDOW: Duration = Duration::from_millis(500);
+
+    pub fn new(now: Instant) -> TickRate {
+        TickRate {
+            started: now,
+            ticks: 0,
+            per_second: 0.0,
+        }
+    }
+
+    /// 在 `now` 演化了一代
+    pub fn tick(&mut self, now: Instant) {
+        self.ticks += 1;
+        let elapsed = now.saturating_duration_since(self.started);
+        if elapsed >= TickRate::WINDOW {
+            self.per_second = f64::from(self.ticks) / elapsed.as_secs_f64();
+            self.started = now;
+            self.ticks = 0;
+        }
+    }
+
+    /// 最近一次估计的每秒代数, 还不到一次估计的时间时是 0
+    pub fn per_second(&self) -> f64 {
+        self.per_second
+    }
+}
+
+/// 运行时每演化几代画一次的上限
+pub const MAX_DRAW_EVERY: u32 = 64;
+
+/// 每演化 `every` 代画一次, 画面跟不上演化时少画几帧, 演化不用等重画
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct FrameSkip {
+    every: u32,
+    /// 上次画过之后演化了几代
+    ticks: u32,
+}
+
+impl Default for FrameSkip {
+    fn default() -> FrameSkip {
+        FrameSkip::new(1)
+    }
+}
+
+impl FrameSkip {
+    /// `every` 限制在 1 到 `MAX_DRAW_EVERY` 之间
+    pub fn new(every: u32) -> FrameSkip {
+        FrameSkip {
+            every: every.clamp(1, MAX_DRAW_EVERY),
+            ticks: 0,
+        }
+    }
+
+    pub fn every(&self) -> u32 {
+        self.every
+    }
+
+    /// 画得更少: 间隔翻倍, 到 `MAX_DRAW_EVERY` 为止
+    pub fn increase(&mut self) {
+        self.every = (self.every * 2).min(MAX_DRAW_EVERY);
+    }
+
+    /// 画得更多: 间隔减半, 到每一代都画为止
+    pub fn decrease(&mut self) {
+        self.every = (self.every / 2).max(1);
+        self.ticks = self.ticks.min(self.every - 1);
+    }
+
+    /// 演化了一代, 返回这一代要不要画
+    pub fn tick(&mut self) -> bool {
+        self.ticks += 1;
+        if self.ticks >= self.every {
+            self.ticks = 0;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// 输入的跳转目标: `1000` 表示到第 1000 代, `+1000` 表示再演化 1000 代
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Jump {
+    To(i64),
+    By(u64),
+}
+
+impl Jump {
+    /// 从第 `current` 代出发要演化几代, 目标已经过去时返回 None. 没有历史记录, 不能倒回去
+    pub fn steps_from(self, current: i64) -> Option<u64> {
+        match self {
+            Jump::To(target) if target >= current => Some((target - current) as u64),
+            Jump::To(_) => None,
+            Jump::By(steps) => Some(steps),
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum ParseJumpError {
+    Empty,
+    /// 不是非负整数, 或者大到存不下
+    NotANumber(String),
+}
+
+impl fmt::Display for ParseJumpError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseJumpError::Empty => write!(f, "no generation given"),
+            ParseJumpError::NotANumber(text) => write!(f, "not a generation: {:?}", text),
+        }
+    }
+}
+
+impl Error for ParseJumpError {}
+
+impl FromStr for Jump {
+    type Err = ParseJumpError;
+
+    fn from_str(s: &str) -> Result<Jump, ParseJumpError> {
+        let text = s.trim();
+        let (relative, digits) = match text.strip_prefix('+') {
+            Some(digits) => (true, digits),
+            None => (false, text),
+        };
+        if digits.is_empty() {
+            return Err(ParseJumpError::Empty);
+        }
+        let bad = || ParseJumpError::NotANumber(text.to_string());
+        // `parse` 还认 "+5" 这样的写法, 这里只要数字
+        if !digits.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(bad());
+        }
+        if relative {
+            digits.parse().map(Jump::By).map_err(|_| bad())
+        } else {
+            digits.parse().map(Jump::To).map_err(|_| bad())
+        }
+    }
+}
+
+/// 演化线程, 调用 `stop` 或者 drop 时结束并等线程退出
+pub struct SimHandle {
+    commands: Option<Sender<Command>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl SimHandle {
+    /// 线程已经结束时命令直接丢掉
+    pub fn send(&self, command: Command) {
+        if let Some(commands) = self.commands.as_ref() {
+            let _ = commands.send(command);
+        }
+    }
+
+    pub fn stop(&mut self) {
+        // 断开通道, 线程处理完手上的这一轮就退出
+        self.commands = None;
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for SimHandle {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// 启动演化线程, 每隔 `interval` 调用一次 `sim.tick`. 某一代演化得比间隔还慢时
+/// 不会连续补上落下的次数, 从演化结束时重新计时
+pub fn spawn<S: Simulation>(interval: Duration, mut sim: S) -> SimHandle {
+    let (sender, commands) = mpsc::channel();
+    let thread = thread::spawn(move || {
+        let mut interval = interval;
+        let mut next = Instant::now() + interval;
+        loop {
+            // 间隔为 0 或者正忙时不睡眠, 仍然先取走已经到了的命令
+            let wait = if sim.busy() {
+                Duration::from_secs(0)
+            } else {
+                next.saturating_duration_since(Instant::now())
+            };
+            let received = if sim.idle() && !sim.busy() {
+                commands.recv().map_err(|_| RecvTimeoutError::Disconnected)
+            } else if wait > SPIN {
+                commands.recv_timeout(wait - SPIN)
+            } else {
+                commands.try_recv().map_err(|e| match e {
+                    TryRecvError::Empty => RecvTimeoutError::Timeout,
+                    TryRecvError::Disconnected => RecvTimeoutError::Disconnected,
+                })
+            };
+            match received {
+                Ok(Command::SetInterval(new_interval)) => {
+                    interval = new_interval;
+                    next = Instant::now() + interval;
+                    continue;
+                }
+                Ok(command) => {
+                    sim.apply(command);
+                    continue;
+                }
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => break,
+            }
+            if !sim.busy() && Instant::now() < next {
+                thread::yield_now();
+                continue;
+            }
+            sim.tick();
+            let now = Instant::now();
+            next += interval;
+            if next < now {
+                next = now + interval;
+            }
+            if interval == Duration::from_secs(0) || sim.busy() {
+                // 不停地演化时让出时间片, 窗口线程才有机会拿到面板的锁
+                thread::yield_now();
+            }
+        }
+    });
+    SimHandle {
+        commands: Some(sender),
+        thread: Some(thread),
+    }
+}
@@ -0,0 +1,172 @@
+//! 每一代的统计数据, 用来导出人口曲线
+
+use std::collections::VecDeque;
+use std::fmt::Write as _;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::universe::Universe;
+
+/// 一代的统计. `births` 和 `deaths` 是从上一代演化到这一代时按规则发生的变化,
+/// `mutations` 是之后随机翻转的格子数
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct GenerationStats {
+    pub generation: i64,
+    pub population: u32,
+    pub births: u32,
+    pub deaths: u32,
+    pub mutations: u32,
+}
+
+/// 最多保存 `capacity` 代的统计, 超出后丢弃最早的记录
+#[derive(Clone, Debug)]
+pub struct StatsLog {
+    capacity: usize,
+    rows: VecDeque<GenerationStats>,
+}
+
+impl StatsLog {
+    pub fn new(capacity: usize) -> StatsLog {
+        StatsLog {
+            capacity,
+            rows: VecDeque::with_capacity(capacity.min(4096)),
+        }
+    }
+
+    /// 记录宇宙当前这一代
+    pub fn record(&mut self, universe: &Universe) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.rows.len() == self.capacity {
+            self.rows.pop_front();
+        }
+        self.rows.push_back(GenerationStats {
+            generation: universe.count(),
+            population: universe.population(),
+            births: universe.births(),
+            deaths: universe.deaths(),
+            mutations: universe.mutations(),
+        });
+    }
+
+    /// 演化一代并记录
+    pub fn tick(&mut self, universe: &mut Universe) {
+        universe.tick();
+        self.record(universe);
+    }
+
+    pub fn clear(&mut self) {
+        self.rows.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.rows.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &GenerationStats> {
+        self.rows.iter()
+    }
+
+    /// `generation,population,births,deaths,mutations` 表头加上每代一行
+    pub fn to_csv(&self) -> String {
+        let mut out = String::from("generation,population,births,deaths,mutations\n");
+        for row in &self.rows {
+            let _ = writeln!(
+                out,
+                "{},{},{},{},{}",
+                row.generation, row.population, row.births, row.deaths, row.mutations
+            );
+        }
+        out
+    }
+
+    pub fn export_csv(&self, path: &Path) -> io::Result<()> {
+        fs::write(path, self.to_csv())
+    }
+}
+
+/// 最近 `capacity` 代的活细胞数, 画人口曲线用. 满了之后新的一代挤掉最早的一代
+#[derive(Clone, Debug)]
+pub struct PopulationHistory {
+    capacity: usize,
+    counts: VecDeque<u32>,
+}
+
+impl PopulationHistory {
+    pub fn new(capacity: usize) -> PopulationHistory {
+        PopulationHistory {
+            capacity,
+            counts: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    pub fn push(&mut self, population: u32) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.counts.len() == self.capacity {
+            self.counts.pop_front();
+        }
+        self.counts.push_back(population);
+    }
+
+    pub fn clear(&mut self) {
+        self.counts.clear();
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn len(&self) -> usize {
+        self.counts.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.counts.is_empty()
+    }
+
+    /// 从最早到最近
+    pub fn iter(&self) -> impl Iterator<Item = u32> + '_ {
+        self.counts.iter().copied()
+    }
+
+    /// 记下的这些代里最少和最多的活细胞数, 还没有记录时为 None
+    pub fn range(&self) -> Option<(u32, u32)> {
+        let min = self.counts.iter().copied().min()?;
+        let max = self.counts.iter().copied().max()?;
+        Some((min, max))
+    }
+
+    /// 把曲线缩放到 `width` x `height` 像素的框里, 返回每一代的 (x, y), 框的左上角是 (0, 0).
+    /// 横向按 `capacity` 代铺满, 没记满时曲线从左边开始; 纵向最少的在最下面、最多的在
+    /// 最上面, 全都一样时画在中间
+    pub fn points(&self, width: i32, height: i32) -> Vec<(i32, i32)> {
+        let (min, max) = match self.range() {
+            Some(range) => range,
+            None => return Vec::new(),
+        };
+        let span = i64::from(max - min);
+        let (w, h) = (i64::from(width.max(1) - 1), i64::from(height.max(1) - 1));
+        let slots = (self.capacity.max(2) - 1) as i64;
+        self.counts
+            .iter()
+            .enumerate()
+            .map(|(i, &count)| {
+                let x = i as i64 * w / slots;
+                let y = if span == 0 {
+                    h / 2
+                } else {
+                    h - i64::from(count - min) * h / span
+                };
+                (x as i32, y as i32)
+            })
+            .collect()
+    }
+}
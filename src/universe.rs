@@ -0,0 +1,2345 @@
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::rule::{self, Neighborhood, Rule, RuleTable, FIRE_BURNING, FIRE_TREE, TOPPLE_GRAINS};
+use crate::stats::PopulationHistory;
+
+mod bitgrid;
+mod counters;
+mod hashlife;
+#[cfg(feature = "json")]
+mod json;
+#[cfg(feature = "image")]
+mod picture;
+mod snapshot;
+mod sparse;
+mod sparse_engine;
+mod stability;
+mod state;
+mod step;
+mod swar;
+mod tiles;
+
+pub use self::counters::Counters;
+use self::counters::SharedCounters;
+pub use self::hashlife::{HashLifeError, HashLifeUniverse, DEFAULT_NODE_LIMIT, MAX_ADVANCE_LOG};
+#[cfg(feature = "json")]
+pub use self::json::JsonError;
+#[cfg(feature = "image")]
+pub use self::picture::ImageOptions;
+pub use self::snapshot::FrameSnapshot;
+pub use self::sparse::{SparseError, SparseUniverse, CHUNK_SIZE};
+use self::sparse_engine::SparseEngine;
+pub use self::sparse_engine::{Engine, SPARSE_ENTER, SPARSE_EXIT};
+pub use self::stability::{Stability, StabilityDetector};
+pub use self::state::StateError;
+pub use self::step::{step, Stepper};
+use self::tiles::Tiles;
+pub use self::tiles::TILE_SIZE;
+
+/// 默认的网格行列数
+pub const DEFAULT_SIZE: u32 = 64;
+/// 随机生成时活细胞的默认比例
+pub const DEFAULT_DENSITY: f32 = 0.4;
+/// 自动扩展时活细胞离边缘不到这么多格就扩展, 每次在那一侧加上 `GROW_MARGIN` 格
+pub const GROW_TRIGGER: u32 = 2;
+pub const GROW_MARGIN: u32 = 32;
+
+/// 沙堆模型一次落沙最多坍塌 (格子数 × 这个数) 次. 环面上沙粒不会从边缘掉出去,
+/// 沙粒太多时永远停不下来, 到了上限就把多出的沙粒丢掉
+pub const TOPPLE_LIMIT_PER_CELL: u64 = 1024;
+
+/// 森林火灾模型里空地每一代长出树的默认概率
+pub const DEFAULT_GROWTH_RATE: f64 = 0.05;
+/// 森林火灾模型里树每一代被雷击着火的默认概率
+pub const DEFAULT_LIGHTNING_RATE: f64 = 0.0005;
+
+/// 打开拖尾后, 刚死的细胞留下的痕迹要这么多代才褪完
+pub const TRAIL_LENGTH: u8 = 16;
+/// `population_history` 记住最近多少代的活细胞数
+pub const POPULATION_HISTORY: usize = 300;
+/// 格子每变一次状态, 活跃度加这么多; 每一代再减掉 1/2^`ACTIVITY_DECAY_SHIFT`, 至少减 1
+pub const ACTIVITY_STEP: u16 = 256;
+pub const ACTIVITY_DECAY_SHIFT: u32 = 5;
+
+#[derive(Copy, PartialEq, Clone, Debug)]
+pub enum Cell {
+    Alive = 1,
+    Dead = 0,
+}
+
+#[derive(Copy, PartialEq, Eq, Clone, Debug)]
+pub enum UniverseError {
+    /// 细胞数量与 width * height 不一致
+    SizeMismatch { expected: usize, actual: usize },
+    /// 坐标超出了网格范围
+    OutOfBounds { row: u32, col: u32 },
+    /// 两个宇宙的尺寸不同, 值为 (width, height)
+    DimensionMismatch {
+        expected: (u32, u32),
+        actual: (u32, u32),
+    },
+    /// 图案比面板大, 值为 (width, height)
+    PatternTooLarge {
+        pattern: (u32, u32),
+        board: (u32, u32),
+    },
+    /// 格子数超出 u32, 格子的下标放不下, 值为 (width, height)
+    TooLarge { size: (u32, u32) },
+}
+
+impl fmt::Display for UniverseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            UniverseError::SizeMismatch { expected, actual } => {
+                write!(f, "expected {} cells, got {}", expected, actual)
+            }
+            UniverseError::OutOfBounds { row, col } => {
+                write!(f, "cell ({}, {}) is out of bounds", row, col)
+            }
+            UniverseError::DimensionMismatch { expected, actual } => write!(
+                f,
+                "expected a {}x{} universe, got {}x{}",
+                expected.0, expected.1, actual.0, actual.1
+            ),
+            UniverseError::PatternTooLarge { pattern, board } => write!(
+                f,
+                "pattern needs a {}x{} board, this one is {}x{}",
+                pattern.0, pattern.1, board.0, board.1
+            ),
+            UniverseError::TooLarge { size } => {
+                write!(f, "a {}x{} universe has too many cells", size.0, size.1)
+            }
+        }
+    }
+}
+
+impl Error for UniverseError {}
+
+/// 从文本解析宇宙时的错误, 行号和列号从 1 开始
+#[derive(Copy, PartialEq, Eq, Clone, Debug)]
+pub enum ParseUniverseError {
+    /// 没有任何内容
+    Empty,
+    /// 某一行的长度与第一行不同
+    RaggedLine {
+        line: usize,
+        expected: usize,
+        found: usize,
+    },
+    /// 不认识的字符
+    InvalidChar { line: usize, col: usize, ch: char },
+}
+
+impl fmt::Display for ParseUniverseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseUniverseError::Empty => write!(f, "universe text is empty"),
+            ParseUniverseError::RaggedLine {
+                line,
+                expected,
+                found,
+            } => write!(
+                f,
+                "line {} has {} cells, expected {} like the first line",
+                line, found, expected
+            ),
+            ParseUniverseError::InvalidChar { line, col, ch } => write!(
+                f,
+                "invalid character {:?} at line {} column {}, expected one of ◻ ◼ . #",
+                ch, line, col
+            ),
+        }
+    }
+}
+
+impl Error for ParseUniverseError {}
+
+/// 比较是否相等时只看尺寸和细胞, 代数、运行/绘制状态这些随时在变的字段不参与比较
+#[derive(Clone)]
+pub struct Universe {
+    width: u32,
+    height: u32,
+    cells: Vec<Cell>,
+    /// 演化时写下一代的缓冲区, 写完和 `cells` 交换. 里面的内容没有意义,
+    /// 只有 `cells` 需要和其他字段保持一致
+    scratch: Vec<Cell>,
+    /// 两状态 B/S 规则的演化方式和它反复使用的缓冲区
+    stepper: Stepper,
+    /// 稀疏演化时活细胞的集合. 只有上一代是稀疏演化时才有, 面板被整体替换或
+    /// 按别的方式演化后清掉, 下次用到时重新建
+    sparse: Option<SparseEngine>,
+    /// 逐块演化时各块最近是否变化过. 和 `sparse` 一样在面板被整体替换或按别的方式
+    /// 演化后清掉, 规则和拓扑变了之后也要清掉
+    tiles: Option<Tiles>,
+    /// 上次 `mark_drawn` 之后状态变过的格子 (row, col). `redraw` 为 true 时不再记录
+    changes: Vec<(u32, u32)>,
+    /// 面板被整体改写过或变化太多, 下次要整个重画
+    redraw: bool,
+    /// 代数和活细胞数
+    counters: SharedCounters,
+    /// 最近一次演化中诞生和死亡的细胞数
+    births: u32,
+    deaths: u32,
+    rule: Rule,
+    /// 多状态规则下格子的状态, 例如 Generations 的衰亡状态、Wireworld 的导线或
+    /// Immigration 里活细胞的颜色, 0 表示没有额外的状态. 两状态的规则不使用, 保持为空
+    extra: Vec<u8>,
+    /// B0 规则下存的是实际细胞取反后的结果, 见 `Rule::phase_step`
+    inverted: bool,
+    /// 活细胞连续活了几代, 刚出生的是 0, 死细胞也是 0. 按存的细胞算, 和画出来的一致.
+    /// 长度和 `cells` 不同时表示面板刚被整体改写过, 都当作 0
+    ages: Vec<u16>,
+    /// 演化前的细胞, 演化后和新的一代比较来更新 `ages`; 只是反复使用的缓冲区
+    previous: Vec<Cell>,
+    /// 打开拖尾时, 死细胞的痕迹还剩几代褪完: 刚死时是 `TRAIL_LENGTH`, 每代减 1.
+    /// 关掉拖尾时为空, 长度和 `cells` 不同时都当作 0
+    decay: Vec<u8>,
+    trails: bool,
+    /// 格子最近变化得有多频繁, 见 `ACTIVITY_STEP`. 长度和 `cells` 不同时都当作 0
+    activity: Vec<u16>,
+    /// 最近一次演化中活过来和死掉的格子 (row, col), 面板被整体改写过时清空
+    last_born: Vec<(u32, u32)>,
+    last_died: Vec<(u32, u32)>,
+    /// 最近 `POPULATION_HISTORY` 代的活细胞数, 清屏和重新生成时清空
+    history: PopulationHistory,
+    topology: Topology,
+    /// 自动扩展时宽高的上限, None 表示不自动扩展
+    auto_grow: Option<u32>,
+    /// 最近一次演化后在上边和左边扩出的行列数
+    grown: (u32, u32),
+    /// 最近一次演化后需要扩展, 但已经到了上限
+    grow_capped: bool,
+    /// 每一代按规则演化之后, 每个格子被随机翻转的概率
+    mutation_rate: f64,
+    /// 最近一次演化中随机翻转的格子数, 不算在 `births` 和 `deaths` 里
+    mutations: u32,
+    /// 森林火灾模型里空地长树和树被雷击的概率
+    growth_rate: f64,
+    lightning_rate: f64,
+    run_state: RunState,
+    density: f32,
+    rng: StdRng,
+}
+
+/// 窗口是否在不停地演化. 要不要重画不单独记录: 运行时每一代都可能要画,
+/// 暂停和编辑时只有 `needs_frame` 为 true 才画
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum RunState {
+    Running,
+    Paused,
+    /// 暂停时按下了鼠标正在改格子, 松开后回到 `Paused`
+    Editing,
+}
+
+/// `run_until_stable` 的结果, `steps` 是进入最终状态之前经过的代数
+#[derive(Copy, PartialEq, Eq, Clone, Debug)]
+pub enum StableResult {
+    /// 下一代与当前代完全相同
+    Stable { steps: u64 },
+    /// 所有细胞都已死亡
+    Extinct { steps: u64 },
+    /// 达到步数上限仍在变化
+    StepLimit,
+}
+
+/// 两个宇宙逐格组合的方式
+#[derive(Copy, PartialEq, Eq, Clone, Debug)]
+pub enum CombineOp {
+    /// 任一方活着
+    Union,
+    /// 双方都活着
+    Intersection,
+    /// 自己活着而另一方死了
+    Difference,
+    /// 恰好一方活着, 可以用来比较两代之间的变化
+    Xor,
+}
+
+/// 网格边缘的处理方式
+#[derive(Copy, PartialEq, Eq, Clone, Debug, Default)]
+pub enum Topology {
+    /// 上下、左右首尾相接, 滑翔机从一边出去会从另一边回来
+    #[default]
+    Torus,
+    /// 边缘之外都是死细胞
+    Bounded,
+    /// 边缘像镜子一样, 越过边缘的坐标反射回网格里: -1 读第 0 行, 高度读最后一行
+    Mirror,
+}
+
+impl Topology {
+    /// 把长度为 `len` 的一维坐标上的 `coord` 换算到网格里, 有界时越过边缘返回 None
+    pub fn resolve(self, coord: i64, len: u32) -> Option<u32> {
+        let len = i64::from(len);
+        if len == 0 {
+            return None;
+        }
+        let resolved = match self {
+            Topology::Torus => coord.rem_euclid(len),
+            Topology::Bounded if coord < 0 || coord >= len => return None,
+            Topology::Bounded => coord,
+            // 反射的周期是 2 * len, 后一半倒着读
+            Topology::Mirror => {
+                let folded = coord.rem_euclid(2 * len);
+                if folded < len {
+                    folded
+                } else {
+                    2 * len - 1 - folded
+                }
+            }
+        };
+        Some(resolved as u32)
+    }
+
+    /// Ctrl+T 切换的顺序
+    pub fn next(self) -> Topology {
+        match self {
+            Topology::Torus => Topology::Bounded,
+            Topology::Bounded => Topology::Mirror,
+            Topology::Mirror => Topology::Torus,
+        }
+    }
+}
+
+impl fmt::Display for Topology {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Topology::Torus => write!(f, "环面"),
+            Topology::Bounded => write!(f, "有界"),
+            Topology::Mirror => write!(f, "镜像"),
+        }
+    }
+}
+
+/// Moore 邻域和 von Neumann 邻域相对中心的偏移
+const MOORE: [(i64, i64); 8] = [
+    (-1, -1),
+    (-1, 0),
+    (-1, 1),
+    (0, -1),
+    (0, 1),
+    (1, -1),
+    (1, 0),
+    (1, 1),
+];
+const VON_NEUMANN: [(i64, i64); 4] = [(-1, 0), (1, 0), (0, -1), (0, 1)];
+/// 六边形网格上偶数行和奇数行的邻居偏移, 奇数行向右错开半格
+const HEX_EVEN: [(i64, i64); 6] = [(-1, -1), (-1, 0), (0, -1), (0, 1), (1, -1), (1, 0)];
+const HEX_ODD: [(i64, i64); 6] = [(-1, 0), (-1, 1), (0, -1), (0, 1), (1, 0), (1, 1)];
+
+/// 以 (`row`, `col`) 为左上角、`height` 行 `width` 列的矩形区域
+#[derive(Copy, PartialEq, Eq, Clone, Debug)]
+pub struct Region {
+    pub row: u32,
+    pub col: u32,
+    pub height: u32,
+    pub width: u32,
+}
+
+impl Region {
+    pub fn new(row: u32, col: u32, height: u32, width: u32) -> Region {
+        Region {
+            row,
+            col,
+            height,
+            width,
+        }
+    }
+}
+
+/// `resize` 时旧内容在新网格中的位置
+#[derive(Copy, PartialEq, Eq, Clone, Debug)]
+pub enum Anchor {
+    TopLeft,
+    Center,
+    /// 旧内容的左上角放在新网格的 (`row`, `col`), 可以是负数
+    Offset {
+        row: i64,
+        col: i64,
+    },
+}
+
+/// 随机宇宙的构建器, 指定 `seed` 时生成结果可复现
+pub struct UniverseBuilder {
+    width: u32,
+    height: u32,
+    density: f32,
+    seed: Option<u64>,
+}
+
+impl Default for UniverseBuilder {
+    fn default() -> UniverseBuilder {
+        UniverseBuilder::new()
+    }
+}
+
+impl UniverseBuilder {
+    pub fn new() -> UniverseBuilder {
+        UniverseBuilder {
+            width: DEFAULT_SIZE,
+            height: DEFAULT_SIZE,
+            density: DEFAULT_DENSITY,
+            seed: None,
+        }
+    }
+
+    pub fn width(mut self, width: u32) -> UniverseBuilder {
+        self.width = width;
+        self
+    }
+
+    pub fn height(mut self, height: u32) -> UniverseBuilder {
+        self.height = height;
+        self
+    }
+
+    /// 活细胞的比例, 超出 0.0..=1.0 的值会被截断
+    pub fn density(mut self, density: f32) -> UniverseBuilder {
+        self.density = density.clamp(0.0, 1.0);
+        self
+    }
+
+    pub fn seed(mut self, seed: u64) -> UniverseBuilder {
+        self.seed = Some(seed);
+        self
+    }
+
+    pub fn build(self) -> Universe {
+        let mut rng = match self.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
+        let cells = Universe::gen_map(self.width, self.height, self.density, &mut rng);
+        Universe::assemble(self.width, self.height, cells, self.density, rng)
+    }
+}
+
+impl PartialEq for Universe {
+    fn eq(&self, other: &Universe) -> bool {
+        self.same_cells(other)
+    }
+}
+
+impl fmt::Debug for Universe {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Universe")
+            .field("width", &self.width)
+            .field("height", &self.height)
+            .field("count", &self.count())
+            .field("live", &self.population())
+            .finish()
+    }
+}
+
+impl Default for Universe {
+    fn default() -> Universe {
+        Universe::new()
+    }
+}
+
+impl Universe {
+    pub fn new() -> Universe {
+        UniverseBuilder::new().build()
+    }
+
+    pub fn builder() -> UniverseBuilder {
+        UniverseBuilder::new()
+    }
+
+    /// 创建 `width` 列、`height` 行的随机宇宙
+    pub fn with_size(width: u32, height: u32) -> Universe {
+        UniverseBuilder::new().width(width).height(height).build()
+    }
+
+    /// 用给定的细胞数据创建宇宙, `cells` 按行优先排列. 格子数超出 u32 时返回 `TooLarge`
+    pub fn from_cells(
+        width: u32,
+        height: u32,
+        cells: Vec<Cell>,
+    ) -> Result<Universe, UniverseError> {
+        let expected = match width.checked_mul(height) {
+            Some(expected) => expected as usize,
+            None => {
+                return Err(UniverseError::TooLarge {
+                    size: (width, height),
+                })
+            }
+        };
+        if cells.len() != expected {
+            return Err(UniverseError::SizeMismatch {
+                expected,
+                actual: cells.len(),
+            });
+        }
+        Ok(Universe::assemble(
+            width,
+            height,
+            cells,
+            DEFAULT_DENSITY,
+            StdRng::from_entropy(),
+        ))
+    }
+
+    fn assemble(width: u32, height: u32, cells: Vec<Cell>, density: f32, rng: StdRng) -> Universe {
+        let population = Universe::count_alive(&cells);
+        Universe {
+            width,
+            height,
+            cells,
+            scratch: Vec::new(),
+            stepper: Stepper::default(),
+            sparse: None,
+            tiles: None,
+            changes: Vec::new(),
+            redraw: true,
+            counters: SharedCounters::new(0, population),
+            births: 0,
+            deaths: 0,
+            rule: Rule::CONWAY,
+            extra: Vec::new(),
+            inverted: false,
+            ages: Vec::new(),
+            previous: Vec::new(),
+            decay: Vec::new(),
+            trails: false,
+            activity: Vec::new(),
+            last_born: Vec::new(),
+            last_died: Vec::new(),
+            history: PopulationHistory::new(POPULATION_HISTORY),
+            topology: Topology::Torus,
+            auto_grow: None,
+            grown: (0, 0),
+            grow_capped: false,
+            mutation_rate: 0.0,
+            mutations: 0,
+            growth_rate: DEFAULT_GROWTH_RATE,
+            lightning_rate: DEFAULT_LIGHTNING_RATE,
+            run_state: RunState::Running,
+            density,
+            rng,
+        }
+    }
+
+    fn count_alive(cells: &[Cell]) -> u32 {
+        cells.iter().filter(|&&c| c == Cell::Alive).count() as u32
+    }
+
+    fn gen_map(width: u32, height: u32, density: f32, rng: &mut StdRng) -> Vec<Cell> {
+        (0..width as usize * height as usize)
+            .map(|_| {
+                if rng.gen_bool(density as f64) {
+                    Cell::Alive
+                } else {
+                    Cell::Dead
+                }
+            })
+            .collect()
+    }
+    pub fn get_index(&self, row: u32, column: u32) -> usize {
+        (row * self.width + column) as usize
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// 当前的代数
+    pub fn count(&self) -> i64 {
+        self.counters.generation()
+    }
+
+    pub fn cells(&self) -> &[Cell] {
+        &self.cells
+    }
+
+    /// 尺寸相同并且每个细胞的状态都相同
+    pub fn same_cells(&self, other: &Universe) -> bool {
+        self.width == other.width && self.height == other.height && self.cells == other.cells
+    }
+
+    /// 读取 `row` 行 `col` 列的细胞, 越界时返回 `None`
+    pub fn get_cell(&self, row: u32, col: u32) -> Option<Cell> {
+        self.checked_index(row, col).ok().map(|idx| self.cells[idx])
+    }
+
+    /// 带边界检查的 get_index
+    fn checked_index(&self, row: u32, col: u32) -> Result<usize, UniverseError> {
+        if row >= self.height || col >= self.width {
+            return Err(UniverseError::OutOfBounds { row, col });
+        }
+        Ok(self.get_index(row, col))
+    }
+
+    pub fn set_cell(&mut self, cell: Cell, c: u32, r: u32) -> Result<(), UniverseError> {
+        let index = self.checked_index(r, c)?;
+        self.replace(index, cell);
+        // self.cells.insert(index, Cell::Alive);
+        Ok(())
+    }
+
+    /// 修改单个细胞并同步活细胞计数
+    fn replace(&mut self, index: usize, cell: Cell) {
+        let old = self.cells[index];
+        self.cells[index] = cell;
+        if let Some(sparse) = self.sparse.as_mut() {
+            let width = self.width as usize;
+            sparse.set((index / width) as u32, (index % width) as u32, cell);
+        }
+        if let Some(tiles) = self.tiles.as_mut() {
+            let width = self.width as usize;
+            tiles.mark((index / width) as u32, (index % width) as u32);
+        }
+        if let Some(state) = self.extra.get_mut(index) {
+            *state = 0;
+        }
+        // 改过的格子从头算起
+        if let Some(age) = self.ages.get_mut(index) {
+            *age = 0;
+        }
+        if let Some(decay) = self.decay.get_mut(index) {
+            *decay = 0;
+        }
+        self.record_index(index);
+        self.counters
+            .set_population(self.population() + cell as u32 - old as u32);
+    }
+
+    /// 上次 `mark_drawn` 之后状态变过的格子 (row, col), 同一格可能出现多次.
+    /// `needs_redraw` 为 true 时这个列表不完整, 要整个重画
+    pub fn changed_cells(&self) -> &[(u32, u32)] {
+        &self.changes
+    }
+
+    /// 面板被整体替换、重新排列, 或者变化的格子超过一半, 窗口要整个重画
+    pub fn needs_redraw(&self) -> bool {
+        self.redraw
+    }
+
+    /// 窗口移动、换了配色等面板以外的原因需要整个重画时调用
+    pub fn request_redraw(&mut self) {
+        self.redraw = true;
+        self.changes.clear();
+    }
+
+    /// 当前这一代的只读副本, 只复制细胞, 面板很大时也很快. 窗口在锁里拍下快照并
+    /// 调用 `mark_drawn`, 放开锁之后再照着快照画
+    pub fn snapshot(&self) -> Arc<FrameSnapshot> {
+        FrameSnapshot::new(self)
+    }
+
+    /// 窗口画完之后调用, 从这里开始重新记录变化的格子
+    pub fn mark_drawn(&mut self) {
+        self.redraw = false;
+        self.changes.clear();
+    }
+
+    fn record_change(&mut self, row: u32, col: u32) {
+        if self.redraw {
+            return;
+        }
+        // 变化太多时逐格重画不比整个重画快, 列表也不用再长下去
+        if self.changes.len() >= self.cells.len() / 2 {
+            self.request_redraw();
+            return;
+        }
+        self.changes.push((row, col));
+    }
+
+    fn record_index(&mut self, idx: usize) {
+        let width = self.width as usize;
+        self.record_change((idx / width) as u32, (idx % width) as u32);
+    }
+
+    /// 面板被整体改写之后调用: 稀疏演化的活细胞集合和各块的变化作废, 窗口要整个重画
+    fn invalidate(&mut self) {
+        self.sparse = None;
+        self.tiles = None;
+        self.ages.clear();
+        self.decay.clear();
+        self.activity.clear();
+        self.last_born.clear();
+        self.last_died.clear();
+        self.request_redraw();
+    }
+
+    /// 缓存的活细胞数量, 不需要扫描整个网格
+    pub fn population(&self) -> u32 {
+        self.counters.population()
+    }
+
+    /// 和这块面板共用的代数和活细胞数, 别的线程不拿锁也能读.
+    /// 用 `replace_with` 整个换掉面板时计数器不变, 旧的面板换下来之后不再更新它
+    pub fn counters(&self) -> Arc<Counters> {
+        self.counters.handle()
+    }
+
+    /// 换成 `other`, 之前 `counters` 拿到的计数器跟着改成 `other` 的代数和活细胞数.
+    /// 直接赋值的话原来的计数器就停在旧面板上了
+    pub fn replace_with(&mut self, other: Universe) {
+        let counters = std::mem::take(&mut self.counters);
+        *self = other;
+        counters.set_generation(self.count());
+        counters.set_population(self.population());
+        self.counters = counters;
+    }
+
+    /// 按行优先顺序遍历所有活细胞的 (row, col)
+    pub fn live_cells(&self) -> impl Iterator<Item = (u32, u32)> + '_ {
+        let width = self.width;
+        self.cells
+            .iter()
+            .enumerate()
+            .filter(|(_, &cell)| cell == Cell::Alive)
+            .map(move |(i, _)| (i as u32 / width, i as u32 % width))
+    }
+
+    pub fn live_count(&self) -> usize {
+        self.live_cells().count()
+    }
+
+    /// 翻转 `row` 行 `col` 列的细胞并返回新的状态
+    pub fn toggle_cell(&mut self, row: u32, col: u32) -> Result<Cell, UniverseError> {
+        let index = self.checked_index(row, col)?;
+        let cell = match self.cells[index] {
+            Cell::Alive => Cell::Dead,
+            Cell::Dead => Cell::Alive,
+        };
+        self.replace(index, cell);
+        Ok(cell)
+    }
+
+    /// 把图案的活细胞放到以 (`origin_row`, `origin_col`) 为左上角的位置.
+    /// `wrap` 为 true 时越过边界的细胞绕回另一侧, 否则丢弃;
+    /// 不绕回时左上角本身越界会返回错误
+    pub fn insert_pattern(
+        &mut self,
+        pattern: &[(u32, u32)],
+        origin_row: u32,
+        origin_col: u32,
+        wrap: bool,
+    ) -> Result<(), UniverseError> {
+        if !wrap {
+            self.checked_index(origin_row, origin_col)?;
+        }
+        for &(dr, dc) in pattern {
+            let row = origin_row as u64 + dr as u64;
+            let col = origin_col as u64 + dc as u64;
+            let (row, col) = if wrap {
+                (row % self.height as u64, col % self.width as u64)
+            } else if row < self.height as u64 && col < self.width as u64 {
+                (row, col)
+            } else {
+                continue;
+            };
+            let index = self.get_index(row as u32, col as u32);
+            self.replace(index, Cell::Alive);
+        }
+        Ok(())
+    }
+
+    /// 按当前规则的邻域和网格的拓扑数活邻居
+    pub fn live_neighbor_count(&self, row: u32, column: u32) -> u8 {
+        self.neighbor_indices(row, column)
+            .map(|idx| self.cells[idx] as u8)
+            .sum()
+    }
+
+    /// 当前邻域下 (`row`, `col`) 的各个邻居的下标, 越过有界网格边缘的不算
+    fn neighbor_indices(&self, row: u32, col: u32) -> impl Iterator<Item = usize> + '_ {
+        let offsets: &[(i64, i64)] = match self.rule.neighborhood() {
+            Neighborhood::Moore => &MOORE,
+            Neighborhood::VonNeumann => &VON_NEUMANN,
+            Neighborhood::Hexagonal if row % 2 == 0 => &HEX_EVEN,
+            Neighborhood::Hexagonal => &HEX_ODD,
+        };
+        offsets
+            .iter()
+            .filter_map(move |&(d_row, d_col)| self.offset_index(row, col, d_row, d_col))
+    }
+
+    /// (`row`, `col`) 偏移 (`d_row`, `d_col`) 之后的格子下标, 按 `Topology::resolve`
+    /// 换算, 越过有界网格的边缘时返回 None
+    fn offset_index(&self, row: u32, col: u32, d_row: i64, d_col: i64) -> Option<usize> {
+        let r = self.topology.resolve(i64::from(row) + d_row, self.height)?;
+        let c = self.topology.resolve(i64::from(col) + d_col, self.width)?;
+        Some(self.get_index(r, c))
+    }
+
+    pub fn topology(&self) -> Topology {
+        self.topology
+    }
+
+    pub fn set_topology(&mut self, topology: Topology) {
+        self.topology = topology;
+        self.tiles = None;
+    }
+}
+
+impl Universe {
+    pub fn tick(&mut self) {
+        self.step();
+    }
+
+    /// 打开自动扩展: 每一代之后活细胞接近边缘时, 在那一侧扩出 `GROW_MARGIN` 格,
+    /// 宽高不超过 `max_size`. None 关闭
+    pub fn set_auto_grow(&mut self, max_size: Option<u32>) {
+        self.auto_grow = max_size;
+        self.grow_capped = false;
+    }
+
+    pub fn auto_grow(&self) -> Option<u32> {
+        self.auto_grow
+    }
+
+    /// 最近一次演化后在上边和左边扩出的 (行数, 列数). 原来的格子 (row, col)
+    /// 现在在 (row + 行数, col + 列数), 窗口要跟着移动才能看到同样的位置
+    pub fn grown(&self) -> (u32, u32) {
+        self.grown
+    }
+
+    /// 最近一次演化后活细胞已经接近边缘, 但网格到了上限没法再扩展
+    pub fn is_grow_capped(&self) -> bool {
+        self.grow_capped
+    }
+
+    /// 只检查四边各 `GROW_TRIGGER` 行列, 返回上、下、左、右是否有活细胞
+    fn near_border(&self) -> [bool; 4] {
+        let depth = GROW_TRIGGER.min(self.height).min(self.width);
+        let row_alive = |row: u32| {
+            let start = self.get_index(row, 0);
+            self.cells[start..start + self.width as usize].contains(&Cell::Alive)
+        };
+        let col_alive = |col: u32| {
+            (0..self.height).any(|row| self.cells[self.get_index(row, col)] == Cell::Alive)
+        };
+        [
+            (0..depth).any(row_alive),
+            (self.height - depth..self.height).any(row_alive),
+            (0..depth).any(col_alive),
+            (self.width - depth..self.width).any(col_alive),
+        ]
+    }
+
+    fn grow_to_fit(&mut self) {
+        self.grown = (0, 0);
+        self.grow_capped = false;
+        let max_size = match self.auto_grow {
+            Some(max_size) if self.population() > 0 => max_size,
+            _ => return,
+        };
+        let [top, bottom, left, right] = self.near_border();
+        // 一个方向两侧都要扩时先满足上边和左边
+        let mut room_h = max_size.saturating_sub(self.height);
+        let mut room_w = max_size.saturating_sub(self.width);
+        let take = |needed: bool, room: &mut u32| {
+            let margin = if needed { GROW_MARGIN.min(*room) } else { 0 };
+            *room -= margin;
+            margin
+        };
+        let add_top = take(top, &mut room_h);
+        let add_bottom = take(bottom, &mut room_h);
+        let add_left = take(left, &mut room_w);
+        let add_right = take(right, &mut room_w);
+        self.grow_capped = (top && add_top == 0)
+            || (bottom && add_bottom == 0)
+            || (left && add_left == 0)
+            || (right && add_right == 0);
+        if add_top + add_bottom + add_left + add_right == 0 {
+            return;
+        }
+        self.resize(
+            self.width + add_left + add_right,
+            self.height + add_top + add_bottom,
+            Anchor::Offset {
+                row: i64::from(add_top),
+                col: i64::from(add_left),
+            },
+        );
+        self.grown = (add_top, add_left);
+    }
+
+    /// 演化一代, 返回是否有细胞发生了变化
+    fn step(&mut self) -> bool {
+        let mut previous = std::mem::take(&mut self.previous);
+        previous.clear();
+        previous.extend_from_slice(&self.cells);
+        self.previous = previous;
+        let changed = match self.rule {
+            Rule::LargerThanLife { .. } => self.step_larger_than_life(),
+            Rule::Immigration | Rule::QuadLife => self.step_colored(),
+            Rule::Cyclic { .. } => self.step_cyclic(),
+            Rule::ForestFire => self.step_forest_fire(),
+            Rule::Sandpile => self.step_sandpile(),
+            _ => self.step_table(),
+        };
+        changed || self.mutations > 0
+    }
+
+    /// B/S、Generations 和 Wireworld 的演化: 两状态的规则查规则表,
+    /// 多状态的规则按完整的状态演化
+    fn step_table(&mut self) -> bool {
+        let (rule, inverted) = self.rule.phase_step(self.inverted);
+        if let Rule::Life {
+            birth,
+            survive,
+            neighborhood,
+        } = rule
+        {
+            if !self.rule.has_b0() {
+                if neighborhood != Neighborhood::Hexagonal && self.prefers_sparse() {
+                    return self.step_sparse(birth, survive, neighborhood == Neighborhood::Moore);
+                }
+                if let Engine::Tiled | Engine::Auto = self.engine() {
+                    return self.step_tiles(rule);
+                }
+            }
+            return self.step_life(rule, inverted);
+        }
+        let states = rule.states();
+        if states > 2 && self.extra.len() != self.cells.len() {
+            self.extra = vec![0; self.cells.len()];
+        }
+        let table = rule.table();
+        let mut next = self.take_scratch();
+        let mut next_extra = self.extra.clone();
+        let (mut births, mut deaths) = (0, 0);
+        let mut changed = inverted != self.inverted;
+        for row in 0..self.height {
+            for col in 0..self.width {
+                let idx = self.get_index(row, col);
+                let cell = self.cells[idx];
+                let live_neighbors = self.live_neighbor_count(row, col);
+                let next_cell = if states > 2 {
+                    // 多状态的规则按完整的状态演化, 再拆回活细胞和衰亡状态
+                    let state = self.state_at(idx);
+                    let next_state = rule.next_state(state, live_neighbors);
+                    if next_state != state {
+                        changed = true;
+                        self.record_change(row, col);
+                    }
+                    next_extra[idx] = if next_state >= 2 { next_state } else { 0 };
+                    if next_state == 1 {
+                        Cell::Alive
+                    } else {
+                        Cell::Dead
+                    }
+                } else {
+                    let next_cell = table[cell as usize][live_neighbors as usize];
+                    if next_cell != cell {
+                        self.record_change(row, col);
+                    }
+                    next_cell
+                };
+                if next_cell != cell {
+                    match next_cell {
+                        Cell::Alive => births += 1,
+                        Cell::Dead => deaths += 1,
+                    }
+                }
+                next[idx] = next_cell;
+            }
+        }
+        self.extra = next_extra;
+        self.inverted = inverted;
+        self.finish_step(next, births, deaths);
+        changed || births + deaths > 0
+    }
+
+    /// 两状态的 B/S 规则交给 `Stepper`, 结果和逐格查规则表一样
+    fn step_life(&mut self, rule: Rule, inverted: bool) -> bool {
+        let mut next = self.take_scratch();
+        let changes = if self.redraw {
+            None
+        } else {
+            Some(&mut self.changes)
+        };
+        let (births, deaths) = self.stepper.step_tracked(
+            &self.cells,
+            &mut next,
+            (self.width, self.height),
+            rule,
+            self.topology,
+            changes,
+        );
+        if self.changes.len() > self.cells.len() / 2 {
+            self.request_redraw();
+        }
+        let changed = inverted != self.inverted || births + deaths > 0;
+        self.inverted = inverted;
+        self.finish_step(next, births, deaths);
+        changed
+    }
+
+    /// `Engine::Tiled`: 只重新计算上一代附近有变化的块. 各块的变化要留到下一代,
+    /// 换上新的一代时不经过 `finish_step`
+    fn step_tiles(&mut self, rule: Rule) -> bool {
+        let mut next = self.take_scratch();
+        let (width, height) = (self.width, self.height);
+        let tiles = self.tiles.get_or_insert_with(|| Tiles::new(width, height));
+        let changes = if self.redraw {
+            None
+        } else {
+            Some(&mut self.changes)
+        };
+        let (births, deaths) = tiles.step(&self.cells, &mut next, rule, self.topology, changes);
+        if self.changes.len() > self.cells.len() / 2 {
+            self.request_redraw();
+        }
+        self.scratch = std::mem::replace(&mut self.cells, next);
+        self.sparse = None;
+        self.after_step(births, deaths);
+        births + deaths > 0
+    }
+
+    /// 最近一次逐块演化重新计算过的块, 不是逐块演化时为空
+    pub fn dirty_tiles(&self) -> impl Iterator<Item = Region> + '_ {
+        self.tiles.iter().flat_map(|tiles| tiles.dirty())
+    }
+
+    /// 两状态、Moore 或 von Neumann 邻域的规则是否把面板分成横条并行演化, 默认打开.
+    /// 并行和串行的结果完全相同, 其他规则总是串行
+    #[cfg(feature = "rayon")]
+    pub fn set_parallel(&mut self, parallel: bool) {
+        self.stepper.set_parallel(parallel);
+    }
+
+    #[cfg(feature = "rayon")]
+    pub fn is_parallel(&self) -> bool {
+        self.stepper.is_parallel()
+    }
+
+    /// 取出写下一代用的缓冲区, 长度和 `cells` 相同. 只有面板尺寸变了之后才需要重新分配
+    fn take_scratch(&mut self) -> Vec<Cell> {
+        let mut next = std::mem::take(&mut self.scratch);
+        next.resize(self.cells.len(), Cell::Dead);
+        next
+    }
+
+    /// 演化方式, 默认是 `Engine::Dense`
+    pub fn set_engine(&mut self, engine: Engine) {
+        self.stepper.set_engine(engine);
+        if engine != Engine::Sparse && engine != Engine::Auto {
+            self.sparse = None;
+        }
+        if engine != Engine::Tiled && engine != Engine::Auto {
+            self.tiles = None;
+        }
+    }
+
+    pub fn engine(&self) -> Engine {
+        self.stepper.engine()
+    }
+
+    /// 最近一次演化是否是稀疏演化
+    pub fn is_sparse(&self) -> bool {
+        self.sparse.is_some()
+    }
+
+    /// 按 `engine` 和活细胞的比例决定下一代是否稀疏演化
+    fn prefers_sparse(&self) -> bool {
+        match self.engine() {
+            Engine::Dense | Engine::Swar | Engine::Scalar | Engine::Tiled => false,
+            Engine::Sparse => true,
+            Engine::Auto => {
+                let density = f64::from(self.population()) / self.cells.len().max(1) as f64;
+                if self.sparse.is_some() {
+                    density <= SPARSE_EXIT
+                } else {
+                    density < SPARSE_ENTER
+                }
+            }
+        }
+    }
+
+    /// 只处理活细胞和它们的邻居, 面板上只改变化了的格子
+    fn step_sparse(&mut self, birth: u16, survive: u16, moore: bool) -> bool {
+        let size = (self.width, self.height);
+        let topology = self.topology;
+        let (cells, width) = (&self.cells, self.width);
+        let sparse = self
+            .sparse
+            .get_or_insert_with(|| SparseEngine::from_cells(cells, width));
+        let (born, died) = sparse.step(size, birth, survive, moore, topology);
+        for &(row, col) in &born {
+            let idx = self.get_index(row, col);
+            self.cells[idx] = Cell::Alive;
+            self.record_change(row, col);
+        }
+        for &(row, col) in &died {
+            let idx = self.get_index(row, col);
+            self.cells[idx] = Cell::Dead;
+            self.record_change(row, col);
+        }
+        self.tiles = None;
+        self.after_step(born.len() as u32, died.len() as u32);
+        !born.is_empty() || !died.is_empty()
+    }
+
+    /// 换上下一代, 换下来的这一代留作下一次的缓冲区
+    fn finish_step(&mut self, next: Vec<Cell>, births: u32, deaths: u32) {
+        self.scratch = std::mem::replace(&mut self.cells, next);
+        self.sparse = None;
+        self.tiles = None;
+        self.after_step(births, deaths);
+    }
+
+    /// 新的一代已经在 `cells` 里, 更新计数, 再随机突变和自动扩展
+    fn after_step(&mut self, births: u32, deaths: u32) {
+        self.counters.set_generation(self.count() + 1);
+        self.counters
+            .set_population(self.population() + births - deaths);
+        self.births = births;
+        self.deaths = deaths;
+        debug_assert_eq!(self.population(), Universe::count_alive(&self.cells));
+        self.mutate();
+        self.age_cells();
+        self.fade_trails();
+        self.heat_cells();
+        self.note_last_step();
+        self.record_population();
+        self.grow_to_fit();
+    }
+
+    /// 和 `previous` 比较: 活下来的加 1, 刚出生的和死了的归 0
+    fn age_cells(&mut self) {
+        if self.ages.len() != self.cells.len() {
+            self.ages = vec![0; self.cells.len()];
+        }
+        for ((age, &cell), &before) in self.ages.iter_mut().zip(&self.cells).zip(&self.previous) {
+            *age = match (before, cell) {
+                (Cell::Alive, Cell::Alive) => age.saturating_add(1),
+                _ => 0,
+            };
+        }
+    }
+
+    /// 和 `previous` 比较: 刚死的留下 `TRAIL_LENGTH` 代的痕迹, 之前的痕迹褪去一代.
+    /// 只褪色的格子细胞没变, 也要记成变化过的格子, 窗口才会重画
+    fn fade_trails(&mut self) {
+        if !self.trails {
+            return;
+        }
+        if self.decay.len() != self.cells.len() {
+            self.decay = vec![0; self.cells.len()];
+        }
+        let mut fading = Vec::new();
+        for (idx, ((decay, &cell), &before)) in self
+            .decay
+            .iter_mut()
+            .zip(&self.cells)
+            .zip(&self.previous)
+            .enumerate()
+        {
+            *decay = match (before, cell) {
+                (_, Cell::Alive) => 0,
+                (Cell::Alive, Cell::Dead) => TRAIL_LENGTH,
+                (Cell::Dead, Cell::Dead) if *decay > 0 => {
+                    fading.push(idx);
+                    *decay - 1
+                }
+                (Cell::Dead, Cell::Dead) => 0,
+            };
+        }
+        for idx in fading {
+            self.record_index(idx);
+        }
+    }
+
+    /// 和 `previous` 比较: 变了状态的格子活跃度加 `ACTIVITY_STEP`, 所有格子的活跃度慢慢降下来
+    fn heat_cells(&mut self) {
+        if self.activity.len() != self.cells.len() {
+            self.activity = vec![0; self.cells.len()];
+        }
+        for ((heat, &cell), &before) in self.activity.iter_mut().zip(&self.cells).zip(&self.previous) {
+            let cooled = heat.saturating_sub((*heat >> ACTIVITY_DECAY_SHIFT).max(1));
+            *heat = if cell == before {
+                cooled
+            } else {
+                cooled.saturating_add(ACTIVITY_STEP)
+            };
+        }
+    }
+
+    /// `row` 行 `col` 列的格子最近的活跃度, 没变过的是 0. 越界时返回 None
+    pub fn cell_activity(&self, row: u32, col: u32) -> Option<u16> {
+        let idx = self.checked_index(row, col).ok()?;
+        Some(self.activity.get(idx).copied().unwrap_or(0))
+    }
+
+    /// 和 `previous` 比较, 记下这一代活过来和死掉的格子, 随机突变的也算
+    fn note_last_step(&mut self) {
+        self.last_born.clear();
+        self.last_died.clear();
+        let width = self.width as usize;
+        for (idx, (&cell, &before)) in self.cells.iter().zip(&self.previous).enumerate() {
+            let list = match (before, cell) {
+                (Cell::Dead, Cell::Alive) => &mut self.last_born,
+                (Cell::Alive, Cell::Dead) => &mut self.last_died,
+                _ => continue,
+            };
+            list.push(((idx / width) as u32, (idx % width) as u32));
+        }
+    }
+
+    /// 最近一次演化中活过来的格子 (row, col), 行优先的顺序. 清屏、重新生成或
+    /// 整体改写面板之后为空, 编辑格子时不变
+    pub fn last_born(&self) -> &[(u32, u32)] {
+        &self.last_born
+    }
+
+    /// 最近一次演化中死掉的格子, 同 `last_born`
+    pub fn last_died(&self) -> &[(u32, u32)] {
+        &self.last_died
+    }
+
+    /// 最近几代的活细胞数, 每次演化记一次. 清屏、重新生成或换成另一块面板时清空
+    pub fn population_history(&self) -> &PopulationHistory {
+        &self.history
+    }
+
+    /// 把当前的活细胞数记进 `population_history`. 演化时会自动调用; 面板是从别处
+    /// 演化好读进来的 (例如无边界模式下的 `load_viewport`) 时由调用者补上
+    pub fn record_population(&mut self) {
+        self.history.push(self.population());
+    }
+
+    /// 打开或关掉死细胞的拖尾. 关掉时已有的痕迹马上消失
+    pub fn set_trails(&mut self, on: bool) {
+        self.trails = on;
+        if !on && self.decay.iter().any(|&decay| decay > 0) {
+            self.request_redraw();
+        }
+        self.decay.clear();
+    }
+
+    pub fn trails(&self) -> bool {
+        self.trails
+    }
+
+    /// `row` 行 `col` 列的死细胞留下的痕迹还剩几代褪完, 在 1 到 `TRAIL_LENGTH` 之间.
+    /// 越界、是活细胞或者没有痕迹时返回 None
+    pub fn cell_decay(&self, row: u32, col: u32) -> Option<u8> {
+        let idx = self.checked_index(row, col).ok()?;
+        match self.decay.get(idx) {
+            Some(&decay) if decay > 0 => Some(decay),
+            _ => None,
+        }
+    }
+
+    /// `row` 行 `col` 列的活细胞连续活了几代, 刚出生的是 0. 越界或者是死细胞时返回 None
+    pub fn cell_age(&self, row: u32, col: u32) -> Option<u16> {
+        let idx = self.checked_index(row, col).ok()?;
+        match self.cells[idx] {
+            Cell::Alive => Some(self.ages.get(idx).copied().unwrap_or(0)),
+            Cell::Dead => None,
+        }
+    }
+
+    /// 森林火灾模型: 先按规则烧掉着火的格子、点燃邻居着火的树, 没有被点燃的树按
+    /// `lightning_rate` 着火, 空地按 `growth_rate` 长树. 随机数按行优先的顺序取,
+    /// 同样的种子每次演化的结果都一样
+    fn step_forest_fire(&mut self) -> bool {
+        if self.extra.len() != self.cells.len() {
+            self.extra = vec![0; self.cells.len()];
+        }
+        let rule = self.rule;
+        let mut next = self.take_scratch();
+        let mut next_extra = vec![0; self.cells.len()];
+        let (mut births, mut deaths) = (0, 0);
+        let mut changed = false;
+        for row in 0..self.height {
+            for col in 0..self.width {
+                let idx = self.get_index(row, col);
+                let state = self.state_at(idx);
+                let burning = self
+                    .neighbor_indices(row, col)
+                    .filter(|&neighbor| self.state_at(neighbor) == FIRE_BURNING)
+                    .count();
+                let next_state = match rule.next_state(state, burning.min(8) as u8) {
+                    FIRE_TREE if self.rng.gen_bool(self.lightning_rate) => FIRE_BURNING,
+                    0 if state == 0 && self.rng.gen_bool(self.growth_rate) => FIRE_TREE,
+                    s => s,
+                };
+                if next_state != state {
+                    changed = true;
+                    self.record_change(row, col);
+                }
+                next_extra[idx] = if next_state >= 2 { next_state } else { 0 };
+                let next_cell = if next_state == FIRE_TREE {
+                    Cell::Alive
+                } else {
+                    Cell::Dead
+                };
+                if next_cell != self.cells[idx] {
+                    match next_cell {
+                        Cell::Alive => births += 1,
+                        Cell::Dead => deaths += 1,
+                    }
+                }
+                next[idx] = next_cell;
+            }
+        }
+        self.extra = next_extra;
+        self.finish_step(next, births, deaths);
+        changed
+    }
+
+    /// 沙堆模型的一代: 在面板中心落一粒沙, 然后坍塌到稳定
+    fn step_sandpile(&mut self) -> bool {
+        let mut grains = self.grains();
+        let center = self.get_index(self.height / 2, self.width / 2);
+        grains[center] += 1;
+        self.topple(&mut grains);
+        let (next, next_extra) = Universe::split_grains(&grains);
+        // 一粒沙可能引起大片坍塌, 不逐格记录
+        self.request_redraw();
+        let births = (0..next.len())
+            .filter(|&i| next[i] == Cell::Alive && self.cells[i] == Cell::Dead)
+            .count() as u32;
+        let deaths = (0..next.len())
+            .filter(|&i| next[i] == Cell::Dead && self.cells[i] == Cell::Alive)
+            .count() as u32;
+        self.extra = next_extra;
+        self.finish_step(next, births, deaths);
+        true
+    }
+
+    /// 在 (`row`, `col`) 落一粒沙并坍塌到稳定, 返回坍塌的次数. 不算一代
+    pub fn add_grain(&mut self, row: u32, col: u32) -> Result<u64, UniverseError> {
+        let idx = self.checked_index(row, col)?;
+        let mut grains = self.grains();
+        grains[idx] += 1;
+        let topples = self.topple(&mut grains);
+        let (cells, extra) = Universe::split_grains(&grains);
+        self.counters.set_population(Universe::count_alive(&cells));
+        self.cells = cells;
+        self.invalidate();
+        self.extra = extra;
+        Ok(topples)
+    }
+
+    /// 每个格子的沙粒数
+    fn grains(&self) -> Vec<u32> {
+        (0..self.cells.len())
+            .map(|idx| u32::from(self.state_at(idx)))
+            .collect()
+    }
+
+    /// 沙粒数拆回细胞和 `extra`: 一粒沙存为活细胞, 两粒和三粒存在 `extra` 里
+    fn split_grains(grains: &[u32]) -> (Vec<Cell>, Vec<u8>) {
+        let cells = grains
+            .iter()
+            .map(|&n| if n == 1 { Cell::Alive } else { Cell::Dead })
+            .collect();
+        let extra = grains
+            .iter()
+            .map(|&n| if n >= 2 { n as u8 } else { 0 })
+            .collect();
+        (cells, extra)
+    }
+
+    /// 让沙粒数达到 `TOPPLE_GRAINS` 的格子反复坍塌, 直到全部稳定, 返回坍塌的次数.
+    /// 坍塌的先后不影响最终结果, 这里用一个栈按任意顺序处理.
+    /// 越过有界网格边缘的沙粒丢掉, 超过 `TOPPLE_LIMIT_PER_CELL` 时多出的沙粒也丢掉
+    fn topple(&self, grains: &mut [u32]) -> u64 {
+        let full = u32::from(TOPPLE_GRAINS);
+        let limit = TOPPLE_LIMIT_PER_CELL * grains.len() as u64;
+        let mut unstable: Vec<usize> = (0..grains.len()).filter(|&i| grains[i] >= full).collect();
+        let mut topples = 0u64;
+        while let Some(idx) = unstable.pop() {
+            if grains[idx] < full {
+                continue;
+            }
+            if topples >= limit {
+                for n in grains.iter_mut() {
+                    *n = (*n).min(full - 1);
+                }
+                break;
+            }
+            let times = grains[idx] / full;
+            grains[idx] %= full;
+            topples += u64::from(times);
+            let (row, col) = (idx as u32 / self.width, idx as u32 % self.width);
+            for &(d_row, d_col) in VON_NEUMANN.iter() {
+                if let Some(neighbor) = self.offset_index(row, col, d_row, d_col) {
+                    grains[neighbor] += times;
+                    if grains[neighbor] >= full {
+                        unstable.push(neighbor);
+                    }
+                }
+            }
+        }
+        topples
+    }
+
+    /// 森林火灾模型里空地每一代长出树的概率. 超出 0 到 1 的值按边界处理, NaN 当作 0
+    pub fn set_growth_rate(&mut self, rate: f64) {
+        self.growth_rate = clamp_probability(rate);
+    }
+
+    pub fn growth_rate(&self) -> f64 {
+        self.growth_rate
+    }
+
+    /// 森林火灾模型里树每一代被雷击着火的概率, 取值范围同 `set_growth_rate`
+    pub fn set_lightning_rate(&mut self, rate: f64) {
+        self.lightning_rate = clamp_probability(rate);
+    }
+
+    pub fn lightning_rate(&self) -> f64 {
+        self.lightning_rate
+    }
+
+    /// 用宇宙自己的随机数生成器翻转格子, 同样的种子每次翻转的格子都一样
+    fn mutate(&mut self) {
+        self.mutations = 0;
+        if self.mutation_rate <= 0.0 {
+            return;
+        }
+        for idx in 0..self.cells.len() {
+            if self.rng.gen_bool(self.mutation_rate) {
+                let flipped = match self.cells[idx] {
+                    Cell::Alive => Cell::Dead,
+                    Cell::Dead => Cell::Alive,
+                };
+                self.replace(idx, flipped);
+                self.mutations += 1;
+            }
+        }
+    }
+
+    /// 每一代之后每个格子随机翻转的概率, 0 关闭. 超出 0 到 1 的值按边界处理
+    pub fn set_mutation_rate(&mut self, rate: f64) {
+        self.mutation_rate = clamp_probability(rate);
+    }
+
+    pub fn mutation_rate(&self) -> f64 {
+        self.mutation_rate
+    }
+
+    /// 最近一次演化中随机翻转的格子数
+    pub fn mutations(&self) -> u32 {
+        self.mutations
+    }
+
+    /// 带颜色的 B3/S23: 存活的细胞保持颜色, 新生的细胞取三个父细胞里占多数的颜色,
+    /// QuadLife 里三个父细胞颜色都不同时取剩下的那种颜色
+    fn step_colored(&mut self) -> bool {
+        if self.extra.len() != self.cells.len() {
+            self.extra = vec![0; self.cells.len()];
+        }
+        let colors = self.rule.colors() as usize;
+        let mut next = self.take_scratch();
+        let mut next_extra = vec![0; self.cells.len()];
+        let (mut births, mut deaths) = (0, 0);
+        for row in 0..self.height {
+            for col in 0..self.width {
+                let idx = self.get_index(row, col);
+                // 下标 0 不用, 1..=colors 是各颜色的活邻居数
+                let mut parents = [0u8; 5];
+                for &(d_row, d_col) in MOORE.iter() {
+                    if let Some(neighbor) = self.offset_index(row, col, d_row, d_col) {
+                        if self.cells[neighbor] == Cell::Alive {
+                            parents[self.state_at(neighbor) as usize] += 1;
+                        }
+                    }
+                }
+                let live: u8 = parents.iter().sum();
+                let color = match self.cells[idx] {
+                    Cell::Alive if live == 2 || live == 3 => self.state_at(idx),
+                    Cell::Dead if live == 3 => {
+                        let majority = (1..=colors).find(|&c| parents[c] >= 2);
+                        let missing = (1..=colors).find(|&c| parents[c] == 0);
+                        majority.or(missing).unwrap_or(1) as u8
+                    }
+                    _ => 0,
+                };
+                if color != self.state_at(idx) {
+                    self.record_change(row, col);
+                }
+                let next_cell = if color == 0 { Cell::Dead } else { Cell::Alive };
+                if next_cell != self.cells[idx] {
+                    match next_cell {
+                        Cell::Alive => births += 1,
+                        Cell::Dead => deaths += 1,
+                    }
+                }
+                next[idx] = next_cell;
+                next_extra[idx] = color;
+            }
+        }
+        self.extra = next_extra;
+        self.finish_step(next, births, deaths);
+        births + deaths > 0
+    }
+
+    /// 循环元胞自动机: 有邻居处于下一个状态的格子前进一个状态.
+    /// 状态 1 存为活细胞, 其余非零的状态存在 `extra` 里
+    fn step_cyclic(&mut self) -> bool {
+        if self.extra.len() != self.cells.len() {
+            self.extra = vec![0; self.cells.len()];
+        }
+        let rule = self.rule;
+        let mut next = self.take_scratch();
+        let mut next_extra = vec![0; self.cells.len()];
+        let (mut births, mut deaths) = (0, 0);
+        let mut changed = false;
+        for row in 0..self.height {
+            for col in 0..self.width {
+                let idx = self.get_index(row, col);
+                let state = self.state_at(idx);
+                let successor = rule.next_state(state, 1);
+                let ahead = self
+                    .neighbor_indices(row, col)
+                    .any(|neighbor| self.state_at(neighbor) == successor);
+                let next_state = rule.next_state(state, ahead as u8);
+                if next_state != state {
+                    changed = true;
+                    self.record_change(row, col);
+                }
+                next_extra[idx] = if next_state >= 2 { next_state } else { 0 };
+                let next_cell = if next_state == 1 {
+                    Cell::Alive
+                } else {
+                    Cell::Dead
+                };
+                if next_cell != self.cells[idx] {
+                    match next_cell {
+                        Cell::Alive => births += 1,
+                        Cell::Dead => deaths += 1,
+                    }
+                }
+                next[idx] = next_cell;
+            }
+        }
+        self.extra = next_extra;
+        self.finish_step(next, births, deaths);
+        changed
+    }
+
+    /// Larger than Life 的演化, 邻居数一次性用前缀和算出来
+    fn step_larger_than_life(&mut self) -> bool {
+        let (radius, birth, survive, include_center) = match self.rule {
+            Rule::LargerThanLife {
+                radius,
+                birth,
+                survive,
+                include_center,
+            } => (radius, birth, survive, include_center),
+            _ => unreachable!("only called for Larger than Life rules"),
+        };
+        let counts = self.range_neighbor_counts(u32::from(radius));
+        let mut next = self.take_scratch();
+        let (mut births, mut deaths) = (0, 0);
+        for (idx, &count) in counts.iter().enumerate() {
+            let cell = self.cells[idx];
+            let next_cell = match cell {
+                Cell::Alive if survive.contains(count + include_center as u32) => Cell::Alive,
+                Cell::Dead if birth.contains(count) => Cell::Alive,
+                _ => Cell::Dead,
+            };
+            if next_cell != cell {
+                match next_cell {
+                    Cell::Alive => births += 1,
+                    Cell::Dead => deaths += 1,
+                }
+                self.record_index(idx);
+            }
+            next[idx] = next_cell;
+        }
+        self.finish_step(next, births, deaths);
+        births + deaths > 0
+    }
+
+    /// 以 (`row`, `col`) 为中心、半径为 `radius` 的正方形里的活细胞数, 不含中心.
+    /// 逐格累加, 复杂度是 O(radius²)
+    pub fn range_neighbor_count(&self, row: u32, col: u32, radius: u32) -> u32 {
+        let r = i64::from(radius);
+        let mut count = 0;
+        for d_row in -r..=r {
+            for d_col in -r..=r {
+                if d_row == 0 && d_col == 0 {
+                    continue;
+                }
+                if let Some(idx) = self.offset_index(row, col, d_row, d_col) {
+                    count += self.cells[idx] as u32;
+                }
+            }
+        }
+        count
+    }
+
+    /// 所有格子的 `range_neighbor_count`, 按行优先排列. 先在四周各扩出 `radius`
+    /// 格 (按 `Topology::resolve` 取值, 有界时是死细胞) 建二维前缀和,
+    /// 每个格子只需要常数时间
+    pub fn range_neighbor_counts(&self, radius: u32) -> Vec<u32> {
+        let (height, width) = (self.height as usize, self.width as usize);
+        let r = radius as usize;
+        let (padded_h, padded_w) = (height + 2 * r, width + 2 * r);
+        // sums[i][j] 是扩展网格左上角 i 行 j 列的活细胞数
+        let stride = padded_w + 1;
+        let mut sums = vec![0u32; (padded_h + 1) * stride];
+        for i in 0..padded_h {
+            let row = self.topology.resolve(i as i64 - r as i64, self.height);
+            let mut line = 0;
+            for j in 0..padded_w {
+                let col = self.topology.resolve(j as i64 - r as i64, self.width);
+                if let (Some(row), Some(col)) = (row, col) {
+                    line += self.cells[self.get_index(row, col)] as u32;
+                }
+                sums[(i + 1) * stride + j + 1] = sums[i * stride + j + 1] + line;
+            }
+        }
+        let side = 2 * r + 1;
+        let mut counts = Vec::with_capacity(height * width);
+        for row in 0..height {
+            for col in 0..width {
+                let (top, left) = (row * stride, col);
+                let (bottom, right) = ((row + side) * stride, col + side);
+                let total = sums[bottom + right] + sums[top + left]
+                    - sums[top + right]
+                    - sums[bottom + left];
+                counts.push(total - self.cells[row * width + col] as u32);
+            }
+        }
+        counts
+    }
+
+    pub fn rule(&self) -> Rule {
+        self.rule
+    }
+
+    /// 更换演化规则, 从下一次 `tick` 开始生效. 默认是 Conway 的 B3/S23.
+    /// 换成两状态的规则时, 正在衰亡的细胞直接变成死细胞
+    pub fn set_rule(&mut self, rule: Rule) {
+        if self.inverted && !rule.has_b0() {
+            self.invert_cells();
+        }
+        let colors_changed = rule.colors() != self.rule.colors();
+        self.rule = rule;
+        self.tiles = None;
+        // 同样的状态在不同的规则下画成不同的颜色
+        self.request_redraw();
+        if rule.states() <= 2 {
+            self.extra = Vec::new();
+        } else if colors_changed {
+            // 颜色和别的规则的状态含义不同, 换规则后活细胞都从第一种颜色开始
+            self.clear_extra();
+        }
+    }
+
+    /// 当前存的细胞是否是实际状态取反的结果. 含 B0 的规则每一代都会让整个背景
+    /// 亮起来, 所以按 Golly 的做法在取反和不取反之间交替演化, 面板上画的就是存的
+    /// 细胞, 不会整屏闪烁. `cells`、`population` 等也都按存的细胞计算
+    pub fn is_inverted(&self) -> bool {
+        self.inverted
+    }
+
+    /// 实际的细胞状态, 即存的细胞在取反相位时再取反一次
+    pub fn actual_cell(&self, row: u32, col: u32) -> Option<Cell> {
+        let cell = self.get_cell(row, col)?;
+        Some(match (cell, self.inverted) {
+            (Cell::Alive, true) => Cell::Dead,
+            (Cell::Dead, true) => Cell::Alive,
+            (cell, false) => cell,
+        })
+    }
+
+    /// 实际的活细胞数, 取反相位时是存的死细胞数
+    pub fn actual_population(&self) -> u32 {
+        if self.inverted {
+            self.width * self.height - self.population()
+        } else {
+            self.population()
+        }
+    }
+
+    /// 把存的细胞全部取反, 回到不取反的相位
+    fn invert_cells(&mut self) {
+        self.invalidate();
+        for cell in self.cells.iter_mut() {
+            *cell = match *cell {
+                Cell::Alive => Cell::Dead,
+                Cell::Dead => Cell::Alive,
+            };
+        }
+        self.counters
+            .set_population(self.width * self.height - self.population());
+        self.inverted = false;
+    }
+
+    /// 用查找表设置两状态的规则, 保留当前的邻域. 表会换算成等价的 B/S 规则,
+    /// 所以 `rule()` 返回的是 B/S 规则串
+    pub fn set_rule_table(&mut self, table: RuleTable) {
+        let rule = Rule::from_table(&table).with_neighborhood(self.neighborhood());
+        self.set_rule(rule);
+    }
+
+    /// 格子在多状态规则下的状态: 0 为死, 1 为活, 2 及以上的含义由规则决定,
+    /// Generations 里是衰亡中, Wireworld 里是电子尾和导线,
+    /// Immigration 和 QuadLife 里是活细胞的颜色 (从 1 开始)
+    pub fn state(&self, row: u32, col: u32) -> Option<u8> {
+        let idx = self.checked_index(row, col).ok()?;
+        Some(self.state_at(idx))
+    }
+
+    fn state_at(&self, idx: usize) -> u8 {
+        match self.extra.get(idx) {
+            Some(&state) if state != 0 => state,
+            _ => self.cells[idx] as u8,
+        }
+    }
+
+    /// 直接设置格子的状态, 例如 Wireworld 的导线或 Immigration 的颜色.
+    /// 当前规则没有的状态当作死细胞
+    pub fn set_state(&mut self, row: u32, col: u32, state: u8) -> Result<(), UniverseError> {
+        let idx = self.checked_index(row, col)?;
+        let cell = if state >= 1 && state <= self.rule.colors() {
+            Cell::Alive
+        } else {
+            Cell::Dead
+        };
+        self.replace(idx, cell);
+        if state >= 2 && state < self.rule.states() {
+            if self.extra.len() != self.cells.len() {
+                self.extra = vec![0; self.cells.len()];
+            }
+            self.extra[idx] = state;
+        }
+        Ok(())
+    }
+
+    /// 面板整体被替换或重新排列后, 多出来的状态不再对应原来的格子, 全部清掉
+    fn clear_extra(&mut self) {
+        if !self.extra.is_empty() {
+            self.extra = vec![0; self.cells.len()];
+        }
+    }
+
+    pub fn neighborhood(&self) -> Neighborhood {
+        self.rule.neighborhood()
+    }
+
+    /// 保留规则的其余条件, 只换邻域, 从下一次 `tick` 开始生效
+    pub fn set_neighborhood(&mut self, neighborhood: Neighborhood) {
+        self.rule = self.rule.with_neighborhood(neighborhood);
+        self.tiles = None;
+        self.request_redraw();
+    }
+
+    /// 上一次演化中由死变活的细胞数, 还没有演化过时为 0
+    pub fn births(&self) -> u32 {
+        self.births
+    }
+
+    /// 上一次演化中由活变死的细胞数
+    pub fn deaths(&self) -> u32 {
+        self.deaths
+    }
+
+    pub fn step_n(&mut self, n: u64) {
+        for _ in 0..n {
+            self.step();
+        }
+    }
+
+    /// 持续演化直到变成静物、全部死亡或达到 `max_steps` 代. B0 规则按实际的细胞判断,
+    /// 不看存的细胞是不是取反的
+    pub fn run_until_stable(&mut self, max_steps: u64) -> StableResult {
+        for steps in 0..max_steps {
+            if self.actual_population() == 0 {
+                return StableResult::Extinct { steps };
+            }
+            let inverted = self.inverted;
+            let changed = self.step();
+            let still = if self.inverted != inverted {
+                // 相位翻了: 存的细胞全部取反才是实际没变
+                self.mutations == 0 && self.cells.iter().zip(&self.previous).all(|(a, b)| a != b)
+            } else {
+                !changed
+            };
+            if still {
+                return StableResult::Stable { steps };
+            }
+        }
+        if self.actual_population() == 0 {
+            return StableResult::Extinct { steps: max_steps };
+        }
+        StableResult::StepLimit
+    }
+}
+
+impl fmt::Display for Universe {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for line in self.cells.as_slice().chunks(self.width as usize) {
+            for &cell in line {
+                let symbol = if cell == Cell::Dead { '◻' } else { '◼' };
+                write!(f, "{}", symbol)?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+/// 解析 Display 输出的文本, 同时接受 ASCII 的 '.' (死) 和 '#' (活)
+impl FromStr for Universe {
+    type Err = ParseUniverseError;
+
+    fn from_str(s: &str) -> Result<Universe, ParseUniverseError> {
+        let lines: Vec<&str> = s.lines().map(|l| l.trim_end_matches('\r')).collect();
+        let len = lines
+            .iter()
+            .rposition(|l| !l.is_empty())
+            .map_or(0, |i| i + 1);
+        let lines = &lines[..len];
+        if lines.is_empty() {
+            return Err(ParseUniverseError::Empty);
+        }
+        let width = lines[0].chars().count();
+        let mut cells = Vec::with_capacity(width * lines.len());
+        for (i, line) in lines.iter().enumerate() {
+            let found = line.chars().count();
+            if found != width {
+                return Err(ParseUniverseError::RaggedLine {
+                    line: i + 1,
+                    expected: width,
+                    found,
+                });
+            }
+            for (j, ch) in line.chars().enumerate() {
+                cells.push(match ch {
+                    '◻' | '.' => Cell::Dead,
+                    '◼' | '#' => Cell::Alive,
+                    _ => {
+                        return Err(ParseUniverseError::InvalidChar {
+                            line: i + 1,
+                            col: j + 1,
+                            ch,
+                        })
+                    }
+                });
+            }
+        }
+        Ok(
+            Universe::from_cells(width as u32, lines.len() as u32, cells)
+                .expect("cell count matches the parsed dimensions"),
+        )
+    }
+}
+
+impl Universe {
+    pub fn dead_all(&mut self) {
+        self.history.clear();
+        self.counters.set_generation(0);
+        for cell in self.cells.iter_mut() {
+            *cell = Cell::Dead;
+        }
+        self.invalidate();
+        self.clear_extra();
+        self.inverted = false;
+        self.counters.set_population(0);
+        self.run_state = RunState::Paused;
+    }
+
+    pub fn reset(&mut self) {
+        self.history.clear();
+        self.counters.set_generation(0);
+        self.cells = Universe::gen_map(self.width, self.height, self.density, &mut self.rng);
+        self.invalidate();
+        self.clear_extra();
+        self.inverted = false;
+        self.counters
+            .set_population(Universe::count_alive(&self.cells));
+    }
+
+    pub fn run_state(&self) -> RunState {
+        self.run_state
+    }
+
+    pub fn set_run_state(&mut self, state: RunState) {
+        self.run_state = state;
+    }
+
+    /// 没在演化, 编辑中也算
+    pub fn is_paused(&self) -> bool {
+        self.run_state != RunState::Running
+    }
+
+    /// 运行时暂停, 暂停或编辑时继续
+    pub fn toggle_run_state(&mut self) {
+        self.run_state = match self.run_state {
+            RunState::Running => RunState::Paused,
+            RunState::Paused | RunState::Editing => RunState::Running,
+        };
+    }
+
+    /// 鼠标按下或拖动时调用. 暂停时进入编辑并返回 true, 运行时不能改格子, 返回 false
+    pub fn begin_edit(&mut self) -> bool {
+        if self.run_state == RunState::Running {
+            return false;
+        }
+        self.run_state = RunState::Editing;
+        true
+    }
+
+    /// 鼠标松开时调用, 编辑中回到暂停; 编辑途中已经继续演化了就不变
+    pub fn end_edit(&mut self) {
+        if self.run_state == RunState::Editing {
+            self.run_state = RunState::Paused;
+        }
+    }
+
+    /// 上次 `mark_drawn` 之后有格子变过或者请求过整个重画, 暂停时也要画一帧
+    pub fn needs_frame(&self) -> bool {
+        self.redraw || !self.changes.is_empty()
+    }
+}
+
+impl Universe {
+    /// 区域内所有格子的下标. `wrap` 为 true 时越过边界的部分绕回另一侧,
+    /// 否则截断到网格内
+    fn region_indices(
+        width: u32,
+        height: u32,
+        region: Region,
+        wrap: bool,
+    ) -> impl Iterator<Item = usize> {
+        let (row, col, rows, cols) = if wrap {
+            (
+                region.row % height,
+                region.col % width,
+                region.height.min(height),
+                region.width.min(width),
+            )
+        } else {
+            let row = region.row.min(height);
+            let col = region.col.min(width);
+            (
+                row,
+                col,
+                region.row.saturating_add(region.height).min(height) - row,
+                region.col.saturating_add(region.width).min(width) - col,
+            )
+        };
+        (0..rows).flat_map(move |i| {
+            let r = (row + i) % height;
+            (0..cols).map(move |j| (r * width + (col + j) % width) as usize)
+        })
+    }
+
+    pub fn fill_region(&mut self, region: Region, cell: Cell, wrap: bool) {
+        for idx in Universe::region_indices(self.width, self.height, region, wrap) {
+            self.replace(idx, cell);
+        }
+    }
+
+    pub fn clear_region(&mut self, region: Region, wrap: bool) {
+        self.fill_region(region, Cell::Dead, wrap);
+    }
+
+    /// 用宇宙自己的随机数生成器随机生成一条规则 (见 `rule::random_rule`), 换上它,
+    /// 再按当前的密度重新随机生成面板. 同样的种子探索出的规则序列总是一样
+    pub fn explore_rule(&mut self) -> Rule {
+        let rule = rule::random_rule(&mut self.rng);
+        self.set_rule(rule);
+        self.reset();
+        rule
+    }
+
+    /// 从头开始, 每个格子在当前规则的所有状态里均匀随机取一个.
+    /// 循环元胞自动机用它代替按密度生成的 `reset`
+    pub fn randomize_states(&mut self) {
+        self.counters.set_generation(0);
+        self.inverted = false;
+        self.counters.set_population(0);
+        self.cells = vec![Cell::Dead; self.cells.len()];
+        self.invalidate();
+        let states = self.rule.states();
+        self.extra = if states > 2 {
+            vec![0; self.cells.len()]
+        } else {
+            Vec::new()
+        };
+        for idx in 0..self.cells.len() {
+            let state = self.rng.gen_range(0, states);
+            if state >= 1 && state <= self.rule.colors() {
+                self.cells[idx] = Cell::Alive;
+                self.counters.set_population(self.population() + 1);
+            }
+            if state >= 2 {
+                self.extra[idx] = state;
+            }
+        }
+    }
+
+    /// 按 `density` 的比例随机重新生成区域内的细胞
+    pub fn randomize_region(&mut self, region: Region, density: f32, wrap: bool) {
+        let density = density.clamp(0.0, 1.0) as f64;
+        for idx in Universe::region_indices(self.width, self.height, region, wrap) {
+            let cell = if self.rng.gen_bool(density) {
+                Cell::Alive
+            } else {
+                Cell::Dead
+            };
+            self.replace(idx, cell);
+        }
+    }
+}
+
+fn clamp_probability(rate: f64) -> f64 {
+    if rate.is_nan() {
+        0.0
+    } else {
+        rate.clamp(0.0, 1.0)
+    }
+}
+
+/// 按行优先把细胞每 8 个打包成一个字节, 高位在前
+pub(crate) fn pack_cells(cells: &[Cell]) -> Vec<u8> {
+    let mut bytes = vec![0u8; cells.len().div_ceil(8)];
+    for (i, &cell) in cells.iter().enumerate() {
+        if cell == Cell::Alive {
+            bytes[i / 8] |= 0x80 >> (i % 8);
+        }
+    }
+    bytes
+}
+
+/// `pack_cells` 的逆操作, 调用方保证 `bytes` 至少有 `len` 位
+pub(crate) fn unpack_cells(bytes: &[u8], len: usize) -> Vec<Cell> {
+    (0..len)
+        .map(|i| {
+            if bytes[i / 8] & (0x80 >> (i % 8)) != 0 {
+                Cell::Alive
+            } else {
+                Cell::Dead
+            }
+        })
+        .collect()
+}
+
+#[cfg(feature = "serde")]
+mod serde_impl {
+    use serde::de::{self, Deserializer};
+    use serde::ser::Serializer;
+    use serde::{Deserialize, Serialize};
+
+    use super::{pack_cells, unpack_cells, Cell, Universe};
+
+    impl Serialize for Cell {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            serializer.serialize_u8(*self as u8)
+        }
+    }
+
+    impl<'de> Deserialize<'de> for Cell {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Cell, D::Error> {
+            match u8::deserialize(deserializer)? {
+                0 => Ok(Cell::Dead),
+                1 => Ok(Cell::Alive),
+                v => Err(de::Error::custom(format!("invalid cell value {}", v))),
+            }
+        }
+    }
+
+    /// 序列化时的形式, 细胞用 `pack_cells` 打包
+    #[derive(Serialize, Deserialize)]
+    #[serde(rename = "Universe")]
+    struct Repr {
+        width: u32,
+        height: u32,
+        count: i64,
+        cells: Vec<u8>,
+    }
+
+    impl Serialize for Universe {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            Repr {
+                width: self.width,
+                height: self.height,
+                count: self.count(),
+                cells: pack_cells(&self.cells),
+            }
+            .serialize(serializer)
+        }
+    }
+
+    impl<'de> Deserialize<'de> for Universe {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Universe, D::Error> {
+            let repr = Repr::deserialize(deserializer)?;
+            let len = repr.width as usize * repr.height as usize;
+            if repr.cells.len() != len.div_ceil(8) {
+                return Err(de::Error::custom(format!(
+                    "{}x{} universe needs {} bytes of cells, got {}",
+                    repr.width,
+                    repr.height,
+                    len.div_ceil(8),
+                    repr.cells.len()
+                )));
+            }
+            let cells = unpack_cells(&repr.cells, len);
+            let u =
+                Universe::from_cells(repr.width, repr.height, cells).map_err(de::Error::custom)?;
+            u.counters.set_generation(repr.count);
+            Ok(u)
+        }
+    }
+}
+
+impl Universe {
+    /// 改变网格尺寸, 保留原有细胞、多状态规则下的状态和代数.
+    /// 超出新范围的细胞被丢弃, 新增的格子都是死细胞
+    pub fn resize(&mut self, new_width: u32, new_height: u32, anchor: Anchor) {
+        assert!(
+            new_width > 0 && new_height > 0,
+            "universe must not be empty"
+        );
+        let (d_row, d_col) = match anchor {
+            Anchor::TopLeft => (0, 0),
+            Anchor::Center => (
+                (new_height as i64 - self.height as i64) / 2,
+                (new_width as i64 - self.width as i64) / 2,
+            ),
+            Anchor::Offset { row, col } => (row, col),
+        };
+        let new_index = |row: u32, col: u32| {
+            let r = row as i64 + d_row;
+            let c = col as i64 + d_col;
+            if r >= 0 && r < new_height as i64 && c >= 0 && c < new_width as i64 {
+                Some((r * new_width as i64 + c) as usize)
+            } else {
+                None
+            }
+        };
+        let mut cells = vec![Cell::Dead; (new_width * new_height) as usize];
+        for (row, col) in self.live_cells() {
+            if let Some(idx) = new_index(row, col) {
+                cells[idx] = Cell::Alive;
+            }
+        }
+        if !self.extra.is_empty() {
+            let mut extra = vec![0; cells.len()];
+            for (idx, &state) in self.extra.iter().enumerate() {
+                let (row, col) = (idx as u32 / self.width, idx as u32 % self.width);
+                if let Some(new_idx) = new_index(row, col) {
+                    extra[new_idx] = state;
+                }
+            }
+            self.extra = extra;
+        }
+        // 自动扩展时细胞的年龄、拖尾、活跃度和上一代的变化跟着搬过去
+        let ages = self.move_values(&self.ages, cells.len(), &new_index);
+        let decay = self.move_values(&self.decay, cells.len(), &new_index);
+        let activity = self.move_values(&self.activity, cells.len(), &new_index);
+        let moved = |list: &[(u32, u32)]| -> Vec<(u32, u32)> {
+            list.iter()
+                .filter_map(|&(row, col)| new_index(row, col))
+                .map(|idx| (idx as u32 / new_width, idx as u32 % new_width))
+                .collect()
+        };
+        let (last_born, last_died) = (moved(&self.last_born), moved(&self.last_died));
+        self.width = new_width;
+        self.height = new_height;
+        self.counters.set_population(Universe::count_alive(&cells));
+        self.cells = cells;
+        self.invalidate();
+        self.ages = ages;
+        self.decay = decay;
+        self.activity = activity;
+        self.last_born = last_born;
+        self.last_died = last_died;
+    }
+
+    /// `resize` 里把按格子存的数搬到 `new_index` 给的新位置. 长度和 `cells` 不同的
+    /// 本来就都当作 0, 还是返回空的
+    fn move_values<T: Copy + Default>(
+        &self,
+        values: &[T],
+        len: usize,
+        new_index: &dyn Fn(u32, u32) -> Option<usize>,
+    ) -> Vec<T> {
+        if values.len() != self.cells.len() {
+            return Vec::new();
+        }
+        let mut moved = vec![T::default(); len];
+        for (idx, &value) in values.iter().enumerate() {
+            let (row, col) = (idx as u32 / self.width, idx as u32 % self.width);
+            if let Some(new_idx) = new_index(row, col) {
+                moved[new_idx] = value;
+            }
+        }
+        moved
+    }
+
+    /// 把所有细胞整体平移 (`d_row`, `d_col`). `wrap` 为 true 时移出边界的细胞
+    /// 从另一侧出现, 否则被丢弃
+    pub fn translate(&mut self, d_row: i32, d_col: i32, wrap: bool) {
+        let height = self.height as i64;
+        let width = self.width as i64;
+        let mut cells = vec![Cell::Dead; self.cells.len()];
+        for (row, col) in self.live_cells() {
+            let mut r = row as i64 + d_row as i64;
+            let mut c = col as i64 + d_col as i64;
+            if wrap {
+                r = r.rem_euclid(height);
+                c = c.rem_euclid(width);
+            } else if r < 0 || r >= height || c < 0 || c >= width {
+                continue;
+            }
+            cells[(r * width + c) as usize] = Cell::Alive;
+        }
+        self.counters.set_population(Universe::count_alive(&cells));
+        self.cells = cells;
+        self.invalidate();
+        self.clear_extra();
+    }
+}
+
+impl Universe {
+    /// 按 `f` 给出的 (row, col) -> (row, col) 映射重新排列细胞
+    fn remap<F>(&mut self, new_width: u32, new_height: u32, f: F)
+    where
+        F: Fn(u32, u32) -> (u32, u32),
+    {
+        let mut cells = vec![Cell::Dead; self.cells.len()];
+        for (row, col) in self.live_cells() {
+            let (r, c) = f(row, col);
+            cells[(r * new_width + c) as usize] = Cell::Alive;
+        }
+        self.width = new_width;
+        self.height = new_height;
+        self.cells = cells;
+        self.invalidate();
+        self.clear_extra();
+    }
+
+    /// 顺时针旋转 90 度, 宽高互换
+    pub fn rotate90_cw(&mut self) {
+        let height = self.height;
+        self.remap(self.height, self.width, |r, c| (c, height - 1 - r));
+    }
+
+    /// 逆时针旋转 90 度, 宽高互换
+    pub fn rotate90_ccw(&mut self) {
+        let width = self.width;
+        self.remap(self.height, self.width, |r, c| (width - 1 - c, r));
+    }
+
+    /// 左右镜像
+    pub fn flip_horizontal(&mut self) {
+        let width = self.width;
+        self.remap(self.width, self.height, |r, c| (r, width - 1 - c));
+    }
+
+    /// 上下镜像
+    pub fn flip_vertical(&mut self) {
+        let height = self.height;
+        self.remap(self.width, self.height, |r, c| (height - 1 - r, c));
+    }
+}
+
+impl Universe {
+    /// 活细胞的外接矩形 (min_row, min_col, max_row, max_col), 不考虑环面的接缝;
+    /// 没有活细胞时返回 `None`
+    pub fn bounding_box(&self) -> Option<(u32, u32, u32, u32)> {
+        let mut cells = self.live_cells();
+        let (row, col) = cells.next()?;
+        Some(
+            cells.fold((row, col, row, col), |(r0, c0, r1, c1), (r, c)| {
+                (r0.min(r), c0.min(c), r1.max(r), c1.max(c))
+            }),
+        )
+    }
+
+    /// 允许跨过环面接缝的最小外接矩形. 返回的区域可能越过右边或下边,
+    /// 越界的部分绕回另一侧, 和 `fill_region(.., wrap = true)` 的约定一致.
+    ///
+    /// 导出 RLE 等平面格式时应当使用这个版本: 一个正好停在接缝上的滑翔机
+    /// 用 `bounding_box` 会得到几乎整个网格, 这里仍然是 3x3
+    pub fn wrapped_bounding_box(&self) -> Option<Region> {
+        let mut rows = vec![false; self.height as usize];
+        let mut cols = vec![false; self.width as usize];
+        let mut any = false;
+        for (row, col) in self.live_cells() {
+            rows[row as usize] = true;
+            cols[col as usize] = true;
+            any = true;
+        }
+        if !any {
+            return None;
+        }
+        let (row, height) = Universe::tight_span(&rows);
+        let (col, width) = Universe::tight_span(&cols);
+        Some(Region::new(row, col, height, width))
+    }
+
+    /// 环形数组上覆盖所有 true 的最短区间 (起点, 长度), 也就是跳过最长的一段空白.
+    /// 长度相同时优先选择不跨过接缝的区间
+    fn tight_span(occupied: &[bool]) -> (u32, u32) {
+        let n = occupied.len();
+        let first = occupied.iter().position(|&o| o).unwrap_or(0);
+        let (mut best_gap, mut best_start) = (0, first);
+        let mut gap = 0;
+        for k in 1..=n {
+            let i = (first + k) % n;
+            if !occupied[i] {
+                gap += 1;
+                continue;
+            }
+            let wraps = |start: usize, gap: usize| start + (n - gap) > n;
+            if gap > best_gap || (gap == best_gap && wraps(best_start, best_gap) && !wraps(i, gap))
+            {
+                best_gap = gap;
+                best_start = i;
+            }
+            gap = 0;
+        }
+        (best_start as u32, (n - best_gap) as u32)
+    }
+}
+
+impl Universe {
+    /// 和尺寸相同的 `other` 逐格组合, 结果写回自身
+    pub fn combine(&mut self, other: &Universe, op: CombineOp) -> Result<(), UniverseError> {
+        if self.width != other.width || self.height != other.height {
+            return Err(UniverseError::DimensionMismatch {
+                expected: (self.width, self.height),
+                actual: (other.width, other.height),
+            });
+        }
+        self.invalidate();
+        for (cell, &theirs) in self.cells.iter_mut().zip(other.cells.iter()) {
+            let a = *cell == Cell::Alive;
+            let b = theirs == Cell::Alive;
+            let alive = match op {
+                CombineOp::Union => a || b,
+                CombineOp::Intersection => a && b,
+                CombineOp::Difference => a && !b,
+                CombineOp::Xor => a != b,
+            };
+            *cell = if alive { Cell::Alive } else { Cell::Dead };
+        }
+        self.clear_extra();
+        self.counters
+            .set_population(Universe::count_alive(&self.cells));
+        Ok(())
+    }
+
+    /// 不修改自身的 `combine`
+    pub fn combined(&self, other: &Universe, op: CombineOp) -> Result<Universe, UniverseError> {
+        let mut u = self.clone();
+        u.combine(other, op)?;
+        Ok(u)
+    }
+}
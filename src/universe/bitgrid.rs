@@ -0,0 +1,326 @@
+//! 一位一个细胞的面板. 每一行从新的 `u64` 开始, 第 `c` 列在这一行第 `c / 64` 个字的
+//! 第 `c % 64` 位, 最后一个字里超出宽度的位总是 0.
+//!
+//! 两状态的 B/S 规则演化时先把 `Vec<Cell>` 打包成这种面板, 整字算出八个方向的邻居,
+//! 按位相加成邻居数的位平面, 再一次挑出 64 个格子的下一代.
+//! `Universe` 留着两块这样的面板反复使用, 尺寸不变时演化不需要分配内存
+
+use super::{Cell, Topology};
+
+/// 按位相加时的四个位平面, 第 k 个是邻居数的第 k 位
+#[derive(Default)]
+pub(crate) struct Counts([u64; 4]);
+
+impl Counts {
+    pub(crate) fn add(&mut self, plane: u64) {
+        let mut carry = plane;
+        for bit in self.0.iter_mut() {
+            let next = *bit & carry;
+            *bit ^= carry;
+            carry = next;
+            if carry == 0 {
+                break;
+            }
+        }
+    }
+
+    /// 邻居数恰好为 `n` 的那些位
+    pub(crate) fn equals(&self, n: usize) -> u64 {
+        self.0.iter().enumerate().fold(!0, |acc, (k, &plane)| {
+            acc & if n & (1 << k) != 0 { plane } else { !plane }
+        })
+    }
+
+    /// 邻居数落在 9 位掩码 `mask` 里的那些位
+    pub(crate) fn matching(&self, mask: u16) -> u64 {
+        (0..=8)
+            .filter(|n| mask & (1 << n) != 0)
+            .fold(0, |acc, n| acc | self.equals(n))
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub(crate) struct BitGrid {
+    width: u32,
+    height: u32,
+    /// 每一行占几个字
+    stride: usize,
+    words: Vec<u64>,
+}
+
+impl BitGrid {
+    pub(crate) fn new(width: u32, height: u32) -> BitGrid {
+        let stride = (width as usize).div_ceil(64);
+        BitGrid {
+            width,
+            height,
+            stride,
+            words: vec![0; stride * height as usize],
+        }
+    }
+
+    /// 换成 `width` x `height` 的空面板, 尺寸没变时沿用原来的内存
+    fn reshape(&mut self, width: u32, height: u32) {
+        if (self.width, self.height) != (width, height) {
+            *self = BitGrid::new(width, height);
+        }
+    }
+
+    /// 把按行优先的细胞打包进来, `cells` 的长度必须是 `width * height`
+    pub(crate) fn pack(&mut self, cells: &[Cell], width: u32, height: u32) {
+        self.reshape(width, height);
+        if width == 0 {
+            return;
+        }
+        let lines = cells.chunks(width as usize);
+        for (line, words) in lines.zip(self.words.chunks_mut(self.stride)) {
+            pack_row(line, words);
+        }
+    }
+
+    /// 拆回按行优先的细胞, 写进 `cells`
+    pub(crate) fn write_cells(&self, cells: &mut [Cell]) {
+        if self.width == 0 {
+            return;
+        }
+        let lines = cells.chunks_mut(self.width as usize);
+        for (line, words) in lines.zip(self.words.chunks(self.stride)) {
+            unpack_row(words, line);
+        }
+    }
+
+    /// 逐行并行的 `pack`
+    #[cfg(feature = "rayon")]
+    pub(crate) fn par_pack(&mut self, cells: &[Cell], width: u32, height: u32) {
+        use rayon::prelude::*;
+
+        self.reshape(width, height);
+        if width == 0 {
+            return;
+        }
+        let lines = cells.par_chunks(width as usize);
+        lines
+            .zip(self.words.par_chunks_mut(self.stride))
+            .for_each(|(line, words)| pack_row(line, words));
+    }
+
+    /// 逐行并行的 `write_cells`
+    #[cfg(feature = "rayon")]
+    pub(crate) fn par_write_cells(&self, cells: &mut [Cell]) {
+        use rayon::prelude::*;
+
+        if self.width == 0 {
+            return;
+        }
+        let lines = cells.par_chunks_mut(self.width as usize);
+        lines
+            .zip(self.words.par_chunks(self.stride))
+            .for_each(|(line, words)| unpack_row(words, line));
+    }
+
+    /// 这一格活着、`other` 里同一格死了的格子数
+    pub(crate) fn count_gone(&self, other: &BitGrid) -> u32 {
+        self.words
+            .iter()
+            .zip(other.words.iter())
+            .map(|(&a, &b)| (a & !b).count_ones())
+            .sum()
+    }
+
+    /// 对这一代和 `other` 里状态不同的每个格子调用 `f(row, col)`, 按行优先的顺序
+    pub(crate) fn for_each_change(&self, other: &BitGrid, mut f: impl FnMut(u32, u32)) {
+        for (i, (&a, &b)) in self.words.iter().zip(other.words.iter()).enumerate() {
+            let mut diff = a ^ b;
+            while diff != 0 {
+                let bit = diff.trailing_zeros();
+                f(
+                    (i / self.stride) as u32,
+                    (i % self.stride) as u32 * 64 + bit,
+                );
+                diff &= diff - 1;
+            }
+        }
+    }
+
+    fn row(&self, row: u32) -> &[u64] {
+        let start = row as usize * self.stride;
+        &self.words[start..start + self.stride]
+    }
+
+    /// 最后一个字里属于面板的那些位
+    fn last_mask(&self) -> u64 {
+        match self.width % 64 {
+            0 => !0,
+            bits => (1 << bits) - 1,
+        }
+    }
+
+    /// 按两状态规则的复活和存活掩码演化一代, 结果写进 `next`. 邻居按 `topology` 换算,
+    /// `moore` 为 false 时只数上下左右
+    pub(crate) fn step_into(
+        &self,
+        next: &mut BitGrid,
+        birth: u16,
+        survive: u16,
+        moore: bool,
+        topology: Topology,
+    ) {
+        next.reshape(self.width, self.height);
+        if self.width == 0 || self.height == 0 {
+            return;
+        }
+        let stepper = RowStep::new(self, birth, survive, moore, topology);
+        for (row, out) in next.words.chunks_mut(self.stride).enumerate() {
+            stepper.run(row as u32, out);
+        }
+    }
+
+    /// 同 `step_into`, 但把下一代按每 `BAND_ROWS` 行一条分成横条, 交给 rayon 并行计算.
+    /// 每一条都只读当前代, 环面接缝处的行和串行时读到的一样, 结果完全相同
+    #[cfg(feature = "rayon")]
+    pub(crate) fn par_step_into(
+        &self,
+        next: &mut BitGrid,
+        birth: u16,
+        survive: u16,
+        moore: bool,
+        topology: Topology,
+    ) {
+        use rayon::prelude::*;
+
+        next.reshape(self.width, self.height);
+        if self.width == 0 || self.height == 0 {
+            return;
+        }
+        let stepper = RowStep::new(self, birth, survive, moore, topology);
+        let stride = self.stride;
+        next.words
+            .par_chunks_mut(stride * BAND_ROWS)
+            .enumerate()
+            .for_each(|(band, out)| {
+                for (i, line) in out.chunks_mut(stride).enumerate() {
+                    stepper.run((band * BAND_ROWS + i) as u32, line);
+                }
+            });
+    }
+}
+
+fn pack_row(line: &[Cell], words: &mut [u64]) {
+    for word in words.iter_mut() {
+        *word = 0;
+    }
+    for (col, &cell) in line.iter().enumerate() {
+        words[col / 64] |= (cell as u64) << (col % 64);
+    }
+}
+
+fn unpack_row(words: &[u64], line: &mut [Cell]) {
+    for (col, cell) in line.iter_mut().enumerate() {
+        *cell = if words[col / 64] >> (col % 64) & 1 != 0 {
+            Cell::Alive
+        } else {
+            Cell::Dead
+        };
+    }
+}
+
+/// 并行演化时每条横条的行数
+#[cfg(feature = "rayon")]
+const BAND_ROWS: usize = 16;
+
+/// 演化一行需要的全部参数, 只读, 可以在线程之间共享
+struct RowStep<'a> {
+    grid: &'a BitGrid,
+    birth: u16,
+    survive: u16,
+    moore: bool,
+    topology: Topology,
+    /// 左右边缘之外的那一列换算回面板里的哪一列, 越过有界网格的边缘时为 None
+    left_edge: Option<u32>,
+    right_edge: Option<u32>,
+    /// 每行最后一个字的下标, 以及面板最后一列在这个字里的位置
+    last: usize,
+    top_bit: u32,
+    mask: u64,
+}
+
+impl<'a> RowStep<'a> {
+    fn new(grid: &'a BitGrid, birth: u16, survive: u16, moore: bool, topology: Topology) -> Self {
+        RowStep {
+            grid,
+            birth,
+            survive,
+            moore,
+            topology,
+            left_edge: topology.resolve(-1, grid.width),
+            right_edge: topology.resolve(i64::from(grid.width), grid.width),
+            last: grid.stride - 1,
+            top_bit: (grid.width - 1) % 64,
+            mask: grid.last_mask(),
+        }
+    }
+
+    /// 第 `r` 行, 越过有界网格上下边缘时为 None, 即全是死细胞
+    fn line(&self, r: i64) -> Option<&'a [u64]> {
+        let grid = self.grid;
+        self.topology.resolve(r, grid.height).map(|r| grid.row(r))
+    }
+
+    fn edge_bit(line: &[u64], col: Option<u32>) -> u64 {
+        col.map_or(0, |c| line[c as usize / 64] >> (c % 64) & 1)
+    }
+
+    fn word(line: Option<&[u64]>, w: usize) -> u64 {
+        line.map_or(0, |line| line[w])
+    }
+
+    /// 第 `w` 个字的左邻居, 即每一位换成它左边那一格
+    fn west(&self, line: Option<&[u64]>, w: usize) -> u64 {
+        line.map_or(0, |line| {
+            let carry = if w == 0 {
+                RowStep::edge_bit(line, self.left_edge)
+            } else {
+                line[w - 1] >> 63
+            };
+            line[w] << 1 | carry
+        })
+    }
+
+    /// 第 `w` 个字的右邻居
+    fn east(&self, line: Option<&[u64]>, w: usize) -> u64 {
+        line.map_or(0, |line| {
+            if w == self.last {
+                line[w] >> 1 | RowStep::edge_bit(line, self.right_edge) << self.top_bit
+            } else {
+                line[w] >> 1 | line[w + 1] << 63
+            }
+        })
+    }
+
+    /// 算出第 `row` 行的下一代, 写进 `out`
+    fn run(&self, row: u32, out: &mut [u64]) {
+        let above = self.line(i64::from(row) - 1);
+        let below = self.line(i64::from(row) + 1);
+        let current = Some(self.grid.row(row));
+        for (w, slot) in out.iter_mut().enumerate() {
+            let mut counts = Counts::default();
+            counts.add(RowStep::word(above, w));
+            counts.add(RowStep::word(below, w));
+            counts.add(self.west(current, w));
+            counts.add(self.east(current, w));
+            if self.moore {
+                counts.add(self.west(above, w));
+                counts.add(self.east(above, w));
+                counts.add(self.west(below, w));
+                counts.add(self.east(below, w));
+            }
+            let alive = RowStep::word(current, w);
+            let mut next =
+                (alive & counts.matching(self.survive)) | (!alive & counts.matching(self.birth));
+            if w == self.last {
+                next &= self.mask;
+            }
+            *slot = next;
+        }
+    }
+}
@@ -0,0 +1,61 @@
+//! 代数和活细胞数存成原子变量, 拿到 `Universe::counters` 之后不拿面板的锁也能读
+
+use std::ops::Deref;
+use std::sync::atomic::{AtomicI64, AtomicU32, Ordering};
+use std::sync::Arc;
+
+/// 面板每次变化后更新. 两个数分开读写, 演化中途读到的可能分别属于相邻的两代
+#[derive(Debug, Default)]
+pub struct Counters {
+    generation: AtomicI64,
+    population: AtomicU32,
+}
+
+impl Counters {
+    pub fn generation(&self) -> i64 {
+        self.generation.load(Ordering::Relaxed)
+    }
+
+    pub fn population(&self) -> u32 {
+        self.population.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn set_generation(&self, generation: i64) {
+        self.generation.store(generation, Ordering::Relaxed);
+    }
+
+    pub(crate) fn set_population(&self, population: u32) {
+        self.population.store(population, Ordering::Relaxed);
+    }
+}
+
+/// `Universe` 持有的计数器. 复制面板时复制的是数值, 新面板演化不会改到原来的计数器
+#[derive(Debug, Default)]
+pub(crate) struct SharedCounters(Arc<Counters>);
+
+impl SharedCounters {
+    pub(crate) fn new(generation: i64, population: u32) -> SharedCounters {
+        let counters = SharedCounters::default();
+        counters.set_generation(generation);
+        counters.set_population(population);
+        counters
+    }
+
+    pub(crate) fn handle(&self) -> Arc<Counters> {
+        Arc::clone(&self.0)
+    }
+}
+
+impl Clone for SharedCounters {
+    fn clone(&self) -> SharedCounters {
+        SharedCounters::new(self.generation(), self.population())
+    }
+}
+
+impl Deref for SharedCounters {
+    type Target = Counters;
+
+    fn deref(&self) -> &Counters {
+        &self.0
+    }
+}
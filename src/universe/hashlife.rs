@@ -0,0 +1,577 @@
+//! HashLife: 把无边界的平面存成四叉树, 内容相同的子树只存一份, 每个节点记住
+//! 自己中间那一半在若干代之后的样子. 有规律的图案 (比如滑翔机枪) 可以一次跳过
+//! 2^k 代, 几百万代也只要很少的时间.
+//!
+//! 第 `level` 层的节点是 2^level x 2^level 的正方形, 第 0 层是单个格子.
+//! 节点按 (西北, 东北, 西南, 东南) 四个子节点去重, 存在 `nodes` 里, 用下标互相引用
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+
+use super::{Cell, Universe, MOORE, VON_NEUMANN};
+use crate::rule::{Neighborhood, Rule};
+
+/// `advance` 一次最多跳过 2^MAX_ADVANCE_LOG 代, 再大坐标就可能超出 `i64`
+pub const MAX_ADVANCE_LOG: u32 = 56;
+/// 节点数的默认上限, 超过之后下一次 `advance` 之前回收用不到的节点
+pub const DEFAULT_NODE_LIMIT: usize = 1 << 21;
+
+#[derive(Copy, PartialEq, Eq, Clone, Debug)]
+pub enum HashLifeError {
+    /// 只支持 Moore 或 von Neumann 邻域下两状态、不含 B0 的 B/S 规则
+    UnsupportedRule(Rule),
+    /// `advance` 的指数超过了 `MAX_ADVANCE_LOG`
+    StepTooLarge(u32),
+}
+
+impl fmt::Display for HashLifeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            HashLifeError::UnsupportedRule(rule) => {
+                write!(f, "rule {} cannot run on a HashLife plane", rule)
+            }
+            HashLifeError::StepTooLarge(log2) => write!(
+                f,
+                "cannot advance 2^{} generations at once, the limit is 2^{}",
+                log2, MAX_ADVANCE_LOG
+            ),
+        }
+    }
+}
+
+impl Error for HashLifeError {}
+
+type NodeId = u32;
+
+/// 没有子节点, 或者还没算过下一代
+const NONE: NodeId = NodeId::MAX;
+const DEAD: NodeId = 0;
+const ALIVE: NodeId = 1;
+
+#[derive(Copy, Clone, Debug)]
+struct Node {
+    /// 西北, 东北, 西南, 东南
+    children: [NodeId; 4],
+    level: u8,
+    population: u64,
+    /// 中间那一半在 2^min(step_log, level - 2) 代之后的样子
+    next: NodeId,
+}
+
+impl Node {
+    fn leaf(population: u64) -> Node {
+        Node {
+            children: [NONE; 4],
+            level: 0,
+            population,
+            next: NONE,
+        }
+    }
+}
+
+/// 用 HashLife 演化的无边界平面, 坐标 (row, col) 可以是负数
+#[derive(Clone, Debug)]
+pub struct HashLifeUniverse {
+    nodes: Vec<Node>,
+    /// 四个子节点到节点的下标, 保证相同的子树只有一份
+    table: HashMap<[NodeId; 4], NodeId>,
+    /// 每一层全空的节点
+    empty: Vec<NodeId>,
+    /// 以原点为中心的根节点, 第 `level` 层的根覆盖 [-2^(level-1), 2^(level-1)) 的行和列
+    root: NodeId,
+    /// 节点里记下的 `next` 是按这个指数算的
+    step_log: u32,
+    node_limit: usize,
+    rule: Rule,
+    birth: u16,
+    survive: u16,
+    moore: bool,
+    count: i64,
+}
+
+impl Default for HashLifeUniverse {
+    fn default() -> HashLifeUniverse {
+        HashLifeUniverse::new()
+    }
+}
+
+impl HashLifeUniverse {
+    pub fn new() -> HashLifeUniverse {
+        let mut u = HashLifeUniverse {
+            nodes: Vec::new(),
+            table: HashMap::new(),
+            empty: Vec::new(),
+            root: NONE,
+            step_log: 0,
+            node_limit: DEFAULT_NODE_LIMIT,
+            rule: Rule::CONWAY,
+            birth: 0,
+            survive: 0,
+            moore: true,
+            count: 0,
+        };
+        u.set_rule(Rule::CONWAY).expect("Conway is supported");
+        u.clear();
+        u
+    }
+
+    pub fn with_rule(rule: Rule) -> Result<HashLifeUniverse, HashLifeError> {
+        let mut u = HashLifeUniverse::new();
+        u.set_rule(rule)?;
+        Ok(u)
+    }
+
+    pub fn rule(&self) -> Rule {
+        self.rule
+    }
+
+    /// 换规则会丢掉所有记下的结果
+    pub fn set_rule(&mut self, rule: Rule) -> Result<(), HashLifeError> {
+        match rule {
+            Rule::Life {
+                birth,
+                survive,
+                neighborhood,
+            } if !rule.has_b0() && neighborhood != Neighborhood::Hexagonal => {
+                self.rule = rule;
+                self.birth = birth;
+                self.survive = survive;
+                self.moore = neighborhood == Neighborhood::Moore;
+                self.clear_results();
+                Ok(())
+            }
+            _ => Err(HashLifeError::UnsupportedRule(rule)),
+        }
+    }
+
+    pub fn count(&self) -> i64 {
+        self.count
+    }
+
+    pub fn population(&self) -> u64 {
+        self.nodes[self.root as usize].population
+    }
+
+    /// 现在存着的节点数, 包括已经用不到、还没回收的
+    pub fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn node_limit(&self) -> usize {
+        self.node_limit
+    }
+
+    /// 节点数超过 `limit` 时, 下一次 `advance` 之前只保留当前图案用到的节点,
+    /// 记下的结果全部丢掉. 一次 `advance` 中间不会回收
+    pub fn set_node_limit(&mut self, limit: usize) {
+        self.node_limit = limit;
+    }
+
+    /// 清空平面, 代数归零
+    pub fn clear(&mut self) {
+        self.nodes.clear();
+        self.table.clear();
+        self.empty.clear();
+        self.nodes.push(Node::leaf(0));
+        self.nodes.push(Node::leaf(1));
+        self.root = self.empty_node(3);
+        self.count = 0;
+    }
+
+    fn node(&self, id: NodeId) -> Node {
+        self.nodes[id as usize]
+    }
+
+    fn level(&self) -> u32 {
+        u32::from(self.node(self.root).level)
+    }
+
+    /// 根节点一半的边长, 即行和列的范围是 [-half, half)
+    fn half(&self) -> i64 {
+        1 << (self.level() - 1)
+    }
+
+    fn join(&mut self, children: [NodeId; 4]) -> NodeId {
+        if let Some(&id) = self.table.get(&children) {
+            return id;
+        }
+        let level = self.node(children[0]).level + 1;
+        let population = children.iter().map(|&c| self.node(c).population).sum();
+        let id = self.nodes.len() as NodeId;
+        self.nodes.push(Node {
+            children,
+            level,
+            population,
+            next: NONE,
+        });
+        self.table.insert(children, id);
+        id
+    }
+
+    fn empty_node(&mut self, level: u8) -> NodeId {
+        if self.empty.is_empty() {
+            self.empty.push(DEAD);
+        }
+        while self.empty.len() <= level as usize {
+            let below = *self.empty.last().expect("level 0 is always there");
+            let id = self.join([below; 4]);
+            self.empty.push(id);
+        }
+        self.empty[level as usize]
+    }
+
+    /// 根节点往外扩一层, 原来的内容留在中间
+    fn expand(&mut self) {
+        let level = self.node(self.root).level;
+        let e = self.empty_node(level - 1);
+        let [nw, ne, sw, se] = self.node(self.root).children;
+        let children = [
+            self.join([e, e, e, nw]),
+            self.join([e, e, ne, e]),
+            self.join([e, sw, e, e]),
+            self.join([se, e, e, e]),
+        ];
+        self.root = self.join(children);
+    }
+
+    pub fn get_cell(&self, row: i64, col: i64) -> Cell {
+        let half = self.half();
+        if row < -half || row >= half || col < -half || col >= half {
+            return Cell::Dead;
+        }
+        let (mut r, mut c) = ((row + half) as u64, (col + half) as u64);
+        let mut id = self.root;
+        loop {
+            let node = self.node(id);
+            if node.population == 0 {
+                return Cell::Dead;
+            }
+            if node.level == 0 {
+                return Cell::Alive;
+            }
+            let h = 1 << (node.level - 1);
+            id = node.children[quadrant(r, c, h)];
+            r %= h;
+            c %= h;
+        }
+    }
+
+    pub fn set_cell(&mut self, row: i64, col: i64, cell: Cell) {
+        while {
+            let half = self.half();
+            row < -half || row >= half || col < -half || col >= half
+        } {
+            self.expand();
+        }
+        let half = self.half();
+        self.root = self.set_in(self.root, (row + half) as u64, (col + half) as u64, cell);
+    }
+
+    fn set_in(&mut self, id: NodeId, r: u64, c: u64, cell: Cell) -> NodeId {
+        let node = self.node(id);
+        if node.level == 0 {
+            return match cell {
+                Cell::Alive => ALIVE,
+                Cell::Dead => DEAD,
+            };
+        }
+        let h = 1 << (node.level - 1);
+        let mut children = node.children;
+        let q = quadrant(r, c, h);
+        children[q] = self.set_in(children[q], r % h, c % h, cell);
+        self.join(children)
+    }
+
+    /// 把图案的活细胞放到以 (`origin_row`, `origin_col`) 为左上角的位置
+    pub fn insert_pattern(&mut self, pattern: &[(u32, u32)], origin_row: i64, origin_col: i64) {
+        for &(row, col) in pattern {
+            self.set_cell(
+                origin_row + i64::from(row),
+                origin_col + i64::from(col),
+                Cell::Alive,
+            );
+        }
+    }
+
+    /// 所有活细胞的 (row, col), 按行优先排好序
+    pub fn live_cells(&self) -> Vec<(i64, i64)> {
+        let half = self.half();
+        let mut cells = Vec::new();
+        self.visit(self.root, -half, -half, &mut |row, col| {
+            cells.push((row, col))
+        });
+        cells.sort_unstable();
+        cells
+    }
+
+    /// 对左上角在 (`top`, `left`) 的节点里的每个活细胞调用 `f`
+    fn visit(&self, id: NodeId, top: i64, left: i64, f: &mut dyn FnMut(i64, i64)) {
+        let node = self.node(id);
+        if node.population == 0 {
+            return;
+        }
+        if node.level == 0 {
+            f(top, left);
+            return;
+        }
+        let h = 1i64 << (node.level - 1);
+        for (q, &child) in node.children.iter().enumerate() {
+            let (dr, dc) = ((q / 2) as i64 * h, (q % 2) as i64 * h);
+            self.visit(child, top + dr, left + dc, f);
+        }
+    }
+
+    /// 推进 2^`log2` 代. 根节点先扩大到图案和它在这段时间里能走到的地方都落在中间,
+    /// 再一次取出中间那一半的结果
+    pub fn advance(&mut self, log2: u32) -> Result<(), HashLifeError> {
+        if log2 > MAX_ADVANCE_LOG {
+            return Err(HashLifeError::StepTooLarge(log2));
+        }
+        if self.nodes.len() > self.node_limit {
+            self.collect();
+        }
+        if self.step_log != log2 {
+            self.step_log = log2;
+            self.clear_results();
+        }
+        // 活细胞每代最多走一格, 图案落在中间四分之一里、根节点又够大时,
+        // 2^log2 代之后它仍然在中间那一半里
+        while self.level() < log2 + 3 || !self.padded() {
+            self.expand();
+        }
+        self.root = self.successor(self.root);
+        self.count += 1 << log2;
+        Ok(())
+    }
+
+    /// 推进 `n` 代, 按二进制拆成若干次 `advance`. `n` 不小于 2^(MAX_ADVANCE_LOG + 1) 时
+    /// 一代也不走, 返回最高位对应的 `StepTooLarge`
+    pub fn step_n(&mut self, n: u64) -> Result<(), HashLifeError> {
+        let top = 63 - n.leading_zeros().min(63);
+        if top > MAX_ADVANCE_LOG {
+            return Err(HashLifeError::StepTooLarge(top));
+        }
+        for log2 in 0..=top {
+            if n & (1 << log2) != 0 {
+                self.advance(log2)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// 所有活细胞都在根节点中间四分之一的正方形里
+    fn padded(&mut self) -> bool {
+        let centre = self.centre(self.root);
+        let inner = self.centre(centre);
+        self.node(inner).population == self.population()
+    }
+
+    /// 节点中间那一半, 低一层
+    fn centre(&mut self, id: NodeId) -> NodeId {
+        let [nw, ne, sw, se] = self.node(id).children;
+        self.join([
+            self.node(nw).children[3],
+            self.node(ne).children[2],
+            self.node(sw).children[1],
+            self.node(se).children[0],
+        ])
+    }
+
+    /// 左右并排的两个节点之间、跨在接缝上的那一块, 和它们同一层
+    fn between_columns(&mut self, west: NodeId, east: NodeId) -> NodeId {
+        let (w, e) = (self.node(west).children, self.node(east).children);
+        self.join([w[1], e[0], w[3], e[2]])
+    }
+
+    /// 上下叠着的两个节点之间的那一块
+    fn between_rows(&mut self, north: NodeId, south: NodeId) -> NodeId {
+        let (n, s) = (self.node(north).children, self.node(south).children);
+        self.join([n[2], n[3], s[0], s[1]])
+    }
+
+    /// 节点中间那一半在 2^min(step_log, level - 2) 代之后的样子
+    fn successor(&mut self, id: NodeId) -> NodeId {
+        let node = self.node(id);
+        if node.next != NONE {
+            return node.next;
+        }
+        let result = if node.population == 0 {
+            self.empty_node(node.level - 1)
+        } else if node.level == 2 {
+            self.step_leaves(id)
+        } else {
+            let [nw, ne, sw, se] = node.children;
+            let north = self.between_columns(nw, ne);
+            let west = self.between_rows(nw, sw);
+            let centre = self.centre(id);
+            let east = self.between_rows(ne, se);
+            let south = self.between_columns(sw, se);
+            let mut parts = [nw, north, ne, west, centre, east, sw, south, se];
+            for part in parts.iter_mut() {
+                *part = self.successor(*part);
+            }
+            // 九块各自推进了一半的代数, 拼成四块之后还差的一半要么再推进,
+            // 要么 (步长比这一层小时) 直接取中间
+            let full = self.step_log + 2 >= u32::from(node.level);
+            let mut quarters = [NONE; 4];
+            for (q, slot) in quarters.iter_mut().enumerate() {
+                let (r, c) = (q / 2, q % 2);
+                let i = r * 3 + c;
+                let joined = self.join([parts[i], parts[i + 1], parts[i + 3], parts[i + 4]]);
+                *slot = if full {
+                    self.successor(joined)
+                } else {
+                    self.centre(joined)
+                };
+            }
+            self.join(quarters)
+        };
+        self.nodes[id as usize].next = result;
+        result
+    }
+
+    /// 4x4 的节点中间 2x2 的下一代
+    fn step_leaves(&mut self, id: NodeId) -> NodeId {
+        let mut grid = [[false; 4]; 4];
+        for (q, &child) in self.node(id).children.iter().enumerate() {
+            for (k, &leaf) in self.node(child).children.iter().enumerate() {
+                grid[q / 2 * 2 + k / 2][q % 2 * 2 + k % 2] = leaf == ALIVE;
+            }
+        }
+        let offsets: &[(i64, i64)] = if self.moore { &MOORE } else { &VON_NEUMANN };
+        let mut out = [DEAD; 4];
+        for (k, slot) in out.iter_mut().enumerate() {
+            let (row, col) = (1 + k as i64 / 2, 1 + k as i64 % 2);
+            let n = offsets
+                .iter()
+                .filter(|&&(dr, dc)| grid[(row + dr) as usize][(col + dc) as usize])
+                .count();
+            let mask = if grid[row as usize][col as usize] {
+                self.survive
+            } else {
+                self.birth
+            };
+            if mask & (1 << n) != 0 {
+                *slot = ALIVE;
+            }
+        }
+        self.join(out)
+    }
+
+    fn clear_results(&mut self) {
+        for node in self.nodes.iter_mut() {
+            node.next = NONE;
+        }
+    }
+
+    /// 只留下根节点用到的节点, 重新编号
+    fn collect(&mut self) {
+        let old = std::mem::take(&mut self.nodes);
+        let mut moved = HashMap::new();
+        self.table.clear();
+        self.empty.clear();
+        self.nodes.push(Node::leaf(0));
+        self.nodes.push(Node::leaf(1));
+        moved.insert(DEAD, DEAD);
+        moved.insert(ALIVE, ALIVE);
+        self.root = self.copy_from(&old, self.root, &mut moved);
+    }
+
+    fn copy_from(
+        &mut self,
+        old: &[Node],
+        id: NodeId,
+        moved: &mut HashMap<NodeId, NodeId>,
+    ) -> NodeId {
+        if let Some(&new) = moved.get(&id) {
+            return new;
+        }
+        let mut children = old[id as usize].children;
+        for child in children.iter_mut() {
+            *child = self.copy_from(old, *child, moved);
+        }
+        let new = self.join(children);
+        moved.insert(id, new);
+        new
+    }
+
+    /// 把 `u` 的整个面板当作以 (`top`, `left`) 为左上角的一块区域读进来,
+    /// 规则和代数也一起带过来
+    pub fn from_dense(
+        u: &Universe,
+        top: i64,
+        left: i64,
+    ) -> Result<HashLifeUniverse, HashLifeError> {
+        let mut hashlife = HashLifeUniverse::with_rule(u.rule())?;
+        hashlife.store_viewport(u, top, left);
+        hashlife.count = u.count();
+        Ok(hashlife)
+    }
+
+    /// 用 `u` 的面板覆盖以 (`top`, `left`) 为左上角、同样大小的区域
+    pub fn store_viewport(&mut self, u: &Universe, top: i64, left: i64) {
+        for row in 0..u.height() {
+            for col in 0..u.width() {
+                let (r, c) = (top + i64::from(row), left + i64::from(col));
+                let cell = u.cells[u.get_index(row, col)];
+                if cell != self.get_cell(r, c) {
+                    self.set_cell(r, c, cell);
+                }
+            }
+        }
+    }
+
+    /// 以 (`top`, `left`) 为左上角、`height` 行 `width` 列的区域, 作为一个普通的宇宙
+    pub fn to_dense(&self, top: i64, left: i64, width: u32, height: u32) -> Universe {
+        let mut cells = vec![Cell::Dead; width as usize * height as usize];
+        let half = self.half();
+        let (bottom, right) = (top + i64::from(height), left + i64::from(width));
+        self.visit_region(
+            self.root,
+            -half,
+            -half,
+            (top, left, bottom, right),
+            &mut |r, c| {
+                cells[(r - top) as usize * width as usize + (c - left) as usize] = Cell::Alive;
+            },
+        );
+        let mut u =
+            Universe::from_cells(width, height, cells).expect("cell count matches the size");
+        u.rule = self.rule;
+        u.counters.set_generation(self.count);
+        u
+    }
+
+    /// 同 `visit`, 但跳过和 [top, bottom) x [left, right) 不相交的子树
+    fn visit_region(
+        &self,
+        id: NodeId,
+        top: i64,
+        left: i64,
+        region: (i64, i64, i64, i64),
+        f: &mut dyn FnMut(i64, i64),
+    ) {
+        let node = self.node(id);
+        let size = 1i64 << node.level;
+        let (r0, c0, r1, c1) = region;
+        if node.population == 0 || top >= r1 || left >= c1 || top + size <= r0 || left + size <= c0
+        {
+            return;
+        }
+        if node.level == 0 {
+            f(top, left);
+            return;
+        }
+        let h = size / 2;
+        for (q, &child) in node.children.iter().enumerate() {
+            let (dr, dc) = ((q / 2) as i64 * h, (q % 2) as i64 * h);
+            self.visit_region(child, top + dr, left + dc, region, f);
+        }
+    }
+}
+
+/// 节点内 (`r`, `c`) 落在哪个子节点, `h` 是子节点的边长
+fn quadrant(r: u64, c: u64, h: u64) -> usize {
+    (r >= h) as usize * 2 + (c >= h) as usize
+}
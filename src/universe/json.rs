@@ -0,0 +1,76 @@
+//! 给外部工具用的 JSON 格式, 只记录活细胞的坐标:
+//!
+//! ```json
+//! { "width": 64, "height": 64, "generation": 10, "alive": [[0, 1], [2, 3]] }
+//! ```
+
+use std::error::Error;
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use super::{Cell, Universe};
+
+#[derive(Serialize, Deserialize)]
+struct Board {
+    width: u32,
+    height: u32,
+    generation: i64,
+    alive: Vec<(u32, u32)>,
+}
+
+#[derive(Debug)]
+pub enum JsonError {
+    Syntax(serde_json::Error),
+    /// 宽或高为 0
+    Empty,
+    /// 活细胞坐标超出了声明的尺寸
+    OutOfRange {
+        row: u32,
+        col: u32,
+    },
+}
+
+impl fmt::Display for JsonError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            JsonError::Syntax(e) => write!(f, "{}", e),
+            JsonError::Empty => write!(f, "width and height must be positive"),
+            JsonError::OutOfRange { row, col } => {
+                write!(f, "alive cell [{}, {}] is outside the board", row, col)
+            }
+        }
+    }
+}
+
+impl Error for JsonError {}
+
+impl Universe {
+    pub fn to_json(&self) -> String {
+        let board = Board {
+            width: self.width,
+            height: self.height,
+            generation: self.count(),
+            alive: self.live_cells().collect(),
+        };
+        serde_json::to_string(&board).expect("board serializes to JSON")
+    }
+
+    pub fn from_json(text: &str) -> Result<Universe, JsonError> {
+        let board: Board = serde_json::from_str(text).map_err(JsonError::Syntax)?;
+        if board.width == 0 || board.height == 0 {
+            return Err(JsonError::Empty);
+        }
+        let mut cells = vec![Cell::Dead; board.width as usize * board.height as usize];
+        for &(row, col) in &board.alive {
+            if row >= board.height || col >= board.width {
+                return Err(JsonError::OutOfRange { row, col });
+            }
+            cells[(row * board.width + col) as usize] = Cell::Alive;
+        }
+        let u = Universe::from_cells(board.width, board.height, cells)
+            .expect("cell count matches the board size");
+        u.counters.set_generation(board.generation);
+        Ok(u)
+    }
+}
@@ -0,0 +1,90 @@
+//! 用图片生成面板: 按最近邻采样缩放到网格大小, 比阈值暗的像素为活细胞
+
+use std::path::Path;
+
+use image::{DynamicImage, GenericImageView, ImageError, ImageReader, Limits};
+
+use super::{Cell, Universe, DEFAULT_SIZE};
+
+/// 图片的最大边长, 更大的图片在解码前就拒绝, 避免占用过多内存
+const MAX_IMAGE_SIDE: u32 = 16384;
+
+#[derive(Clone, Copy, Debug)]
+pub struct ImageOptions {
+    pub width: u32,
+    pub height: u32,
+    /// 亮度低于此值的像素为活细胞
+    pub threshold: u8,
+    /// 图片比网格小时是否放大. 为 false 时按原尺寸居中放置
+    pub scale_up: bool,
+}
+
+impl Default for ImageOptions {
+    fn default() -> ImageOptions {
+        ImageOptions {
+            width: DEFAULT_SIZE,
+            height: DEFAULT_SIZE,
+            threshold: 128,
+            scale_up: true,
+        }
+    }
+}
+
+impl Universe {
+    /// 读取 PNG/BMP/JPEG 图片, 生成默认大小的面板
+    pub fn from_image(path: &Path, threshold: u8) -> Result<Universe, ImageError> {
+        let options = ImageOptions {
+            threshold,
+            ..ImageOptions::default()
+        };
+        Universe::from_image_with(path, &options)
+    }
+
+    pub fn from_image_with(path: &Path, options: &ImageOptions) -> Result<Universe, ImageError> {
+        let mut limits = Limits::default();
+        limits.max_image_width = Some(MAX_IMAGE_SIDE);
+        limits.max_image_height = Some(MAX_IMAGE_SIDE);
+        let mut reader = ImageReader::open(path)?.with_guessed_format()?;
+        reader.limits(limits);
+        Ok(Universe::from_dynamic_image(&reader.decode()?, options))
+    }
+
+    /// 只对落在网格上的像素采样, 处理的像素数与图片大小无关. 半透明以下的像素为死细胞
+    pub fn from_dynamic_image(image: &DynamicImage, options: &ImageOptions) -> Universe {
+        assert!(
+            options.width > 0 && options.height > 0,
+            "universe size must be positive"
+        );
+        let (width, height) = (options.width, options.height);
+        let (img_w, img_h) = image.dimensions();
+        let mut cells = vec![Cell::Dead; width as usize * height as usize];
+        if img_w > 0 && img_h > 0 {
+            sample(image, width, options, &mut cells);
+        }
+        Universe::from_cells(width, height, cells).expect("cell count matches the grid size")
+    }
+}
+
+/// 把采样结果写进 `cells`, 它的大小是 `width` x `options.height`
+fn sample(image: &DynamicImage, width: u32, options: &ImageOptions, cells: &mut [Cell]) {
+    let height = options.height;
+    let (img_w, img_h) = image.dimensions();
+    // 小图片不放大时原样居中, 否则拉伸到整个网格
+    let fits = img_w <= width && img_h <= height;
+    let (out_w, out_h, left, top) = if fits && !options.scale_up {
+        (img_w, img_h, (width - img_w) / 2, (height - img_h) / 2)
+    } else {
+        (width, height, 0, 0)
+    };
+    for row in 0..out_h {
+        let y = (u64::from(row) * u64::from(img_h) / u64::from(out_h)) as u32;
+        for col in 0..out_w {
+            let x = (u64::from(col) * u64::from(img_w) / u64::from(out_w)) as u32;
+            let [r, g, b, a] = image.get_pixel(x, y).0;
+            let luma = (299 * u32::from(r) + 587 * u32::from(g) + 114 * u32::from(b)) / 1000;
+            if a >= 128 && luma < u32::from(options.threshold) {
+                cells[((top + row) * width + left + col) as usize] = Cell::Alive;
+            }
+        }
+    }
+}
@@ -0,0 +1,159 @@
+//! 某一代面板的只读副本. 拿着锁的时候只复制细胞, 之后画多久都不会挡住演化
+
+use std::sync::Arc;
+
+use super::{pack_cells, Topology, Universe, HEX_EVEN, HEX_ODD, MOORE, VON_NEUMANN};
+use crate::rule::{Neighborhood, Rule};
+
+/// `Universe::snapshot` 的结果, 可以交给别的线程慢慢读
+#[derive(Clone, Debug)]
+pub struct FrameSnapshot {
+    width: u32,
+    height: u32,
+    /// 按 `pack_cells` 每 8 个格子打包成一个字节
+    packed: Vec<u8>,
+    /// 多状态规则下格子的额外状态, 同 `Universe` 里的 `extra`; 两状态的规则为空
+    extra: Vec<u8>,
+    /// 同 `Universe` 里的 `ages`, 为空时都当作 0
+    ages: Vec<u16>,
+    /// 同 `Universe` 里的 `decay`, 为空时都没有痕迹
+    decay: Vec<u8>,
+    /// 同 `Universe` 里的 `activity`, 为空时都是 0
+    activity: Vec<u16>,
+    count: i64,
+    population: u32,
+    rule: Rule,
+    topology: Topology,
+    /// 拍快照时还没画过的变化, 见 `Universe::changed_cells`
+    changes: Vec<(u32, u32)>,
+    redraw: bool,
+}
+
+impl FrameSnapshot {
+    pub(crate) fn new(u: &Universe) -> Arc<FrameSnapshot> {
+        Arc::new(FrameSnapshot {
+            width: u.width,
+            height: u.height,
+            packed: pack_cells(&u.cells),
+            extra: u.extra.clone(),
+            ages: if u.ages.len() == u.cells.len() {
+                u.ages.clone()
+            } else {
+                Vec::new()
+            },
+            decay: if u.decay.len() == u.cells.len() {
+                u.decay.clone()
+            } else {
+                Vec::new()
+            },
+            activity: if u.activity.len() == u.cells.len() {
+                u.activity.clone()
+            } else {
+                Vec::new()
+            },
+            count: u.count(),
+            population: u.population(),
+            rule: u.rule,
+            topology: u.topology,
+            changes: u.changes.clone(),
+            redraw: u.redraw,
+        })
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// 拍快照时的代数
+    pub fn generation(&self) -> i64 {
+        self.count
+    }
+
+    pub fn population(&self) -> u32 {
+        self.population
+    }
+
+    pub fn rule(&self) -> Rule {
+        self.rule
+    }
+
+    pub fn topology(&self) -> Topology {
+        self.topology
+    }
+
+    /// 同 `Universe::state`, 越界时返回 None
+    pub fn state(&self, row: u32, col: u32) -> Option<u8> {
+        if row >= self.height || col >= self.width {
+            return None;
+        }
+        let idx = (row * self.width + col) as usize;
+        match self.extra.get(idx) {
+            Some(&state) if state != 0 => Some(state),
+            _ => Some((self.packed[idx / 8] >> (7 - idx % 8)) & 1),
+        }
+    }
+
+    /// 同 `Universe::live_neighbor_count`, 越界时返回 0
+    pub fn live_neighbor_count(&self, row: u32, col: u32) -> u8 {
+        if row >= self.height || col >= self.width {
+            return 0;
+        }
+        let offsets: &[(i64, i64)] = match self.rule.neighborhood() {
+            Neighborhood::Moore => &MOORE,
+            Neighborhood::VonNeumann => &VON_NEUMANN,
+            Neighborhood::Hexagonal if row % 2 == 0 => &HEX_EVEN,
+            Neighborhood::Hexagonal => &HEX_ODD,
+        };
+        offsets
+            .iter()
+            .filter_map(|&(d_row, d_col)| {
+                let r = self.topology.resolve(i64::from(row) + d_row, self.height)?;
+                let c = self.topology.resolve(i64::from(col) + d_col, self.width)?;
+                let idx = (r * self.width + c) as usize;
+                Some((self.packed[idx / 8] >> (7 - idx % 8)) & 1)
+            })
+            .sum()
+    }
+
+    /// 同 `Universe::cell_age`
+    pub fn cell_age(&self, row: u32, col: u32) -> Option<u16> {
+        if self.state(row, col)? != 1 {
+            return None;
+        }
+        let idx = (row * self.width + col) as usize;
+        Some(self.ages.get(idx).copied().unwrap_or(0))
+    }
+
+    /// 同 `Universe::cell_decay`
+    pub fn cell_decay(&self, row: u32, col: u32) -> Option<u8> {
+        if row >= self.height || col >= self.width {
+            return None;
+        }
+        match self.decay.get((row * self.width + col) as usize) {
+            Some(&decay) if decay > 0 => Some(decay),
+            _ => None,
+        }
+    }
+
+    /// 同 `Universe::cell_activity`
+    pub fn cell_activity(&self, row: u32, col: u32) -> Option<u16> {
+        if row >= self.height || col >= self.width {
+            return None;
+        }
+        Some(self.activity.get((row * self.width + col) as usize).copied().unwrap_or(0))
+    }
+
+    /// 拍快照时 `Universe::changed_cells` 的内容
+    pub fn changed_cells(&self) -> &[(u32, u32)] {
+        &self.changes
+    }
+
+    /// 拍快照时 `Universe::needs_redraw` 的结果
+    pub fn needs_redraw(&self) -> bool {
+        self.redraw
+    }
+}
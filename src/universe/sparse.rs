@@ -0,0 +1,342 @@
+//! 无边界的宇宙. 活细胞按 64x64 的块存在 HashMap 里, 坐标是有符号的,
+//! 每一代只处理有活细胞的块和边上有活细胞的相邻块.
+//!
+//! 块里每一行是一个 `u64`, 第 `c` 位是第 `c` 列. 演化时整行一起算:
+//! 把八个方向的邻居行按位相加成四个位平面, 再按规则挑出下一代的活细胞
+
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fmt;
+
+use super::bitgrid::Counts;
+use super::{Cell, Universe};
+use crate::rule::{Neighborhood, Rule};
+
+/// 块的边长
+pub const CHUNK_SIZE: i64 = 64;
+
+type Chunk = [u64; CHUNK_SIZE as usize];
+
+#[derive(Copy, PartialEq, Eq, Clone, Debug)]
+pub enum SparseError {
+    /// 只支持 Moore 或 von Neumann 邻域下两状态、不含 B0 的 B/S 规则.
+    /// B0 会让无限的背景全部亮起来
+    UnsupportedRule(Rule),
+}
+
+impl fmt::Display for SparseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SparseError::UnsupportedRule(rule) => {
+                write!(f, "rule {} cannot run on an unbounded plane", rule)
+            }
+        }
+    }
+}
+
+impl Error for SparseError {}
+
+/// 无边界的平面, 坐标 (row, col) 可以是负数
+#[derive(Clone, Debug)]
+pub struct SparseUniverse {
+    /// 以 (chunk_x, chunk_y) 为键, 即列和行各自除以 `CHUNK_SIZE` 向下取整
+    chunks: HashMap<(i64, i64), Chunk>,
+    rule: Rule,
+    count: i64,
+    population: u64,
+}
+
+impl Default for SparseUniverse {
+    fn default() -> SparseUniverse {
+        SparseUniverse::new()
+    }
+}
+
+/// 格子所在的块和块内的行列
+fn locate(row: i64, col: i64) -> ((i64, i64), usize, usize) {
+    (
+        (col.div_euclid(CHUNK_SIZE), row.div_euclid(CHUNK_SIZE)),
+        row.rem_euclid(CHUNK_SIZE) as usize,
+        col.rem_euclid(CHUNK_SIZE) as usize,
+    )
+}
+
+impl SparseUniverse {
+    pub fn new() -> SparseUniverse {
+        SparseUniverse {
+            chunks: HashMap::new(),
+            rule: Rule::CONWAY,
+            count: 0,
+            population: 0,
+        }
+    }
+
+    pub fn with_rule(rule: Rule) -> Result<SparseUniverse, SparseError> {
+        let mut u = SparseUniverse::new();
+        u.set_rule(rule)?;
+        Ok(u)
+    }
+
+    pub fn rule(&self) -> Rule {
+        self.rule
+    }
+
+    pub fn set_rule(&mut self, rule: Rule) -> Result<(), SparseError> {
+        match rule {
+            Rule::Life { neighborhood, .. }
+                if !rule.has_b0() && neighborhood != Neighborhood::Hexagonal =>
+            {
+                self.rule = rule;
+                Ok(())
+            }
+            _ => Err(SparseError::UnsupportedRule(rule)),
+        }
+    }
+
+    pub fn count(&self) -> i64 {
+        self.count
+    }
+
+    pub fn population(&self) -> u64 {
+        self.population
+    }
+
+    /// 存着的块数, 没有活细胞的块会在演化时被丢掉
+    pub fn chunk_count(&self) -> usize {
+        self.chunks.len()
+    }
+
+    pub fn get_cell(&self, row: i64, col: i64) -> Cell {
+        let (key, r, c) = locate(row, col);
+        match self.chunks.get(&key) {
+            Some(chunk) if chunk[r] & (1 << c) != 0 => Cell::Alive,
+            _ => Cell::Dead,
+        }
+    }
+
+    pub fn set_cell(&mut self, row: i64, col: i64, cell: Cell) {
+        let (key, r, c) = locate(row, col);
+        let was_alive = self.get_cell(row, col) == Cell::Alive;
+        match (cell, was_alive) {
+            (Cell::Alive, false) => {
+                self.chunks.entry(key).or_insert([0; CHUNK_SIZE as usize])[r] |= 1 << c;
+                self.population += 1;
+            }
+            (Cell::Dead, true) => {
+                let chunk = self.chunks.get_mut(&key).expect("live cell has a chunk");
+                chunk[r] &= !(1 << c);
+                if chunk.iter().all(|&line| line == 0) {
+                    self.chunks.remove(&key);
+                }
+                self.population -= 1;
+            }
+            _ => {}
+        }
+    }
+
+    /// 把图案的活细胞放到以 (`origin_row`, `origin_col`) 为左上角的位置
+    pub fn insert_pattern(&mut self, pattern: &[(u32, u32)], origin_row: i64, origin_col: i64) {
+        for &(row, col) in pattern {
+            self.set_cell(
+                origin_row + i64::from(row),
+                origin_col + i64::from(col),
+                Cell::Alive,
+            );
+        }
+    }
+
+    /// 所有活细胞的 (row, col), 顺序不固定
+    pub fn live_cells(&self) -> impl Iterator<Item = (i64, i64)> + '_ {
+        self.chunks.iter().flat_map(|(&(chunk_x, chunk_y), chunk)| {
+            chunk.iter().enumerate().flat_map(move |(r, &line)| {
+                (0..CHUNK_SIZE as usize)
+                    .filter(move |&c| line & (1 << c) != 0)
+                    .map(move |c| {
+                        (
+                            chunk_y * CHUNK_SIZE + r as i64,
+                            chunk_x * CHUNK_SIZE + c as i64,
+                        )
+                    })
+            })
+        })
+    }
+
+    /// 活细胞的外接矩形 (min_row, min_col, max_row, max_col), 没有活细胞时返回 `None`
+    pub fn bounding_box(&self) -> Option<(i64, i64, i64, i64)> {
+        let mut cells = self.live_cells();
+        let (row, col) = cells.next()?;
+        Some(
+            cells.fold((row, col, row, col), |(r0, c0, r1, c1), (r, c)| {
+                (r0.min(r), c0.min(c), r1.max(r), c1.max(c))
+            }),
+        )
+    }
+
+    pub fn tick(&mut self) {
+        let table = self.rule.table();
+        let birth: Vec<usize> = (0..9).filter(|&n| table[0][n] == Cell::Alive).collect();
+        let survive: Vec<usize> = (0..9).filter(|&n| table[1][n] == Cell::Alive).collect();
+        let moore = self.rule.neighborhood() == Neighborhood::Moore;
+
+        let mut next = HashMap::with_capacity(self.chunks.len());
+        for key in self.active_chunks() {
+            let chunk = self.step_chunk(key, moore, &birth, &survive);
+            if chunk.iter().any(|&line| line != 0) {
+                next.insert(key, chunk);
+            }
+        }
+        self.chunks = next;
+        self.count += 1;
+        self.population = self
+            .chunks
+            .values()
+            .flat_map(|chunk| chunk.iter())
+            .map(|line| u64::from(line.count_ones()))
+            .sum();
+    }
+
+    pub fn step_n(&mut self, n: u64) {
+        for _ in 0..n {
+            self.tick();
+        }
+    }
+
+    /// 下一代可能有活细胞的块: 现有的块, 以及边缘上有活细胞的那一侧的相邻块
+    fn active_chunks(&self) -> HashSet<(i64, i64)> {
+        const LAST: usize = CHUNK_SIZE as usize - 1;
+        let mut active = HashSet::with_capacity(self.chunks.len() * 2);
+        for (&(x, y), chunk) in &self.chunks {
+            active.insert((x, y));
+            let west = chunk.iter().any(|&line| line & 1 != 0);
+            let east = chunk.iter().any(|&line| line >> LAST != 0);
+            let north = chunk[0] != 0;
+            let south = chunk[LAST] != 0;
+            let sides = [
+                (west, (-1, 0)),
+                (east, (1, 0)),
+                (north, (0, -1)),
+                (south, (0, 1)),
+                (chunk[0] & 1 != 0, (-1, -1)),
+                (chunk[0] >> LAST != 0, (1, -1)),
+                (chunk[LAST] & 1 != 0, (-1, 1)),
+                (chunk[LAST] >> LAST != 0, (1, 1)),
+            ];
+            for &(touches, (dx, dy)) in sides.iter() {
+                if touches {
+                    active.insert((x + dx, y + dy));
+                }
+            }
+        }
+        active
+    }
+
+    fn step_chunk(
+        &self,
+        (x, y): (i64, i64),
+        moore: bool,
+        birth: &[usize],
+        survive: &[usize],
+    ) -> Chunk {
+        // 周围 3x3 个块, neighbors[dy + 1][dx + 1]
+        let mut neighbors = [[None; 3]; 3];
+        for (dy, line) in neighbors.iter_mut().enumerate() {
+            for (dx, slot) in line.iter_mut().enumerate() {
+                *slot = self.chunks.get(&(x + dx as i64 - 1, y + dy as i64 - 1));
+            }
+        }
+        // 块里第 `r` 行 (可以是 -1 或 `CHUNK_SIZE`, 即上下相邻块的边) 连同左右两侧各一格,
+        // 返回 (行, 左边一格, 右边一格)
+        let line = |r: i64| {
+            let (dy, r) = (r.div_euclid(CHUNK_SIZE), r.rem_euclid(CHUNK_SIZE) as usize);
+            let chunks = &neighbors[(dy + 1) as usize];
+            let row_of = |dx: usize| chunks[dx].map_or(0, |c: &Chunk| c[r]);
+            (row_of(1), row_of(0) >> (CHUNK_SIZE - 1), row_of(2) & 1)
+        };
+        let mut out = [0; CHUNK_SIZE as usize];
+        // 列 c 的左邻居在第 c - 1 位, 移到第 c 位; 右邻居同理
+        let west = |(line, left, _): (u64, u64, u64)| line << 1 | left;
+        let east = |(line, _, right): (u64, u64, u64)| line >> 1 | right << (CHUNK_SIZE - 1);
+        let mut above = line(-1);
+        let mut current = line(0);
+        for (r, slot) in out.iter_mut().enumerate() {
+            let below = line(r as i64 + 1);
+            if above == (0, 0, 0) && current == (0, 0, 0) && below == (0, 0, 0) {
+                above = current;
+                current = below;
+                continue;
+            }
+            let mut counts = Counts::default();
+            counts.add(above.0);
+            counts.add(below.0);
+            counts.add(west(current));
+            counts.add(east(current));
+            if moore {
+                counts.add(west(above));
+                counts.add(east(above));
+                counts.add(west(below));
+                counts.add(east(below));
+            }
+            let any = |ns: &[usize]| ns.iter().fold(0, |acc, &n| acc | counts.equals(n));
+            let alive = current.0;
+            *slot = (alive & any(survive)) | (!alive & any(birth));
+            above = current;
+            current = below;
+        }
+        out
+    }
+
+    /// 把 `u` 的整个面板当作以 (`top`, `left`) 为左上角的一块区域读进来,
+    /// 规则和代数也一起带过来
+    pub fn from_dense(u: &Universe, top: i64, left: i64) -> Result<SparseUniverse, SparseError> {
+        let mut sparse = SparseUniverse::with_rule(u.rule())?;
+        sparse.store_viewport(u, top, left);
+        sparse.count = u.count();
+        Ok(sparse)
+    }
+
+    /// 用 `u` 的面板覆盖以 (`top`, `left`) 为左上角、同样大小的区域
+    pub fn store_viewport(&mut self, u: &Universe, top: i64, left: i64) {
+        for row in 0..u.height() {
+            for col in 0..u.width() {
+                let cell = u.cells[u.get_index(row, col)];
+                self.set_cell(top + i64::from(row), left + i64::from(col), cell);
+            }
+        }
+    }
+
+    /// 以 (`top`, `left`) 为左上角、`height` 行 `width` 列的区域, 作为一个普通的宇宙
+    pub fn to_dense(&self, top: i64, left: i64, width: u32, height: u32) -> Universe {
+        let len = width as usize * height as usize;
+        let mut u = Universe::from_cells(width, height, vec![Cell::Dead; len])
+            .expect("cell count matches the size");
+        u.rule = self.rule;
+        u.load_viewport(self, top, left);
+        u
+    }
+}
+
+impl Universe {
+    /// 用 `sparse` 中以 (`top`, `left`) 为左上角的区域替换整个面板, 代数跟着
+    /// `sparse` 走. 规则、运行状态等其余设置不变. 和原来不同的格子记进 `changed_cells`
+    pub fn load_viewport(&mut self, sparse: &SparseUniverse, top: i64, left: i64) {
+        for row in 0..self.height {
+            for col in 0..self.width {
+                let idx = self.get_index(row, col);
+                let cell = sparse.get_cell(top + i64::from(row), left + i64::from(col));
+                if cell != self.cells[idx] {
+                    self.cells[idx] = cell;
+                    self.record_change(row, col);
+                }
+            }
+        }
+        self.counters
+            .set_population(Universe::count_alive(&self.cells));
+        self.sparse = None;
+        self.tiles = None;
+        self.counters.set_generation(sparse.count());
+        self.births = 0;
+        self.deaths = 0;
+        self.inverted = false;
+        self.clear_extra();
+    }
+}
@@ -0,0 +1,86 @@
+//! 每演化一代比较一次面板的哈希, 和最近几代一样时说明面板停在了静物或振荡器上.
+//! 哈希相同时再逐字节比较, 不会因为碰撞误停
+
+use std::collections::VecDeque;
+
+use super::{pack_cells, Universe};
+
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/// `StabilityDetector::observe` 的结果
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Stability {
+    Changing,
+    /// 没有活细胞了
+    Empty,
+    /// 和 `period` 代之前一模一样, 1 表示静物
+    Repeating {
+        period: u32,
+    },
+}
+
+/// 记住最近 `max_period` 代的面板
+#[derive(Clone, Debug)]
+pub struct StabilityDetector {
+    max_period: usize,
+    /// 最近的在前: (哈希, 打包后的面板)
+    recent: VecDeque<(u64, Vec<u8>)>,
+}
+
+impl StabilityDetector {
+    /// `max_period` 为 1 时只认静物, 2 时还能认出闪烁灯这样的周期 2 振荡器
+    pub fn new(max_period: usize) -> StabilityDetector {
+        StabilityDetector {
+            max_period: max_period.max(1),
+            recent: VecDeque::new(),
+        }
+    }
+
+    pub fn max_period(&self) -> usize {
+        self.max_period
+    }
+
+    /// 面板被整体换掉或者编辑过之后调用, 忘掉之前见过的面板
+    pub fn clear(&mut self) {
+        self.recent.clear();
+    }
+
+    /// 记下 `u` 现在的样子, 和最近见过的比较
+    pub fn observe(&mut self, u: &Universe) -> Stability {
+        if u.population() == 0 && u.extra.iter().all(|&state| state == 0) {
+            self.recent.clear();
+            return Stability::Empty;
+        }
+        let board = board_bytes(u);
+        let hash = fnv1a(&board);
+        let found = self
+            .recent
+            .iter()
+            .position(|(seen, bytes)| *seen == hash && *bytes == board);
+        if self.recent.len() == self.max_period {
+            self.recent.pop_back();
+        }
+        self.recent.push_front((hash, board));
+        match found {
+            Some(i) => Stability::Repeating {
+                period: i as u32 + 1,
+            },
+            None => Stability::Changing,
+        }
+    }
+}
+
+/// 细胞、多状态规则的额外状态和 B0 规则下的取反标记, 一样时下一代也一样
+fn board_bytes(u: &Universe) -> Vec<u8> {
+    let mut bytes = pack_cells(&u.cells);
+    bytes.extend_from_slice(&u.extra);
+    bytes.push(u.inverted as u8);
+    bytes
+}
+
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(FNV_OFFSET, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(FNV_PRIME)
+    })
+}
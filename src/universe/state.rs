@@ -0,0 +1,196 @@
+//! 完整模拟状态的二进制存档.
+//!
+//! 格式 (小端):
+//!
+//! | 字节数 | 内容 |
+//! |--------|------|
+//! | 4 | 魔数 `GLIF` |
+//! | 2 | 版本号 |
+//! | 2 | 标志位, bit 0 表示暂停, 其余为 0 |
+//! | 4 | width |
+//! | 4 | height |
+//! | 8 | 代数 |
+//! | ⌈width*height/8⌉ | `pack_cells` 打包的细胞 |
+//! | 1 | 版本 2 起: 1 表示存的细胞是取反的相位 (见 `Universe::is_inverted`), 否则为 0 |
+//! | 2 | 版本 2 起: 规则串的字节数 n |
+//! | n | 版本 2 起: UTF-8 的规则串 |
+//!
+//! 以后增加字段时提高版本号并追加在细胞数据之后, 旧存档仍然按旧版本读取.
+//! 版本 1 的存档没有相位和规则, 按不取反的 B3/S23 读取
+
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use super::{pack_cells, unpack_cells, RunState, Universe};
+use crate::rule::Rule;
+
+const MAGIC: &[u8; 4] = b"GLIF";
+const VERSION: u16 = 2;
+const HEADER_LEN: usize = 4 + 2 + 2 + 4 + 4 + 8;
+/// 保存时处于暂停状态. 旧存档这里是 0, 按运行中读取
+const FLAG_PAUSED: u16 = 1;
+
+#[derive(Debug)]
+pub enum StateError {
+    Io(io::Error),
+    /// 不是存档文件
+    BadMagic,
+    /// 比当前程序更新的存档版本
+    UnsupportedVersion(u16),
+    /// 文件在 `expected` 字节之前就结束了
+    Truncated {
+        expected: usize,
+        actual: usize,
+    },
+    /// 头里的数据不合理
+    Corrupt(&'static str),
+}
+
+impl fmt::Display for StateError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StateError::Io(e) => write!(f, "{}", e),
+            StateError::BadMagic => write!(f, "not a game_life save file"),
+            StateError::UnsupportedVersion(v) => write!(f, "unsupported save version {}", v),
+            StateError::Truncated { expected, actual } => write!(
+                f,
+                "save file is truncated: expected {} bytes, got {}",
+                expected, actual
+            ),
+            StateError::Corrupt(what) => write!(f, "corrupt save file: {}", what),
+        }
+    }
+}
+
+impl Error for StateError {}
+
+impl From<io::Error> for StateError {
+    fn from(e: io::Error) -> StateError {
+        StateError::Io(e)
+    }
+}
+
+fn read_u16(bytes: &[u8], at: usize) -> u16 {
+    let mut buf = [0; 2];
+    buf.copy_from_slice(&bytes[at..at + 2]);
+    u16::from_le_bytes(buf)
+}
+
+fn read_u32(bytes: &[u8], at: usize) -> u32 {
+    let mut buf = [0; 4];
+    buf.copy_from_slice(&bytes[at..at + 4]);
+    u32::from_le_bytes(buf)
+}
+
+fn read_i64(bytes: &[u8], at: usize) -> i64 {
+    let mut buf = [0; 8];
+    buf.copy_from_slice(&bytes[at..at + 8]);
+    i64::from_le_bytes(buf)
+}
+
+/// 版本 2 在细胞之后的相位和规则串, `tail` 从文件的第 `at` 个字节开始
+fn read_rule(tail: &[u8], at: usize) -> Result<(Rule, bool), StateError> {
+    let truncated = |needed: usize| StateError::Truncated {
+        expected: at + needed,
+        actual: at + tail.len(),
+    };
+    if tail.len() < 3 {
+        return Err(truncated(3));
+    }
+    let inverted = match tail[0] {
+        0 => false,
+        1 => true,
+        _ => return Err(StateError::Corrupt("phase")),
+    };
+    let len = usize::from(read_u16(tail, 1));
+    let text = tail.get(3..3 + len).ok_or_else(|| truncated(3 + len))?;
+    let rule = std::str::from_utf8(text)
+        .ok()
+        .and_then(|text| text.parse().ok())
+        .ok_or(StateError::Corrupt("rule"))?;
+    Ok((rule, inverted))
+}
+
+impl Universe {
+    pub fn to_state_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(HEADER_LEN + self.cells.len().div_ceil(8));
+        out.extend_from_slice(MAGIC);
+        out.extend_from_slice(&VERSION.to_le_bytes());
+        let flags = if self.is_paused() { FLAG_PAUSED } else { 0 };
+        out.extend_from_slice(&flags.to_le_bytes());
+        out.extend_from_slice(&self.width.to_le_bytes());
+        out.extend_from_slice(&self.height.to_le_bytes());
+        out.extend_from_slice(&self.count().to_le_bytes());
+        out.extend_from_slice(&pack_cells(&self.cells));
+        out.push(self.inverted as u8);
+        let rule = self.rule.to_string();
+        out.extend_from_slice(&(rule.len() as u16).to_le_bytes());
+        out.extend_from_slice(rule.as_bytes());
+        out
+    }
+
+    pub fn from_state_bytes(bytes: &[u8]) -> Result<Universe, StateError> {
+        if bytes.len() < 4 || &bytes[..4] != MAGIC {
+            return Err(StateError::BadMagic);
+        }
+        if bytes.len() < HEADER_LEN {
+            return Err(StateError::Truncated {
+                expected: HEADER_LEN,
+                actual: bytes.len(),
+            });
+        }
+        let version = read_u16(bytes, 4);
+        if version == 0 || version > VERSION {
+            return Err(StateError::UnsupportedVersion(version));
+        }
+        let flags = read_u16(bytes, 6);
+        let width = read_u32(bytes, 8);
+        let height = read_u32(bytes, 12);
+        let count = read_i64(bytes, 16);
+        if width == 0 || height == 0 {
+            return Err(StateError::Corrupt("empty universe"));
+        }
+        if count < 0 {
+            return Err(StateError::Corrupt("negative generation"));
+        }
+        let len = (width as usize)
+            .checked_mul(height as usize)
+            .ok_or(StateError::Corrupt("universe too large"))?;
+        let expected = HEADER_LEN + len.div_ceil(8);
+        if bytes.len() < expected {
+            return Err(StateError::Truncated {
+                expected,
+                actual: bytes.len(),
+            });
+        }
+        let cells = unpack_cells(&bytes[HEADER_LEN..], len);
+        let mut u = Universe::from_cells(width, height, cells)
+            .map_err(|_| StateError::Corrupt("cell count"))?;
+        if version >= 2 {
+            let (rule, inverted) = read_rule(&bytes[expected..], expected)?;
+            u.set_rule(rule);
+            if inverted && !rule.has_b0() {
+                return Err(StateError::Corrupt("inverted phase without B0"));
+            }
+            u.inverted = inverted;
+        }
+        u.counters.set_generation(count);
+        if flags & FLAG_PAUSED != 0 {
+            u.run_state = RunState::Paused;
+        }
+        Ok(u)
+    }
+
+    /// 保存宇宙的完整状态, 包括代数和是否暂停
+    pub fn save_state(&self, path: &Path) -> Result<(), StateError> {
+        fs::write(path, self.to_state_bytes())?;
+        Ok(())
+    }
+
+    pub fn load_state(path: &Path) -> Result<Universe, StateError> {
+        Universe::from_state_bytes(&fs::read(path)?)
+    }
+}
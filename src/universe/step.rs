@@ -0,0 +1,208 @@
+//! 两状态 B/S 规则的一代, 只依赖传进来的细胞、尺寸、规则和拓扑, 没有锁、随机数和
+//! 其他全局状态. `Universe::tick` 对这类规则也是交给 `Stepper` 演化的
+
+use super::bitgrid::BitGrid;
+use super::swar::SwarGrid;
+use super::{Cell, Engine, Topology, HEX_EVEN, HEX_ODD, MOORE, VON_NEUMANN};
+use crate::rule::{Neighborhood, Rule};
+
+/// 在环面上按 `rule` 演化一代, 返回下一代. `cells` 按行优先排列, 长度必须是 `width * height`.
+/// 只支持 `Rule::Life`, 其他规则要用到格子的额外状态或随机数, 传进来会 panic
+pub fn step(cells: &[Cell], width: u32, height: u32, rule: Rule) -> Vec<Cell> {
+    let mut next = vec![Cell::Dead; cells.len()];
+    Stepper::new(Engine::Dense).step_into(cells, &mut next, (width, height), rule, Topology::Torus);
+    next
+}
+
+/// 反复演化时留着打包后的面板, 尺寸不变时不需要分配内存
+#[derive(Clone, Debug)]
+pub struct Stepper {
+    engine: Engine,
+    /// 打包成位的当前代和下一代
+    packed: BitGrid,
+    packed_next: BitGrid,
+    /// `Engine::Swar` 用的一个字节一个细胞的当前代
+    bytes: SwarGrid,
+    #[cfg(feature = "rayon")]
+    parallel: bool,
+}
+
+impl Default for Stepper {
+    fn default() -> Stepper {
+        Stepper::new(Engine::Dense)
+    }
+}
+
+impl Stepper {
+    /// `Engine::Sparse`、`Engine::Tiled` 和 `Engine::Auto` 要记住上一代的状态,
+    /// 在这里按 `Engine::Dense` 演化
+    pub fn new(engine: Engine) -> Stepper {
+        Stepper {
+            engine,
+            packed: BitGrid::default(),
+            packed_next: BitGrid::default(),
+            bytes: SwarGrid::default(),
+            #[cfg(feature = "rayon")]
+            parallel: true,
+        }
+    }
+
+    pub fn engine(&self) -> Engine {
+        self.engine
+    }
+
+    pub fn set_engine(&mut self, engine: Engine) {
+        self.engine = engine;
+    }
+
+    /// 打包成位演化时是否把面板分成横条并行演化, 默认打开
+    #[cfg(feature = "rayon")]
+    pub fn set_parallel(&mut self, parallel: bool) {
+        self.parallel = parallel;
+    }
+
+    #[cfg(feature = "rayon")]
+    pub fn is_parallel(&self) -> bool {
+        self.parallel
+    }
+
+    /// 把 `cells` 的下一代写进 `next`, 返回 (诞生数, 死亡数). 两块都按行优先排列,
+    /// 长度必须是 `width * height`. 只支持 `Rule::Life`, 其他规则会 panic
+    pub fn step_into(
+        &mut self,
+        cells: &[Cell],
+        next: &mut [Cell],
+        size: (u32, u32),
+        rule: Rule,
+        topology: Topology,
+    ) -> (u32, u32) {
+        self.step_tracked(cells, next, size, rule, topology, None)
+    }
+
+    /// 同 `step_into`, `changes` 不为 None 时把变化了的格子 (row, col) 追加进去
+    pub(crate) fn step_tracked(
+        &mut self,
+        cells: &[Cell],
+        next: &mut [Cell],
+        (width, height): (u32, u32),
+        rule: Rule,
+        topology: Topology,
+        mut changes: Option<&mut Vec<(u32, u32)>>,
+    ) -> (u32, u32) {
+        let (birth, survive, neighborhood) = match rule {
+            Rule::Life {
+                birth,
+                survive,
+                neighborhood,
+            } => (birth, survive, neighborhood),
+            rule => panic!("{} is not a two-state B/S rule", rule),
+        };
+        assert_eq!(cells.len(), width as usize * height as usize);
+        assert_eq!(next.len(), cells.len());
+        let moore = neighborhood == Neighborhood::Moore;
+        match self.engine {
+            _ if neighborhood == Neighborhood::Hexagonal => {
+                step_scalar(cells, next, (width, height), rule, topology, changes)
+            }
+            Engine::Scalar => step_scalar(cells, next, (width, height), rule, topology, changes),
+            Engine::Swar => {
+                self.bytes.pack(cells, width, height, topology);
+                self.bytes
+                    .step_into(next, birth, survive, moore, |row, col| {
+                        if let Some(changes) = changes.as_mut() {
+                            changes.push((row, col));
+                        }
+                    })
+            }
+            Engine::Dense | Engine::Sparse | Engine::Tiled | Engine::Auto => {
+                self.step_bits(
+                    cells,
+                    next,
+                    (width, height),
+                    (birth, survive),
+                    moore,
+                    topology,
+                );
+                if let Some(changes) = changes {
+                    self.packed
+                        .for_each_change(&self.packed_next, |row, col| changes.push((row, col)));
+                }
+                (
+                    self.packed_next.count_gone(&self.packed),
+                    self.packed.count_gone(&self.packed_next),
+                )
+            }
+        }
+    }
+
+    /// 打包成 `BitGrid` 整字演化
+    fn step_bits(
+        &mut self,
+        cells: &[Cell],
+        next: &mut [Cell],
+        (width, height): (u32, u32),
+        (birth, survive): (u16, u16),
+        moore: bool,
+        topology: Topology,
+    ) {
+        let (packed, next_grid) = (&mut self.packed, &mut self.packed_next);
+        #[cfg(feature = "rayon")]
+        {
+            if self.parallel {
+                packed.par_pack(cells, width, height);
+                packed.par_step_into(next_grid, birth, survive, moore, topology);
+                next_grid.par_write_cells(next);
+                return;
+            }
+        }
+        packed.pack(cells, width, height);
+        packed.step_into(next_grid, birth, survive, moore, topology);
+        next_grid.write_cells(next);
+    }
+}
+
+/// 逐格数邻居查规则表, 六边形邻域和 `Engine::Scalar` 走这里
+fn step_scalar(
+    cells: &[Cell],
+    next: &mut [Cell],
+    (width, height): (u32, u32),
+    rule: Rule,
+    topology: Topology,
+    mut changes: Option<&mut Vec<(u32, u32)>>,
+) -> (u32, u32) {
+    let table = rule.table();
+    let neighborhood = rule.neighborhood();
+    let (mut births, mut deaths) = (0, 0);
+    for row in 0..height {
+        let offsets: &[(i64, i64)] = match neighborhood {
+            Neighborhood::Moore => &MOORE,
+            Neighborhood::VonNeumann => &VON_NEUMANN,
+            Neighborhood::Hexagonal if row % 2 == 0 => &HEX_EVEN,
+            Neighborhood::Hexagonal => &HEX_ODD,
+        };
+        for col in 0..width {
+            let live_neighbors: u8 = offsets
+                .iter()
+                .filter_map(|&(d_row, d_col)| {
+                    let r = topology.resolve(i64::from(row) + d_row, height)?;
+                    let c = topology.resolve(i64::from(col) + d_col, width)?;
+                    Some(cells[(r * width + c) as usize] as u8)
+                })
+                .sum();
+            let idx = (row * width + col) as usize;
+            let cell = cells[idx];
+            let next_cell = table[cell as usize][live_neighbors as usize];
+            if next_cell != cell {
+                match next_cell {
+                    Cell::Alive => births += 1,
+                    Cell::Dead => deaths += 1,
+                }
+                if let Some(changes) = changes.as_mut() {
+                    changes.push((row, col));
+                }
+            }
+            next[idx] = next_cell;
+        }
+    }
+    (births, deaths)
+}
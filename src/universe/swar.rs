@@ -0,0 +1,156 @@
+//! 一个字节一个细胞的面板, 四周各加一圈按 `Topology` 填好的边. 两状态的 B/S 规则
+//! 演化时一个 `u64` 装 8 个格子, 上中下三行错开一格的字直接相加就是 8 个格子各自的
+//! 邻居数, 每个字节最多是 9, 不会进位到相邻的字节.
+//! 1024x1024 的 Conway 比逐格查表快十几倍, 和打包成位的 `BitGrid` 差不多
+
+use super::{Cell, Topology};
+
+/// 每个字节都是 `0x01`
+const ONES: u64 = 0x0101_0101_0101_0101;
+/// 每个字节的最高位
+const HIGHS: u64 = 0x8080_8080_8080_8080;
+/// 活细胞的查找键在邻居数上加上这个数
+const ALIVE_KEY: u8 = 16;
+
+#[derive(Clone, Debug, Default)]
+pub(crate) struct SwarGrid {
+    width: u32,
+    height: u32,
+    /// 每一行占几个字节: 左右两格边之外再多留 8 个字节, 最后一组 8 个格子也能整字读出
+    stride: usize,
+    /// (height + 2) 行, 第 0 行和最后一行是上下的边
+    bytes: Vec<u8>,
+}
+
+impl SwarGrid {
+    /// 换成 `width` x `height`, 尺寸没变时沿用原来的内存
+    fn reshape(&mut self, width: u32, height: u32) {
+        if (self.width, self.height) != (width, height) {
+            let stride = (width as usize + 10).div_ceil(8) * 8;
+            *self = SwarGrid {
+                width,
+                height,
+                stride,
+                bytes: vec![0; stride * (height as usize + 2)],
+            };
+        }
+    }
+
+    /// 把按行优先的细胞和四周的边填进来, `cells` 的长度必须是 `width * height`
+    pub(crate) fn pack(&mut self, cells: &[Cell], width: u32, height: u32, topology: Topology) {
+        self.reshape(width, height);
+        if width == 0 || height == 0 {
+            return;
+        }
+        let (w, stride) = (width as usize, self.stride);
+        let left = topology.resolve(-1, width).map(|c| c as usize);
+        let right = topology
+            .resolve(i64::from(width), width)
+            .map(|c| c as usize);
+        for row in 0..height as usize + 2 {
+            let line = &mut self.bytes[row * stride..(row + 1) * stride];
+            let src = match topology.resolve(row as i64 - 1, height) {
+                Some(r) => &cells[r as usize * w..(r as usize + 1) * w],
+                None => {
+                    line.iter_mut().for_each(|b| *b = 0);
+                    continue;
+                }
+            };
+            for (byte, &cell) in line[1..=w].iter_mut().zip(src) {
+                *byte = cell as u8;
+            }
+            line[0] = left.map_or(0, |c| src[c] as u8);
+            line[w + 1] = right.map_or(0, |c| src[c] as u8);
+        }
+    }
+
+    /// 按复活和存活掩码算出下一代写进 `next`, 每个变化了的格子调用一次 `changed(row, col)`,
+    /// 返回 (诞生数, 死亡数)
+    pub(crate) fn step_into(
+        &self,
+        next: &mut [Cell],
+        birth: u16,
+        survive: u16,
+        moore: bool,
+        mut changed: impl FnMut(u32, u32),
+    ) -> (u32, u32) {
+        // 格子的下一代只看 (邻居数, 自己是否活着), 符合规则的组合编成键, 逐个按字节比较
+        let mut keys = [0u64; 18];
+        let mut len = 0;
+        for n in 0..=8u8 {
+            if birth & (1 << n) != 0 {
+                keys[len] = ONES * u64::from(n);
+                len += 1;
+            }
+            if survive & (1 << n) != 0 {
+                keys[len] = ONES * u64::from(n + ALIVE_KEY);
+                len += 1;
+            }
+        }
+        let keys = &keys[..len];
+        let (w, stride) = (self.width as usize, self.stride);
+        let (mut births, mut deaths) = (0, 0);
+        for row in 0..self.height as usize {
+            let above = &self.bytes[row * stride..];
+            let here = &self.bytes[(row + 1) * stride..];
+            let below = &self.bytes[(row + 2) * stride..];
+            let out = &mut next[row * w..(row + 1) * w];
+            for start in (0..w).step_by(8) {
+                let center = load(here, start + 1);
+                let count = if moore {
+                    load(above, start)
+                        + load(above, start + 1)
+                        + load(above, start + 2)
+                        + load(here, start)
+                        + load(here, start + 2)
+                        + load(below, start)
+                        + load(below, start + 1)
+                        + load(below, start + 2)
+                } else {
+                    load(above, start + 1)
+                        + load(here, start)
+                        + load(here, start + 2)
+                        + load(below, start + 1)
+                };
+                let key = count + center * u64::from(ALIVE_KEY);
+                let hits = keys.iter().fold(0, |acc, &k| acc | zero_bytes(key ^ k));
+                let alive = hits >> 7;
+                let lanes = (w - start).min(8);
+                let valid = if lanes == 8 {
+                    !0
+                } else {
+                    (1 << (lanes * 8)) - 1
+                };
+                let diff = (alive ^ center) & valid;
+                births += (diff & alive).count_ones();
+                deaths += (diff & center).count_ones();
+                for (k, cell) in out[start..start + lanes].iter_mut().enumerate() {
+                    *cell = if alive >> (k * 8) & 1 != 0 {
+                        Cell::Alive
+                    } else {
+                        Cell::Dead
+                    };
+                }
+                let mut rest = diff;
+                while rest != 0 {
+                    let k = rest.trailing_zeros() as usize / 8;
+                    changed(row as u32, (start + k) as u32);
+                    rest &= rest - 1;
+                }
+            }
+        }
+        (births, deaths)
+    }
+}
+
+/// 从 `line[at]` 开始按小端读 8 个字节, 第 k 个字节是第 `at + k` 格
+fn load(line: &[u8], at: usize) -> u64 {
+    let mut word = [0; 8];
+    word.copy_from_slice(&line[at..at + 8]);
+    u64::from_le_bytes(word)
+}
+
+/// 值为 0 的字节最高位是 1, 其余字节全是 0. 每个字节都必须小于 `0x80`
+fn zero_bytes(x: u64) -> u64 {
+    !((x + !HIGHS) | x) & HIGHS
+}
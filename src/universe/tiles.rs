@@ -0,0 +1,198 @@
+//! 按 16x16 的块跳过静止的区域. 两状态的规则下一个格子的下一代只看它和相邻的格子,
+//! 上一代自己和周围 8 块都没有变化的块, 下一代也不会变, 直接照抄;
+//! 其余的块逐行打包成 `u32`, 按位相加数出邻居数.
+//! 面板被单独修改的格子所在的块记为变化过, 整体改写之后所有块重新计算
+
+use super::bitgrid::Counts;
+use super::{Cell, Region, Topology, HEX_EVEN, HEX_ODD, MOORE, VON_NEUMANN};
+use crate::rule::{Neighborhood, Rule};
+
+/// 块的边长
+pub const TILE_SIZE: u32 = 16;
+
+#[derive(Clone, Debug)]
+pub(crate) struct Tiles {
+    width: u32,
+    height: u32,
+    /// 每行几块、每列几块, 最后一块可能不满 `TILE_SIZE`
+    cols: u32,
+    rows: u32,
+    /// 上一代和之后的修改里有格子变化过的块
+    changed: Vec<bool>,
+    /// 最近一次演化重新计算过的块
+    dirty: Vec<bool>,
+}
+
+impl Tiles {
+    /// 所有块都当作变化过, 第一次演化整个面板都要计算
+    pub(crate) fn new(width: u32, height: u32) -> Tiles {
+        let cols = width.div_ceil(TILE_SIZE);
+        let rows = height.div_ceil(TILE_SIZE);
+        let len = (cols * rows) as usize;
+        Tiles {
+            width,
+            height,
+            cols,
+            rows,
+            changed: vec![true; len],
+            dirty: vec![true; len],
+        }
+    }
+
+    /// 面板上的 (`row`, `col`) 被单独修改过
+    pub(crate) fn mark(&mut self, row: u32, col: u32) {
+        let idx = (row / TILE_SIZE * self.cols + col / TILE_SIZE) as usize;
+        self.changed[idx] = true;
+    }
+
+    /// 最近一次演化重新计算过的块在面板上的范围
+    pub(crate) fn dirty(&self) -> impl Iterator<Item = Region> + '_ {
+        self.dirty
+            .iter()
+            .enumerate()
+            .filter(|(_, &dirty)| dirty)
+            .map(move |(idx, _)| self.region(idx as u32 / self.cols, idx as u32 % self.cols))
+    }
+
+    fn region(&self, tile_row: u32, tile_col: u32) -> Region {
+        let (row, col) = (tile_row * TILE_SIZE, tile_col * TILE_SIZE);
+        Region::new(
+            row,
+            col,
+            TILE_SIZE.min(self.height - row),
+            TILE_SIZE.min(self.width - col),
+        )
+    }
+
+    /// 自己或周围 8 块 (按 `topology` 连到对边) 变化过的块要重新计算
+    fn mark_dirty(&mut self, topology: Topology) {
+        for tile_row in 0..self.rows {
+            for tile_col in 0..self.cols {
+                let near_change = MOORE.iter().chain([(0, 0)].iter()).any(|&(d_row, d_col)| {
+                    let r = topology.resolve(i64::from(tile_row) + d_row, self.rows);
+                    let c = topology.resolve(i64::from(tile_col) + d_col, self.cols);
+                    match (r, c) {
+                        (Some(r), Some(c)) => self.changed[(r * self.cols + c) as usize],
+                        _ => false,
+                    }
+                });
+                self.dirty[(tile_row * self.cols + tile_col) as usize] = near_change;
+            }
+        }
+        self.changed.iter_mut().for_each(|changed| *changed = false);
+    }
+
+    /// 把 `cells` 的下一代写进 `next`, 只重新计算需要的块, 返回 (诞生数, 死亡数).
+    /// `rule` 必须是不含 B0 的 `Rule::Life`, 含 B0 时空白的块每一代也会变
+    pub(crate) fn step(
+        &mut self,
+        cells: &[Cell],
+        next: &mut [Cell],
+        rule: Rule,
+        topology: Topology,
+        mut changes: Option<&mut Vec<(u32, u32)>>,
+    ) -> (u32, u32) {
+        let (birth, survive) = match rule {
+            Rule::Life { birth, survive, .. } if birth & 1 == 0 => (birth, survive),
+            rule => panic!("{} cannot skip quiet tiles", rule),
+        };
+        self.mark_dirty(topology);
+        let board = Board {
+            cells,
+            width: self.width,
+            height: self.height,
+            topology,
+        };
+        let neighborhood = rule.neighborhood();
+        let (mut births, mut deaths) = (0, 0);
+        for idx in 0..self.dirty.len() {
+            let tile = self.region(idx as u32 / self.cols, idx as u32 % self.cols);
+            if !self.dirty[idx] {
+                for row in tile.row..tile.row + tile.height {
+                    let start = (row * self.width + tile.col) as usize;
+                    let end = start + tile.width as usize;
+                    next[start..end].copy_from_slice(&cells[start..end]);
+                }
+                continue;
+            }
+            let mask = (1u64 << tile.width) - 1;
+            // 第 k 行是面板上第 tile.row + k - 1 行, 第 j 位是第 tile.col + j - 1 列
+            let mut lines = [0u32; TILE_SIZE as usize + 2];
+            for (k, line) in lines.iter_mut().enumerate().take(tile.height as usize + 2) {
+                *line = board.window(i64::from(tile.row) + k as i64 - 1, tile.col, tile.width);
+            }
+            for k in 0..tile.height as usize {
+                let row = tile.row + k as u32;
+                let offsets: &[(i64, i64)] = match neighborhood {
+                    Neighborhood::Moore => &MOORE,
+                    Neighborhood::VonNeumann => &VON_NEUMANN,
+                    Neighborhood::Hexagonal if row % 2 == 0 => &HEX_EVEN,
+                    Neighborhood::Hexagonal => &HEX_ODD,
+                };
+                let mut counts = Counts::default();
+                for &(d_row, d_col) in offsets {
+                    let line = lines[(k as i64 + 1 + d_row) as usize];
+                    counts.add(u64::from(line >> (d_col + 1)));
+                }
+                let center = u64::from(lines[k + 1] >> 1) & mask;
+                let alive =
+                    (center & counts.matching(survive)) | (!center & counts.matching(birth));
+                let alive = alive & mask;
+                let diff = alive ^ center;
+                if diff == 0 {
+                    let start = (row * self.width + tile.col) as usize;
+                    let end = start + tile.width as usize;
+                    next[start..end].copy_from_slice(&cells[start..end]);
+                    continue;
+                }
+                self.changed[idx] = true;
+                births += (diff & alive).count_ones();
+                deaths += (diff & center).count_ones();
+                for j in 0..tile.width {
+                    let col = tile.col + j;
+                    next[(row * self.width + col) as usize] = if alive >> j & 1 != 0 {
+                        Cell::Alive
+                    } else {
+                        Cell::Dead
+                    };
+                    if diff >> j & 1 != 0 {
+                        if let Some(changes) = changes.as_mut() {
+                            changes.push((row, col));
+                        }
+                    }
+                }
+            }
+        }
+        (births, deaths)
+    }
+}
+
+/// 按拓扑读面板上的格子
+struct Board<'a> {
+    cells: &'a [Cell],
+    width: u32,
+    height: u32,
+    topology: Topology,
+}
+
+impl Board<'_> {
+    /// 第 `row` 行从 `col - 1` 开始的 `len + 2` 格, 第 j 位是第 `col + j - 1` 列.
+    /// 越过有界网格边缘的格子算作死细胞
+    fn window(&self, row: i64, col: u32, len: u32) -> u32 {
+        let row = match self.topology.resolve(row, self.height) {
+            Some(row) => row,
+            None => return 0,
+        };
+        let line = &self.cells[(row * self.width) as usize..((row + 1) * self.width) as usize];
+        let edge = |c: i64| {
+            self.topology
+                .resolve(c, self.width)
+                .map_or(0, |c| line[c as usize] as u32)
+        };
+        let mut bits = edge(i64::from(col) - 1) | edge(i64::from(col + len)) << (len + 1);
+        for (j, &cell) in line[col as usize..(col + len) as usize].iter().enumerate() {
+            bits |= (cell as u32) << (j + 1);
+        }
+        bits
+    }
+}
@@ -0,0 +1,371 @@
+//! 窗口坐标和格子之间的换算, 与具体的绘图接口无关
+
+/// 客户区坐标 (`x`, `y`) 落在哪个格子上, 返回 (row, col). 每格占 `cell_w` x `cell_h`
+/// 像素, 面板共 `cols` 列 `rows` 行; 在面板之外返回 None
+pub fn cell_at(
+    x: i32,
+    y: i32,
+    cell_w: i32,
+    cell_h: i32,
+    cols: u32,
+    rows: u32,
+) -> Option<(u32, u32)> {
+    if x < 0 || y < 0 || cell_w <= 0 || cell_h <= 0 {
+        return None;
+    }
+    let (col, row) = ((x / cell_w) as u32, (y / cell_h) as u32);
+    if col < cols && row < rows {
+        Some((row, col))
+    } else {
+        None
+    }
+}
+
+/// 方格面板在窗口里的布局. 格子和网格线都按整像素排, 画格子、画网格线、
+/// 鼠标换算和窗口大小都从这里算, 彼此对得上
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct Layout {
+    /// 窗口里显示的列数和行数, 和面板本身的大小无关
+    pub grid_cols: u32,
+    pub grid_rows: u32,
+    /// 一个格子内部的边长, 不含网格线
+    pub cell_px: i32,
+    /// 格子之间、网格四周的网格线宽度, 可以是 0
+    pub gridline_px: i32,
+    /// 网格左上角 (最外面的网格线) 的坐标
+    pub origin: (i32, i32),
+}
+
+impl Layout {
+    /// 相邻两格左上角之间的距离
+    pub const fn pitch(&self) -> i32 {
+        self.cell_px + self.gridline_px
+    }
+
+    /// 整个方格网格的宽和高, 包括最外面的网格线
+    pub const fn size(&self) -> (i32, i32) {
+        (
+            self.grid_cols as i32 * self.pitch() + self.gridline_px,
+            self.grid_rows as i32 * self.pitch() + self.gridline_px,
+        )
+    }
+
+    /// 同样行列的六边形网格 (见 `hex_corners`) 的外接矩形: 奇数行多出半格, 最后一行多出下面的尖
+    pub const fn hex_size(&self) -> (i32, i32) {
+        let pitch = self.pitch();
+        (
+            self.grid_cols as i32 * pitch + pitch / 2 + 1,
+            self.grid_rows as i32 * pitch + pitch / 3 + 1,
+        )
+    }
+
+    /// 不画网格线时的布局: 格子之间的间距不变, 原来网格线的位置也归格子, 不留缝
+    pub const fn without_gridlines(&self) -> Layout {
+        Layout {
+            cell_px: self.pitch(),
+            gridline_px: 0,
+            ..*self
+        }
+    }
+
+    /// 第 `i` 条竖网格线左边的 x, 第 0 条在最左边, 第 `grid_cols` 条在最右边
+    pub const fn line_x(&self, i: u32) -> i32 {
+        self.origin.0 + i as i32 * self.pitch()
+    }
+
+    /// 第 `i` 条横网格线上边的 y
+    pub const fn line_y(&self, i: u32) -> i32 {
+        self.origin.1 + i as i32 * self.pitch()
+    }
+
+    /// 第 `row` 行第 `col` 列格子的内部, (left, top, right, bottom), 右边和下边不含
+    pub const fn cell_rect(&self, row: u32, col: u32) -> (i32, i32, i32, i32) {
+        let (left, top) = (
+            self.line_x(col) + self.gridline_px,
+            self.line_y(row) + self.gridline_px,
+        );
+        (left, top, left + self.cell_px, top + self.cell_px)
+    }
+
+    /// 坐标 (`x`, `y`) 落在哪个格子上, 返回 (row, col), 同 `cell_rect` 互逆.
+    /// 网格线上和网格外面返回 None
+    pub fn cell_at(&self, x: i32, y: i32) -> Option<(u32, u32)> {
+        let pitch = self.pitch();
+        if pitch <= 0 || self.cell_px <= 0 {
+            return None;
+        }
+        let along = |offset: i32, count: u32| {
+            let offset = offset - self.gridline_px;
+            if offset < 0 || offset % pitch >= self.cell_px {
+                return None;
+            }
+            let index = (offset / pitch) as u32;
+            if index < count {
+                Some(index)
+            } else {
+                None
+            }
+        };
+        let col = along(x - self.origin.0, self.grid_cols)?;
+        let row = along(y - self.origin.1, self.grid_rows)?;
+        Some((row, col))
+    }
+
+    /// 六边形网格上的 `cell_at`, 六边形之间没有网格线, 行距和列距都是 `pitch`
+    pub fn hex_cell_at(&self, x: i32, y: i32) -> Option<(u32, u32)> {
+        let pitch = self.pitch();
+        hex_cell_at(
+            x - self.origin.0,
+            y - self.origin.1,
+            pitch,
+            pitch,
+            self.grid_cols,
+            self.grid_rows,
+        )
+    }
+
+    /// 六边形网格上第 `row` 行第 `col` 列六边形的顶点
+    pub fn hex_corners(&self, row: u32, col: u32) -> [(i32, i32); 6] {
+        let pitch = self.pitch();
+        let mut corners = hex_corners(row, col, pitch, pitch);
+        for corner in corners.iter_mut() {
+            *corner = (corner.0 + self.origin.0, corner.1 + self.origin.1);
+        }
+        corners
+    }
+}
+
+/// 缩放时格子内部边长的范围, 像素
+pub const MIN_CELL_PX: i32 = 2;
+pub const MAX_CELL_PX: i32 = 64;
+/// 格子内部小于这么多像素时自动不画网格线, 网格线的位置归格子
+pub const GRIDLINE_MIN_PX: i32 = 5;
+
+/// 窗口看着面板的哪一块、放大多少. 把整个面板按当前的缩放画成一张大图, 窗口左上角
+/// 落在大图的 `scroll` 像素上; 大图的左上角是第 0 条网格线. 窗口和鼠标都按它换算,
+/// 画出来的格子和鼠标点到的格子总是同一个
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct Camera {
+    cell_px: i32,
+    /// 格子够大时网格线的宽度, 见 `GRIDLINE_MIN_PX`
+    gridline_px: i32,
+    scroll: (i64, i64),
+    /// 窗口里放面板的区域的宽和高, 像素
+    view: (i32, i32),
+    /// 面板的列数和行数
+    board: (u32, u32),
+}
+
+impl Camera {
+    /// 窗口左上角对着面板左上角, 面板和窗口的大小之后用 `fit` 设置
+    pub fn new(cell_px: i32, gridline_px: i32, view: (i32, i32)) -> Camera {
+        Camera {
+            cell_px: cell_px.clamp(MIN_CELL_PX, MAX_CELL_PX),
+            gridline_px,
+            scroll: (0, 0),
+            view,
+            board: (0, 0),
+        }
+    }
+
+    /// 格子内部的边长, 不含网格线
+    pub fn cell_px(&self) -> i32 {
+        self.cell_px
+    }
+
+    /// 实际画出来的网格线宽度, 格子太小时是 0
+    pub fn gridline_px(&self) -> i32 {
+        if self.cell_px >= GRIDLINE_MIN_PX {
+            self.gridline_px
+        } else {
+            0
+        }
+    }
+
+    /// 相邻两格左上角之间的距离
+    pub fn pitch(&self) -> i32 {
+        self.cell_px + self.gridline_px()
+    }
+
+    /// 窗口左上角在大图上的像素
+    pub fn scroll(&self) -> (i64, i64) {
+        self.scroll
+    }
+
+    /// 窗口里的像素 (`x`, `y`) 落在面板的哪一格, 返回 (row, col). 可能在面板外面,
+    /// 网格线算作它右边和下边的格子
+    pub fn screen_to_world(&self, x: i32, y: i32) -> (i64, i64) {
+        let pitch = i64::from(self.pitch());
+        (
+            (self.scroll.1 + i64::from(y)).div_euclid(pitch),
+            (self.scroll.0 + i64::from(x)).div_euclid(pitch),
+        )
+    }
+
+    /// 面板上第 `row` 行第 `col` 列的格子连同它左边和上边的网格线, 左上角在窗口里的 (x, y)
+    pub fn world_to_screen(&self, row: i64, col: i64) -> (i64, i64) {
+        let pitch = i64::from(self.pitch());
+        (col * pitch - self.scroll.0, row * pitch - self.scroll.1)
+    }
+
+    /// 面板有 `board` (列数, 行数) 那么大, 窗口里放面板的区域有 `view` 像素那么大,
+    /// 都记下来之后收回到面板范围内
+    pub fn fit(&mut self, board: (u32, u32), view: (i32, i32)) {
+        self.board = board;
+        self.view = view;
+        self.clamp();
+    }
+
+    /// 窗口比面板小时不露出面板外面, 比面板大时面板贴着左上角
+    fn clamp(&mut self) {
+        let pitch = i64::from(self.pitch());
+        let gridline = i64::from(self.gridline_px());
+        let limit =
+            |cells: u32, view: i32| (i64::from(cells) * pitch + gridline - i64::from(view)).max(0);
+        let (max_x, max_y) = (
+            limit(self.board.0, self.view.0),
+            limit(self.board.1, self.view.1),
+        );
+        self.scroll = (self.scroll.0.clamp(0, max_x), self.scroll.1.clamp(0, max_y));
+    }
+
+    /// 整格地移动 `rows` 行 `cols` 列, 不超出面板
+    pub fn pan(&mut self, rows: i64, cols: i64) {
+        let pitch = i64::from(self.pitch());
+        self.scroll = (self.scroll.0 + cols * pitch, self.scroll.1 + rows * pitch);
+        self.clamp();
+    }
+
+    /// 格子的边长换成 `cell_px` (限制在 `MIN_CELL_PX` 到 `MAX_CELL_PX` 之间),
+    /// 窗口里的像素 (`x`, `y`) 对着的面板上那一点不动. 到了面板边上时按 `fit` 收回来
+    pub fn zoom_at(&mut self, x: i32, y: i32, cell_px: i32) {
+        let old = self.pitch() as f64;
+        self.cell_px = cell_px.clamp(MIN_CELL_PX, MAX_CELL_PX);
+        let new = self.pitch() as f64;
+        // 按像素的中心算, 缩放之后这个像素还在原来那一格里
+        let keep = |scroll: i64, at: i32| {
+            let world = ((scroll + i64::from(at)) as f64 + 0.5) / old;
+            (world * new).floor() as i64 - i64::from(at)
+        };
+        self.scroll = (keep(self.scroll.0, x), keep(self.scroll.1, y));
+        self.clamp();
+    }
+
+    /// 鼠标滚轮转 `notches` 格之后的格子边长: 每格放大或缩小大约 1/4, 至少变 1 像素
+    pub fn zoomed(&self, notches: i32) -> i32 {
+        let mut cell_px = self.cell_px;
+        for _ in 0..notches.unsigned_abs() {
+            let next = if notches > 0 {
+                (cell_px + cell_px / 4).max(cell_px + 1)
+            } else {
+                (cell_px - cell_px / 5).min(cell_px - 1)
+            };
+            cell_px = next.clamp(MIN_CELL_PX, MAX_CELL_PX);
+        }
+        cell_px
+    }
+
+    /// 窗口左上角那一格 (可能只露出一部分) 在面板上的 (row, col). `hex` 时行号取不大于它的偶数,
+    /// 六边形网格的奇数行才和面板上的奇数行一样错开半格
+    pub fn top_left(&self, hex: bool) -> (i64, i64) {
+        let (row, col) = self.screen_to_world(0, 0);
+        if hex {
+            (row - row.rem_euclid(2), col)
+        } else {
+            (row, col)
+        }
+    }
+
+    /// 以 `top_left` 那一格为第 0 行第 0 列、铺满窗口的布局, 最多到面板的最后一行一列.
+    /// 窗口左上角压着半格时 `origin` 是负的
+    pub fn layout(&self, hex: bool) -> Layout {
+        let pitch = self.pitch();
+        let (top, left) = self.top_left(hex);
+        let (x, y) = self.world_to_screen(top, left);
+        let (x, y) = (x as i32, y as i32);
+        let count = |from: i32, view: i32, first: i64, cells: u32| {
+            let shown = (view - from + pitch - 1) / pitch;
+            (shown.max(0) as u32).min((i64::from(cells) - first).max(0) as u32)
+        };
+        Layout {
+            grid_cols: count(x, self.view.0, left, self.board.0),
+            grid_rows: count(y, self.view.1, top, self.board.1),
+            cell_px: self.cell_px,
+            gridline_px: self.gridline_px(),
+            origin: (x, y),
+        }
+    }
+}
+
+/// 六边形网格 (奇数行右移半格) 上第 `row` 行第 `col` 列的六边形中心.
+/// 左右相邻的中心相距 `cell_w`, 上下相邻的行相距 `cell_h`, 第 0 行的尖顶在 y = 0
+fn hex_center(row: i64, col: i64, cell_w: f64, cell_h: f64) -> (f64, f64) {
+    let shift = if row.rem_euclid(2) == 1 {
+        cell_w / 2.0
+    } else {
+        0.0
+    };
+    (
+        col as f64 * cell_w + cell_w / 2.0 + shift,
+        row as f64 * cell_h + cell_h * 2.0 / 3.0,
+    )
+}
+
+/// 六边形的六个顶点, 从正上方开始顺时针. 竖边占行距的 2/3, 相邻的行正好拼在一起
+pub fn hex_corners(row: u32, col: u32, cell_w: i32, cell_h: i32) -> [(i32, i32); 6] {
+    let (w, h) = (f64::from(cell_w), f64::from(cell_h));
+    let (x, y) = hex_center(i64::from(row), i64::from(col), w, h);
+    let offsets = [
+        (0.0, -2.0 / 3.0),
+        (0.5, -1.0 / 3.0),
+        (0.5, 1.0 / 3.0),
+        (0.0, 2.0 / 3.0),
+        (-0.5, 1.0 / 3.0),
+        (-0.5, -1.0 / 3.0),
+    ];
+    let mut corners = [(0, 0); 6];
+    for (corner, &(dx, dy)) in corners.iter_mut().zip(offsets.iter()) {
+        *corner = ((x + dx * w).round() as i32, (y + dy * h).round() as i32);
+    }
+    corners
+}
+
+/// 客户区坐标 (`x`, `y`) 的像素落在哪个六边形上, 返回 (row, col), 布局同 `hex_corners`.
+/// 把横向按比例缩放成正六边形后, 六边形就是离中心最近的那些点,
+/// 所以只要比较上下三行里最近的中心. 不在任何六边形上时返回 None
+pub fn hex_cell_at(
+    x: i32,
+    y: i32,
+    cell_w: i32,
+    cell_h: i32,
+    cols: u32,
+    rows: u32,
+) -> Option<(u32, u32)> {
+    if cell_w <= 0 || cell_h <= 0 {
+        return None;
+    }
+    let (w, h) = (f64::from(cell_w), f64::from(cell_h));
+    // 取像素中心, 正好落在边上的只有很少的情况
+    let (px, py) = (f64::from(x) + 0.5, f64::from(y) + 0.5);
+    // 正六边形的宽是边长的 √3 倍, 边长是行距的 2/3
+    let scale = 3f64.sqrt() * h * 2.0 / 3.0 / w;
+    let band = (py / h).floor() as i64;
+    let mut best: Option<(f64, i64, i64)> = None;
+    for row in band - 1..=band + 1 {
+        let shift = if row.rem_euclid(2) == 1 { w / 2.0 } else { 0.0 };
+        let col = ((px - shift) / w).floor() as i64;
+        for col in col - 1..=col + 1 {
+            let (cx, cy) = hex_center(row, col, w, h);
+            let distance = ((px - cx) * scale).powi(2) + (py - cy).powi(2);
+            if !matches!(best, Some((d, _, _)) if d <= distance) {
+                best = Some((distance, row, col));
+            }
+        }
+    }
+    let (_, row, col) = best?;
+    if row >= 0 && col >= 0 && row < i64::from(rows) && col < i64::from(cols) {
+        Some((row as u32, col as u32))
+    } else {
+        None
+    }
+}
@@ -0,0 +1,111 @@
+//! 轮询文件的修改时间, 文件改动后通知调用者重新加载
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant, SystemTime};
+
+/// 文件的修改时间和长度, 读不到文件时为 None
+type Stamp = Option<(SystemTime, u64)>;
+
+fn stamp(path: &Path) -> Stamp {
+    let meta = fs::metadata(path).ok()?;
+    Some((meta.modified().ok()?, meta.len()))
+}
+
+/// 记录文件上一次的状态. 编辑器保存时常常连续写好几次, 所以文件变化后要等
+/// `debounce` 时间内不再变化才算一次修改
+pub struct FileWatch {
+    path: PathBuf,
+    seen: Stamp,
+    changed_at: Option<Instant>,
+    debounce: Duration,
+}
+
+impl FileWatch {
+    pub fn new(path: &Path, debounce: Duration) -> FileWatch {
+        FileWatch {
+            path: path.to_path_buf(),
+            seen: stamp(path),
+            changed_at: None,
+            debounce,
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// 检查一次文件, 一次修改稳定下来时返回 true
+    pub fn poll(&mut self, now: Instant) -> bool {
+        let current = stamp(&self.path);
+        if current != self.seen {
+            self.seen = current;
+            self.changed_at = Some(now);
+            return false;
+        }
+        match self.changed_at {
+            Some(at) if now.duration_since(at) >= self.debounce && current.is_some() => {
+                self.changed_at = None;
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+/// 后台轮询线程, 调用 `stop` 或者 drop 时结束
+pub struct WatchHandle {
+    path: PathBuf,
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl WatchHandle {
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn stop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for WatchHandle {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// 每隔 `interval` 检查一次 `path`, 文件修改稳定后在后台线程里调用 `on_change`
+pub fn spawn<F>(
+    path: &Path,
+    interval: Duration,
+    debounce: Duration,
+    mut on_change: F,
+) -> WatchHandle
+where
+    F: FnMut() + Send + 'static,
+{
+    let stop = Arc::new(AtomicBool::new(false));
+    let mut watch = FileWatch::new(path, debounce);
+    let flag = Arc::clone(&stop);
+    let thread = thread::spawn(move || {
+        while !flag.load(Ordering::Relaxed) {
+            thread::sleep(interval);
+            if watch.poll(Instant::now()) {
+                on_change();
+            }
+        }
+    });
+    WatchHandle {
+        path: path.to_path_buf(),
+        stop,
+        thread: Some(thread),
+    }
+}
@@ -0,0 +1,58 @@
+//! 用计数的全局分配器检查演化时是否还在分配内存. 这个文件里只能有一个测试,
+//! 否则其他测试线程的分配也会算进来
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use life_game::{Topology, Universe};
+
+struct Counting;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::SeqCst);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+#[test]
+fn two_state_ticks_reuse_their_buffers() {
+    let mut u = Universe::builder().width(200).height(150).seed(3).build();
+    // rayon 分派任务时自己会分配内存, 这里只看面板的缓冲区
+    #[cfg(feature = "rayon")]
+    u.set_parallel(false);
+    let mut expected = u.clone();
+    // 第一代建好缓冲区
+    u.tick();
+    for topology in [Topology::Torus, Topology::Bounded, Topology::Mirror] {
+        u.set_topology(topology);
+        let before = ALLOCATIONS.load(Ordering::SeqCst);
+        u.step_n(50);
+        assert_eq!(ALLOCATIONS.load(Ordering::SeqCst), before, "{}", topology);
+    }
+
+    // 交换缓冲区不影响演化的结果
+    expected.tick();
+    for topology in [Topology::Torus, Topology::Bounded, Topology::Mirror] {
+        expected.set_topology(topology);
+        for _ in 0..50 {
+            let cells = expected.cells().to_vec();
+            let mut fresh = Universe::from_cells(200, 150, cells).unwrap();
+            fresh.set_topology(topology);
+            fresh.tick();
+            expected.tick();
+            assert!(expected.same_cells(&fresh));
+        }
+    }
+    assert!(u.same_cells(&expected));
+    assert_eq!(u.count(), expected.count());
+}
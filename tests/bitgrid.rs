@@ -0,0 +1,106 @@
+use life_game::{Cell, Rule, Topology, Universe};
+
+/// 逐格数邻居再查规则表, 即打包成位之前的演化方式
+fn reference_step(u: &Universe) -> Vec<Cell> {
+    let rule = u.rule();
+    let mut next = Vec::with_capacity(u.cells().len());
+    for row in 0..u.height() {
+        for col in 0..u.width() {
+            let cell = u.get_cell(row, col).unwrap();
+            next.push(rule.applies(cell, u.live_neighbor_count(row, col)));
+        }
+    }
+    next
+}
+
+#[test]
+fn bit_packed_step_matches_cell_by_cell_step() {
+    let sizes = [
+        (1, 1),
+        (5, 1),
+        (1, 6),
+        (7, 3),
+        (63, 10),
+        (64, 9),
+        (65, 11),
+        (130, 7),
+    ];
+    let rules = [
+        "B3/S23",
+        "B36/S23",
+        "B2/S",
+        "B3678/S34678",
+        "B2/S013V",
+        "B1/S1V",
+    ];
+    let topologies = [Topology::Torus, Topology::Bounded, Topology::Mirror];
+    for seed in 0..12 {
+        for &(width, height) in sizes.iter() {
+            for text in rules.iter() {
+                for &topology in topologies.iter() {
+                    let mut u = Universe::builder()
+                        .width(width)
+                        .height(height)
+                        .density(0.4)
+                        .seed(seed)
+                        .build();
+                    u.set_rule(text.parse::<Rule>().unwrap());
+                    u.set_topology(topology);
+                    for generation in 0..3 {
+                        let expected = reference_step(&u);
+                        let population = u.population();
+                        u.tick();
+                        assert_eq!(
+                            u.cells(),
+                            &expected[..],
+                            "seed {} {}x{} {} {} generation {}",
+                            seed,
+                            width,
+                            height,
+                            text,
+                            topology,
+                            generation
+                        );
+                        assert_eq!(u.population() + u.deaths(), population + u.births());
+                        assert_eq!(u.population(), u.live_count() as u32);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(feature = "rayon")]
+#[test]
+fn parallel_step_matches_serial_step() {
+    // 高度不是横条行数的整数倍, 最后一条比较短
+    for &(width, height, topology) in [
+        (200, 150, Topology::Torus),
+        (70, 33, Topology::Bounded),
+        (129, 47, Topology::Mirror),
+    ]
+    .iter()
+    {
+        let mut serial = Universe::builder()
+            .width(width)
+            .height(height)
+            .seed(21)
+            .build();
+        serial.set_topology(topology);
+        serial.set_parallel(false);
+        let mut parallel = serial.clone();
+        parallel.set_parallel(true);
+        assert!(parallel.is_parallel());
+        for generation in 0..100 {
+            serial.tick();
+            parallel.tick();
+            assert!(
+                serial.same_cells(&parallel),
+                "{} generation {}",
+                topology,
+                generation
+            );
+            assert_eq!(serial.population(), parallel.population());
+        }
+    }
+}
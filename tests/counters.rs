@@ -0,0 +1,93 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, RwLock};
+use std::thread;
+
+use life_game::patterns::GLIDER;
+use life_game::{Counters, Universe};
+
+fn assert_matches(counters: &Counters, u: &Universe) {
+    assert_eq!(counters.generation(), u.count());
+    assert_eq!(counters.population() as usize, u.live_count());
+}
+
+#[test]
+fn counters_follow_the_board() {
+    let mut u = Universe::builder().width(32).height(24).seed(1).build();
+    let counters = u.counters();
+    assert_matches(&counters, &u);
+    u.step_n(7);
+    assert_matches(&counters, &u);
+    u.toggle_cell(3, 4).unwrap();
+    assert_matches(&counters, &u);
+    u.dead_all();
+    assert_eq!((counters.generation(), counters.population()), (0, 0));
+    u.insert_pattern(GLIDER, 5, 5, false).unwrap();
+    u.step_n(4);
+    assert_matches(&counters, &u);
+    u.reset();
+    assert_matches(&counters, &u);
+
+    // 复制出来的面板有自己的计数器
+    let mut copy = u.clone();
+    copy.step_n(3);
+    assert_matches(&counters, &u);
+    assert_matches(&copy.counters(), &copy);
+
+    // 整个换掉之后原来的计数器跟着新面板
+    u.replace_with(copy);
+    assert_matches(&counters, &u);
+    u.tick();
+    assert_matches(&counters, &u);
+}
+
+#[test]
+fn counters_match_after_concurrent_ticks_and_resets() {
+    let universe = Arc::new(RwLock::new(
+        Universe::builder().width(64).height(64).seed(4).build(),
+    ));
+    let counters = universe.read().unwrap().counters();
+    let size = 64 * 64;
+    let done = Arc::new(AtomicBool::new(false));
+
+    // 不拿锁一直读, 读到的数始终在合理范围内
+    let reader = {
+        let (counters, done) = (Arc::clone(&counters), Arc::clone(&done));
+        thread::spawn(move || {
+            let mut reads = 0u64;
+            loop {
+                assert!(counters.population() <= size);
+                assert!(counters.generation() >= 0);
+                reads += 1;
+                if done.load(Ordering::Relaxed) {
+                    return reads;
+                }
+            }
+        })
+    };
+
+    let writers: Vec<_> = (0..4)
+        .map(|id: u32| {
+            let (universe, counters) = (Arc::clone(&universe), Arc::clone(&counters));
+            thread::spawn(move || {
+                for i in 0..50u32 {
+                    let mut u = universe.write().unwrap();
+                    match (id + i) % 5 {
+                        0 => u.reset(),
+                        1 => u.dead_all(),
+                        2 => {
+                            u.toggle_cell(i % 64, id * 16).unwrap();
+                        }
+                        _ => u.step_n(3),
+                    }
+                    assert_matches(&counters, &u);
+                }
+            })
+        })
+        .collect();
+    for writer in writers {
+        writer.join().unwrap();
+    }
+    done.store(true, Ordering::Relaxed);
+    assert!(reader.join().unwrap() > 0);
+    assert_matches(&counters, &universe.read().unwrap());
+}
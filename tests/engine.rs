@@ -0,0 +1,279 @@
+use life_game::patterns::{GLIDER, GOSPER_GLIDER_GUN};
+use life_game::universe::{step, SPARSE_EXIT};
+use life_game::{Cell, Engine, Rule, Stepper, Topology, Universe};
+
+/// R-pentomino, 一千多代之后才稳定下来
+const R_PENTOMINO: &[(u32, u32)] = &[(0, 1), (0, 2), (1, 0), (1, 1), (2, 1)];
+
+/// 图案里活细胞的 (row, col)
+type Pattern = &'static [(u32, u32)];
+
+fn pair(width: u32, height: u32, density: f32, seed: u64) -> (Universe, Universe) {
+    let dense = Universe::builder()
+        .width(width)
+        .height(height)
+        .density(density)
+        .seed(seed)
+        .build();
+    let mut sparse = dense.clone();
+    sparse.set_engine(Engine::Sparse);
+    (dense, sparse)
+}
+
+#[test]
+fn sparse_engine_matches_dense_engine() {
+    let rules = [
+        "B3/S23",
+        "B36/S23",
+        "B2/S",
+        "B3/S012345678",
+        "B1/S1V",
+        "B2/S0",
+    ];
+    let topologies = [Topology::Torus, Topology::Bounded, Topology::Mirror];
+    for seed in 0..4 {
+        for &(width, height) in [(1, 1), (3, 2), (40, 25), (65, 17)].iter() {
+            for text in rules.iter() {
+                for &topology in topologies.iter() {
+                    let (mut dense, mut sparse) = pair(width, height, 0.2, seed);
+                    for u in [&mut dense, &mut sparse].iter_mut() {
+                        u.set_rule(text.parse().unwrap());
+                        u.set_topology(topology);
+                    }
+                    for generation in 0..12 {
+                        dense.tick();
+                        sparse.tick();
+                        assert!(sparse.is_sparse());
+                        assert!(
+                            dense.same_cells(&sparse),
+                            "seed {} {}x{} {} {} generation {}",
+                            seed,
+                            width,
+                            height,
+                            text,
+                            topology,
+                            generation
+                        );
+                        assert_eq!(dense.population(), sparse.population());
+                        assert_eq!(
+                            (dense.births(), dense.deaths()),
+                            (sparse.births(), sparse.deaths())
+                        );
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn swar_engine_matches_scalar_engine() {
+    // 宽度覆盖不满 8 格、正好 8 格和跨过好几组的情况
+    let rules = [
+        "B3/S23",
+        "B36/S125",
+        "B0/S8",
+        "B1357/S02468",
+        "B2/S1V",
+        "B2/S34H",
+    ];
+    let topologies = [Topology::Torus, Topology::Bounded, Topology::Mirror];
+    for seed in 0..3 {
+        for &(width, height) in [(1, 1), (2, 3), (7, 5), (8, 8), (9, 4), (65, 17)].iter() {
+            for text in rules.iter() {
+                for &topology in topologies.iter() {
+                    let mut scalar = Universe::builder()
+                        .width(width)
+                        .height(height)
+                        .density(0.35)
+                        .seed(seed)
+                        .build();
+                    scalar.set_rule(text.parse().unwrap());
+                    scalar.set_topology(topology);
+                    let mut swar = scalar.clone();
+                    scalar.set_engine(Engine::Scalar);
+                    swar.set_engine(Engine::Swar);
+                    for generation in 0..10 {
+                        scalar.tick();
+                        swar.tick();
+                        assert!(
+                            scalar.same_cells(&swar),
+                            "seed {} {}x{} {} {} generation {}",
+                            seed,
+                            width,
+                            height,
+                            text,
+                            topology,
+                            generation
+                        );
+                        assert_eq!(
+                            (scalar.births(), scalar.deaths()),
+                            (swar.births(), swar.deaths())
+                        );
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn pure_step_matches_tick() {
+    let mut u = Universe::builder().width(50).height(30).seed(9).build();
+    let mut cells = u.cells().to_vec();
+    for _ in 0..20 {
+        cells = step(&cells, 50, 30, Rule::CONWAY);
+        u.tick();
+        assert_eq!(u.cells(), &cells[..]);
+    }
+
+    // 有界的六边形网格也一样, 返回的诞生和死亡数和 `tick` 相同
+    let rule: Rule = "B2/S34H".parse().unwrap();
+    u.set_rule(rule);
+    u.set_topology(Topology::Bounded);
+    let mut stepper = Stepper::new(Engine::Swar);
+    let mut next = vec![Cell::Dead; cells.len()];
+    for _ in 0..20 {
+        let counts = stepper.step_into(&cells, &mut next, (50, 30), rule, Topology::Bounded);
+        std::mem::swap(&mut cells, &mut next);
+        u.tick();
+        assert_eq!(u.cells(), &cells[..]);
+        assert_eq!(counts, (u.births(), u.deaths()));
+    }
+}
+
+#[test]
+fn tiled_engine_matches_dense_engine() {
+    // 尺寸不是 16 的整数倍, 滑翔机和枪打出的滑翔机会穿过块的边界和环面的接缝
+    let boards: [(Pattern, u32, u32); 4] = [
+        (GLIDER, 0, 0),
+        (GOSPER_GLIDER_GUN, 3, 5),
+        (R_PENTOMINO, 20, 30),
+        (&[], 0, 0),
+    ];
+    let cases = [
+        ("B3/S23", Topology::Torus),
+        ("B3/S23", Topology::Bounded),
+        ("B36/S23", Topology::Mirror),
+        ("B2/S34H", Topology::Torus),
+        ("B2/S1V", Topology::Torus),
+    ];
+    for (i, &(pattern, row, col)) in boards.iter().enumerate() {
+        for &(text, topology) in cases.iter() {
+            let mut dense = Universe::builder().width(70).height(45).seed(3).build();
+            if !pattern.is_empty() {
+                dense.dead_all();
+                dense.insert_pattern(pattern, row, col, true).unwrap();
+            }
+            dense.set_rule(text.parse().unwrap());
+            dense.set_topology(topology);
+            let mut tiled = dense.clone();
+            tiled.set_engine(Engine::Tiled);
+            for generation in 0..500 {
+                dense.tick();
+                tiled.tick();
+                assert!(
+                    dense.same_cells(&tiled),
+                    "board {} {} {} generation {}",
+                    i,
+                    text,
+                    topology,
+                    generation
+                );
+                assert_eq!(
+                    (dense.births(), dense.deaths()),
+                    (tiled.births(), tiled.deaths())
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn tiled_engine_follows_edits_and_skips_quiet_tiles() {
+    let mut dense = Universe::with_size(64, 64);
+    dense.dead_all();
+    dense.insert_pattern(GLIDER, 1, 1, true).unwrap();
+    let mut tiled = dense.clone();
+    tiled.set_engine(Engine::Tiled);
+    for generation in 0..200u32 {
+        if generation % 50 == 25 {
+            // 在静止的块里单独放一个方块, 它所在的块要重新计算
+            for u in [&mut dense, &mut tiled].iter_mut() {
+                u.set_cell(Cell::Alive, 40, 40).unwrap();
+                u.set_cell(Cell::Alive, 41, 40).unwrap();
+                u.set_cell(Cell::Alive, 40, 41).unwrap();
+            }
+        }
+        dense.tick();
+        tiled.tick();
+        assert!(dense.same_cells(&tiled), "generation {}", generation);
+        // 一个滑翔机和几个方块最多牵动两块周围的 3x3 块
+        assert!(tiled.dirty_tiles().count() <= 18);
+    }
+    assert!(dense.dirty_tiles().next().is_none());
+}
+
+#[test]
+fn sparse_engine_follows_edits_between_ticks() {
+    let (mut dense, mut sparse) = pair(48, 32, 0.1, 4);
+    for generation in 0..40u32 {
+        // 单个格子的修改、翻转、整体清空和重新生成都要同步到活细胞集合里
+        match generation % 10 {
+            3 => {
+                for u in [&mut dense, &mut sparse].iter_mut() {
+                    u.insert_pattern(GLIDER, generation, generation, true)
+                        .unwrap();
+                }
+            }
+            5 => {
+                for u in [&mut dense, &mut sparse].iter_mut() {
+                    u.toggle_cell(7, 9).unwrap();
+                    u.set_cell(Cell::Dead, 0, 0).unwrap();
+                }
+            }
+            7 => {
+                for u in [&mut dense, &mut sparse].iter_mut() {
+                    u.flip_horizontal();
+                }
+            }
+            9 => {
+                for u in [&mut dense, &mut sparse].iter_mut() {
+                    u.reset();
+                }
+            }
+            _ => {}
+        }
+        dense.tick();
+        sparse.tick();
+        assert!(dense.same_cells(&sparse), "generation {}", generation);
+        assert_eq!(dense.population(), sparse.population());
+    }
+}
+
+#[test]
+fn auto_engine_switches_with_density() {
+    let mut u = Universe::with_size(100, 100);
+    u.dead_all();
+    u.set_engine(Engine::Auto);
+    u.insert_pattern(GLIDER, 10, 10, true).unwrap();
+    u.tick();
+    assert!(u.is_sparse());
+
+    // 比例升到 SPARSE_EXIT 以上时换回逐行扫描
+    let cells = (100.0 * 100.0 * (SPARSE_EXIT + 0.05)) as u32;
+    for i in 0..cells {
+        u.set_cell(Cell::Alive, i % 100, 50 + i / 100 % 50).unwrap();
+    }
+    u.tick();
+    assert!(!u.is_sparse());
+
+    // 不支持的规则总是逐行扫描
+    u.set_engine(Engine::Sparse);
+    u.set_rule(Rule::BRIANS_BRAIN);
+    u.tick();
+    assert!(!u.is_sparse());
+    u.set_rule("B2/S34H".parse().unwrap());
+    u.tick();
+    assert!(!u.is_sparse());
+}
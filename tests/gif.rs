@@ -0,0 +1,78 @@
+#![cfg(feature = "gif")]
+
+use life_game::export::{RecordError, Recorder};
+use life_game::Universe;
+
+fn blinker() -> Universe {
+    ".....\n.....\n.###.\n.....\n.....\n".parse().unwrap()
+}
+
+fn decode(bytes: &[u8]) -> Vec<(u16, u16, u16, Vec<u8>)> {
+    let mut options = gif::DecodeOptions::new();
+    options.set_color_output(gif::ColorOutput::Indexed);
+    let mut decoder = options.read_info(bytes).unwrap();
+    let mut frames = Vec::new();
+    while let Some(frame) = decoder.read_next_frame().unwrap() {
+        frames.push((
+            frame.width,
+            frame.height,
+            frame.delay,
+            frame.buffer.to_vec(),
+        ));
+    }
+    frames
+}
+
+#[test]
+fn one_frame_per_generation() {
+    let mut u = blinker();
+    let mut rec = Recorder::new(2, 7, 100);
+    assert!(!rec.capture(&u));
+    rec.start(&u);
+    assert!(rec.is_recording());
+    for _ in 0..3 {
+        u.tick();
+        assert!(rec.capture(&u));
+        // 同一代里多次触发计时器不会多出帧
+        assert!(!rec.capture(&u));
+    }
+    assert_eq!(rec.frame_count(), 4);
+
+    let mut out = Vec::new();
+    assert_eq!(rec.finish(&mut out).unwrap(), 4);
+    assert!(!rec.is_recording());
+    assert_eq!(rec.frame_count(), 0);
+
+    let frames = decode(&out);
+    assert_eq!(frames.len(), 4);
+    for (i, (w, h, delay, pixels)) in frames.iter().enumerate() {
+        assert_eq!((*w, *h, *delay), (10, 10, 7));
+        let at = |row: usize, col: usize| pixels[row * 2 * 10 + col * 2];
+        // 偶数代是横条, 奇数代是竖条
+        assert_eq!(at(2, 1), if i % 2 == 0 { 1 } else { 0 });
+        assert_eq!(at(1, 2), if i % 2 == 0 { 0 } else { 1 });
+        assert_eq!(at(2, 2), 1);
+        assert_eq!(at(0, 0), 0);
+    }
+}
+
+#[test]
+fn frame_count_is_capped() {
+    let mut u = blinker();
+    let mut rec = Recorder::new(1, 5, 3);
+    rec.start(&u);
+    for _ in 0..10 {
+        u.tick();
+        rec.capture(&u);
+    }
+    assert_eq!(rec.frame_count(), 3);
+    let mut out = Vec::new();
+    rec.finish(&mut out).unwrap();
+    assert_eq!(decode(&out).len(), 3);
+}
+
+#[test]
+fn finishing_without_frames_is_an_error() {
+    let mut rec = Recorder::new(1, 5, 3);
+    assert!(matches!(rec.finish(Vec::new()), Err(RecordError::NoFrames)));
+}
@@ -0,0 +1,139 @@
+use life_game::patterns::{BLINKER, GLIDER, GOSPER_GLIDER_GUN};
+use life_game::universe::MAX_ADVANCE_LOG;
+use life_game::{Cell, HashLifeError, HashLifeUniverse, Region, Rule, Topology, Universe};
+
+/// R-pentomino, 一千多代之后才稳定下来
+const R_PENTOMINO: &[(u32, u32)] = &[(0, 1), (0, 2), (1, 0), (1, 1), (2, 1)];
+
+/// 240x240 的有界面板, 图案放在中间, 256 代之内碰不到边
+fn board(pattern: &[(u32, u32)]) -> Universe {
+    let mut u = Universe::from_cells(240, 240, vec![Cell::Dead; 240 * 240]).unwrap();
+    u.set_topology(Topology::Bounded);
+    u.insert_pattern(pattern, 100, 100, false).unwrap();
+    u
+}
+
+#[test]
+fn matches_dense_for_256_generations() {
+    let mut soup = Universe::builder().width(240).height(240).seed(7).build();
+    soup.dead_all();
+    soup.set_topology(Topology::Bounded);
+    soup.randomize_region(Region::new(104, 104, 32, 32), 0.4, false);
+    let boards = [
+        board(GLIDER),
+        board(GOSPER_GLIDER_GUN),
+        board(R_PENTOMINO),
+        soup,
+    ];
+    for (i, mut dense) in boards.iter().cloned().enumerate() {
+        // 放在负坐标上, 原点落在面板里面
+        let mut hashlife = HashLifeUniverse::from_dense(&dense, -200, -123).unwrap();
+        for generation in 1..=256 {
+            dense.tick();
+            hashlife.advance(0).unwrap();
+            let view = hashlife.to_dense(-200, -123, 240, 240);
+            assert!(
+                view.same_cells(&dense),
+                "board {} generation {}",
+                i,
+                generation
+            );
+            assert_eq!(hashlife.population(), u64::from(dense.population()));
+        }
+        assert_eq!(hashlife.count(), 256);
+    }
+}
+
+#[test]
+fn big_steps_match_single_steps() {
+    let mut single = HashLifeUniverse::new();
+    single.insert_pattern(R_PENTOMINO, 3, -5);
+    let mut jumped = single.clone();
+    for _ in 0..256 {
+        single.advance(0).unwrap();
+    }
+    jumped.advance(8).unwrap();
+    assert_eq!(jumped.count(), 256);
+    assert_eq!(jumped.live_cells(), single.live_cells());
+
+    // 按二进制拆开的 `step_n` 和分几次走到同一代的结果相同
+    let mut by_bits = HashLifeUniverse::new();
+    by_bits.insert_pattern(R_PENTOMINO, 3, -5);
+    by_bits.step_n(1234).unwrap();
+    for _ in 0..1234 - 256 {
+        single.advance(0).unwrap();
+    }
+    assert_eq!(by_bits.live_cells(), single.live_cells());
+}
+
+#[test]
+fn gosper_gun_runs_a_million_generations() {
+    let mut u = HashLifeUniverse::new();
+    u.insert_pattern(GOSPER_GLIDER_GUN, 0, 0);
+    u.step_n(999_990).unwrap();
+    assert_eq!(u.count(), 999_990);
+    // 周期 30 的整数倍时枪本身回到初始状态, 外面全是完整的滑翔机
+    let gun = u.to_dense(0, 0, 36, 9);
+    let mut original = Universe::from_cells(36, 9, vec![Cell::Dead; 36 * 9]).unwrap();
+    original
+        .insert_pattern(GOSPER_GLIDER_GUN, 0, 0, false)
+        .unwrap();
+    assert!(gun.same_cells(&original));
+    let gliders = (u.population() - GOSPER_GLIDER_GUN.len() as u64) / 5;
+    assert_eq!(gliders, 999_990 / 30);
+
+    u.step_n(10).unwrap();
+    assert_eq!(u.count(), 1_000_000);
+    // 最早的滑翔机走了将近 25 万格, 仍然按每 4 代一格往右下方走
+    let far = u.live_cells().into_iter().max().unwrap();
+    assert!(far.0 > 249_000 && far.1 > 249_000, "{:?}", far);
+}
+
+#[test]
+fn collects_nodes_over_the_limit() {
+    let mut u = HashLifeUniverse::new();
+    u.insert_pattern(R_PENTOMINO, 0, 0);
+    let mut reference = u.clone();
+    u.set_node_limit(1000);
+    for _ in 0..300 {
+        u.advance(0).unwrap();
+        assert!(u.node_count() < 20_000, "{} nodes", u.node_count());
+    }
+    reference.step_n(300).unwrap();
+    assert_eq!(u.live_cells(), reference.live_cells());
+    assert!(reference.node_count() > u.node_count());
+}
+
+#[test]
+fn edits_and_errors() {
+    let mut u = HashLifeUniverse::new();
+    u.insert_pattern(BLINKER, -1_000_000, 5_000_000);
+    assert_eq!(u.get_cell(-1_000_000, 5_000_001), Cell::Alive);
+    u.set_cell(-1_000_000, 5_000_001, Cell::Dead);
+    assert_eq!(u.population(), 2);
+    u.advance(0).unwrap();
+    assert_eq!(u.population(), 0);
+    u.clear();
+    assert_eq!((u.population(), u.count()), (0, 0));
+
+    assert_eq!(
+        u.advance(MAX_ADVANCE_LOG + 1).unwrap_err(),
+        HashLifeError::StepTooLarge(MAX_ADVANCE_LOG + 1)
+    );
+    u.insert_pattern(BLINKER, 0, 0);
+    assert_eq!(
+        u.step_n(u64::MAX).unwrap_err(),
+        HashLifeError::StepTooLarge(63)
+    );
+    assert_eq!(u.count(), 0);
+    let most = (2 << MAX_ADVANCE_LOG) - 1;
+    u.step_n(most).unwrap();
+    assert_eq!((u.count(), u.population()), (most as i64, 3));
+    let b0: Rule = "B0/S8".parse().unwrap();
+    assert_eq!(
+        HashLifeUniverse::with_rule(b0).unwrap_err(),
+        HashLifeError::UnsupportedRule(b0)
+    );
+    assert!(u.set_rule(Rule::BRIANS_BRAIN).is_err());
+    assert_eq!(u.rule(), Rule::CONWAY);
+}
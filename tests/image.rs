@@ -0,0 +1,94 @@
+#![cfg(feature = "image")]
+
+use std::env;
+use std::fs;
+
+use image::{DynamicImage, Rgba, RgbaImage};
+use life_game::universe::ImageOptions;
+use life_game::{Cell, Universe};
+
+fn options(width: u32, height: u32) -> ImageOptions {
+    ImageOptions {
+        width,
+        height,
+        ..ImageOptions::default()
+    }
+}
+
+// 左半边黑, 右半边白
+fn halves(width: u32, height: u32) -> DynamicImage {
+    DynamicImage::ImageRgba8(RgbaImage::from_fn(width, height, |x, _| {
+        if x < width / 2 {
+            Rgba([0, 0, 0, 255])
+        } else {
+            Rgba([255, 255, 255, 255])
+        }
+    }))
+}
+
+#[test]
+fn dark_pixels_become_alive() {
+    let u = Universe::from_dynamic_image(&halves(40, 20), &options(8, 4));
+    assert_eq!(u.to_string(), "◼◼◼◼◻◻◻◻\n".repeat(4));
+
+    // 中灰在阈值两侧
+    let gray = DynamicImage::ImageRgba8(RgbaImage::from_pixel(4, 4, Rgba([100, 100, 100, 255])));
+    let dark = Universe::from_dynamic_image(&gray, &options(2, 2));
+    assert_eq!(dark.population(), 4);
+    let light = Universe::from_dynamic_image(
+        &gray,
+        &ImageOptions {
+            threshold: 90,
+            ..options(2, 2)
+        },
+    );
+    assert_eq!(light.population(), 0);
+}
+
+#[test]
+fn transparent_pixels_are_dead() {
+    let img = RgbaImage::from_fn(4, 1, |x, _| {
+        Rgba([0, 0, 0, if x % 2 == 0 { 255 } else { 0 }])
+    });
+    let u = Universe::from_dynamic_image(&DynamicImage::ImageRgba8(img), &options(4, 1));
+    assert_eq!(u.to_string(), "◼◻◼◻\n");
+}
+
+#[test]
+fn small_images_can_be_centered_without_scaling() {
+    let black = DynamicImage::ImageRgba8(RgbaImage::from_pixel(2, 2, Rgba([0, 0, 0, 255])));
+    let centered = Universe::from_dynamic_image(
+        &black,
+        &ImageOptions {
+            scale_up: false,
+            ..options(6, 4)
+        },
+    );
+    assert_eq!(
+        centered.live_cells().collect::<Vec<_>>(),
+        vec![(1, 2), (1, 3), (2, 2), (2, 3)]
+    );
+    let scaled = Universe::from_dynamic_image(&black, &options(6, 4));
+    assert_eq!(scaled.population(), 24);
+}
+
+#[test]
+fn loads_png_from_disk() {
+    let path = env::temp_dir().join(format!("life_import_{}.png", std::process::id()));
+    halves(64, 64).save(&path).unwrap();
+    let u = Universe::from_image(&path, 128).unwrap();
+    fs::remove_file(&path).unwrap();
+    assert_eq!((u.width(), u.height()), (64, 64));
+    assert_eq!(u.get_cell(10, 31), Some(Cell::Alive));
+    assert_eq!(u.get_cell(10, 32), Some(Cell::Dead));
+    assert_eq!(u.population(), 32 * 64);
+}
+
+#[test]
+fn unreadable_files_are_errors() {
+    let path = env::temp_dir().join(format!("life_import_{}.bmp", std::process::id()));
+    fs::write(&path, b"not an image").unwrap();
+    let result = Universe::from_image(&path, 128);
+    fs::remove_file(&path).unwrap();
+    assert!(result.is_err());
+}
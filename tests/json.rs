@@ -0,0 +1,51 @@
+#![cfg(feature = "json")]
+
+use life_game::universe::JsonError;
+use life_game::Universe;
+
+#[test]
+fn json_round_trip() {
+    let mut u = Universe::builder().width(12).height(9).seed(4).build();
+    u.step_n(7);
+    let text = u.to_json();
+    assert!(text.starts_with(r#"{"width":12,"height":9,"generation":7,"alive":[["#));
+    let back = Universe::from_json(&text).unwrap();
+    assert_eq!(back, u);
+    assert_eq!(back.count(), 7);
+}
+
+#[test]
+fn sparse_board_lists_live_cells_only() {
+    let u: Universe = "...\n.#.\n".parse().unwrap();
+    assert_eq!(
+        u.to_json(),
+        r#"{"width":3,"height":2,"generation":0,"alive":[[1,1]]}"#
+    );
+}
+
+#[test]
+fn accepts_whitespace_from_other_tools() {
+    let text =
+        "{\n  \"alive\": [[0, 2]],\n  \"generation\": 3,\n  \"height\": 1,\n  \"width\": 4\n}";
+    let u = Universe::from_json(text).unwrap();
+    assert_eq!(u.to_string(), "◻◻◼◻\n");
+    assert_eq!(u.count(), 3);
+}
+
+#[test]
+fn out_of_range_coordinates_are_rejected() {
+    let text = r#"{"width":3,"height":2,"generation":0,"alive":[[0,0],[2,1]]}"#;
+    assert!(matches!(
+        Universe::from_json(text),
+        Err(JsonError::OutOfRange { row: 2, col: 1 })
+    ));
+    let text = r#"{"width":3,"height":2,"generation":0,"alive":[[1,3]]}"#;
+    assert!(matches!(
+        Universe::from_json(text),
+        Err(JsonError::OutOfRange { row: 1, col: 3 })
+    ));
+    assert!(matches!(
+        Universe::from_json(r#"{"width":3}"#),
+        Err(JsonError::Syntax(_))
+    ));
+}
@@ -0,0 +1,65 @@
+use std::path::Path;
+
+use life_game::formats::life106::{parse, serialize, Life106Error};
+use life_game::formats::{parse_by_extension, FormatError, Pattern};
+
+#[test]
+fn negative_coordinates_are_normalized() {
+    let text = "#Life 1.06\n0 -1\n1 0\n-1 1\n0 1\n1 1\n";
+    let p = parse(text).unwrap();
+    assert_eq!((p.width, p.height), (3, 3));
+    assert_eq!(p.cells, vec![(0, 1), (1, 2), (2, 0), (2, 1), (2, 2)]);
+}
+
+#[test]
+fn windows_line_endings() {
+    let p = parse("#Life 1.06\r\n0 0\r\n1 0\r\n2 0\r\n").unwrap();
+    assert_eq!(p.cells, vec![(0, 0), (0, 1), (0, 2)]);
+    assert_eq!((p.width, p.height), (3, 1));
+}
+
+#[test]
+fn serialize_is_relative_to_bounding_box() {
+    let p = Pattern {
+        width: 10,
+        height: 10,
+        cells: vec![(4, 5), (5, 6)],
+        ..Pattern::default()
+    };
+    assert_eq!(serialize(&p), "#Life 1.06\n0 0\n1 1\n");
+    let back = parse(&serialize(&p)).unwrap();
+    assert_eq!(back.cells, vec![(0, 0), (1, 1)]);
+}
+
+#[test]
+fn malformed_files_are_rejected() {
+    assert_eq!(parse("0 0\n").unwrap_err(), Life106Error::MissingHeader);
+    assert_eq!(
+        parse("#Life 1.06\n0 0\n1 x\n").unwrap_err(),
+        Life106Error::BadLine { line: 3 }
+    );
+    assert_eq!(
+        parse("#Life 1.06\n0 0 0\n").unwrap_err(),
+        Life106Error::BadLine { line: 2 }
+    );
+}
+
+#[test]
+fn coordinates_beyond_u32_are_rejected() {
+    let too_wide = format!("#Life 1.06\n{} 0\n{} 0\n", i64::MIN, i64::MAX);
+    assert_eq!(parse(&too_wide).unwrap_err(), Life106Error::TooLarge);
+    let too_tall = format!("#Life 1.06\n0 0\n0 {}\n", u32::MAX);
+    assert_eq!(parse(&too_tall).unwrap_err(), Life106Error::TooLarge);
+    let widest = format!("#Life 1.06\n0 0\n{} 0\n", u32::MAX - 1);
+    assert_eq!(parse(&widest).unwrap().width, u32::MAX);
+}
+
+#[test]
+fn lif_extension_sniffs_header() {
+    let p = parse_by_extension(Path::new("glider.lif"), "#Life 1.06\n0 0\n").unwrap();
+    assert_eq!(p.cells, vec![(0, 0)]);
+    assert!(matches!(
+        parse_by_extension(Path::new("glider.lif"), "#Life 1.05\n#P 0 0\n*\n"),
+        Err(FormatError::UnknownFormat(_))
+    ));
+}
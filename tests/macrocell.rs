@@ -0,0 +1,207 @@
+use std::env;
+use std::fs;
+use std::path::Path;
+
+use life_game::formats::macrocell::{parse, read_universe, serialize, write_universe};
+use life_game::formats::macrocell::{Cropped, MacrocellError};
+use life_game::formats::{load_board_file, parse_by_extension, Pattern};
+use life_game::patterns::GLIDER;
+use life_game::Universe;
+
+const GLIDER_MC: &str = "[M2] (golly 2.0)
+#N Glider
+#R B3/S23
+.*$..*$***$
+";
+
+// 两个方块: 一个在左上的叶子里, 另一个在右下, 共用同一个叶子节点
+const TWO_BLOCKS_MC: &str = "[M2] (golly 2.0)
+#R B3/S23
+**$**$
+4 1 0 0 1
+";
+
+// 64x64 的四叉树, 只在最右下角有一个方块
+const FAR_BLOCK_MC: &str = "[M2] (golly 2.0)
+......**$......**$
+4 0 0 0 1
+5 0 0 0 2
+6 0 0 0 3
+";
+
+#[test]
+fn parses_single_leaf() {
+    let p = parse(GLIDER_MC).unwrap();
+    assert_eq!(p.cells, GLIDER.to_vec());
+    assert_eq!((p.width, p.height), (3, 3));
+    assert_eq!(p.name.as_deref(), Some("Glider"));
+    assert_eq!(p.rule.as_deref(), Some("B3/S23"));
+}
+
+#[test]
+fn shared_nodes_expand_in_each_quadrant() {
+    let p = parse(TWO_BLOCKS_MC).unwrap();
+    assert_eq!(
+        p.cells,
+        vec![
+            (0, 0),
+            (0, 1),
+            (1, 0),
+            (1, 1),
+            (8, 8),
+            (8, 9),
+            (9, 8),
+            (9, 9)
+        ]
+    );
+    assert_eq!((p.width, p.height), (10, 10));
+}
+
+#[test]
+fn serialize_round_trips() {
+    let p = parse(TWO_BLOCKS_MC).unwrap();
+    assert_eq!(
+        serialize(&p),
+        "[M2] (game_life)\n#R B3/S23\n**$**$\n4 1 0 0 1\n"
+    );
+    assert_eq!(parse(&serialize(&p)).unwrap(), p);
+
+    let glider = parse(GLIDER_MC).unwrap();
+    assert_eq!(
+        serialize(&glider),
+        "[M2] (game_life)\n#N Glider\n#R B3/S23\n.*$..*$***$\n"
+    );
+    assert_eq!(parse(&serialize(&glider)).unwrap(), glider);
+}
+
+#[test]
+fn universe_round_trips_through_flat_cells() {
+    let u = Universe::builder().width(20).height(13).seed(9).build();
+    let text = write_universe(&u);
+    let (back, cropped) = read_universe(&text, 20, 13).unwrap();
+    assert_eq!(back, u);
+    assert_eq!(cropped, None);
+}
+
+#[test]
+fn larger_files_are_cropped_with_a_warning() {
+    let (u, cropped) = read_universe(TWO_BLOCKS_MC, 8, 8).unwrap();
+    assert_eq!(
+        u.live_cells().collect::<Vec<_>>(),
+        vec![(0, 0), (0, 1), (1, 0), (1, 1)]
+    );
+    assert_eq!(cropped, Some(Cropped { dropped: 4 }));
+
+    let (u, cropped) = read_universe(FAR_BLOCK_MC, 63, 64).unwrap();
+    assert_eq!(u.live_cells().collect::<Vec<_>>(), vec![(56, 62), (57, 62)]);
+    assert_eq!(cropped, Some(Cropped { dropped: 2 }));
+
+    let p = parse(FAR_BLOCK_MC).unwrap();
+    assert_eq!((p.width, p.height), (2, 2));
+}
+
+#[test]
+fn malformed_files_are_rejected() {
+    assert_eq!(parse(".*$\n").unwrap_err(), MacrocellError::MissingHeader);
+    assert_eq!(
+        parse("[M2]\n.........*$\n").unwrap_err(),
+        MacrocellError::BadLeaf { line: 2 }
+    );
+    // 引用了还没定义的节点
+    assert_eq!(
+        parse("[M2]\n*$\n4 1 0 0 2\n").unwrap_err(),
+        MacrocellError::BadNode { line: 3 }
+    );
+    // 第 5 层的子节点必须是第 4 层
+    assert_eq!(
+        parse("[M2]\n*$\n5 1 0 0 0\n").unwrap_err(),
+        MacrocellError::BadNode { line: 3 }
+    );
+}
+
+/// 从一个叶子起, 每层四个象限都指向上一层, 一直到 `top` 层: 整棵树都是活细胞
+fn full_tree(top: u32) -> String {
+    let mut text = String::from("[M2]\n");
+    text.push_str(&"********$".repeat(8));
+    text.push('\n');
+    for level in 4..=top {
+        let child = level - 3;
+        text.push_str(&format!(
+            "{} {} {} {} {}\n",
+            level, child, child, child, child
+        ));
+    }
+    text
+}
+
+/// 只在最左上和最右下各有一个活细胞的 `top` 层的树
+fn far_corners(top: u32) -> String {
+    let mut text = String::from("[M2]\n*$\n$$$$$$$.......*$\n");
+    for level in 4..top {
+        let (first, last) = (2 * level - 7, 2 * level - 6);
+        text.push_str(&format!("{} {} 0 0 0\n", level, first));
+        text.push_str(&format!("{} 0 0 0 {}\n", level, last));
+    }
+    let (first, last) = (2 * top - 7, 2 * top - 6);
+    text.push_str(&format!("{} {} 0 0 {}\n", top, first, last));
+    text
+}
+
+#[test]
+fn huge_trees_are_rejected_before_expanding() {
+    // 第 62 层全满的树有 4^62 个活细胞, 加起来会溢出 u64
+    assert_eq!(parse(&full_tree(62)).unwrap_err(), MacrocellError::TooLarge);
+    assert_eq!(
+        read_universe(&full_tree(62), 8, 8).unwrap_err(),
+        MacrocellError::TooLarge
+    );
+    // 2^20 x 2^20 全是活细胞, 超出 u32 个格子
+    assert_eq!(parse(&full_tree(20)).unwrap_err(), MacrocellError::TooLarge);
+    // 只有两个活细胞, 但两端相距 2^40, 放不进 u32 坐标
+    assert_eq!(
+        parse(&far_corners(40)).unwrap_err(),
+        MacrocellError::TooLarge
+    );
+
+    // 面板够小时只展开面板里的部分
+    let (u, cropped) = read_universe(&full_tree(20), 4, 2).unwrap();
+    assert_eq!(u.live_cells().count(), 8);
+    assert_eq!(
+        cropped,
+        Some(Cropped {
+            dropped: (1 << 40) - 8
+        })
+    );
+    let (u, cropped) = read_universe(&far_corners(40), 4, 4).unwrap();
+    assert_eq!(u.live_cells().collect::<Vec<_>>(), vec![(0, 0)]);
+    assert_eq!(cropped, Some(Cropped { dropped: 1 }));
+
+    let p = parse(&far_corners(16)).unwrap();
+    assert_eq!((p.width, p.height), (1 << 16, 1 << 16));
+    assert_eq!(p.cells, vec![(0, 0), ((1 << 16) - 1, (1 << 16) - 1)]);
+}
+
+#[test]
+fn board_loading_crops_files_that_do_not_fit() {
+    let path = env::temp_dir().join(format!("life_far_corners_{}.mc", std::process::id()));
+    fs::write(&path, far_corners(40)).unwrap();
+    let (p, cropped) = load_board_file(&path, 16, 8).unwrap();
+    assert_eq!((p.width, p.height), (16, 8));
+    assert_eq!(p.cells, vec![(0, 0)]);
+    assert_eq!(cropped, Some(Cropped { dropped: 1 }));
+
+    // 放得下时和其他格式一样按外接矩形返回, 不提示裁剪
+    fs::write(&path, TWO_BLOCKS_MC).unwrap();
+    let (p, cropped) = load_board_file(&path, 16, 16).unwrap();
+    assert_eq!(p, parse(TWO_BLOCKS_MC).unwrap());
+    assert_eq!(cropped, None);
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn mc_extension_is_recognized() {
+    let p = parse_by_extension(Path::new("glider.MC"), GLIDER_MC).unwrap();
+    assert_eq!(p.cells, GLIDER.to_vec());
+    let empty = Pattern::default();
+    assert_eq!(parse(&serialize(&empty)).unwrap().cells, empty.cells);
+}
@@ -0,0 +1,62 @@
+use life_game::{Cell, ParseUniverseError, Universe};
+
+#[test]
+fn ascii_and_glyphs_parse_the_same() {
+    let ascii: Universe = ".#.\n..#\n###\n".parse().unwrap();
+    let glyphs: Universe = "◻◼◻\n◻◻◼\n◼◼◼\n".parse().unwrap();
+    assert_eq!(ascii.width(), 3);
+    assert_eq!(ascii.height(), 3);
+    assert_eq!(ascii.cells(), glyphs.cells());
+    assert_eq!(ascii.get_cell(0, 1), Some(Cell::Alive));
+    assert_eq!(ascii.get_cell(1, 0), Some(Cell::Dead));
+}
+
+#[test]
+fn display_round_trips_on_non_square_board() {
+    let u = Universe::builder().width(9).height(4).seed(11).build();
+    let text = u.to_string();
+    let parsed: Universe = text.parse().unwrap();
+    assert_eq!(parsed.width(), 9);
+    assert_eq!(parsed.height(), 4);
+    assert_eq!(parsed.to_string(), text);
+}
+
+#[test]
+fn trailing_newline_variations() {
+    let expected: Universe = "#.\n.#".parse().unwrap();
+    for text in &["#.\n.#\n", "#.\r\n.#\r\n", "#.\n.#\n\n\n", "#.\r\n.#"] {
+        let u: Universe = text.parse().unwrap();
+        assert_eq!(u.cells(), expected.cells(), "{:?}", text);
+        assert_eq!(u.height(), 2);
+    }
+}
+
+#[test]
+fn ragged_lines_are_rejected() {
+    let err = "###\n##\n".parse::<Universe>().err();
+    assert_eq!(
+        err,
+        Some(ParseUniverseError::RaggedLine {
+            line: 2,
+            expected: 3,
+            found: 2
+        })
+    );
+}
+
+#[test]
+fn invalid_characters_are_rejected() {
+    let err = "#.\n.x\n".parse::<Universe>().err();
+    assert_eq!(
+        err,
+        Some(ParseUniverseError::InvalidChar {
+            line: 2,
+            col: 2,
+            ch: 'x'
+        })
+    );
+    assert_eq!(
+        "\n\n".parse::<Universe>().err(),
+        Some(ParseUniverseError::Empty)
+    );
+}
@@ -0,0 +1,79 @@
+use life_game::formats::{parse_text, FormatError, Pattern};
+use life_game::patterns::GLIDER;
+use life_game::view::cell_at;
+use life_game::{Cell, Universe, UniverseError};
+
+fn empty(width: u32, height: u32) -> Universe {
+    Universe::from_cells(width, height, vec![Cell::Dead; (width * height) as usize]).unwrap()
+}
+
+#[test]
+fn clipboard_text_is_rle_or_plaintext() {
+    let rle = parse_text("x = 3, y = 3\nbo$2bo$3o!\n").unwrap();
+    assert_eq!(rle.cells, GLIDER.to_vec());
+    let cells = parse_text("!Name: Glider\n.O\n..O\nOOO\n").unwrap();
+    assert_eq!(cells.cells, GLIDER.to_vec());
+    assert!(matches!(
+        parse_text("hello world"),
+        Err(FormatError::Plaintext(_))
+    ));
+    // 有 RLE 头但内容错误时报告 RLE 的错误
+    assert!(matches!(
+        parse_text("x = 3, y = 3\nbo$2bo$3o\n"),
+        Err(FormatError::Rle(_))
+    ));
+}
+
+#[test]
+fn place_at_cursor_or_center() {
+    let glider = parse_text(".O\n..O\nOOO\n").unwrap();
+    let mut u = empty(10, 8);
+    assert_eq!(glider.place(&mut u, Some((1, 2))).unwrap(), (1, 2));
+    assert_eq!(
+        u.live_cells().collect::<Vec<_>>(),
+        vec![(1, 3), (2, 4), (3, 2), (3, 3), (3, 4)]
+    );
+
+    let mut u = empty(10, 8);
+    assert_eq!(glider.place(&mut u, None).unwrap(), (2, 3));
+    assert_eq!(u.population(), 5);
+
+    // 靠近右下角时绕回左上
+    let mut u = empty(10, 8);
+    glider.place(&mut u, Some((7, 9))).unwrap();
+    assert_eq!(u.get_cell(7, 0), Some(Cell::Alive));
+    assert_eq!(u.get_cell(1, 9), Some(Cell::Alive));
+}
+
+#[test]
+fn oversized_patterns_report_required_size() {
+    let wide = Pattern {
+        width: 12,
+        height: 1,
+        cells: vec![(0, 0), (0, 11)],
+        ..Pattern::default()
+    };
+    let mut u = empty(10, 8);
+    let err = wide.place(&mut u, None).unwrap_err();
+    assert_eq!(
+        err,
+        UniverseError::PatternTooLarge {
+            pattern: (12, 1),
+            board: (10, 8)
+        }
+    );
+    assert_eq!(
+        err.to_string(),
+        "pattern needs a 12x1 board, this one is 10x8"
+    );
+    assert_eq!(u.population(), 0);
+}
+
+#[test]
+fn cursor_maps_to_cell() {
+    assert_eq!(cell_at(0, 0, 13, 13, 64, 64), Some((0, 0)));
+    assert_eq!(cell_at(27, 14, 13, 13, 64, 64), Some((1, 2)));
+    assert_eq!(cell_at(13 * 64 - 1, 5, 13, 13, 64, 64), Some((0, 63)));
+    assert_eq!(cell_at(13 * 64, 5, 13, 13, 64, 64), None);
+    assert_eq!(cell_at(-1, 5, 13, 13, 64, 64), None);
+}
@@ -0,0 +1,60 @@
+use std::path::Path;
+
+use life_game::formats::plaintext::{parse, serialize, PlaintextError};
+use life_game::formats::{parse_by_extension, FormatError};
+
+#[test]
+fn blinker_round_trips() {
+    let text = "!Name: Blinker\nOOO\n";
+    let p = parse(text).unwrap();
+    assert_eq!(p.name.as_deref(), Some("Blinker"));
+    assert_eq!((p.width, p.height), (3, 1));
+    assert_eq!(p.cells, vec![(0, 0), (0, 1), (0, 2)]);
+    assert_eq!(serialize(&p), text);
+    assert_eq!(parse(&serialize(&p)).unwrap(), p);
+}
+
+#[test]
+fn multi_comment_file_with_ragged_lines() {
+    let text = "!Name: Glider\n!Author: Richard K. Guy\n!The smallest spaceship.\n.O\n..O\nOOO\n";
+    let p = parse(text).unwrap();
+    assert_eq!(
+        p.comments,
+        vec![
+            "Author: Richard K. Guy".to_string(),
+            "The smallest spaceship.".to_string()
+        ]
+    );
+    assert_eq!((p.width, p.height), (3, 3));
+    assert_eq!(p.cells, vec![(0, 1), (1, 2), (2, 0), (2, 1), (2, 2)]);
+    let out = serialize(&p);
+    assert!(out.ends_with(".O.\n..O\nOOO\n"));
+    assert_eq!(parse(&out).unwrap(), p);
+}
+
+#[test]
+fn blank_lines_are_dead_rows() {
+    let p = parse("O\n\nO\n\n").unwrap();
+    assert_eq!((p.width, p.height), (1, 3));
+    assert_eq!(p.cells, vec![(0, 0), (2, 0)]);
+}
+
+#[test]
+fn unexpected_characters_are_rejected() {
+    assert_eq!(
+        parse("!c\n.O\n.x\n").unwrap_err(),
+        PlaintextError::UnexpectedChar { line: 3, ch: 'x' }
+    );
+}
+
+#[test]
+fn loader_dispatches_on_extension() {
+    let p = parse_by_extension(Path::new("blinker.CELLS"), "OOO\n").unwrap();
+    assert_eq!(p.cells.len(), 3);
+    let p = parse_by_extension(Path::new("dir/blinker.rle"), "x = 3, y = 1\n3o!").unwrap();
+    assert_eq!(p.cells.len(), 3);
+    assert!(matches!(
+        parse_by_extension(Path::new("blinker.txt"), "OOO\n"),
+        Err(FormatError::UnknownFormat(_))
+    ));
+}
@@ -0,0 +1,57 @@
+#![cfg(feature = "png")]
+
+use std::env;
+use std::fs::{self, File};
+
+use life_game::export::{export_heat_png, export_png, HEAT_PALETTE};
+use life_game::Universe;
+
+#[test]
+fn png_has_scaled_size_and_black_live_cells() {
+    // 左上角和右下角各有一个活细胞
+    let u: Universe = "#...\n....\n...#\n".parse().unwrap();
+    let path = env::temp_dir().join(format!("life_export_{}.png", std::process::id()));
+    export_png(&u, 5, &path).unwrap();
+
+    let decoder = png::Decoder::new(File::open(&path).unwrap());
+    let mut reader = decoder.read_info().unwrap();
+    let mut pixels = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut pixels).unwrap();
+    fs::remove_file(&path).unwrap();
+
+    assert_eq!((info.width, info.height), (20, 15));
+    assert_eq!(info.color_type, png::ColorType::Grayscale);
+    let at = |x: usize, y: usize| pixels[y * info.line_size + x];
+    assert_eq!(at(0, 0), 0);
+    assert_eq!(at(4, 4), 0);
+    assert_eq!(at(5, 0), 255);
+    assert_eq!(at(0, 5), 255);
+    assert_eq!(at(10, 7), 255);
+    assert_eq!(at(15, 10), 0);
+    assert_eq!(at(19, 14), 0);
+    assert_eq!(at(14, 14), 255);
+}
+
+#[test]
+fn heat_png_colors_cells_by_activity() {
+    let mut u: Universe = ".....\n.....\n.###.\n.....\n.....\n".parse().unwrap();
+    for _ in 0..30 {
+        u.tick();
+    }
+    let path = env::temp_dir().join(format!("life_heat_{}.png", std::process::id()));
+    export_heat_png(&u, 2, &path).unwrap();
+
+    let decoder = png::Decoder::new(File::open(&path).unwrap());
+    let mut reader = decoder.read_info().unwrap();
+    let mut pixels = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut pixels).unwrap();
+    fs::remove_file(&path).unwrap();
+
+    assert_eq!((info.width, info.height), (10, 10));
+    assert_eq!(info.color_type, png::ColorType::Rgb);
+    let at = |x: usize, y: usize| &pixels[y * info.line_size + x * 3..][..3];
+    // 角落从没变过, 闪烁器的两端每代都变, 中间一直活着
+    assert_eq!(at(0, 0), HEAT_PALETTE[0]);
+    assert_eq!(at(2, 4), HEAT_PALETTE[HEAT_PALETTE.len() - 1]);
+    assert_eq!(at(5, 5), HEAT_PALETTE[0]);
+}
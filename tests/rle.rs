@@ -0,0 +1,130 @@
+use life_game::formats::rle::{parse_rle, write_rle, RleError};
+use life_game::formats::Pattern;
+use life_game::patterns::GLIDER;
+use life_game::{Cell, Universe};
+
+const GLIDER_RLE: &str = "#N Glider
+#C The smallest spaceship.
+x = 3, y = 3, rule = B3/S23
+bob$2bo$3o!
+";
+
+const GOSPER_GUN_RLE: &str = "#N Gosper glider gun
+#O Bill Gosper
+x = 36, y = 9, rule = B3/S23
+24bo$22bobo$12b2o6b2o12b2o$11bo3bo4b2o12b2o$2o8bo5bo3b2o$2o8bo3bob2o4b
+obo$10bo5bo7bo$11bo3bo$12b2o!
+";
+
+fn empty(width: u32, height: u32) -> Universe {
+    Universe::from_cells(width, height, vec![Cell::Dead; (width * height) as usize]).unwrap()
+}
+
+#[test]
+fn parses_glider() {
+    let p = parse_rle(GLIDER_RLE).unwrap();
+    assert_eq!(p.width, 3);
+    assert_eq!(p.height, 3);
+    assert_eq!(p.cells, GLIDER.to_vec());
+    assert_eq!(p.name.as_deref(), Some("Glider"));
+    assert_eq!(p.comments, vec!["The smallest spaceship.".to_string()]);
+    assert_eq!(p.rule.as_deref(), Some("B3/S23"));
+}
+
+#[test]
+fn parses_gosper_gun_and_it_fires() {
+    let p = parse_rle(GOSPER_GUN_RLE).unwrap();
+    assert_eq!((p.width, p.height), (36, 9));
+    assert_eq!(p.cells.len(), 36);
+    let mut u = empty(60, 40);
+    p.stamp(&mut u, 2, 2, false).unwrap();
+    assert_eq!(u.population(), 36);
+    // 一个周期之后枪恢复原样并多出一个滑翔机
+    u.step_n(30);
+    assert_eq!(u.population(), 41);
+}
+
+#[test]
+fn header_without_rule_and_old_style_rule_are_accepted() {
+    let p = parse_rle("x = 2, y = 1\n2o!").unwrap();
+    assert_eq!(p.cells, vec![(0, 0), (0, 1)]);
+    let p = parse_rle("x=3,y=2,rule=23/3\no$2bo!").unwrap();
+    assert_eq!(p.cells, vec![(0, 0), (1, 2)]);
+}
+
+#[test]
+fn unknown_rule_is_reported() {
+    let err = parse_rle("x = 3, y = 3, rule = B39/S23\n3o!").unwrap_err();
+    assert_eq!(err, RleError::UnsupportedRule("B39/S23".to_string()));
+    let p = parse_rle("x = 3, y = 1, rule = B36/S23\n3o!").unwrap();
+    assert_eq!(p.rule.as_deref(), Some("B36/S23"));
+    let p = parse_rle("x = 3, y = 1, rule = R5,C0,M1,S34..58,B34..45,NM\n3o!").unwrap();
+    assert_eq!(p.rule.as_deref(), Some("R5,C0,M1,S34..58,B34..45,NM"));
+}
+
+#[test]
+fn missing_terminator_is_an_error() {
+    assert_eq!(
+        parse_rle("x = 3, y = 3\nbob$2bo$3o").unwrap_err(),
+        RleError::MissingTerminator
+    );
+}
+
+#[test]
+fn counts_overflowing_the_extent_are_errors() {
+    assert_eq!(
+        parse_rle("x = 3, y = 3\n4o!").unwrap_err(),
+        RleError::OutOfExtent { row: 0, col: 3 }
+    );
+    assert_eq!(
+        parse_rle("x = 3, y = 2\no$o$o!").unwrap_err(),
+        RleError::OutOfExtent { row: 2, col: 0 }
+    );
+    assert_eq!(
+        parse_rle("x = 3, y = 3\n99999999999o!").unwrap_err(),
+        RleError::BadCount { line: 2 }
+    );
+}
+
+#[test]
+fn malformed_input_is_rejected() {
+    assert_eq!(
+        parse_rle("#C only a comment\n").unwrap_err(),
+        RleError::MissingHeader
+    );
+    assert!(matches!(
+        parse_rle("x = three, y = 3\n3o!").unwrap_err(),
+        RleError::BadHeader(_)
+    ));
+    assert_eq!(
+        parse_rle("x = 3, y = 3\nbqb!").unwrap_err(),
+        RleError::UnexpectedChar { line: 2, ch: 'q' }
+    );
+}
+
+#[test]
+fn write_round_trips_gosper_gun() {
+    let p = parse_rle(GOSPER_GUN_RLE).unwrap();
+    let text = write_rle(&p);
+    assert!(text.lines().all(|l| l.len() <= 70));
+    let back = parse_rle(&text).unwrap();
+    assert_eq!(back, p);
+}
+
+#[test]
+fn pattern_from_universe_crops_across_seam() {
+    let mut u = empty(10, 8);
+    u.insert_pattern(GLIDER, 7, 9, true).unwrap();
+    let p = Pattern::from_universe(&u);
+    assert_eq!((p.width, p.height), (3, 3));
+    assert_eq!(p.cells, GLIDER.to_vec());
+    assert_eq!(write_rle(&p), "x = 3, y = 3, rule = B3/S23\nbo$2bo$3o!\n");
+}
+
+#[test]
+fn empty_board_writes_header_only() {
+    let text = write_rle(&Pattern::from_universe(&empty(8, 8)));
+    assert_eq!(text, "x = 0, y = 0, rule = B3/S23\n!\n");
+    let p = parse_rle(&text).unwrap();
+    assert!(p.cells.is_empty());
+}
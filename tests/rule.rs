@@ -0,0 +1,765 @@
+use life_game::rule::{self, Range, RuleHistory, PRESETS};
+use life_game::{Cell, Neighborhood, Rule, RuleError, Topology, Universe};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+const REPLICATOR: &[(u32, u32)] = &[
+    (0, 2),
+    (0, 3),
+    (0, 4),
+    (1, 1),
+    (1, 4),
+    (2, 0),
+    (2, 4),
+    (3, 0),
+    (3, 3),
+    (4, 0),
+    (4, 1),
+    (4, 2),
+];
+
+fn empty(width: u32, height: u32) -> Universe {
+    Universe::from_cells(width, height, vec![Cell::Dead; (width * height) as usize]).unwrap()
+}
+
+#[test]
+fn parses_common_notations() {
+    let conway: Rule = "B3/S23".parse().unwrap();
+    assert_eq!(conway, Rule::CONWAY);
+    assert_eq!(conway, Rule::default());
+    assert_eq!("23/3".parse::<Rule>().unwrap(), Rule::CONWAY);
+    assert_eq!("s23/b3".parse::<Rule>().unwrap(), Rule::CONWAY);
+    assert_eq!(" B3/S23 ".parse::<Rule>().unwrap(), Rule::CONWAY);
+
+    let seeds: Rule = "B2/S".parse().unwrap();
+    assert_eq!(seeds, Rule::new(&[2], &[]));
+    assert_eq!(seeds.to_string(), "B2/S");
+    let high_life: Rule = "B36/S23".parse().unwrap();
+    assert_eq!(high_life.to_string(), "B36/S23");
+    assert_eq!(high_life.births().collect::<Vec<_>>(), vec![3, 6]);
+    assert_eq!(high_life.survivals().collect::<Vec<_>>(), vec![2, 3]);
+}
+
+#[test]
+fn rejects_bad_rules() {
+    assert_eq!(
+        "B39/S23".parse::<Rule>().unwrap_err(),
+        RuleError::NeighborOutOfRange('9')
+    );
+    assert_eq!(
+        "B33/S23".parse::<Rule>().unwrap_err(),
+        RuleError::Duplicate(3)
+    );
+    for garbage in [
+        "",
+        "B3",
+        "B3/S23/C4/5",
+        "X3/S23",
+        "B3/23",
+        "B3a/S23",
+        "hello",
+    ]
+    .iter()
+    {
+        assert_eq!(
+            garbage.parse::<Rule>().unwrap_err(),
+            RuleError::Malformed(garbage.to_string()),
+            "{:?}",
+            garbage
+        );
+    }
+    assert_eq!(
+        RuleError::Duplicate(3).to_string(),
+        "neighbor count 3 appears twice"
+    );
+}
+
+#[test]
+fn applies_uses_birth_and_survival_masks() {
+    let rule = Rule::CONWAY;
+    assert_eq!(rule.applies(Cell::Dead, 3), Cell::Alive);
+    assert_eq!(rule.applies(Cell::Dead, 2), Cell::Dead);
+    assert_eq!(rule.applies(Cell::Alive, 2), Cell::Alive);
+    assert_eq!(rule.applies(Cell::Alive, 4), Cell::Dead);
+    assert_eq!(rule.applies(Cell::Alive, 8), Cell::Dead);
+}
+
+#[test]
+fn high_life_replicator_copies_itself() {
+    let mut u = empty(24, 24);
+    u.insert_pattern(REPLICATOR, 8, 8, false).unwrap();
+    let mut conway = u.clone();
+    u.set_rule("B36/S23".parse().unwrap());
+    assert_eq!(u.rule().to_string(), "B36/S23");
+    u.step_n(12);
+
+    // 12 代之后沿对角线分裂成两个副本
+    let mut expected = empty(24, 24);
+    expected.insert_pattern(REPLICATOR, 6, 6, false).unwrap();
+    expected.insert_pattern(REPLICATOR, 10, 10, false).unwrap();
+    assert_eq!(u, expected);
+    assert_eq!(u.population(), 24);
+
+    conway.step_n(12);
+    assert_ne!(conway, expected);
+}
+
+#[test]
+fn seeds_kills_every_live_cell() {
+    let mut u = Universe::builder().width(20).height(20).seed(3).build();
+    u.set_rule("B2/S".parse().unwrap());
+    for _ in 0..5 {
+        let before: Vec<(u32, u32)> = u.live_cells().collect();
+        u.tick();
+        for (row, col) in before {
+            assert_eq!(u.get_cell(row, col), Some(Cell::Dead));
+        }
+    }
+}
+
+#[test]
+fn rule_change_applies_from_next_tick() {
+    let mut u: Universe = ".....\n.....\n.###.\n.....\n.....\n".parse().unwrap();
+    let before = u.clone();
+    u.set_rule("B/S012345678".parse().unwrap());
+    assert_eq!(u, before);
+    u.tick();
+    assert_eq!(u, before);
+}
+
+#[test]
+fn presets_parse_and_are_named() {
+    for (i, &(name, text)) in PRESETS.iter().enumerate() {
+        let rule: Rule = text.parse().unwrap();
+        assert_eq!(rule.to_string(), text);
+        assert_eq!(rule::preset(name), Some(rule));
+        assert_eq!(rule::preset_index(&rule), Some(i));
+        assert_eq!(rule.name(), Some(name));
+    }
+    assert_eq!(rule::preset("highlife"), Some("B36/S23".parse().unwrap()));
+    assert_eq!(rule::preset("nope"), None);
+    assert_eq!(Rule::new(&[1], &[1]).name(), None);
+}
+
+#[test]
+fn switching_rule_keeps_the_board() {
+    let mut u = Universe::builder().width(16).height(16).seed(7).build();
+    let before = u.clone();
+    u.set_rule(rule::preset("Maze").unwrap());
+    assert_eq!(u.cells(), before.cells());
+    assert_eq!(u.count(), before.count());
+}
+
+#[test]
+fn parses_generations_rules() {
+    let star_wars: Rule = "345/2/4".parse().unwrap();
+    assert_eq!(star_wars, Rule::generations(&[2], &[3, 4, 5], 4));
+    assert_eq!(star_wars.states(), 4);
+    assert_eq!(star_wars.to_string(), "B2/S345/C4");
+    assert_eq!("b2/s345/c4".parse::<Rule>().unwrap(), star_wars);
+    assert_eq!("B2/S345/4".parse::<Rule>().unwrap(), star_wars);
+
+    // 两个状态的 Generations 就是普通的 B/S 规则
+    assert_eq!("B3/S23/C2".parse::<Rule>().unwrap(), Rule::CONWAY);
+    assert_eq!(Rule::generations(&[3], &[2, 3], 2), Rule::CONWAY);
+    assert_eq!(Rule::CONWAY.states(), 2);
+
+    assert_eq!(
+        "B2/S/C1".parse::<Rule>().unwrap_err(),
+        RuleError::BadStateCount("1".to_string())
+    );
+    assert_eq!(
+        "B2/S/C256".parse::<Rule>().unwrap_err(),
+        RuleError::BadStateCount("256".to_string())
+    );
+}
+
+#[test]
+fn dying_cells_decay_through_every_state() {
+    let mut u: Universe = ".....\n.....\n..#..\n.....\n.....\n".parse().unwrap();
+    u.set_rule("B/S/C4".parse().unwrap());
+    assert_eq!(u.state(2, 2), Some(1));
+
+    u.tick();
+    assert_eq!(u.state(2, 2), Some(2));
+    assert_eq!(u.get_cell(2, 2), Some(Cell::Dead));
+    assert_eq!(u.population(), 0);
+    assert_eq!(u.deaths(), 1);
+    u.tick();
+    assert_eq!(u.state(2, 2), Some(3));
+    u.tick();
+    assert_eq!(u.state(2, 2), Some(0));
+}
+
+#[test]
+fn dying_cells_are_not_live_neighbors() {
+    let mut u = empty(8, 8);
+    u.insert_pattern(&[(1, 1), (2, 1)], 0, 0, false).unwrap();
+    u.set_rule("B2/S/C3".parse().unwrap());
+
+    u.tick();
+    assert_eq!(u.state(1, 1), Some(2));
+    assert_eq!(u.state(2, 1), Some(2));
+    let born: Vec<(u32, u32)> = u.live_cells().collect();
+    assert_eq!(born, vec![(1, 0), (1, 2), (2, 0), (2, 2)]);
+
+    // (0, 1) 旁边有两个活细胞和一个衰亡中的细胞, 只数活的
+    u.tick();
+    assert_eq!(u.state(0, 1), Some(1));
+    assert_eq!(u.state(3, 1), Some(1));
+    assert_eq!(u.state(1, 1), Some(0));
+}
+
+#[test]
+fn two_state_rule_drops_dying_cells() {
+    let mut u: Universe = "...\n.#.\n...\n".parse().unwrap();
+    u.set_rule("B/S/C5".parse().unwrap());
+    u.tick();
+    assert_eq!(u.state(1, 1), Some(2));
+    u.set_rule(Rule::CONWAY);
+    assert_eq!(u.state(1, 1), Some(0));
+    u.toggle_cell(1, 1).unwrap();
+    assert_eq!(u.state(1, 1), Some(1));
+}
+
+#[test]
+fn brians_brain_cycles_through_three_phases() {
+    assert_eq!("B2/S/C3".parse::<Rule>().unwrap(), Rule::BRIANS_BRAIN);
+    assert_eq!(Rule::BRIANS_BRAIN.name(), Some("Brian's Brain"));
+
+    // 单个放电的细胞周围只有一个邻居在放电, 不会引发新的放电
+    let mut u: Universe = ".....\n.....\n..#..\n.....\n.....\n".parse().unwrap();
+    u.set_rule(Rule::BRIANS_BRAIN);
+    u.tick();
+    assert_eq!(u.state(2, 2), Some(2));
+    assert_eq!(u.population(), 0);
+    u.tick();
+    assert_eq!(u.state(2, 2), Some(0));
+    u.tick();
+    assert_eq!(u.state(2, 2), Some(0));
+}
+
+#[test]
+fn brians_brain_fires_on_exactly_two_neighbors() {
+    let mut u = empty(6, 6);
+    u.insert_pattern(&[(2, 2), (2, 3)], 0, 0, false).unwrap();
+    u.set_rule(Rule::BRIANS_BRAIN);
+    u.tick();
+    assert_eq!(u.state(2, 2), Some(2));
+    assert_eq!(u.state(2, 3), Some(2));
+    let firing: Vec<(u32, u32)> = u.live_cells().collect();
+    assert_eq!(firing, vec![(1, 2), (1, 3), (3, 2), (3, 3)]);
+    u.tick();
+    // 不应期结束后恢复就绪, 上一代放电的细胞进入不应期
+    assert_eq!(u.state(2, 2), Some(0));
+    assert_eq!(u.state(1, 2), Some(2));
+    assert_eq!(u.state(3, 3), Some(2));
+}
+
+#[test]
+fn trailing_v_selects_von_neumann() {
+    let rule: Rule = "B2/S013V".parse().unwrap();
+    assert_eq!(rule.neighborhood(), Neighborhood::VonNeumann);
+    assert_eq!(rule.to_string(), "B2/S013V");
+    assert_eq!(
+        rule.with_neighborhood(Neighborhood::Moore).to_string(),
+        "B2/S013"
+    );
+    assert_eq!("b2/s013v".parse::<Rule>().unwrap(), rule);
+    assert_eq!("B2/S/C3V".parse::<Rule>().unwrap().to_string(), "B2/S/C3V");
+    assert_eq!(Rule::CONWAY.neighborhood(), Neighborhood::Moore);
+
+    // 4 邻域数不到 5 以上, 但解析时仍然接受
+    let wide: Rule = "B25/S78V".parse().unwrap();
+    assert_eq!(wide.unreachable_counts().collect::<Vec<_>>(), vec![5, 7, 8]);
+    assert_eq!(Rule::CONWAY.unreachable_counts().count(), 0);
+}
+
+#[test]
+fn plus_seed_differs_between_neighborhoods() {
+    let plus = ".......\n.......\n...#...\n..###..\n...#...\n.......\n.......\n";
+    let mut moore: Universe = plus.parse().unwrap();
+    let mut von_neumann = moore.clone();
+    moore.set_rule("B2/S013".parse().unwrap());
+    von_neumann.set_rule("B2/S013V".parse().unwrap());
+    assert_eq!(von_neumann.neighborhood(), Neighborhood::VonNeumann);
+
+    // (2, 2) 斜对着中心, 只有 Moore 邻域会把中心算进去
+    assert_eq!(moore.live_neighbor_count(2, 2), 3);
+    assert_eq!(von_neumann.live_neighbor_count(2, 2), 2);
+    assert_eq!(von_neumann.live_neighbor_count(3, 3), 4);
+
+    moore.tick();
+    von_neumann.tick();
+    assert_ne!(moore, von_neumann);
+    let expected: Universe = ".......\n.......\n..###..\n..#.#..\n..###..\n.......\n.......\n"
+        .parse()
+        .unwrap();
+    assert_eq!(von_neumann, expected);
+}
+
+#[test]
+fn set_neighborhood_keeps_the_rest_of_the_rule() {
+    let mut u = Universe::builder().width(8).height(8).seed(1).build();
+    u.set_rule(Rule::BRIANS_BRAIN);
+    u.set_neighborhood(Neighborhood::VonNeumann);
+    assert_eq!(u.rule().to_string(), "B2/S/C3V");
+    assert_eq!(u.rule().states(), 3);
+}
+
+#[test]
+fn parses_larger_than_life() {
+    let bosco: Rule = "R5,C0,M1,S34..58,B34..45,NM".parse().unwrap();
+    assert_eq!(
+        bosco,
+        Rule::LargerThanLife {
+            radius: 5,
+            birth: Range::new(34, 45),
+            survive: Range::new(34, 58),
+            include_center: true,
+        }
+    );
+    assert_eq!(bosco.to_string(), "R5,C0,M1,S34..58,B34..45,NM");
+    assert_eq!(rule::preset("Bosco's Rule"), Some(bosco));
+    assert_eq!(
+        "r2,m0,s3..5,b4..4".parse::<Rule>().unwrap().to_string(),
+        "R2,C0,M0,S3..5,B4..4,NM"
+    );
+
+    assert_eq!(
+        "R5,C3,M1,S34..58,B34..45,NM".parse::<Rule>().unwrap_err(),
+        RuleError::BadStateCount("3".to_string())
+    );
+    for bad in [
+        "R0,S1..2,B1..2",
+        "R2,S5..3,B1..2",
+        "R2,S1..2",
+        "R2,S1..2,B1..2,NN",
+    ]
+    .iter()
+    {
+        assert_eq!(
+            bad.parse::<Rule>().unwrap_err(),
+            RuleError::Malformed(bad.to_string()),
+            "{:?}",
+            bad
+        );
+    }
+}
+
+#[test]
+fn prefix_sums_match_naive_counts() {
+    // 7x5 的面板上半径 4 的窗口会绕回来, 同一个格子被数不止一次
+    let cases = [(20, 16, 1), (20, 16, 5), (7, 5, 4)];
+    for (&(width, height, radius), &topology) in cases.iter().flat_map(|case| {
+        [Topology::Torus, Topology::Bounded, Topology::Mirror]
+            .iter()
+            .map(move |t| (case, t))
+    }) {
+        let mut u = Universe::builder()
+            .width(width)
+            .height(height)
+            .seed(u64::from(radius))
+            .build();
+        u.set_topology(topology);
+        let counts = u.range_neighbor_counts(radius);
+        for row in 0..height {
+            for col in 0..width {
+                assert_eq!(
+                    counts[(row * width + col) as usize],
+                    u.range_neighbor_count(row, col, radius),
+                    "({}, {}) radius {} {:?}",
+                    row,
+                    col,
+                    radius,
+                    topology
+                );
+            }
+        }
+        if radius == 1 {
+            assert!(counts
+                .iter()
+                .enumerate()
+                .all(|(i, &n)| n
+                    == u32::from(u.live_neighbor_count(i as u32 / width, i as u32 % width))));
+        }
+    }
+}
+
+#[test]
+fn radius_one_larger_than_life_is_conway() {
+    let mut conway = Universe::builder().width(24).height(24).seed(11).build();
+    let mut ltl = conway.clone();
+    ltl.set_rule("R1,C0,M0,S2..3,B3..3,NM".parse().unwrap());
+    for _ in 0..20 {
+        conway.tick();
+        ltl.tick();
+        assert_eq!(ltl, conway);
+        assert_eq!(ltl.births(), conway.births());
+    }
+}
+
+/// 不做相位变换, 直接在环面上按 B/S 规则演化一代, 用来对照
+fn literal_step(u: &Universe, rule: Rule) -> Universe {
+    let mut cells = Vec::new();
+    for row in 0..u.height() {
+        for col in 0..u.width() {
+            let cell = u.get_cell(row, col).unwrap();
+            cells.push(rule.applies(cell, u.live_neighbor_count(row, col)));
+        }
+    }
+    Universe::from_cells(u.width(), u.height(), cells).unwrap()
+}
+
+fn actual(u: &Universe) -> Universe {
+    let mut cells = Vec::new();
+    for row in 0..u.height() {
+        for col in 0..u.width() {
+            cells.push(u.actual_cell(row, col).unwrap());
+        }
+    }
+    Universe::from_cells(u.width(), u.height(), cells).unwrap()
+}
+
+#[test]
+fn b0_s8_inverts_once_and_stays_inverted() {
+    let mut u = empty(7, 7);
+    u.set_cell(Cell::Alive, 3, 3).unwrap();
+    let rule: Rule = "B0/S8".parse().unwrap();
+    u.set_rule(rule);
+    assert_eq!(u.rule().to_string(), "B0/S8");
+
+    // 背景全部亮起, 只有原来的细胞和它的 8 个邻居是暗的
+    u.tick();
+    assert!(u.is_inverted());
+    let mut hole = empty(7, 7);
+    hole.insert_pattern(square(3).as_slice(), 2, 2, false)
+        .unwrap();
+    assert_eq!(u, hole);
+    assert_eq!(u.actual_cell(0, 0), Some(Cell::Alive));
+    assert_eq!(u.actual_cell(3, 3), Some(Cell::Dead));
+
+    // 洞的中心没有活邻居, 重新亮起; 紧挨着洞的活细胞不满 8 个邻居, 熄灭
+    u.tick();
+    assert!(u.is_inverted());
+    let mut ring = empty(7, 7);
+    ring.insert_pattern(square(5).as_slice(), 1, 1, false)
+        .unwrap();
+    ring.set_cell(Cell::Dead, 3, 3).unwrap();
+    assert_eq!(u, ring);
+    assert_eq!(u.actual_cell(3, 3), Some(Cell::Alive));
+    assert_eq!(u.actual_cell(0, 3), Some(Cell::Alive));
+    assert_eq!(u.actual_cell(1, 3), Some(Cell::Dead));
+}
+
+fn square(side: u32) -> Vec<(u32, u32)> {
+    (0..side)
+        .flat_map(|r| (0..side).map(move |c| (r, c)))
+        .collect()
+}
+
+#[test]
+fn b0_rules_match_literal_evolution() {
+    for text in ["B0/S8", "B0/S", "B03/S23", "B0123478/S01234678", "B0/S2V"].iter() {
+        let rule: Rule = text.parse().unwrap();
+        let mut u = Universe::builder().width(12).height(10).seed(4).build();
+        let mut literal = u.clone();
+        literal.set_rule(rule);
+        u.set_rule(rule);
+        for generation in 1..=6 {
+            literal = literal_step(&literal, rule);
+            literal.set_rule(rule);
+            u.tick();
+            assert_eq!(actual(&u), literal, "{} generation {}", text, generation);
+            let (phase_rule, _) = rule.phase_step(u.is_inverted());
+            assert!(!phase_rule.has_b0());
+        }
+    }
+}
+
+#[test]
+fn b0_without_s8_alternates_phase() {
+    let mut u = Universe::builder().width(8).height(8).seed(2).build();
+    u.set_rule("B03/S23".parse().unwrap());
+    let phases: Vec<bool> = (0..4)
+        .map(|_| {
+            u.tick();
+            u.is_inverted()
+        })
+        .collect();
+    assert_eq!(phases, vec![true, false, true, false]);
+
+    // 换成不含 B0 的规则时换回实际状态
+    u.tick();
+    assert!(u.is_inverted());
+    let before = actual(&u);
+    u.set_rule(Rule::CONWAY);
+    assert!(!u.is_inverted());
+    assert_eq!(u, before);
+    assert_eq!(u.population() as usize, u.live_count());
+}
+
+#[test]
+fn rule_tables_round_trip() {
+    let table = Rule::CONWAY.table();
+    assert_eq!(table[Cell::Dead as usize][3], Cell::Alive);
+    assert_eq!(table[Cell::Alive as usize][2], Cell::Alive);
+    assert_eq!(table[Cell::Alive as usize][4], Cell::Dead);
+    assert_eq!(Rule::from_table(&table), Rule::CONWAY);
+
+    let parity = rule::table_from_fn(|_, n| if n % 2 == 1 { Cell::Alive } else { Cell::Dead });
+    assert_eq!(Rule::from_table(&parity).to_string(), "B1357/S1357");
+    for text in ["B36/S23", "B2/S", "B3/S012345678"].iter() {
+        let rule: Rule = text.parse().unwrap();
+        assert_eq!(Rule::from_table(&rule.table()), rule);
+    }
+}
+
+#[test]
+fn conway_table_matches_hand_written_rule() {
+    let conway = |cell: Cell, n: u8| match (cell, n) {
+        (Cell::Alive, 2) | (Cell::Alive, 3) | (Cell::Dead, 3) => Cell::Alive,
+        _ => Cell::Dead,
+    };
+    let mut u = Universe::builder().width(32).height(24).seed(9).build();
+    u.set_rule_table(rule::table_from_fn(conway));
+    assert_eq!(u.rule(), Rule::CONWAY);
+    let mut expected = u.clone();
+    for generation in 1..=100 {
+        let cells: Vec<Cell> = (0..expected.height())
+            .flat_map(|row| (0..expected.width()).map(move |col| (row, col)))
+            .map(|(row, col)| {
+                conway(
+                    expected.get_cell(row, col).unwrap(),
+                    expected.live_neighbor_count(row, col),
+                )
+            })
+            .collect();
+        expected = Universe::from_cells(expected.width(), expected.height(), cells).unwrap();
+        u.tick();
+        assert_eq!(u.cells(), expected.cells(), "generation {}", generation);
+    }
+}
+
+#[test]
+fn rule_table_keeps_neighborhood() {
+    let mut u = empty(6, 6);
+    u.set_neighborhood(Neighborhood::VonNeumann);
+    u.set_rule_table(Rule::BRIANS_BRAIN.table());
+    assert_eq!(u.rule().to_string(), "B2/SV");
+}
+
+/// 5x5 面板中间一行三个细胞, 演化一代后竖过来
+fn colored_blinker(rule: Rule, colors: [u8; 3]) -> Universe {
+    let mut u = empty(5, 5);
+    u.set_rule(rule);
+    for (col, &color) in colors.iter().enumerate() {
+        u.set_state(2, col as u32 + 1, color).unwrap();
+    }
+    u.tick();
+    u
+}
+
+#[test]
+fn immigration_births_take_majority_color() {
+    let u = colored_blinker(Rule::Immigration, [2, 2, 2]);
+    assert_eq!(u.state(1, 2), Some(2));
+    assert_eq!(u.state(3, 2), Some(2));
+    assert_eq!(u.state(2, 1), Some(0));
+
+    let u = colored_blinker(Rule::Immigration, [1, 2, 2]);
+    assert_eq!(u.state(1, 2), Some(2));
+    assert_eq!(u.state(3, 2), Some(2));
+    // 中间的细胞存活, 颜色不变
+    let u = colored_blinker(Rule::Immigration, [2, 1, 2]);
+    assert_eq!(u.state(2, 2), Some(1));
+    assert_eq!(u.population(), 3);
+}
+
+#[test]
+fn quadlife_births_take_missing_color() {
+    let u = colored_blinker(Rule::QuadLife, [1, 2, 3]);
+    assert_eq!(u.state(1, 2), Some(4));
+    assert_eq!(u.state(3, 2), Some(4));
+    assert_eq!(u.state(2, 2), Some(2));
+
+    let u = colored_blinker(Rule::QuadLife, [3, 1, 3]);
+    assert_eq!(u.state(1, 2), Some(3));
+}
+
+#[test]
+fn colored_rules_parse_by_name() {
+    assert_eq!("immigration".parse::<Rule>().unwrap(), Rule::Immigration);
+    assert_eq!(
+        Rule::QuadLife.to_string().parse::<Rule>().unwrap(),
+        Rule::QuadLife
+    );
+    assert_eq!(Rule::QuadLife.states(), 5);
+    assert_eq!(Rule::CONWAY.colors(), 1);
+}
+
+#[test]
+fn parses_hexagonal_rules() {
+    let hex: Rule = "B2/S34H".parse().unwrap();
+    assert_eq!(hex.neighborhood(), Neighborhood::Hexagonal);
+    assert_eq!(hex.to_string(), "B2/S34H");
+    assert_eq!(Neighborhood::Hexagonal.size(), 6);
+    let wide: Rule = "b3/s78h".parse().unwrap();
+    assert_eq!(wide.unreachable_counts().collect::<Vec<_>>(), vec![7, 8]);
+}
+
+#[test]
+fn hexagonal_neighbors_follow_the_row_offset() {
+    let mut u = empty(6, 6);
+    u.set_rule("B2/S34H".parse().unwrap());
+    // 奇数行向右错开, (1, 1) 的邻居是上下两行的第 1、2 列
+    u.set_cell(Cell::Alive, 1, 1).unwrap();
+    for &(row, col) in [(0, 1), (0, 2), (1, 0), (1, 2), (2, 1), (2, 2)].iter() {
+        assert_eq!(u.live_neighbor_count(row, col), 1, "({}, {})", row, col);
+    }
+    assert_eq!(u.live_neighbor_count(0, 0), 0);
+    assert_eq!(u.live_neighbor_count(2, 0), 0);
+
+    // 偶数行的邻居是上下两行的前一列和同一列, 而且邻居关系是对称的
+    for idx in 0..36 {
+        let mut single = empty(6, 6);
+        single.set_rule("B2/S34H".parse().unwrap());
+        single.set_cell(Cell::Alive, idx % 6, idx / 6).unwrap();
+        let total: u32 = (0..36)
+            .map(|i| u32::from(single.live_neighbor_count(i / 6, i % 6)))
+            .sum();
+        assert_eq!(total, 6);
+    }
+}
+
+fn cyclic_board(neighborhood: Neighborhood) -> Universe {
+    let mut u = empty(3, 3);
+    u.set_topology(Topology::Bounded);
+    u.set_rule(Rule::Cyclic {
+        states: 4,
+        neighborhood,
+    });
+    let board = [[0, 1, 2], [3, 0, 0], [1, 2, 3]];
+    for (row, states) in board.iter().enumerate() {
+        for (col, &state) in states.iter().enumerate() {
+            u.set_state(row as u32, col as u32, state).unwrap();
+        }
+    }
+    u
+}
+
+fn states(u: &Universe) -> Vec<Vec<u8>> {
+    (0..u.height())
+        .map(|row| {
+            (0..u.width())
+                .map(|col| u.state(row, col).unwrap())
+                .collect()
+        })
+        .collect()
+}
+
+#[test]
+fn cyclic_advances_when_a_neighbor_is_ahead() {
+    let mut u = cyclic_board(Neighborhood::Moore);
+    u.tick();
+    // 3 的下一个状态是 0, 右上角的 2 周围没有 3, 保持不变
+    assert_eq!(states(&u), [[1, 2, 2], [0, 1, 1], [2, 3, 0]]);
+    assert_eq!(u.population(), 3);
+
+    // 只看上下左右时, 中间一行右边的格子看不到左下方的 1
+    let mut u = cyclic_board(Neighborhood::VonNeumann);
+    u.tick();
+    assert_eq!(states(&u), [[1, 2, 2], [0, 1, 0], [2, 3, 0]]);
+}
+
+#[test]
+fn cyclic_parses_state_count_and_neighborhood() {
+    assert_eq!("cyclic".parse::<Rule>().unwrap(), Rule::CYCLIC);
+    let rule = "Cyclic/C8V".parse::<Rule>().unwrap();
+    assert_eq!(
+        rule,
+        Rule::Cyclic {
+            states: 8,
+            neighborhood: Neighborhood::VonNeumann
+        }
+    );
+    assert_eq!(rule.to_string(), "Cyclic/C8V");
+    assert_eq!(rule.states(), 8);
+    assert_eq!(
+        "Cyclic/C1".parse::<Rule>(),
+        Err(RuleError::BadStateCount("1".to_string()))
+    );
+}
+
+#[test]
+fn cyclic_randomize_uses_every_state() {
+    let mut u = Universe::builder().width(32).height(32).seed(7).build();
+    u.set_rule(Rule::CYCLIC);
+    u.randomize_states();
+    let mut seen = [false; 14];
+    for row in 0..32 {
+        for col in 0..32 {
+            seen[u.state(row, col).unwrap() as usize] = true;
+        }
+    }
+    assert!(seen.iter().all(|&s| s));
+    assert_eq!(u.population(), u.live_count() as u32);
+    assert_eq!(u.count(), 0);
+}
+
+#[test]
+fn random_rules_avoid_b0_and_b1_and_repeat_per_seed() {
+    let mut rng = StdRng::seed_from_u64(42);
+    let rules: Vec<Rule> = (0..200).map(|_| rule::random_rule(&mut rng)).collect();
+    for rule in rules.iter() {
+        let births: Vec<u8> = rule.births().collect();
+        assert!(!births.is_empty(), "{}", rule);
+        assert!(births.iter().all(|&n| n >= 2), "{}", rule);
+        assert_eq!(rule.to_string().parse::<Rule>().as_ref(), Ok(rule));
+    }
+    let with_s2_or_s3 = rules
+        .iter()
+        .filter(|rule| rule.survivals().any(|n| n == 2 || n == 3))
+        .count();
+    assert!(with_s2_or_s3 > 150, "{} of 200", with_s2_or_s3);
+
+    let mut again = StdRng::seed_from_u64(42);
+    assert_eq!(rule::random_rule(&mut again), rules[0]);
+}
+
+#[test]
+fn explore_rule_reseeds_reproducibly() {
+    let explore = || {
+        let mut u = Universe::builder().width(16).height(16).seed(8).build();
+        let rules: Vec<Rule> = (0..3).map(|_| u.explore_rule()).collect();
+        (rules, u)
+    };
+    let ((first, a), (second, b)) = (explore(), explore());
+    assert_eq!(first, second);
+    assert_eq!(a.rule(), first[2]);
+    assert!(a.same_cells(&b));
+    assert_eq!(a.count(), 0);
+}
+
+#[test]
+fn rule_history_keeps_the_last_ten() {
+    let mut history = RuleHistory::new();
+    assert_eq!(history.back(), None);
+    let rules: Vec<Rule> = (0..12u8).map(|n| Rule::new(&[n % 8 + 1], &[2])).collect();
+    for &rule in rules.iter() {
+        history.push(rule);
+    }
+    assert_eq!(history.len(), rule::HISTORY_LEN);
+    assert_eq!(history.current(), Some(rules[11]));
+    assert_eq!(history.forward(), Some(rules[11]));
+    assert_eq!(history.back(), Some(rules[10]));
+    for _ in 0..20 {
+        history.back();
+    }
+    assert_eq!(history.current(), Some(rules[2]));
+    assert_eq!(history.forward(), Some(rules[3]));
+    // 新规则总是加在最后
+    history.push(Rule::CONWAY);
+    assert_eq!(history.current(), Some(Rule::CONWAY));
+    assert_eq!(history.iter().next(), Some(&rules[3]));
+}
@@ -0,0 +1,47 @@
+#![cfg(feature = "serde")]
+
+use life_game::patterns::GLIDER;
+use life_game::{Cell, Universe};
+
+fn glider_board() -> Universe {
+    let mut u = Universe::from_cells(7, 5, vec![Cell::Dead; 35]).unwrap();
+    u.insert_pattern(GLIDER, 1, 2, false).unwrap();
+    u.step_n(3);
+    u
+}
+
+#[test]
+fn json_round_trip() {
+    let u = glider_board();
+    let text = serde_json::to_string(&u).unwrap();
+    let back: Universe = serde_json::from_str(&text).unwrap();
+    assert_eq!(back.width(), 7);
+    assert_eq!(back.height(), 5);
+    assert_eq!(back.count(), 3);
+    assert_eq!(back.cells(), u.cells());
+}
+
+#[test]
+fn bincode_round_trip_is_compact() {
+    let u = glider_board();
+    let bytes = bincode::serialize(&u).unwrap();
+    // 4 + 4 + 8 字节的头, 8 字节长度前缀, 35 个细胞打包成 5 字节
+    assert_eq!(bytes.len(), 4 + 4 + 8 + 8 + 5);
+    let back: Universe = bincode::deserialize(&bytes).unwrap();
+    assert_eq!(back.cells(), u.cells());
+    assert_eq!(back.count(), u.count());
+}
+
+#[test]
+fn cell_is_a_single_number() {
+    assert_eq!(serde_json::to_string(&Cell::Alive).unwrap(), "1");
+    assert_eq!(serde_json::from_str::<Cell>("0").unwrap(), Cell::Dead);
+    assert!(serde_json::from_str::<Cell>("2").is_err());
+}
+
+#[test]
+fn mismatched_dimensions_are_rejected() {
+    let text = r#"{"width":4,"height":4,"count":0,"cells":[0]}"#;
+    let err = serde_json::from_str::<Universe>(text).err().unwrap();
+    assert!(err.to_string().contains("needs 2 bytes"));
+}
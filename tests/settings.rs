@@ -0,0 +1,75 @@
+use std::env;
+use std::fs;
+
+use life_game::settings::{Settings, BLANK_COLOR};
+
+#[test]
+fn settings_round_trip() {
+    let settings = Settings {
+        theme: 2,
+        show_grid: false,
+        ..Settings::default()
+    };
+    assert_eq!(
+        settings.serialize(),
+        format!(
+            "theme=2\nshow_grid=false\ncustom_colors={}\n",
+            vec!["#FFFFFF"; 16].join(",")
+        )
+    );
+    assert_eq!(Settings::parse(&settings.serialize()), settings);
+}
+
+#[test]
+fn custom_colors_round_trip() {
+    let mut custom_colors = [BLANK_COLOR; 16];
+    custom_colors[3] = 0x0012_3456;
+    let settings = Settings {
+        theme: 3,
+        // 0x00BBGGRR: 红、绿、蓝
+        custom_theme: Some([0x0000_00FF, 0x0000_FF00, 0x00FF_0000]),
+        custom_colors,
+        ..Settings::default()
+    };
+    let text = settings.serialize();
+    assert!(
+        text.contains("custom_theme=#FF0000,#00FF00,#0000FF\n"),
+        "{}",
+        text
+    );
+    assert!(text.contains(",#563412,"), "{}", text);
+    assert_eq!(Settings::parse(&text), settings);
+
+    // 个数不对或者不是颜色时整行跳过
+    let bad = Settings::parse("custom_theme=#FF0000,#00FF00\ncustom_colors=#GGGGGG\n");
+    assert_eq!(bad, Settings::default());
+}
+
+#[test]
+fn bad_lines_keep_defaults() {
+    let settings = Settings::parse("# 注释\ntheme=abc\nshow_grid = false\nunknown=1\n\n");
+    assert_eq!(
+        settings,
+        Settings {
+            show_grid: false,
+            ..Settings::default()
+        }
+    );
+    assert_eq!(Settings::parse(""), Settings::default());
+}
+
+#[test]
+fn missing_file_loads_defaults() {
+    let dir = env::temp_dir().join(format!("life_settings_{}", std::process::id()));
+    let path = dir.join("settings.ini");
+    assert_eq!(Settings::load(&path).unwrap(), Settings::default());
+
+    let settings = Settings {
+        theme: 1,
+        show_grid: false,
+        ..Settings::default()
+    };
+    settings.save(&path).unwrap();
+    assert_eq!(Settings::load(&path).unwrap(), settings);
+    fs::remove_dir_all(&dir).unwrap();
+}
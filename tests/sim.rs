@@ -0,0 +1,276 @@
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use life_game::sim::{self, Command, FrameSkip, Simulation, TickRate, MAX_DRAW_EVERY};
+
+/// 记下收到的命令和运行时演化的次数
+#[derive(Default)]
+struct Log {
+    commands: Vec<Command>,
+    running: bool,
+    ticks: u32,
+}
+
+struct Counter(Arc<Mutex<Log>>);
+
+impl Simulation for Counter {
+    fn apply(&mut self, command: Command) {
+        let mut log = self.0.lock().unwrap();
+        log.commands.push(command);
+        log.running = match command {
+            Command::Pause => false,
+            Command::Resume => true,
+            Command::Toggle => !log.running,
+            _ => log.running,
+        };
+    }
+
+    fn tick(&mut self) {
+        let mut log = self.0.lock().unwrap();
+        if log.running {
+            log.ticks += 1;
+        }
+    }
+}
+
+fn wait_for(log: &Mutex<Log>, ticks: u32) {
+    for _ in 0..2000 {
+        if log.lock().unwrap().ticks >= ticks {
+            return;
+        }
+        thread::sleep(Duration::from_millis(1));
+    }
+    panic!("only {} ticks", log.lock().unwrap().ticks);
+}
+
+#[test]
+fn commands_arrive_in_order_between_ticks() {
+    let log = Arc::new(Mutex::new(Log::default()));
+    let mut handle = sim::spawn(Duration::from_millis(1), Counter(Arc::clone(&log)));
+    handle.send(Command::Resume);
+    wait_for(&log, 5);
+    handle.send(Command::Pause);
+    handle.send(Command::Reset);
+    handle.send(Command::SetInterval(Duration::from_secs(0)));
+    handle.send(Command::Toggle);
+    let before = log.lock().unwrap().ticks;
+    wait_for(&log, before + 100);
+    handle.stop();
+
+    // 线程已经退出, 之后的命令和演化都不会再发生
+    let stopped = log.lock().unwrap().ticks;
+    handle.send(Command::Pause);
+    thread::sleep(Duration::from_millis(20));
+    let log = log.lock().unwrap();
+    assert_eq!(log.ticks, stopped);
+    assert_eq!(
+        log.commands,
+        [
+            Command::Resume,
+            Command::Pause,
+            Command::Reset,
+            Command::Toggle
+        ]
+    );
+}
+
+#[test]
+fn paused_simulation_still_ticks_but_does_not_advance() {
+    let log = Arc::new(Mutex::new(Log::default()));
+    let handle = sim::spawn(Duration::from_millis(1), Counter(Arc::clone(&log)));
+    thread::sleep(Duration::from_millis(20));
+    drop(handle);
+    assert_eq!(log.lock().unwrap().ticks, 0);
+}
+
+/// 每次 `tick` 演化一代, 还有没演化完的代数时一直忙
+struct Chunked {
+    left: u64,
+    done: Arc<Mutex<u64>>,
+}
+
+impl Simulation for Chunked {
+    fn apply(&mut self, command: Command) {
+        match command {
+            Command::Advance(n) => self.left = n,
+            Command::Cancel => self.left = 0,
+            _ => {}
+        }
+    }
+
+    fn tick(&mut self) {
+        if self.left > 0 {
+            self.left -= 1;
+            *self.done.lock().unwrap() += 1;
+        }
+    }
+
+    fn busy(&self) -> bool {
+        self.left > 0
+    }
+}
+
+#[test]
+fn busy_simulation_ignores_the_interval_and_can_be_cancelled() {
+    let done = Arc::new(Mutex::new(0));
+    let sim = Chunked {
+        left: 0,
+        done: Arc::clone(&done),
+    };
+    // 间隔一小时, 不忙的时候一代也不会演化
+    let handle = sim::spawn(Duration::from_secs(3600), sim);
+    handle.send(Command::Advance(50));
+    let finished = (0..2000).any(|_| {
+        thread::sleep(Duration::from_millis(1));
+        *done.lock().unwrap() == 50
+    });
+    assert!(finished);
+
+    handle.send(Command::Advance(u64::MAX));
+    while *done.lock().unwrap() < 1000 {
+        thread::sleep(Duration::from_millis(1));
+    }
+    handle.send(Command::Cancel);
+    thread::sleep(Duration::from_millis(20));
+    let cancelled = *done.lock().unwrap();
+    thread::sleep(Duration::from_millis(20));
+    assert_eq!(*done.lock().unwrap(), cancelled);
+    drop(handle);
+}
+
+#[test]
+fn frame_skip_draws_every_nth_generation() {
+    let mut skip = FrameSkip::default();
+    assert_eq!(skip.every(), 1);
+    assert!((0..5).all(|_| skip.tick()));
+
+    skip.increase();
+    skip.increase();
+    skip.increase();
+    assert_eq!(skip.every(), 8);
+    let drawn: Vec<bool> = (0..16).map(|_| skip.tick()).collect();
+    assert_eq!(drawn.iter().filter(|&&d| d).count(), 2);
+    assert!(drawn[7] && drawn[15]);
+
+    // 减小间隔时已经演化的代数不会超过新的间隔
+    (0..6).for_each(|_| {
+        skip.tick();
+    });
+    skip.decrease();
+    assert_eq!(skip.every(), 4);
+    assert!(skip.tick());
+
+    (0..10).for_each(|_| skip.increase());
+    assert_eq!(skip.every(), MAX_DRAW_EVERY);
+    (0..10).for_each(|_| skip.decrease());
+    assert_eq!(skip.every(), 1);
+    assert_eq!(FrameSkip::new(0).every(), 1);
+    assert_eq!(FrameSkip::new(1000).every(), MAX_DRAW_EVERY);
+}
+
+fn ticks_in(interval: Duration, run: Duration) -> u32 {
+    let log = Arc::new(Mutex::new(Log::default()));
+    let handle = sim::spawn(interval, Counter(Arc::clone(&log)));
+    handle.send(Command::Resume);
+    thread::sleep(run);
+    drop(handle);
+    let ticks = log.lock().unwrap().ticks;
+    ticks
+}
+
+/// 睡眠的精度取决于系统的定时器, 机器很忙时也可能睡过头, 所以只要求大致达到.
+/// 上限不会超过: 线程从来不会提前演化, 也不会补上落下的代数
+#[test]
+fn short_intervals_reach_their_tick_rate() {
+    let ticks = ticks_in(Duration::from_millis(2), Duration::from_millis(400));
+    assert!(
+        (100..=201).contains(&ticks),
+        "{} ticks at 2ms in 400ms",
+        ticks
+    );
+    let unlimited = ticks_in(Duration::from_secs(0), Duration::from_millis(100));
+    assert!(unlimited > 1000, "{} unlimited ticks in 100ms", unlimited);
+}
+
+#[test]
+fn speed_ladder_steps_and_clamps() {
+    let ms = Duration::from_millis;
+    assert_eq!(sim::faster(ms(10)), ms(5));
+    assert_eq!(sim::slower(ms(10)), ms(25));
+    assert_eq!(sim::faster(ms(1)), ms(0));
+    assert_eq!(sim::faster(ms(0)), ms(0));
+    assert_eq!(sim::slower(ms(1000)), ms(1000));
+    // 不在档位上的间隔换到相邻的档位
+    assert_eq!(sim::faster(ms(30)), ms(25));
+    assert_eq!(sim::slower(ms(30)), ms(50));
+    assert_eq!(sim::slower(ms(5000)), ms(5000));
+}
+
+#[test]
+fn tick_rate_counts_generations_per_second() {
+    let start = Instant::now();
+    let mut rate = TickRate::new(start);
+    for i in 1..50 {
+        rate.tick(start + Duration::from_millis(i * 10));
+    }
+    assert_eq!(rate.per_second(), 0.0);
+    rate.tick(start + Duration::from_millis(500));
+    assert!((rate.per_second() - 100.0).abs() < 1e-9);
+}
+
+/// 暂停时闲着, 收到命令之前一次 `tick` 都不会调用
+struct Idle(Arc<Mutex<Log>>);
+
+impl Simulation for Idle {
+    fn apply(&mut self, command: Command) {
+        Counter(Arc::clone(&self.0)).apply(command);
+    }
+
+    fn tick(&mut self) {
+        self.0.lock().unwrap().ticks += 1;
+    }
+
+    fn idle(&self) -> bool {
+        !self.0.lock().unwrap().running
+    }
+}
+
+#[test]
+fn idle_simulation_sleeps_until_a_command_arrives() {
+    let log = Arc::new(Mutex::new(Log::default()));
+    let handle = sim::spawn(Duration::from_millis(1), Idle(Arc::clone(&log)));
+    thread::sleep(Duration::from_millis(30));
+    assert_eq!(log.lock().unwrap().ticks, 0);
+
+    handle.send(Command::Resume);
+    wait_for(&log, 5);
+    handle.send(Command::Pause);
+    thread::sleep(Duration::from_millis(10));
+    let paused = log.lock().unwrap().ticks;
+    thread::sleep(Duration::from_millis(30));
+    assert_eq!(log.lock().unwrap().ticks, paused);
+    // 闲着的时候也能马上退出
+    drop(handle);
+}
+
+#[test]
+fn jump_targets_parse_and_refuse_the_past() {
+    use life_game::sim::{Jump, ParseJumpError};
+
+    assert_eq!("1000".parse(), Ok(Jump::To(1000)));
+    assert_eq!(" +25 ".parse(), Ok(Jump::By(25)));
+    assert_eq!("".parse::<Jump>(), Err(ParseJumpError::Empty));
+    assert_eq!("+".parse::<Jump>(), Err(ParseJumpError::Empty));
+    for bad in &["abc", "-5", "1e3", "++5", "12 34", "99999999999999999999"] {
+        assert_eq!(
+            bad.parse::<Jump>(),
+            Err(ParseJumpError::NotANumber(bad.to_string()))
+        );
+    }
+
+    assert_eq!(Jump::To(1000).steps_from(400), Some(600));
+    assert_eq!(Jump::To(400).steps_from(400), Some(0));
+    assert_eq!(Jump::To(399).steps_from(400), None);
+    assert_eq!(Jump::By(7).steps_from(400), Some(7));
+}
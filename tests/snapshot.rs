@@ -0,0 +1,116 @@
+use std::sync::{Arc, RwLock};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use life_game::rule::WIRE_CONDUCTOR;
+use life_game::{Rule, Topology, Universe};
+
+#[test]
+fn snapshot_keeps_the_generation_it_was_taken_at() {
+    let mut u = Universe::builder().width(40).height(30).seed(3).build();
+    u.set_rule(Rule::Wireworld);
+    u.set_state(5, 7, WIRE_CONDUCTOR).unwrap();
+    u.mark_drawn();
+    u.toggle_cell(2, 3).unwrap();
+    let frame = u.snapshot();
+    let expected: Vec<_> = (0..30)
+        .flat_map(|row| (0..40).map(move |col| (row, col)))
+        .map(|(row, col)| u.state(row, col))
+        .collect();
+    let changed = u.changed_cells().to_vec();
+    u.step_n(5);
+
+    let states: Vec<_> = (0..30)
+        .flat_map(|row| (0..40).map(move |col| (row, col)))
+        .map(|(row, col)| frame.state(row, col))
+        .collect();
+    assert_eq!(states, expected);
+    assert_eq!(frame.state(30, 0), None);
+    assert_eq!(frame.changed_cells(), &changed[..]);
+    assert_eq!(frame.generation(), u.count() - 5);
+    assert_eq!(frame.rule(), Rule::Wireworld);
+}
+
+#[test]
+fn snapshot_counts_neighbors_like_the_board() {
+    for rule in &["B3/S23", "B2/S34H", "B2/S1V"] {
+        for &topology in &[Topology::Torus, Topology::Bounded, Topology::Mirror] {
+            let mut u = Universe::builder().width(13).height(9).seed(5).build();
+            u.set_rule(rule.parse().unwrap());
+            u.set_topology(topology);
+            let frame = u.snapshot();
+            assert_eq!(frame.topology(), topology);
+            for row in 0..9 {
+                for col in 0..13 {
+                    assert_eq!(
+                        frame.live_neighbor_count(row, col),
+                        u.live_neighbor_count(row, col),
+                        "{} {:?} ({}, {})",
+                        rule,
+                        topology,
+                        row,
+                        col
+                    );
+                }
+            }
+            assert_eq!(frame.live_neighbor_count(9, 0), 0);
+        }
+    }
+}
+
+/// 演化线程每一代都要拿写锁; 窗口拍完快照就放锁, 再花 `RENDER` 照着快照画.
+/// 演化线程等锁的时间只和拍快照有关, 不会被画的时间拖长
+#[test]
+fn rendering_from_snapshots_does_not_block_ticks() {
+    const RENDER: Duration = Duration::from_millis(20);
+    const GENERATIONS: u32 = 200;
+    let universe = Arc::new(RwLock::new(
+        Universe::builder().width(256).height(256).seed(9).build(),
+    ));
+
+    let sim = {
+        let universe = Arc::clone(&universe);
+        thread::spawn(move || {
+            let mut longest = Duration::from_secs(0);
+            for _ in 0..GENERATIONS {
+                let start = Instant::now();
+                let mut u = universe.write().unwrap();
+                longest = longest.max(start.elapsed());
+                u.tick();
+                drop(u);
+                thread::sleep(Duration::from_millis(1));
+            }
+            longest
+        })
+    };
+
+    let mut frames = 0;
+    let mut last = -1;
+    while last < i64::from(GENERATIONS) {
+        let frame = {
+            let mut u = universe.write().unwrap();
+            let frame = u.snapshot();
+            u.mark_drawn();
+            frame
+        };
+        // 照着快照逐格读一遍, 读到的总是完整的一代
+        let alive = (0..frame.height())
+            .flat_map(|row| (0..frame.width()).map(move |col| (row, col)))
+            .filter(|&(row, col)| frame.state(row, col) == Some(1))
+            .count();
+        assert_eq!(alive, frame.population() as usize);
+        assert!(frame.generation() >= last);
+        last = frame.generation();
+        thread::sleep(RENDER);
+        frames += 1;
+    }
+
+    let longest = sim.join().unwrap();
+    assert!(frames > 1);
+    assert!(
+        longest < RENDER,
+        "tick waited {:?} for the lock, render takes {:?}",
+        longest,
+        RENDER
+    );
+}
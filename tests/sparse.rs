@@ -0,0 +1,95 @@
+use life_game::patterns::{GLIDER, GOSPER_GLIDER_GUN};
+use life_game::{
+    Cell, Neighborhood, Region, Rule, SparseError, SparseUniverse, Topology, Universe,
+};
+
+/// 64x64 的有界面板, 中间 16x16 的区域随机撒点
+fn soup(seed: u64) -> Universe {
+    let mut u = Universe::builder().width(64).height(64).seed(seed).build();
+    u.dead_all();
+    u.set_topology(Topology::Bounded);
+    u.randomize_region(Region::new(24, 24, 16, 16), 0.5, false);
+    u
+}
+
+#[test]
+fn sparse_matches_dense_away_from_edges() {
+    // 16 代之内活细胞最多向外扩 16 格, 不会碰到有界面板的边
+    let rules = [
+        Rule::CONWAY,
+        "B36/S23".parse().unwrap(),
+        "B2/S".parse().unwrap(),
+        Rule::CONWAY.with_neighborhood(Neighborhood::VonNeumann),
+    ];
+    for (seed, &rule) in rules.iter().enumerate() {
+        let mut dense = soup(seed as u64);
+        dense.set_rule(rule);
+        // 放在负坐标上, 区域跨过块的边界
+        let mut sparse = SparseUniverse::from_dense(&dense, -40, -90).unwrap();
+        for generation in 1..=16 {
+            dense.tick();
+            sparse.tick();
+            let view = sparse.to_dense(-40, -90, 64, 64);
+            assert!(
+                view.same_cells(&dense),
+                "{} generation {}",
+                rule,
+                generation
+            );
+            assert_eq!(sparse.population(), u64::from(dense.population()));
+        }
+        assert_eq!(sparse.count(), 16);
+    }
+}
+
+#[test]
+fn glider_crosses_chunk_boundaries() {
+    let mut u = SparseUniverse::new();
+    u.insert_pattern(GLIDER, -2, -2);
+    // 每 4 代向右下方移动一格, 400 代后走过了好几个块
+    u.step_n(400);
+    let mut expected = SparseUniverse::new();
+    expected.insert_pattern(GLIDER, 98, 98);
+    let mut cells: Vec<_> = u.live_cells().collect();
+    let mut want: Vec<_> = expected.live_cells().collect();
+    cells.sort_unstable();
+    want.sort_unstable();
+    assert_eq!(cells, want);
+    assert_eq!(u.chunk_count(), 1);
+}
+
+#[test]
+fn gosper_gun_stream_keeps_extending() {
+    let mut u = SparseUniverse::new();
+    u.insert_pattern(GOSPER_GLIDER_GUN, 0, 0);
+    u.step_n(9990);
+    // 周期 30 的整数倍时枪本身回到初始状态, 外面全是完整的滑翔机
+    let gun = u.to_dense(0, 0, 36, 9);
+    let mut original = Universe::from_cells(36, 9, vec![Cell::Dead; 36 * 9]).unwrap();
+    original
+        .insert_pattern(GOSPER_GLIDER_GUN, 0, 0, false)
+        .unwrap();
+    assert!(gun.same_cells(&original));
+    let gliders = (u.population() - GOSPER_GLIDER_GUN.len() as u64) / 5;
+    assert!(gliders >= 330, "only {} gliders", gliders);
+
+    u.step_n(10);
+    let (top, left, bottom, right) = u.bounding_box().unwrap();
+    assert_eq!((top, left), (0, 0));
+    // 滑翔机每 4 代走一格, 最早的那个已经走出两千多格
+    assert!(bottom > 2400 && right > 2400, "{} {}", bottom, right);
+}
+
+#[test]
+fn rejects_rules_without_a_finite_background() {
+    let b0: Rule = "B0/S8".parse().unwrap();
+    assert_eq!(
+        SparseUniverse::with_rule(b0).unwrap_err(),
+        SparseError::UnsupportedRule(b0)
+    );
+    assert!(SparseUniverse::with_rule(Rule::BRIANS_BRAIN).is_err());
+    let mut u = SparseUniverse::new();
+    u.set_cell(-1, -1, Cell::Alive);
+    u.set_cell(-1, -1, Cell::Dead);
+    assert_eq!((u.population(), u.chunk_count()), (0, 0));
+}
@@ -0,0 +1,57 @@
+use life_game::patterns::{BLINKER, BLOCK, GLIDER};
+use life_game::{Cell, Rule, Stability, StabilityDetector, Universe};
+
+fn board(pattern: &[(u32, u32)]) -> Universe {
+    let mut u = Universe::from_cells(8, 8, vec![Cell::Dead; 64]).unwrap();
+    u.insert_pattern(pattern, 3, 3, false).unwrap();
+    u
+}
+
+/// 演化到停下为止, 返回停下时的代数和结果
+fn run(u: &mut Universe, detector: &mut StabilityDetector, max: u32) -> Option<(i64, Stability)> {
+    detector.observe(u);
+    for _ in 0..max {
+        u.tick();
+        match detector.observe(u) {
+            Stability::Changing => {}
+            stop => return Some((u.count(), stop)),
+        }
+    }
+    None
+}
+
+#[test]
+fn block_stops_after_one_tick() {
+    let mut u = board(BLOCK);
+    let stop = run(&mut u, &mut StabilityDetector::new(1), 10);
+    assert_eq!(stop, Some((1, Stability::Repeating { period: 1 })));
+}
+
+#[test]
+fn blinker_needs_period_two_detection() {
+    let mut u = board(BLINKER);
+    assert_eq!(run(&mut u, &mut StabilityDetector::new(1), 20), None);
+
+    let mut u = board(BLINKER);
+    let stop = run(&mut u, &mut StabilityDetector::new(2), 20);
+    assert_eq!(stop, Some((2, Stability::Repeating { period: 2 })));
+}
+
+#[test]
+fn glider_keeps_changing_and_empty_board_stops() {
+    let mut u = board(GLIDER);
+    assert_eq!(run(&mut u, &mut StabilityDetector::new(2), 40), None);
+
+    let mut u = board(&[(0, 0)]);
+    let stop = run(&mut u, &mut StabilityDetector::new(2), 5);
+    assert_eq!(stop, Some((1, Stability::Empty)));
+}
+
+#[test]
+fn decaying_cells_are_not_empty() {
+    // Brian's Brain 里刚死的细胞还在衰亡, 面板要再过一代才算空
+    let mut u = board(&[(0, 0)]);
+    u.set_rule(Rule::BRIANS_BRAIN);
+    let stop = run(&mut u, &mut StabilityDetector::new(2), 5);
+    assert_eq!(stop, Some((2, Stability::Empty)));
+}
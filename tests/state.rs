@@ -0,0 +1,140 @@
+use std::env;
+use std::fs;
+
+use life_game::{Cell, Rule, RunState, StateError, Universe};
+
+fn sample() -> Universe {
+    let mut u = Universe::builder().width(13).height(7).seed(8).build();
+    u.step_n(5);
+    u
+}
+
+#[test]
+fn bytes_round_trip() {
+    let u = sample();
+    let bytes = u.to_state_bytes();
+    assert_eq!(&bytes[..4], b"GLIF");
+    // 24 字节的头、91 个细胞打包成的 12 字节、相位和 "B3/S23" 加上它的长度
+    assert_eq!(bytes.len(), 24 + 12 + 1 + 2 + 6);
+    let back = Universe::from_state_bytes(&bytes).unwrap();
+    assert_eq!(back, u);
+    assert_eq!(back.count(), 5);
+    assert_eq!(back.population(), u.population());
+}
+
+#[test]
+fn file_round_trip() {
+    let path = env::temp_dir().join(format!("game_life_state_{}.sav", std::process::id()));
+    let u = sample();
+    u.save_state(&path).unwrap();
+    let back = Universe::load_state(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+    assert_eq!(back, u);
+    assert_eq!(back.count(), u.count());
+}
+
+#[test]
+fn truncated_files_are_errors() {
+    let bytes = sample().to_state_bytes();
+    for len in [0, 3, 10, 23, 24, 30, bytes.len() - 1].iter() {
+        let err = Universe::from_state_bytes(&bytes[..*len]).err().unwrap();
+        match (len, err) {
+            (0, StateError::BadMagic) | (3, StateError::BadMagic) => {}
+            (_, StateError::Truncated { actual, .. }) => assert_eq!(actual, *len),
+            (_, e) => panic!("unexpected error for {} bytes: {}", len, e),
+        }
+    }
+}
+
+#[test]
+fn corrupt_headers_are_errors() {
+    let good = sample().to_state_bytes();
+
+    let mut bytes = good.clone();
+    bytes[0] = b'X';
+    assert!(matches!(
+        Universe::from_state_bytes(&bytes),
+        Err(StateError::BadMagic)
+    ));
+
+    let mut bytes = good.clone();
+    bytes[4] = 99;
+    assert!(matches!(
+        Universe::from_state_bytes(&bytes),
+        Err(StateError::UnsupportedVersion(99))
+    ));
+
+    let mut bytes = good.clone();
+    bytes[8..12].copy_from_slice(&0u32.to_le_bytes());
+    assert!(matches!(
+        Universe::from_state_bytes(&bytes),
+        Err(StateError::Corrupt(_))
+    ));
+
+    // 巨大的尺寸不能导致按声明的大小分配内存
+    let mut bytes = good;
+    bytes[8..12].copy_from_slice(&u32::MAX.to_le_bytes());
+    bytes[12..16].copy_from_slice(&u32::MAX.to_le_bytes());
+    assert!(Universe::from_state_bytes(&bytes).is_err());
+}
+
+#[test]
+fn missing_file_is_io_error() {
+    let path = env::temp_dir().join("game_life_state_does_not_exist.sav");
+    assert!(matches!(
+        Universe::load_state(&path),
+        Err(StateError::Io(_))
+    ));
+}
+
+#[test]
+fn paused_flag_round_trips() {
+    let mut u = sample();
+    assert!(!Universe::from_state_bytes(&u.to_state_bytes())
+        .unwrap()
+        .is_paused());
+    u.set_run_state(RunState::Paused);
+    let bytes = u.to_state_bytes();
+    assert_eq!(&bytes[6..8], &[1, 0]);
+    assert!(Universe::from_state_bytes(&bytes).unwrap().is_paused());
+}
+
+#[test]
+fn b0_phase_and_rule_round_trip() {
+    let mut u = Universe::from_cells(4, 4, vec![Cell::Dead; 16]).unwrap();
+    let rule: Rule = "B0/S".parse().unwrap();
+    u.set_rule(rule);
+    u.step_n(1);
+    assert!(u.is_inverted());
+    assert_eq!(u.actual_cell(0, 0), Some(Cell::Alive));
+
+    let mut back = Universe::from_state_bytes(&u.to_state_bytes()).unwrap();
+    assert_eq!(back.rule(), rule);
+    assert!(back.is_inverted());
+    assert_eq!(back.actual_cell(0, 0), Some(Cell::Alive));
+    // 读回来之后接着演化, 和没存过的一样
+    u.step_n(1);
+    back.step_n(1);
+    assert_eq!(back.actual_cell(0, 0), u.actual_cell(0, 0));
+    assert_eq!(back.actual_cell(0, 0), Some(Cell::Dead));
+}
+
+#[test]
+fn version_1_saves_load_as_conway() {
+    let mut u = sample();
+    u.set_rule("B36/S23".parse().unwrap());
+    let mut bytes = u.to_state_bytes();
+    bytes.truncate(24 + 12);
+    bytes[4..6].copy_from_slice(&1u16.to_le_bytes());
+    let back = Universe::from_state_bytes(&bytes).unwrap();
+    assert_eq!(back.rule(), Rule::CONWAY);
+    assert_eq!(back.population(), u.population());
+
+    // 不含 B0 的规则不能是取反的相位
+    let mut bytes = sample().to_state_bytes();
+    bytes[24 + 12] = 1;
+    assert!(matches!(
+        Universe::from_state_bytes(&bytes),
+        Err(StateError::Corrupt(_))
+    ));
+}
@@ -0,0 +1,121 @@
+use std::env;
+use std::fs;
+
+use life_game::stats::{GenerationStats, PopulationHistory, StatsLog};
+use life_game::Universe;
+
+fn blinker() -> Universe {
+    ".....\n.....\n.###.\n.....\n.....\n".parse().unwrap()
+}
+
+#[test]
+fn blinker_births_and_deaths() {
+    let mut u = blinker();
+    assert_eq!((u.births(), u.deaths()), (0, 0));
+    let mut log = StatsLog::new(100);
+    for _ in 0..4 {
+        log.tick(&mut u);
+    }
+    let rows: Vec<GenerationStats> = log.iter().copied().collect();
+    assert_eq!(rows.len(), 4);
+    for (i, row) in rows.iter().enumerate() {
+        assert_eq!(
+            *row,
+            GenerationStats {
+                generation: i as i64 + 1,
+                population: 3,
+                births: 2,
+                deaths: 2,
+                mutations: 0,
+            }
+        );
+    }
+}
+
+#[test]
+fn buffer_is_bounded() {
+    let mut u = blinker();
+    let mut log = StatsLog::new(3);
+    for _ in 0..10 {
+        log.tick(&mut u);
+    }
+    assert_eq!(log.len(), 3);
+    let generations: Vec<i64> = log.iter().map(|row| row.generation).collect();
+    assert_eq!(generations, vec![8, 9, 10]);
+    log.clear();
+    assert!(log.is_empty());
+}
+
+#[test]
+fn csv_has_one_row_per_generation() {
+    let mut u: Universe = "....\n.##.\n.#..\n....\n".parse().unwrap();
+    let mut log = StatsLog::new(10);
+    log.record(&u);
+    log.tick(&mut u);
+    log.tick(&mut u);
+    assert_eq!(
+        log.to_csv(),
+        "generation,population,births,deaths,mutations\n0,3,0,0,0\n1,4,1,0,0\n2,4,0,0,0\n"
+    );
+    let path = env::temp_dir().join(format!("life_stats_{}.csv", std::process::id()));
+    log.export_csv(&path).unwrap();
+    let text = fs::read_to_string(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+    assert_eq!(text, log.to_csv());
+}
+
+#[test]
+fn mutations_are_recorded_separately() {
+    let mut u = blinker();
+    u.set_mutation_rate(1.0);
+    let mut log = StatsLog::new(10);
+    log.tick(&mut u);
+    let row = *log.iter().next().unwrap();
+    assert_eq!((row.births, row.deaths, row.mutations), (2, 2, 25));
+    assert_eq!(row.population, 25 - 3);
+}
+
+#[test]
+fn population_history_keeps_the_latest_generations() {
+    let mut history = PopulationHistory::new(4);
+    assert_eq!(history.range(), None);
+    assert!(history.points(100, 20).is_empty());
+    for count in [5, 9, 2, 7, 3, 8] {
+        history.push(count);
+    }
+    assert_eq!(history.len(), 4);
+    assert_eq!(history.iter().collect::<Vec<_>>(), vec![2, 7, 3, 8]);
+    assert_eq!(history.range(), Some((2, 8)));
+    history.clear();
+    assert!(history.is_empty());
+}
+
+#[test]
+fn population_points_are_scaled_to_the_box() {
+    let mut history = PopulationHistory::new(5);
+    for count in [10, 40, 25] {
+        history.push(count);
+    }
+    // 横向按 5 代铺满 101 像素, 纵向最少的在最下面
+    assert_eq!(history.points(101, 31), vec![(0, 30), (25, 0), (50, 15)]);
+
+    let mut flat = PopulationHistory::new(3);
+    flat.push(6);
+    flat.push(6);
+    assert_eq!(flat.points(11, 11), vec![(0, 5), (5, 5)]);
+}
+
+#[test]
+fn ticks_feed_the_population_history() {
+    let mut u: Universe = "....\n.##.\n.#..\n....\n".parse().unwrap();
+    assert!(u.population_history().is_empty());
+    u.tick();
+    u.tick();
+    let counts: Vec<u32> = u.population_history().iter().collect();
+    assert_eq!(counts, vec![4, 4]);
+    u.reset();
+    assert!(u.population_history().is_empty());
+    u.tick();
+    u.dead_all();
+    assert!(u.population_history().is_empty());
+}
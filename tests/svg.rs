@@ -0,0 +1,46 @@
+use std::env;
+use std::fs;
+
+use life_game::export::{export_svg, render_svg};
+use life_game::Universe;
+
+fn runs(u: &Universe) -> usize {
+    u.to_string()
+        .lines()
+        .map(|line| line.split('◻').filter(|run| !run.is_empty()).count())
+        .sum()
+}
+
+#[test]
+fn one_rect_per_horizontal_run() {
+    let u: Universe = "##.#.\n.....\n#####\n.##..\n".parse().unwrap();
+    let svg = render_svg(&u, 10, false);
+    assert!(svg.starts_with("<svg "));
+    assert!(svg.trim_end().ends_with("</svg>"));
+    assert!(svg.contains(r#"width="50" height="40""#));
+    assert_eq!(svg.matches(r#"fill="black""#).count(), runs(&u));
+    assert_eq!(runs(&u), 4);
+    assert!(svg.contains(r#"<rect x="0" y="0" width="20" height="10" fill="black"/>"#));
+    assert!(svg.contains(r#"<rect x="0" y="20" width="50" height="10" fill="black"/>"#));
+    assert!(svg.contains(r#"<rect x="10" y="30" width="20" height="10" fill="black"/>"#));
+    assert_eq!(svg.matches(r#"fill="white""#).count(), 1);
+    assert!(!svg.contains("<path"));
+}
+
+#[test]
+fn grid_is_optional() {
+    let u: Universe = "#.\n.#\n".parse().unwrap();
+    let svg = render_svg(&u, 4, true);
+    assert!(svg.contains(r#"<path d="M0 0V8M4 0V8M8 0V8M0 0H8M0 4H8M0 8H8""#));
+    assert_eq!(svg.matches("<rect").count(), 3);
+}
+
+#[test]
+fn writes_file() {
+    let u: Universe = "#.\n.#\n".parse().unwrap();
+    let path = env::temp_dir().join(format!("life_export_{}.svg", std::process::id()));
+    export_svg(&u, 3, &path).unwrap();
+    let text = fs::read_to_string(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+    assert_eq!(text, render_svg(&u, 3, false));
+}
@@ -0,0 +1,1198 @@
+use life_game::patterns::{BLINKER, BLOCK, GLIDER};
+use life_game::rule::{FIRE_BURNING, WIRE_CONDUCTOR};
+use life_game::export::{heat_level, HEAT_PALETTE};
+use life_game::universe::{ACTIVITY_STEP, TOPPLE_LIMIT_PER_CELL, TRAIL_LENGTH};
+use life_game::{
+    Anchor, Cell, CombineOp, Engine, Region, Rule, RunState, StableResult, Topology, Universe,
+    UniverseError,
+};
+
+fn alive_at(u: &Universe, row: u32, col: u32) -> bool {
+    u.get_cell(row, col) == Some(Cell::Alive)
+}
+
+#[test]
+fn block_is_still_life() {
+    let mut u = Universe::new();
+    u.dead_all();
+    for &(r, c) in &[(1, 1), (1, 2), (2, 1), (2, 2)] {
+        u.set_cell(Cell::Alive, c, r).unwrap();
+    }
+    let before = u.cells().to_vec();
+    u.tick();
+    assert_eq!(u.cells(), &before[..]);
+    assert_eq!(u.count(), 1);
+}
+
+#[test]
+fn lonely_cell_dies() {
+    let mut u = Universe::new();
+    u.dead_all();
+    u.set_cell(Cell::Alive, 10, 10).unwrap();
+    u.tick();
+    assert!(!alive_at(&u, 10, 10));
+    assert!(u.cells().iter().all(|&c| c == Cell::Dead));
+}
+
+fn live(u: &Universe) -> Vec<(u32, u32)> {
+    u.live_cells().collect()
+}
+
+#[test]
+fn with_size_allocates_non_square_board() {
+    let u = Universe::with_size(3, 5);
+    assert_eq!(u.width(), 3);
+    assert_eq!(u.height(), 5);
+    assert_eq!(u.cells().len(), 15);
+    assert_eq!(u.get_index(4, 2), 14);
+}
+
+#[test]
+fn blinker_flips_across_seam_on_3x5() {
+    // 3 列的环面上, 横向的一相会首尾相接, 所以这里只验证第一次翻转
+    let mut u = Universe::with_size(3, 5);
+    u.dead_all();
+    for &r in &[4, 0, 1] {
+        u.set_cell(Cell::Alive, 1, r).unwrap();
+    }
+    u.tick();
+    assert_eq!(live(&u), vec![(0, 0), (0, 1), (0, 2)]);
+}
+
+#[test]
+fn blinker_oscillates_across_seam_on_non_square_board() {
+    let mut u = Universe::with_size(6, 5);
+    u.dead_all();
+    for &c in &[5, 0, 1] {
+        u.set_cell(Cell::Alive, c, 0).unwrap();
+    }
+    let horizontal = live(&u);
+    u.tick();
+    assert_eq!(live(&u), vec![(0, 0), (1, 0), (4, 0)]);
+    u.tick();
+    assert_eq!(live(&u), horizontal);
+}
+
+fn board(width: u32, height: u32, alive: &[(u32, u32)]) -> Universe {
+    let mut cells = vec![Cell::Dead; (width * height) as usize];
+    for &(r, c) in alive {
+        cells[(r * width + c) as usize] = Cell::Alive;
+    }
+    Universe::from_cells(width, height, cells).unwrap()
+}
+
+#[test]
+fn from_cells_rejects_wrong_length() {
+    let err = Universe::from_cells(4, 4, vec![Cell::Dead; 15]).err();
+    assert_eq!(
+        err,
+        Some(UniverseError::SizeMismatch {
+            expected: 16,
+            actual: 15
+        })
+    );
+}
+
+#[test]
+fn from_cells_rejects_sizes_beyond_u32_cells() {
+    // 65536 * 65536 在 u32 里会绕回 0, 空的 cells 不能被当成对的长度
+    let err = Universe::from_cells(65536, 65536, Vec::new()).err();
+    assert_eq!(
+        err,
+        Some(UniverseError::TooLarge {
+            size: (65536, 65536)
+        })
+    );
+    assert!(Universe::from_cells(u32::MAX, 2, Vec::new()).is_err());
+}
+
+#[test]
+fn from_cells_keeps_data_and_starts_at_generation_zero() {
+    let u = board(4, 3, &[(0, 0), (2, 3)]);
+    assert_eq!(u.count(), 0);
+    assert_eq!(u.run_state(), RunState::Running);
+    assert!(u.needs_frame());
+    assert_eq!(live(&u), vec![(0, 0), (2, 3)]);
+}
+
+#[test]
+fn beehive_is_still_life() {
+    let alive = [(1, 2), (1, 3), (2, 1), (2, 4), (3, 2), (3, 3)];
+    let mut u = board(6, 5, &alive);
+    for _ in 0..4 {
+        u.tick();
+        assert_eq!(live(&u), alive.to_vec());
+    }
+}
+
+#[test]
+fn toad_has_period_two() {
+    let phase0 = [(2, 2), (2, 3), (2, 4), (3, 1), (3, 2), (3, 3)];
+    let phase1 = [(1, 3), (2, 1), (2, 4), (3, 1), (3, 4), (4, 2)];
+    let mut u = board(6, 6, &phase0);
+    u.tick();
+    assert_eq!(live(&u), phase1.to_vec());
+    u.tick();
+    assert_eq!(live(&u), phase0.to_vec());
+    assert_eq!(u.count(), 2);
+}
+
+#[test]
+fn get_cell_is_bounds_checked() {
+    let u = board(4, 3, &[(2, 3)]);
+    assert_eq!(u.get_cell(2, 3), Some(Cell::Alive));
+    assert_eq!(u.get_cell(0, 0), Some(Cell::Dead));
+    assert_eq!(u.get_cell(3, 0), None);
+    assert_eq!(u.get_cell(0, 4), None);
+}
+
+#[test]
+fn live_cells_yields_glider_in_row_major_order() {
+    let u = board(8, 8, &[(2, 2), (0, 1), (1, 2), (2, 0), (2, 1)]);
+    let cells: Vec<_> = u.live_cells().collect();
+    assert_eq!(cells, vec![(0, 1), (1, 2), (2, 0), (2, 1), (2, 2)]);
+    assert_eq!(u.live_count(), 5);
+}
+
+#[test]
+fn live_cells_of_dead_board_is_empty() {
+    let u = board(5, 7, &[]);
+    assert_eq!(u.live_cells().next(), None);
+    assert_eq!(u.live_count(), 0);
+}
+
+#[test]
+fn toggle_cell_flips_and_returns_new_state() {
+    let mut u = board(4, 4, &[(1, 1)]);
+    assert_eq!(u.toggle_cell(1, 1), Ok(Cell::Dead));
+    assert_eq!(u.get_cell(1, 1), Some(Cell::Dead));
+    assert_eq!(u.toggle_cell(1, 1), Ok(Cell::Alive));
+    assert_eq!(u.toggle_cell(0, 3), Ok(Cell::Alive));
+    assert_eq!(live(&u), vec![(0, 3), (1, 1)]);
+}
+
+#[test]
+fn toggle_cell_out_of_bounds_is_an_error() {
+    let mut u = board(4, 4, &[(1, 1)]);
+    assert_eq!(
+        u.toggle_cell(4, 0),
+        Err(UniverseError::OutOfBounds { row: 4, col: 0 })
+    );
+    assert_eq!(
+        u.toggle_cell(0, 4),
+        Err(UniverseError::OutOfBounds { row: 0, col: 4 })
+    );
+    assert_eq!(live(&u), vec![(1, 1)]);
+}
+
+#[test]
+fn insert_pattern_places_offsets() {
+    let mut u = board(6, 6, &[]);
+    u.insert_pattern(BLOCK, 2, 3, false).unwrap();
+    assert_eq!(live(&u), vec![(2, 3), (2, 4), (3, 3), (3, 4)]);
+}
+
+#[test]
+fn insert_pattern_wraps_glider_to_far_side() {
+    let mut u = board(8, 8, &[]);
+    u.insert_pattern(GLIDER, 6, 7, true).unwrap();
+    assert_eq!(live(&u), vec![(0, 0), (0, 1), (0, 7), (6, 0), (7, 1)]);
+    // 绕回的滑翔机照常演化: 4 代后整体向右下移动一格
+    for _ in 0..4 {
+        u.tick();
+    }
+    let mut moved = board(8, 8, &[]);
+    moved.insert_pattern(GLIDER, 7, 0, true).unwrap();
+    assert_eq!(live(&u), live(&moved));
+}
+
+#[test]
+fn insert_pattern_without_wrap_drops_cells() {
+    let mut u = board(8, 8, &[]);
+    u.insert_pattern(GLIDER, 6, 7, false).unwrap();
+    assert_eq!(live(&u), vec![]);
+    u.insert_pattern(BLINKER, 7, 6, false).unwrap();
+    assert_eq!(live(&u), vec![(7, 6), (7, 7)]);
+}
+
+#[test]
+fn builder_with_same_seed_is_reproducible() {
+    let a = Universe::builder()
+        .width(20)
+        .height(10)
+        .density(0.3)
+        .seed(42)
+        .build();
+    let b = Universe::builder()
+        .width(20)
+        .height(10)
+        .density(0.3)
+        .seed(42)
+        .build();
+    assert_eq!(a.width(), 20);
+    assert_eq!(a.height(), 10);
+    assert_eq!(a.cells(), b.cells());
+}
+
+#[test]
+fn builder_density_extremes() {
+    let empty = Universe::builder().density(0.0).seed(1).build();
+    assert!(empty.cells().iter().all(|&c| c == Cell::Dead));
+    let full = Universe::builder().density(1.0).seed(1).build();
+    assert!(full.cells().iter().all(|&c| c == Cell::Alive));
+}
+
+#[test]
+fn reset_with_seed_rerolls_deterministically() {
+    let mut a = Universe::builder().width(16).height(16).seed(5).build();
+    let mut b = Universe::builder().width(16).height(16).seed(5).build();
+    a.reset();
+    b.reset();
+    assert_eq!(a.cells(), b.cells());
+}
+
+#[test]
+fn step_n_advances_generations() {
+    let mut u = board(6, 6, &[(2, 1), (2, 2), (2, 3)]);
+    u.step_n(3);
+    assert_eq!(u.count(), 3);
+    assert_eq!(live(&u), vec![(1, 2), (2, 2), (3, 2)]);
+}
+
+#[test]
+fn block_is_stable_immediately() {
+    let mut u = board(6, 6, &[]);
+    u.insert_pattern(BLOCK, 2, 2, false).unwrap();
+    assert_eq!(u.run_until_stable(10), StableResult::Stable { steps: 0 });
+}
+
+#[test]
+fn blinker_never_stabilizes() {
+    let mut u = board(6, 6, &[]);
+    u.insert_pattern(BLINKER, 2, 1, false).unwrap();
+    assert_eq!(u.run_until_stable(50), StableResult::StepLimit);
+    assert_eq!(u.count(), 50);
+}
+
+#[test]
+fn lonely_cell_goes_extinct() {
+    let mut u = board(6, 6, &[(3, 3)]);
+    assert_eq!(u.run_until_stable(10), StableResult::Extinct { steps: 1 });
+}
+
+#[test]
+fn b0_rules_stabilize_on_actual_cells() {
+    let full: Vec<(u32, u32)> = (0..6).flat_map(|r| (0..6).map(move |c| (r, c))).collect();
+    // 全满的面板在 B0/S8 下不变, 存的细胞却取反成了全空
+    let mut u = board(6, 6, &full);
+    u.set_rule("B0/S8".parse().unwrap());
+    assert_eq!(u.run_until_stable(10), StableResult::Stable { steps: 0 });
+    assert!(u.is_inverted());
+    assert_eq!((u.population(), u.actual_population()), (0, 36));
+
+    // B0/S 下全满的面板下一代全部死亡, 存的细胞是全满的
+    let mut u = board(6, 6, &full);
+    u.set_rule("B0/S".parse().unwrap());
+    assert_eq!(u.run_until_stable(10), StableResult::Extinct { steps: 1 });
+    assert_eq!((u.population(), u.actual_population()), (36, 0));
+}
+
+#[test]
+fn r_pentomino_hits_step_cap() {
+    let mut u = board(100, 100, &[]);
+    u.insert_pattern(&[(0, 1), (0, 2), (1, 0), (1, 1), (2, 1)], 50, 50, false)
+        .unwrap();
+    assert_eq!(u.run_until_stable(200), StableResult::StepLimit);
+}
+
+#[test]
+fn fill_region_is_clamped_to_grid() {
+    let mut u = board(5, 4, &[]);
+    u.fill_region(Region::new(2, 3, 10, 10), Cell::Alive, false);
+    assert_eq!(live(&u), vec![(2, 3), (2, 4), (3, 3), (3, 4)]);
+}
+
+#[test]
+fn fill_region_wraps_across_seam() {
+    let mut u = board(5, 4, &[]);
+    u.fill_region(Region::new(3, 4, 2, 2), Cell::Alive, true);
+    assert_eq!(live(&u), vec![(0, 0), (0, 4), (3, 0), (3, 4)]);
+}
+
+#[test]
+fn clear_region_wraps_and_keeps_outside() {
+    let mut u = board(5, 4, &[]);
+    u.fill_region(Region::new(0, 0, 4, 5), Cell::Alive, false);
+    u.clear_region(Region::new(3, 4, 2, 2), true);
+    assert_eq!(u.live_count(), 16);
+    assert_eq!(u.get_cell(0, 0), Some(Cell::Dead));
+    assert_eq!(u.get_cell(3, 4), Some(Cell::Dead));
+    assert_eq!(u.get_cell(1, 1), Some(Cell::Alive));
+}
+
+#[test]
+fn oversized_wrapping_region_covers_board_once() {
+    let mut u = board(5, 4, &[(1, 1)]);
+    u.clear_region(Region::new(7, 9, 100, 100), true);
+    assert_eq!(u.live_count(), 0);
+}
+
+#[test]
+fn randomize_region_only_touches_region() {
+    let mut u = Universe::builder()
+        .width(8)
+        .height(8)
+        .density(0.0)
+        .seed(3)
+        .build();
+    u.randomize_region(Region::new(6, 6, 4, 4), 1.0, true);
+    let mut expected = Vec::new();
+    for &r in &[0, 1, 6, 7] {
+        for &c in &[0, 1, 6, 7] {
+            expected.push((r, c));
+        }
+    }
+    assert_eq!(live(&u), expected);
+    u.randomize_region(Region::new(6, 6, 4, 4), 0.0, false);
+    expected.retain(|&(r, c)| r < 6 || c < 6);
+    assert_eq!(live(&u), expected);
+}
+
+#[test]
+fn set_cell_out_of_bounds_leaves_cells_untouched() {
+    let mut u = board(4, 3, &[(0, 0)]);
+    let before = u.cells().to_vec();
+    assert_eq!(
+        u.set_cell(Cell::Alive, 0, 3),
+        Err(UniverseError::OutOfBounds { row: 3, col: 0 })
+    );
+    assert_eq!(u.cells(), &before[..]);
+}
+
+#[test]
+fn insert_pattern_with_origin_off_grid_is_an_error() {
+    let mut u = board(4, 4, &[]);
+    assert_eq!(
+        u.insert_pattern(BLOCK, 4, 0, false),
+        Err(UniverseError::OutOfBounds { row: 4, col: 0 })
+    );
+    assert_eq!(u.live_count(), 0);
+    u.insert_pattern(BLOCK, 4, 0, true).unwrap();
+    assert_eq!(live(&u), vec![(0, 0), (0, 1), (1, 0), (1, 1)]);
+}
+
+#[test]
+fn ticked_copies_of_same_seed_stay_equal() {
+    let mut a = Universe::builder().width(20).height(12).seed(9).build();
+    let mut b = Universe::builder().width(20).height(12).seed(9).build();
+    let mut c = a.clone();
+    for _ in 0..10 {
+        a.tick();
+        b.tick();
+        c.tick();
+        assert_eq!(a, b);
+        assert!(a.same_cells(&c));
+    }
+}
+
+#[test]
+fn run_state_toggles_between_running_and_paused() {
+    let mut u = board(5, 5, &[(1, 1)]);
+    u.toggle_run_state();
+    assert_eq!(u.run_state(), RunState::Paused);
+    assert!(u.is_paused());
+    u.toggle_run_state();
+    assert_eq!(u.run_state(), RunState::Running);
+    assert!(!u.is_paused());
+    u.set_run_state(RunState::Paused);
+    u.dead_all();
+    assert_eq!(u.run_state(), RunState::Paused);
+}
+
+#[test]
+fn edits_are_only_allowed_while_paused() {
+    let mut u = board(5, 5, &[(1, 1)]);
+    assert!(!u.begin_edit());
+    assert_eq!(u.run_state(), RunState::Running);
+    u.end_edit();
+    assert_eq!(u.run_state(), RunState::Running);
+
+    // 暂停时按下鼠标进入编辑, 拖动时保持编辑, 松开回到暂停
+    u.toggle_run_state();
+    assert!(u.begin_edit());
+    u.toggle_cell(2, 2).unwrap();
+    assert!(u.begin_edit());
+    assert_eq!(u.run_state(), RunState::Editing);
+    assert!(u.is_paused());
+    u.end_edit();
+    assert_eq!(u.run_state(), RunState::Paused);
+    assert!(u.needs_frame());
+}
+
+#[test]
+fn resuming_after_an_edit_runs_the_edited_board() {
+    let mut u = board(5, 5, &[]);
+    u.set_run_state(RunState::Paused);
+    assert!(u.begin_edit());
+    for col in 1..4 {
+        u.toggle_cell(2, col).unwrap();
+    }
+    // 还按着鼠标就继续演化, 松开时不会再变回暂停
+    u.toggle_run_state();
+    assert_eq!(u.run_state(), RunState::Running);
+    u.end_edit();
+    assert_eq!(u.run_state(), RunState::Running);
+    assert!(!u.begin_edit());
+    u.tick();
+    assert_eq!(live(&u), vec![(1, 2), (2, 2), (3, 2)]);
+}
+
+#[test]
+fn equality_ignores_transient_state() {
+    let mut a = board(5, 5, &[(1, 1), (1, 2), (2, 1), (2, 2)]);
+    let b = a.clone();
+    a.tick();
+    a.set_run_state(RunState::Paused);
+    a.mark_drawn();
+    assert_eq!(a.count(), 1);
+    assert_eq!(a, b);
+    assert_ne!(a, board(5, 5, &[(1, 1)]));
+    assert_ne!(a, board(5, 6, &[(1, 1), (1, 2), (2, 1), (2, 2)]));
+}
+
+#[test]
+fn debug_is_a_summary() {
+    let u = board(5, 4, &[(0, 0), (3, 4)]);
+    assert_eq!(
+        format!("{:?}", u),
+        "Universe { width: 5, height: 4, count: 0, live: 2 }"
+    );
+}
+
+fn assert_population(u: &Universe) {
+    assert_eq!(u.population() as usize, u.live_count());
+}
+
+#[test]
+fn population_tracks_every_mutation() {
+    let mut u = Universe::builder().width(16).height(12).seed(21).build();
+    assert_population(&u);
+    for _ in 0..20 {
+        u.tick();
+        assert_population(&u);
+    }
+    u.set_cell(Cell::Alive, 0, 0).unwrap();
+    u.set_cell(Cell::Alive, 0, 0).unwrap();
+    assert_population(&u);
+    u.toggle_cell(5, 5).unwrap();
+    assert_population(&u);
+    u.insert_pattern(GLIDER, 10, 10, true).unwrap();
+    assert_population(&u);
+    u.fill_region(Region::new(2, 2, 3, 3), Cell::Alive, false);
+    assert_population(&u);
+    u.randomize_region(Region::new(8, 12, 6, 6), 0.5, true);
+    assert_population(&u);
+    u.reset();
+    assert_population(&u);
+    u.dead_all();
+    assert_eq!(u.population(), 0);
+}
+
+#[test]
+fn resize_grow_top_left_keeps_coordinates() {
+    // 方块跨过右下角的接缝
+    let mut u = board(4, 4, &[(0, 0), (0, 3), (3, 0), (3, 3)]);
+    u.tick();
+    u.resize(6, 5, Anchor::TopLeft);
+    assert_eq!(u.width(), 6);
+    assert_eq!(u.height(), 5);
+    assert_eq!(u.count(), 1);
+    assert_eq!(live(&u), vec![(0, 0), (0, 3), (3, 0), (3, 3)]);
+    assert_eq!(u.population(), 4);
+    // 在更大的网格里接缝被拆开, 四个角不再相邻
+    u.tick();
+    assert_eq!(u.population(), 0);
+}
+
+#[test]
+fn resize_grow_centered() {
+    let mut u = board(3, 3, &[(0, 0), (1, 1), (2, 2)]);
+    u.resize(7, 5, Anchor::Center);
+    assert_eq!(live(&u), vec![(1, 2), (2, 3), (3, 4)]);
+}
+
+#[test]
+fn resize_shrink_drops_cells_across_seam() {
+    let mut u = board(6, 6, &[(0, 0), (0, 5), (5, 0), (5, 5), (2, 2)]);
+    u.resize(4, 4, Anchor::TopLeft);
+    assert_eq!(live(&u), vec![(0, 0), (2, 2)]);
+    let mut u = board(6, 6, &[(0, 0), (0, 5), (5, 0), (5, 5), (2, 2)]);
+    u.resize(4, 4, Anchor::Center);
+    assert_eq!(live(&u), vec![(1, 1)]);
+    assert_eq!(u.population(), 1);
+}
+
+#[test]
+fn translate_by_full_board_is_noop_with_wrap() {
+    let mut u = board(9, 7, &[]);
+    u.insert_pattern(GLIDER, 5, 7, true).unwrap();
+    let before = u.clone();
+    u.translate(7, 9, true);
+    assert_eq!(u, before);
+    u.translate(-14, -27, true);
+    assert_eq!(u, before);
+}
+
+#[test]
+fn translate_negative_offsets() {
+    let mut u = board(6, 6, &[]);
+    u.insert_pattern(BLOCK, 0, 1, false).unwrap();
+    let mut wrapped = u.clone();
+    wrapped.translate(-1, -2, true);
+    assert_eq!(live(&wrapped), vec![(0, 0), (0, 5), (5, 0), (5, 5)]);
+    u.translate(-1, -2, false);
+    assert_eq!(live(&u), vec![(0, 0)]);
+    assert_eq!(u.population(), 1);
+}
+
+#[test]
+fn four_clockwise_rotations_are_identity() {
+    let original = Universe::builder().width(7).height(4).seed(17).build();
+    let mut u = original.clone();
+    u.rotate90_cw();
+    assert_eq!(u.width(), 4);
+    assert_eq!(u.height(), 7);
+    for _ in 0..3 {
+        u.rotate90_cw();
+    }
+    assert_eq!(u, original);
+    u.rotate90_cw();
+    u.rotate90_ccw();
+    assert_eq!(u, original);
+    assert_eq!(u.population(), original.population());
+}
+
+#[test]
+fn rotate_non_square_board() {
+    // ##.
+    // ...
+    let mut u = board(3, 2, &[(0, 0), (0, 1)]);
+    u.rotate90_cw();
+    assert_eq!(u.to_string(), "◻◼\n◻◼\n◻◻\n");
+    u.rotate90_ccw();
+    u.rotate90_ccw();
+    assert_eq!(u.to_string(), "◻◻\n◼◻\n◼◻\n");
+}
+
+#[test]
+fn flips_are_involutions() {
+    let original = Universe::builder().width(6).height(5).seed(2).build();
+    let mut u = original.clone();
+    u.flip_horizontal();
+    u.flip_horizontal();
+    assert_eq!(u, original);
+    u.flip_vertical();
+    assert_ne!(u, original);
+    u.flip_vertical();
+    assert_eq!(u, original);
+}
+
+#[test]
+fn flipped_glider_evolves_as_mirrored_glider() {
+    let mut glider = board(10, 10, &[]);
+    glider.insert_pattern(GLIDER, 2, 2, false).unwrap();
+    let mut mirrored = glider.clone();
+    mirrored.flip_horizontal();
+
+    glider.step_n(8);
+    mirrored.step_n(8);
+    glider.flip_horizontal();
+    assert_eq!(mirrored, glider);
+    assert_eq!(mirrored.population(), 5);
+}
+
+#[test]
+fn bounding_box_of_empty_board_is_none() {
+    let u = board(5, 5, &[]);
+    assert_eq!(u.bounding_box(), None);
+    assert_eq!(u.wrapped_bounding_box(), None);
+}
+
+#[test]
+fn bounding_box_of_single_cell() {
+    let u = board(5, 5, &[(3, 1)]);
+    assert_eq!(u.bounding_box(), Some((3, 1, 3, 1)));
+    assert_eq!(u.wrapped_bounding_box(), Some(Region::new(3, 1, 1, 1)));
+}
+
+#[test]
+fn bounding_box_of_full_board() {
+    let mut u = board(6, 4, &[]);
+    u.fill_region(Region::new(0, 0, 4, 6), Cell::Alive, false);
+    assert_eq!(u.bounding_box(), Some((0, 0, 3, 5)));
+    assert_eq!(u.wrapped_bounding_box(), Some(Region::new(0, 0, 4, 6)));
+}
+
+#[test]
+fn bounding_box_of_glider() {
+    let mut u = board(10, 10, &[]);
+    u.insert_pattern(GLIDER, 4, 3, false).unwrap();
+    assert_eq!(u.bounding_box(), Some((4, 3, 6, 5)));
+    assert_eq!(u.wrapped_bounding_box(), Some(Region::new(4, 3, 3, 3)));
+}
+
+#[test]
+fn wrapped_bounding_box_straddles_seam() {
+    let mut u = board(10, 8, &[]);
+    u.insert_pattern(GLIDER, 7, 9, true).unwrap();
+    assert_eq!(u.bounding_box(), Some((0, 0, 7, 9)));
+    assert_eq!(u.wrapped_bounding_box(), Some(Region::new(7, 9, 3, 3)));
+}
+
+#[test]
+fn combine_ops_on_small_boards() {
+    let a = board(3, 2, &[(0, 0), (0, 1), (1, 0)]);
+    let b = board(3, 2, &[(0, 1), (1, 0), (1, 2)]);
+    let cases = [
+        (CombineOp::Union, vec![(0, 0), (0, 1), (1, 0), (1, 2)]),
+        (CombineOp::Intersection, vec![(0, 1), (1, 0)]),
+        (CombineOp::Difference, vec![(0, 0)]),
+        (CombineOp::Xor, vec![(0, 0), (1, 2)]),
+    ];
+    for (op, expected) in cases.iter() {
+        let c = a.combined(&b, *op).unwrap();
+        assert_eq!(&live(&c), expected, "{:?}", op);
+        assert_eq!(c.population() as usize, expected.len());
+    }
+    assert_eq!(live(&a), vec![(0, 0), (0, 1), (1, 0)]);
+}
+
+#[test]
+fn combine_in_place_and_dimension_mismatch() {
+    let mut a = board(3, 2, &[(0, 0)]);
+    a.combine(&board(3, 2, &[(1, 1)]), CombineOp::Union)
+        .unwrap();
+    assert_eq!(live(&a), vec![(0, 0), (1, 1)]);
+    assert_eq!(
+        a.combine(&board(2, 3, &[]), CombineOp::Xor),
+        Err(UniverseError::DimensionMismatch {
+            expected: (3, 2),
+            actual: (2, 3)
+        })
+    );
+    assert_eq!(live(&a), vec![(0, 0), (1, 1)]);
+}
+
+#[test]
+fn xor_shows_what_changed() {
+    let before = board(5, 5, &[(2, 1), (2, 2), (2, 3)]);
+    let mut after = before.clone();
+    after.tick();
+    let diff = before.combined(&after, CombineOp::Xor).unwrap();
+    assert_eq!(live(&diff), vec![(1, 2), (2, 1), (2, 3), (3, 2)]);
+}
+
+/// 8x8 面板左上角的滑翔机演化 32 代, 刚好沿对角线走完一圈
+fn glider_after_lap(topology: Topology) -> Universe {
+    let mut u = Universe::from_cells(8, 8, vec![Cell::Dead; 64]).unwrap();
+    u.set_topology(topology);
+    u.insert_pattern(GLIDER, 0, 0, false).unwrap();
+    u.step_n(32);
+    u
+}
+
+#[test]
+fn glider_wraps_on_torus() {
+    let u = glider_after_lap(Topology::Torus);
+    let mut expected = Universe::from_cells(8, 8, vec![Cell::Dead; 64]).unwrap();
+    expected.insert_pattern(GLIDER, 0, 0, false).unwrap();
+    assert!(u.same_cells(&expected));
+}
+
+#[test]
+fn glider_breaks_up_at_bounded_edge() {
+    let u = glider_after_lap(Topology::Bounded);
+    // 撞到右下角后滑翔机不复存在, 只剩下一个方块
+    let block: Vec<_> = BLOCK.iter().map(|&(r, c)| (r + 6, c + 6)).collect();
+    assert_eq!(u.live_cells().collect::<Vec<_>>(), block);
+    let mut next = u.clone();
+    next.tick();
+    assert!(next.same_cells(&u));
+}
+
+#[test]
+fn bounded_edges_have_no_neighbors_beyond() {
+    let mut u = Universe::from_cells(4, 4, vec![Cell::Dead; 16]).unwrap();
+    u.set_cell(Cell::Alive, 3, 3).unwrap();
+    assert_eq!(u.live_neighbor_count(0, 0), 1);
+    u.set_topology(Topology::Bounded);
+    assert_eq!(u.live_neighbor_count(0, 0), 0);
+    assert_eq!(u.live_neighbor_count(2, 2), 1);
+}
+
+#[test]
+fn resolve_reflects_mirror_coordinates() {
+    let mirror = Topology::Mirror;
+    assert_eq!(mirror.resolve(-1, 8), Some(0));
+    assert_eq!(mirror.resolve(8, 8), Some(7));
+    assert_eq!(mirror.resolve(-2, 8), Some(1));
+    assert_eq!(mirror.resolve(9, 8), Some(6));
+    assert_eq!(mirror.resolve(3, 8), Some(3));
+    // 只有一行时所有坐标都落在这一行上
+    for coord in -3..3 {
+        assert_eq!(mirror.resolve(coord, 1), Some(0));
+    }
+    assert_eq!(Topology::Torus.resolve(-1, 8), Some(7));
+    assert_eq!(Topology::Bounded.resolve(8, 8), None);
+    assert_eq!(Topology::Bounded.resolve(0, 1), Some(0));
+}
+
+#[test]
+fn mirror_corners_and_edges_count_reflections() {
+    let mut u = Universe::from_cells(4, 4, vec![Cell::Dead; 16]).unwrap();
+    u.set_topology(Topology::Mirror);
+    u.set_cell(Cell::Alive, 0, 0).unwrap();
+    // 角上的格子有三个邻居是自己的倒影
+    assert_eq!(u.live_neighbor_count(0, 0), 3);
+    assert_eq!(u.live_neighbor_count(0, 1), 2);
+    assert_eq!(u.live_neighbor_count(1, 1), 1);
+    assert_eq!(u.live_neighbor_count(3, 3), 0);
+
+    // 1xN 的面板上下两侧都是这一行本身
+    let mut line = Universe::from_cells(5, 1, vec![Cell::Dead; 5]).unwrap();
+    line.set_topology(Topology::Mirror);
+    line.set_cell(Cell::Alive, 2, 0).unwrap();
+    assert_eq!(line.live_neighbor_count(0, 2), 2);
+    assert_eq!(line.live_neighbor_count(0, 1), 3);
+    assert_eq!(line.live_neighbor_count(0, 0), 0);
+}
+
+#[test]
+fn blinker_at_mirror_wall_matches_reflected_copy() {
+    // 贴着上边的竖直闪烁器, 和把面板连同倒影一起画出来的两倍高的面板比较
+    let mut u = Universe::from_cells(8, 6, vec![Cell::Dead; 48]).unwrap();
+    u.set_topology(Topology::Mirror);
+    let mut doubled = Universe::from_cells(8, 12, vec![Cell::Dead; 96]).unwrap();
+    doubled.set_topology(Topology::Mirror);
+    for &(r, c) in BLINKER {
+        let (row, col) = (c, r + 3);
+        u.set_cell(Cell::Alive, col, row).unwrap();
+        doubled.set_cell(Cell::Alive, col, row + 6).unwrap();
+        doubled.set_cell(Cell::Alive, col, 5 - row).unwrap();
+    }
+    for _ in 0..6 {
+        u.tick();
+        doubled.tick();
+        for row in 0..6 {
+            for col in 0..8 {
+                assert_eq!(u.get_cell(row, col), doubled.get_cell(row + 6, col));
+            }
+        }
+    }
+    assert!(u.population() > 0);
+}
+
+const R_PENTOMINO: &[(u32, u32)] = &[(0, 1), (0, 2), (1, 0), (1, 1), (2, 1)];
+
+fn r_pentomino(max_size: u32) -> Universe {
+    let mut u = Universe::from_cells(16, 16, vec![Cell::Dead; 256]).unwrap();
+    u.insert_pattern(R_PENTOMINO, 7, 7, false).unwrap();
+    u.set_auto_grow(Some(max_size));
+    u
+}
+
+#[test]
+fn r_pentomino_grows_left_when_it_nears_the_edge() {
+    let mut u = r_pentomino(512);
+    // 第 16 代第一次有活细胞进入左边两列
+    for _ in 0..15 {
+        u.tick();
+        assert_eq!((u.width(), u.height(), u.grown()), (16, 16, (0, 0)));
+    }
+    u.tick();
+    assert_eq!((u.width(), u.height(), u.grown()), (48, 16, (0, 32)));
+    assert!(!u.is_grow_capped());
+
+    // 和足够大、不会碰到边的面板比较, 只差扩出来的偏移
+    let mut reference = Universe::from_cells(100, 100, vec![Cell::Dead; 10_000]).unwrap();
+    reference
+        .insert_pattern(R_PENTOMINO, 40, 40, false)
+        .unwrap();
+    reference.step_n(16);
+    let mut shifted: Vec<_> = reference
+        .live_cells()
+        .map(|(r, c)| (r - 33, c - 1))
+        .collect();
+    shifted.sort_unstable();
+    assert_eq!(u.live_cells().collect::<Vec<_>>(), shifted);
+    assert_eq!(u.count(), 16);
+}
+
+#[test]
+fn auto_grow_stops_at_the_size_cap() {
+    let mut u = r_pentomino(16);
+    u.step_n(16);
+    assert_eq!((u.width(), u.height()), (16, 16));
+    assert!(u.is_grow_capped());
+
+    let mut u = r_pentomino(40);
+    u.step_n(16);
+    assert_eq!((u.width(), u.grown()), (40, (0, 24)));
+}
+
+#[test]
+fn resize_with_offset_keeps_extra_states() {
+    let mut u = Universe::from_cells(4, 4, vec![Cell::Dead; 16]).unwrap();
+    u.set_rule(Rule::Wireworld);
+    u.set_state(1, 1, WIRE_CONDUCTOR).unwrap();
+    u.resize(8, 8, Anchor::Offset { row: 1, col: 2 });
+    assert_eq!(u.state(2, 3), Some(WIRE_CONDUCTOR));
+    assert_eq!(u.state(1, 1), Some(0));
+}
+
+fn seeded(seed: u64) -> Universe {
+    Universe::builder().width(64).height(64).seed(seed).build()
+}
+
+#[test]
+fn zero_mutation_rate_changes_nothing() {
+    let mut plain = seeded(7);
+    let mut noisy = seeded(7);
+    noisy.set_mutation_rate(0.0);
+    plain.step_n(20);
+    noisy.step_n(20);
+    assert!(plain.same_cells(&noisy));
+    assert_eq!(noisy.mutations(), 0);
+}
+
+#[test]
+fn full_mutation_rate_inverts_every_cell() {
+    let mut expected = seeded(3);
+    expected.tick();
+    let mut u = seeded(3);
+    u.set_mutation_rate(1.0);
+    u.tick();
+    assert_eq!(u.mutations(), 64 * 64);
+    assert_eq!(u.population(), 64 * 64 - expected.population());
+    assert!(u
+        .cells()
+        .iter()
+        .zip(expected.cells())
+        .all(|(&a, &b)| a != b));
+}
+
+#[test]
+fn mutations_are_reproducible_and_near_the_rate() {
+    let run = || {
+        let mut u = seeded(11);
+        u.dead_all();
+        u.set_mutation_rate(0.25);
+        u.tick();
+        u
+    };
+    let (a, b) = (run(), run());
+    assert!(a.same_cells(&b));
+    // 4096 个格子, 期望 1024, 标准差约 28
+    let flips = a.mutations();
+    assert!((900..=1150).contains(&flips), "{} flips", flips);
+    assert_eq!(a.population(), flips);
+    assert_eq!((a.births(), a.deaths()), (0, 0));
+
+    let mut clamped = seeded(1);
+    clamped.set_mutation_rate(2.0);
+    assert_eq!(clamped.mutation_rate(), 1.0);
+    clamped.set_mutation_rate(f64::NAN);
+    assert_eq!(clamped.mutation_rate(), 0.0);
+}
+
+fn board_states(u: &Universe) -> Vec<u8> {
+    (0..u.height())
+        .flat_map(|row| (0..u.width()).map(move |col| u.state(row, col).unwrap()))
+        .collect()
+}
+
+#[test]
+fn forest_fire_spreads_and_burns_out_deterministically() {
+    let mut u = board(5, 1, &[(0, 0), (0, 1), (0, 3), (0, 4)]);
+    u.set_topology(Topology::Bounded);
+    u.set_rule(Rule::ForestFire);
+    u.set_growth_rate(0.0);
+    u.set_lightning_rate(0.0);
+    u.set_state(0, 0, FIRE_BURNING).unwrap();
+    u.tick();
+    assert_eq!(board_states(&u), [0, 2, 0, 1, 1]);
+    u.tick();
+    // 中间的空地挡住了火
+    assert_eq!(board_states(&u), [0, 0, 0, 1, 1]);
+    u.tick();
+    assert_eq!(board_states(&u), [0, 0, 0, 1, 1]);
+    assert_eq!(u.population(), 2);
+}
+
+fn seeded_fire(seed: u64) -> Universe {
+    let mut u = seeded(seed);
+    u.set_rule(Rule::ForestFire);
+    u.set_lightning_rate(0.01);
+    u.step_n(5);
+    u
+}
+
+#[test]
+fn forest_fire_with_same_seed_is_reproducible() {
+    let (a, b) = (seeded_fire(5), seeded_fire(5));
+    assert_eq!(board_states(&a), board_states(&b));
+    assert!(board_states(&a).contains(&FIRE_BURNING));
+    assert_eq!(a.population(), a.live_count() as u32);
+    assert_ne!(board_states(&a), board_states(&seeded_fire(6)));
+}
+
+#[test]
+fn forest_fire_tree_density_settles() {
+    let mut u = seeded(3);
+    u.set_rule(Rule::ForestFire);
+    u.set_lightning_rate(0.001);
+    u.step_n(200);
+    // 烧过最初的大火之后, 树的密度在一个稳定的范围里起伏
+    let mean = |u: &mut Universe| {
+        let cells = f64::from(u.width() * u.height());
+        let mut total = 0.0;
+        for _ in 0..100 {
+            u.tick();
+            total += f64::from(u.population()) / cells;
+        }
+        total / 100.0
+    };
+    let (first, second) = (mean(&mut u), mean(&mut u));
+    assert!(first > 0.1 && first < 0.9, "density {}", first);
+    assert!((first - second).abs() < 0.1, "{} then {}", first, second);
+}
+
+fn sandpile(seed: u64, topology: Topology) -> Universe {
+    let mut u = Universe::builder().width(16).height(16).seed(seed).build();
+    u.set_topology(topology);
+    u.set_rule(Rule::Sandpile);
+    u.randomize_states();
+    u
+}
+
+#[test]
+fn sandpile_topples_to_von_neumann_neighbors() {
+    let mut u = board(3, 3, &[]);
+    u.set_topology(Topology::Bounded);
+    u.set_rule(Rule::Sandpile);
+    u.set_state(1, 1, 3).unwrap();
+    assert_eq!(u.add_grain(1, 1), Ok(1));
+    assert_eq!(board_states(&u), [0, 1, 0, 1, 0, 1, 0, 1, 0]);
+    assert_eq!(u.population(), 4);
+    assert_eq!(u.count(), 0);
+
+    // 每一代在中心落一粒沙
+    u.tick();
+    assert_eq!(board_states(&u), [0, 1, 0, 1, 1, 1, 0, 1, 0]);
+    assert!(u.add_grain(3, 0).is_err());
+}
+
+#[test]
+fn sandpile_toppling_is_order_independent() {
+    let drops = [(0, 0), (5, 7), (5, 8), (15, 15), (8, 8), (8, 8), (3, 12)];
+    for &topology in [Topology::Bounded, Topology::Torus].iter() {
+        let mut forward = sandpile(9, topology);
+        let mut backward = forward.clone();
+        for &(row, col) in drops.iter() {
+            forward.add_grain(row, col).unwrap();
+        }
+        for &(row, col) in drops.iter().rev() {
+            backward.add_grain(row, col).unwrap();
+        }
+        assert_eq!(board_states(&forward), board_states(&backward));
+        assert!(board_states(&forward).iter().all(|&n| n < 4));
+    }
+}
+
+#[test]
+fn sandpile_on_torus_stops_at_the_topple_limit() {
+    // 环面上沙粒不会流失, 全是 3 的面板再加一粒永远坍塌不完
+    let mut u = board(4, 4, &[]);
+    u.set_rule(Rule::Sandpile);
+    for row in 0..4 {
+        for col in 0..4 {
+            u.set_state(row, col, 3).unwrap();
+        }
+    }
+    let topples = u.add_grain(0, 0).unwrap();
+    assert_eq!(topples, TOPPLE_LIMIT_PER_CELL * 16);
+    assert!(board_states(&u).iter().all(|&n| n < 4));
+}
+
+#[test]
+fn blinker_changes_four_cells_per_tick() {
+    for &engine in [Engine::Dense, Engine::Sparse].iter() {
+        let mut u = board(16, 16, &[]);
+        u.set_engine(engine);
+        u.insert_pattern(BLINKER, 7, 6, false).unwrap();
+        assert!(u.needs_redraw());
+        u.mark_drawn();
+        for _ in 0..4 {
+            u.tick();
+            let mut changes = u.changed_cells().to_vec();
+            changes.sort_unstable();
+            // 横竖两个相位之间总是两端的两格死去、上下的两格诞生
+            assert_eq!(changes, [(6, 7), (7, 6), (7, 8), (8, 7)], "{:?}", engine);
+            assert!(!u.needs_redraw());
+            u.mark_drawn();
+        }
+    }
+
+    // 单独改的格子也记下来, 整体替换之后只要求整个重画
+    let mut u = board(16, 16, BLINKER);
+    u.mark_drawn();
+    u.toggle_cell(3, 3).unwrap();
+    assert_eq!(u.changed_cells(), &[(3, 3)]);
+    u.reset();
+    assert!(u.needs_redraw());
+    assert!(u.changed_cells().is_empty());
+    u.mark_drawn();
+    u.set_rule(Rule::BRIANS_BRAIN);
+    assert!(u.needs_redraw());
+}
+
+#[test]
+fn blinker_ends_are_always_newborn() {
+    for &engine in [Engine::Dense, Engine::Sparse, Engine::Tiled].iter() {
+        let mut u = board(16, 16, &[]);
+        u.set_engine(engine);
+        u.insert_pattern(BLINKER, 7, 6, false).unwrap();
+        assert_eq!(u.cell_age(7, 7), Some(0));
+        for generation in 1..=6u16 {
+            u.tick();
+            // 中间一格一直活着, 两端每一代都是新出生的
+            assert_eq!(u.cell_age(7, 7), Some(generation), "{:?}", engine);
+            let ends = if generation % 2 == 1 {
+                [(6, 7), (8, 7)]
+            } else {
+                [(7, 6), (7, 8)]
+            };
+            for &(row, col) in ends.iter() {
+                assert_eq!(u.cell_age(row, col), Some(0), "{:?}", engine);
+            }
+            assert_eq!(u.live_count(), 3);
+        }
+        assert_eq!(u.cell_age(0, 0), None);
+        assert_eq!(u.cell_age(16, 0), None);
+    }
+}
+
+#[test]
+fn ages_restart_after_edits_and_resets() {
+    let mut u = board(16, 16, BLOCK);
+    u.step_n(5);
+    assert_eq!(u.cell_age(0, 0), Some(5));
+    u.toggle_cell(0, 0).unwrap();
+    u.toggle_cell(0, 0).unwrap();
+    u.tick();
+    assert_eq!((u.cell_age(0, 0), u.cell_age(1, 1)), (Some(1), Some(6)));
+
+    // 自动扩展时年龄跟着细胞搬走
+    u.resize(20, 20, Anchor::Offset { row: 2, col: 3 });
+    assert_eq!(u.cell_age(3, 4), Some(6));
+
+    u.reset();
+    let alive: Vec<_> = u.live_cells().collect();
+    assert!(!alive.is_empty());
+    assert!(alive
+        .iter()
+        .all(|&(row, col)| u.cell_age(row, col) == Some(0)));
+    u.dead_all();
+    u.insert_pattern(BLOCK, 4, 4, false).unwrap();
+    u.tick();
+    assert_eq!(u.cell_age(4, 4), Some(1));
+}
+
+#[test]
+fn blinker_step_records_births_and_deaths() {
+    let mut u = board(16, 16, &[]);
+    u.insert_pattern(BLINKER, 7, 6, false).unwrap();
+    assert!(u.last_born().is_empty() && u.last_died().is_empty());
+    u.tick();
+    assert_eq!(u.last_born(), &[(6, 7), (8, 7)]);
+    assert_eq!(u.last_died(), &[(7, 6), (7, 8)]);
+    u.tick();
+    assert_eq!(u.last_born(), &[(7, 6), (7, 8)]);
+    assert_eq!(u.last_died(), &[(6, 7), (8, 7)]);
+
+    // 编辑不改上一代的变化, 清屏和重新生成时清空
+    u.toggle_cell(0, 0).unwrap();
+    assert_eq!(u.last_born().len(), 2);
+    u.dead_all();
+    assert!(u.last_born().is_empty() && u.last_died().is_empty());
+    u.tick();
+    u.reset();
+    assert!(u.last_born().is_empty() && u.last_died().is_empty());
+}
+
+#[test]
+fn activity_heats_changing_cells_and_cools_down() {
+    let mut u = board(16, 16, &[]);
+    u.insert_pattern(BLINKER, 7, 6, false).unwrap();
+    u.tick();
+    // 两端死掉、上下活过来, 中间一直活着
+    assert_eq!(u.cell_activity(7, 6), Some(ACTIVITY_STEP));
+    assert_eq!(u.cell_activity(6, 7), Some(ACTIVITY_STEP));
+    assert_eq!(u.cell_activity(7, 7), Some(0));
+    assert_eq!(u.cell_activity(16, 0), None);
+    for _ in 0..40 {
+        u.tick();
+    }
+    let hot = u.cell_activity(7, 6).unwrap();
+    assert_eq!(heat_level(hot), HEAT_PALETTE.len() - 1);
+    assert_eq!(heat_level(0), 0);
+    assert_eq!(heat_level(1), 1);
+
+    // 停下来之后慢慢冷却, 重新生成时清零
+    u.dead_all();
+    assert_eq!(u.cell_activity(7, 6), Some(0));
+    u.insert_pattern(BLINKER, 7, 6, false).unwrap();
+    u.tick();
+    u.toggle_cell(6, 7).unwrap();
+    u.toggle_cell(7, 7).unwrap();
+    u.toggle_cell(8, 7).unwrap();
+    let before = u.cell_activity(7, 6).unwrap();
+    u.tick();
+    let after = u.cell_activity(7, 6).unwrap();
+    assert!(after < before && after > 0);
+    u.reset();
+    assert_eq!(u.cell_activity(7, 6), Some(0));
+}
+
+#[test]
+fn trails_fade_out_and_repaint() {
+    let mut u = Universe::from_cells(8, 8, vec![Cell::Dead; 64]).unwrap();
+    u.toggle_cell(3, 3).unwrap();
+    u.tick();
+    // 默认不记拖尾
+    assert_eq!(u.cell_decay(3, 3), None);
+
+    u.set_trails(true);
+    u.toggle_cell(3, 3).unwrap();
+    u.tick();
+    assert_eq!(u.cell_decay(3, 3), Some(TRAIL_LENGTH));
+    for left in (1..TRAIL_LENGTH).rev() {
+        u.mark_drawn();
+        u.tick();
+        assert_eq!(u.cell_decay(3, 3), Some(left));
+        // 细胞没变, 褪色的格子也要重画
+        assert!(u.changed_cells().contains(&(3, 3)));
+    }
+    u.tick();
+    assert_eq!(u.cell_decay(3, 3), None);
+
+    u.toggle_cell(3, 3).unwrap();
+    u.tick();
+    u.dead_all();
+    assert_eq!(u.cell_decay(3, 3), None);
+    u.toggle_cell(3, 3).unwrap();
+    u.tick();
+    u.set_trails(false);
+    assert_eq!(u.cell_decay(3, 3), None);
+    assert!(u.needs_redraw());
+}
@@ -0,0 +1,261 @@
+use life_game::view::{hex_cell_at, hex_corners, Camera, Layout, MAX_CELL_PX, MIN_CELL_PX};
+
+/// 12x12 像素的六边形: 竖边占 8 像素, 上下的尖各 4 像素, 顶点都是整数
+fn at(x: i32, y: i32) -> Option<(u32, u32)> {
+    hex_cell_at(x, y, 12, 12, 2, 2)
+}
+
+#[test]
+fn hex_corners_tile_between_rows() {
+    assert_eq!(
+        hex_corners(0, 0, 12, 12),
+        [(6, 0), (12, 4), (12, 12), (6, 16), (0, 12), (0, 4)]
+    );
+    // 奇数行右移半格, 和上一行共用一条斜边
+    let below = hex_corners(1, 0, 12, 12);
+    assert_eq!(below[0], (12, 12));
+    assert_eq!(below[5], (6, 16));
+    assert_eq!(hex_corners(0, 1, 12, 12)[5], (12, 4));
+}
+
+#[test]
+fn hex_centers_map_to_their_cells() {
+    assert_eq!(at(6, 8), Some((0, 0)));
+    assert_eq!(at(18, 8), Some((0, 1)));
+    assert_eq!(at(12, 20), Some((1, 0)));
+    assert_eq!(at(24, 20), Some((1, 1)));
+}
+
+#[test]
+fn hex_vertical_edge() {
+    assert_eq!(at(11, 8), Some((0, 0)));
+    assert_eq!(at(12, 8), Some((0, 1)));
+    assert_eq!(at(17, 20), Some((1, 0)));
+    assert_eq!(at(18, 20), Some((1, 1)));
+}
+
+#[test]
+fn hex_slanted_edges() {
+    // (0, 0) 右下的斜边从 (12, 12) 到 (6, 16), 下面是 (1, 0)
+    assert_eq!(at(8, 13), Some((0, 0)));
+    assert_eq!(at(9, 14), Some((1, 0)));
+    assert_eq!(at(11, 11), Some((0, 0)));
+    assert_eq!(at(11, 12), Some((1, 0)));
+    // (0, 1) 左下的斜边从 (12, 12) 到 (18, 16), 下面同样是 (1, 0)
+    assert_eq!(at(15, 13), Some((0, 1)));
+    assert_eq!(at(15, 15), Some((1, 0)));
+}
+
+#[test]
+fn hex_margins_are_outside() {
+    // 第 0 行尖顶之间的缺口
+    assert_eq!(at(0, 0), None);
+    assert_eq!(at(11, 2), None);
+    assert_eq!(at(6, 1), Some((0, 0)));
+    // 奇数行左边空出的半格, 以及偶数行右边空出的半格
+    assert_eq!(at(1, 20), None);
+    assert_eq!(at(2, 15), None);
+    assert_eq!(at(3, 13), Some((0, 0)));
+    assert_eq!(at(29, 20), Some((1, 1)));
+    assert_eq!(at(29, 8), None);
+    assert_eq!(at(-1, 8), None);
+    assert_eq!(hex_cell_at(6, 8, 0, 12, 2, 2), None);
+}
+
+/// 12 像素的格子, 1 像素的网格线, 3 列 2 行, 网格从 (5, 7) 开始
+const LAYOUT: Layout = Layout {
+    grid_cols: 3,
+    grid_rows: 2,
+    cell_px: 12,
+    gridline_px: 1,
+    origin: (5, 7),
+};
+
+#[test]
+fn layout_sizes() {
+    assert_eq!(LAYOUT.pitch(), 13);
+    assert_eq!(LAYOUT.size(), (40, 27));
+    assert_eq!(LAYOUT.line_x(3), 5 + 39);
+    assert_eq!(LAYOUT.cell_rect(0, 0), (6, 8, 18, 20));
+    assert_eq!(LAYOUT.cell_rect(1, 2), (32, 21, 44, 33));
+}
+
+#[test]
+fn layout_maps_each_cell_pixel_back_to_its_cell() {
+    for row in 0..LAYOUT.grid_rows {
+        for col in 0..LAYOUT.grid_cols {
+            let (left, top, right, bottom) = LAYOUT.cell_rect(row, col);
+            for (x, y) in [
+                (left, top),
+                (right - 1, top),
+                (left, bottom - 1),
+                (right - 1, bottom - 1),
+            ] {
+                assert_eq!(LAYOUT.cell_at(x, y), Some((row, col)), "({}, {})", x, y);
+            }
+        }
+    }
+    // 最后一行最后一列的右下角, 再往外是网格线
+    assert_eq!(LAYOUT.cell_at(43, 32), Some((1, 2)));
+    assert_eq!(LAYOUT.cell_at(44, 32), None);
+    assert_eq!(LAYOUT.cell_at(43, 33), None);
+}
+
+#[test]
+fn layout_gridlines_and_outside_map_to_no_cell() {
+    // 最外面和格子之间的网格线
+    for i in 0..=LAYOUT.grid_cols {
+        assert_eq!(LAYOUT.cell_at(LAYOUT.line_x(i), 10), None);
+    }
+    for i in 0..=LAYOUT.grid_rows {
+        assert_eq!(LAYOUT.cell_at(10, LAYOUT.line_y(i)), None);
+    }
+    assert_eq!(LAYOUT.cell_at(4, 10), None);
+    assert_eq!(LAYOUT.cell_at(10, 6), None);
+    assert_eq!(LAYOUT.cell_at(100, 10), None);
+
+    // 没有网格线时格子挨在一起
+    let packed = Layout {
+        gridline_px: 0,
+        origin: (0, 0),
+        ..LAYOUT
+    };
+    assert_eq!(packed.cell_at(0, 0), Some((0, 0)));
+    assert_eq!(packed.cell_at(12, 0), Some((0, 1)));
+    assert_eq!(packed.cell_at(35, 23), Some((1, 2)));
+    assert_eq!(packed.cell_at(36, 23), None);
+}
+
+#[test]
+fn layout_without_gridlines_fills_the_gutters() {
+    let seamless = LAYOUT.without_gridlines();
+    assert_eq!(seamless.pitch(), LAYOUT.pitch());
+    assert_eq!(seamless.cell_rect(1, 2), (31, 20, 44, 33));
+    // 原来网格线上的像素归右边和下边的格子
+    assert_eq!(seamless.cell_at(LAYOUT.line_x(1), 10), Some((0, 1)));
+    assert_eq!(seamless.cell_at(10, LAYOUT.line_y(1)), Some((1, 0)));
+    assert_eq!(seamless.cell_at(LAYOUT.line_x(3), 10), None);
+}
+
+/// 12 像素的格子加 1 像素的网格线, 130x65 像素的窗口看着 100x50 的面板
+fn camera() -> Camera {
+    let mut camera = Camera::new(12, 1, (130, 65));
+    camera.fit((100, 50), (130, 65));
+    camera
+}
+
+#[test]
+fn camera_maps_screen_and_world_both_ways() {
+    let mut camera = camera();
+    assert_eq!(camera.screen_to_world(0, 0), (0, 0));
+    assert_eq!(camera.screen_to_world(12, 25), (1, 0));
+    assert_eq!(camera.screen_to_world(13, 26), (2, 1));
+    camera.pan(3, 5);
+    assert_eq!(camera.scroll(), (65, 39));
+    assert_eq!(camera.screen_to_world(0, 0), (3, 5));
+    assert_eq!(camera.world_to_screen(3, 5), (0, 0));
+    assert_eq!(camera.world_to_screen(4, 7), (26, 13));
+    for (x, y) in [(0, 0), (7, 30), (129, 64)] {
+        let (row, col) = camera.screen_to_world(x, y);
+        let (sx, sy) = camera.world_to_screen(row, col);
+        assert!(sx <= i64::from(x) && i64::from(x) < sx + 13);
+        assert!(sy <= i64::from(y) && i64::from(y) < sy + 13);
+    }
+}
+
+#[test]
+fn zoom_keeps_the_cell_under_the_cursor() {
+    let mut camera = camera();
+    // 面板够大, 缩小时不会碰到面板的边
+    camera.fit((1000, 1000), (130, 65));
+    camera.pan(500, 300);
+    for &(x, y) in &[(0, 0), (64, 32), (129, 64), (37, 5)] {
+        for &cell_px in &[2, 5, 20, 64, 12] {
+            let before = camera.screen_to_world(x, y);
+            let mut zoomed = camera;
+            zoomed.zoom_at(x, y, cell_px);
+            assert_eq!(zoomed.cell_px(), cell_px);
+            assert_eq!(
+                zoomed.screen_to_world(x, y),
+                before,
+                "({}, {}) at {} px",
+                x,
+                y,
+                cell_px
+            );
+        }
+    }
+}
+
+#[test]
+fn zoom_is_bounded_and_drops_gridlines_when_small() {
+    let mut camera = camera();
+    assert_eq!(camera.zoomed(1), 15);
+    assert_eq!(camera.zoomed(-1), 10);
+    assert_eq!(camera.zoomed(100), MAX_CELL_PX);
+    assert_eq!(camera.zoomed(-100), MIN_CELL_PX);
+    camera.zoom_at(0, 0, 1);
+    assert_eq!(camera.cell_px(), MIN_CELL_PX);
+    assert_eq!((camera.gridline_px(), camera.pitch()), (0, 2));
+    camera.zoom_at(0, 0, 5);
+    assert_eq!(camera.gridline_px(), 1);
+}
+
+#[test]
+fn camera_stays_on_the_board() {
+    let mut camera = camera();
+    camera.pan(-5, -5);
+    assert_eq!(camera.scroll(), (0, 0));
+    // 100 列 x 13 像素 + 最右边的网格线, 减去窗口的宽
+    camera.pan(1000, 1000);
+    assert_eq!(camera.scroll(), (1301 - 130, 651 - 65));
+    // 缩小到整个面板都放得下时贴着左上角
+    camera.fit((40, 20), (130, 65));
+    camera.zoom_at(129, 64, 2);
+    assert_eq!(camera.scroll(), (0, 0));
+    assert_eq!(camera.screen_to_world(0, 0), (0, 0));
+}
+
+#[test]
+fn camera_layout_covers_the_view() {
+    let mut camera = camera();
+    assert_eq!(
+        camera.layout(false),
+        Layout {
+            grid_cols: 10,
+            grid_rows: 5,
+            cell_px: 12,
+            gridline_px: 1,
+            origin: (0, 0),
+        }
+    );
+    // 窗口左上角压着半格时从那一格画起, 右边和下边多露出一格
+    camera.zoom_at(0, 0, 12);
+    camera.pan(1, 2);
+    let mut shifted = camera;
+    shifted.zoom_at(6, 6, 13);
+    let layout = shifted.layout(false);
+    let (top, left) = shifted.top_left(false);
+    assert_eq!(
+        layout.cell_at(6, 6),
+        Some(((1 - top) as u32, (2 - left) as u32))
+    );
+    assert!(layout.origin.0 <= 0 && layout.origin.1 <= 0);
+    assert!(layout.size().0 >= 130 && layout.size().1 >= 65);
+
+    // 六边形网格从偶数行画起
+    camera.pan(1, 0);
+    assert_eq!(camera.top_left(false), (2, 2));
+    assert_eq!(camera.top_left(true), (2, 2));
+    camera.pan(1, 0);
+    assert_eq!(camera.top_left(true), (2, 2));
+    assert_eq!(camera.layout(true).origin, (0, -13));
+
+    // 面板比窗口小时只铺到面板为止
+    let mut small = Camera::new(12, 1, (130, 65));
+    small.fit((4, 3), (130, 65));
+    assert_eq!(
+        (small.layout(false).grid_cols, small.layout(false).grid_rows),
+        (4, 3)
+    );
+}
@@ -0,0 +1,70 @@
+use std::env;
+use std::fs;
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use life_game::watch::{spawn, FileWatch};
+
+fn temp_file(name: &str) -> std::path::PathBuf {
+    env::temp_dir().join(format!("life_watch_{}_{}", std::process::id(), name))
+}
+
+#[test]
+fn rapid_writes_are_debounced() {
+    let path = temp_file("debounce.rle");
+    fs::write(&path, "a").unwrap();
+    let debounce = Duration::from_millis(100);
+    let mut watch = FileWatch::new(&path, debounce);
+    let t0 = Instant::now();
+    assert!(!watch.poll(t0));
+
+    fs::write(&path, "ab").unwrap();
+    assert!(!watch.poll(t0));
+    assert!(!watch.poll(t0 + debounce / 2));
+    // 等待期间又写了一次, 重新计时
+    fs::write(&path, "abc").unwrap();
+    assert!(!watch.poll(t0 + debounce / 2));
+    assert!(!watch.poll(t0 + debounce));
+    assert!(watch.poll(t0 + debounce * 2));
+    assert!(!watch.poll(t0 + debounce * 3));
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn deleted_file_is_not_reported() {
+    let path = temp_file("deleted.rle");
+    fs::write(&path, "a").unwrap();
+    let mut watch = FileWatch::new(&path, Duration::from_millis(10));
+    fs::remove_file(&path).unwrap();
+    let t0 = Instant::now();
+    assert!(!watch.poll(t0));
+    assert!(!watch.poll(t0 + Duration::from_secs(1)));
+    // 重新创建后算一次修改
+    fs::write(&path, "b").unwrap();
+    assert!(!watch.poll(t0 + Duration::from_secs(2)));
+    assert!(watch.poll(t0 + Duration::from_secs(3)));
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn background_thread_notifies_once_per_save() {
+    let path = temp_file("thread.rle");
+    fs::write(&path, "a").unwrap();
+    let (tx, rx) = mpsc::channel();
+    let mut handle = spawn(
+        &path,
+        Duration::from_millis(5),
+        Duration::from_millis(50),
+        move || tx.send(()).unwrap(),
+    );
+    assert_eq!(handle.path(), path.as_path());
+    for content in ["ab", "abc", "abcd"].iter() {
+        fs::write(&path, content).unwrap();
+        thread::sleep(Duration::from_millis(5));
+    }
+    rx.recv_timeout(Duration::from_secs(5)).unwrap();
+    assert!(rx.recv_timeout(Duration::from_millis(200)).is_err());
+    handle.stop();
+    fs::remove_file(&path).unwrap();
+}
@@ -0,0 +1,109 @@
+use life_game::patterns::{wireworld_cells, WIREWORLD_CLOCKS_OR};
+use life_game::rule::{WIRE_CONDUCTOR, WIRE_HEAD, WIRE_TAIL};
+use life_game::{Cell, Rule, Universe};
+
+fn empty(width: u32, height: u32) -> Universe {
+    Universe::from_cells(width, height, vec![Cell::Dead; (width * height) as usize]).unwrap()
+}
+
+/// 把示例电路放在 (1, 1), `quiet` 里的格子改成导线, 用来去掉某个时钟里的电子
+fn circuit(quiet: &[(u32, u32)]) -> Universe {
+    let mut u = empty(40, 12);
+    u.set_rule(Rule::Wireworld);
+    for (row, col, state) in wireworld_cells(WIREWORLD_CLOCKS_OR) {
+        let state = if quiet.contains(&(row, col)) {
+            WIRE_CONDUCTOR
+        } else {
+            state
+        };
+        u.set_state(row + 1, col + 1, state).unwrap();
+    }
+    u
+}
+
+/// 输出线末端出现电子头的代数
+fn output_pulses(mut u: Universe, generations: u64) -> Vec<u64> {
+    let mut pulses = Vec::new();
+    for generation in 1..=generations {
+        u.tick();
+        if u.state(5, 33) == Some(WIRE_HEAD) {
+            pulses.push(generation);
+        }
+    }
+    pulses
+}
+
+#[test]
+fn parses_and_names_wireworld() {
+    let rule: Rule = "WireWorld".parse().unwrap();
+    assert_eq!(rule, Rule::Wireworld);
+    assert_eq!("wireworld".parse::<Rule>().unwrap(), Rule::Wireworld);
+    assert_eq!(rule.to_string(), "WireWorld");
+    assert_eq!(rule.name(), Some("Wireworld"));
+    assert_eq!(rule.states(), 4);
+}
+
+#[test]
+fn transitions_follow_wireworld() {
+    let rule = Rule::Wireworld;
+    assert_eq!(rule.next_state(0, 2), 0);
+    assert_eq!(rule.next_state(WIRE_HEAD, 1), WIRE_TAIL);
+    assert_eq!(rule.next_state(WIRE_TAIL, 1), WIRE_CONDUCTOR);
+    assert_eq!(rule.next_state(WIRE_CONDUCTOR, 0), WIRE_CONDUCTOR);
+    assert_eq!(rule.next_state(WIRE_CONDUCTOR, 1), WIRE_HEAD);
+    assert_eq!(rule.next_state(WIRE_CONDUCTOR, 2), WIRE_HEAD);
+    assert_eq!(rule.next_state(WIRE_CONDUCTOR, 3), WIRE_CONDUCTOR);
+}
+
+#[test]
+fn electron_runs_along_a_wire() {
+    let mut u = empty(12, 3);
+    u.set_rule(Rule::Wireworld);
+    for col in 0..12 {
+        u.set_state(1, col, WIRE_CONDUCTOR).unwrap();
+    }
+    u.set_state(1, 0, WIRE_TAIL).unwrap();
+    u.set_state(1, 1, WIRE_HEAD).unwrap();
+    assert_eq!(u.population(), 1);
+    for step in 1..=8 {
+        u.tick();
+        assert_eq!(u.state(1, 1 + step), Some(WIRE_HEAD));
+        assert_eq!(u.state(1, step), Some(WIRE_TAIL));
+        assert_eq!(u.state(1, step - 1), Some(WIRE_CONDUCTOR));
+        assert_eq!(u.population(), 1);
+    }
+}
+
+#[test]
+fn example_or_gate_merges_both_clocks() {
+    let only_a = output_pulses(circuit(&[(6, 1), (6, 2)]), 200);
+    let only_b = output_pulses(circuit(&[(0, 1), (0, 2)]), 200);
+    let both = output_pulses(circuit(&[]), 200);
+
+    assert_eq!(only_a[0], 30);
+    assert!(only_a.windows(2).all(|w| w[1] - w[0] == 8));
+    assert!(only_b.windows(2).all(|w| w[1] - w[0] == 10));
+
+    let mut union: Vec<u64> = only_a.iter().chain(&only_b).cloned().collect();
+    union.sort_unstable();
+    union.dedup();
+    assert!(both.iter().all(|t| union.contains(t)));
+    // 两个脉冲间隔太近时只能输出一个, 其余的都要出现在输出端
+    for (i, t) in union.iter().enumerate() {
+        let crowded =
+            (i > 0 && t - union[i - 1] <= 2) || union.get(i + 1).is_some_and(|next| next - t <= 2);
+        if !crowded {
+            assert!(both.contains(t), "missing pulse at {}", t);
+        }
+    }
+}
+
+#[test]
+fn set_state_without_multi_state_rule_is_dead() {
+    let mut u = empty(4, 4);
+    u.set_state(1, 1, WIRE_CONDUCTOR).unwrap();
+    assert_eq!(u.state(1, 1), Some(0));
+    u.set_state(1, 1, 1).unwrap();
+    assert_eq!(u.get_cell(1, 1), Some(Cell::Alive));
+    assert!(u.set_state(4, 0, 1).is_err());
+}